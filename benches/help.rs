@@ -0,0 +1,31 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::time::Duration;
+
+/// Number of commands in the simulated bot
+const COMMANDS: usize = 250;
+
+/// Simulates a database-backed command check
+async fn slow_check(_command: usize) -> bool {
+    tokio::time::sleep(Duration::from_millis(2)).await;
+    true
+}
+
+fn help_checks(c: &mut Criterion) {
+    let rt = tokio::runtime::Runtime::new().unwrap();
+
+    let mut group = c.benchmark_group("help_checks");
+    group.sample_size(10);
+
+    // A limit of 1 is the old sequential behaviour
+    for limit in [1, 4, 16, 64] {
+        group.bench_with_input(BenchmarkId::from_parameter(limit), &limit, |b, &limit| {
+            b.to_async(&rt)
+                .iter(|| botox::help::run_bounded((0..COMMANDS).collect(), limit, slow_check))
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, help_checks);
+criterion_main!(benches);
@@ -0,0 +1,38 @@
+CREATE TABLE IF NOT EXISTS botox_suggestion_channels (
+    guild_id BIGINT PRIMARY KEY,
+    channel_id BIGINT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS botox_suggestions (
+    id TEXT PRIMARY KEY,
+    guild_id BIGINT NOT NULL,
+    channel_id BIGINT NOT NULL,
+    message_id BIGINT NOT NULL,
+    author_id BIGINT NOT NULL,
+    content TEXT NOT NULL,
+    status TEXT NOT NULL,
+    votes JSONB NOT NULL DEFAULT '{}'
+);
+
+CREATE INDEX IF NOT EXISTS botox_suggestions_guild_id_idx ON botox_suggestions (guild_id);
+
+CREATE TABLE IF NOT EXISTS botox_prefixes (
+    guild_id BIGINT PRIMARY KEY,
+    prefix TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS botox_blacklist (
+    kind TEXT NOT NULL,
+    id BIGINT NOT NULL,
+    reason TEXT NOT NULL,
+    added_by BIGINT NOT NULL,
+    added_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (kind, id)
+);
+
+CREATE TABLE IF NOT EXISTS botox_channel_restrictions (
+    guild_id BIGINT PRIMARY KEY,
+    allowed BIGINT[] NOT NULL DEFAULT '{}',
+    denied BIGINT[] NOT NULL DEFAULT '{}',
+    bypass_managers BOOLEAN NOT NULL DEFAULT FALSE
+);
@@ -0,0 +1,218 @@
+use poise::serenity_prelude::{
+    self as serenity, ApplicationId, ChannelId, ComponentInteractionDataKind, CreateActionRow,
+    CreateButton, CreateInteractionResponse, CreateInteractionResponseMessage, CreateInvite,
+    CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, GuildId, InviteTargetType,
+    Permissions, RoleId, UserId,
+};
+use poise::CreateReply;
+use std::time::Duration;
+
+use crate::permissions::PermissionInputs;
+use crate::Error;
+
+/// A voice channel activity (embedded app)
+#[derive(Debug, Clone)]
+pub struct Activity {
+    pub name: String,
+    pub application_id: ApplicationId,
+}
+
+impl Activity {
+    pub fn new(name: impl Into<String>, application_id: u64) -> Self {
+        Self {
+            name: name.into(),
+            application_id: ApplicationId::new(application_id),
+        }
+    }
+}
+
+/// Discords first-party activities, some may require the guild to be boosted
+pub fn default_activities() -> Vec<Activity> {
+    vec![
+        Activity::new("Watch Together", 880218394199220334),
+        Activity::new("Poker Night", 755827207812677713),
+        Activity::new("Chess In The Park", 832012774040141894),
+        Activity::new("Checkers In The Park", 832013003968348200),
+        Activity::new("Sketch Heads", 902271654783242291),
+        Activity::new("Letter League", 879863686565621790),
+        Activity::new("SpellCast", 852509694341283871),
+        Activity::new("Blazing 8s", 832025144389533716),
+        Activity::new("Land-io", 903769130790969345),
+        Activity::new("Putt Party", 945737671223947305),
+        Activity::new("Bobble League", 947957217959759964),
+        Activity::new("Know What I Meme", 950505761862189096),
+        Activity::new("Ask Away", 976052223358406656),
+    ]
+}
+
+/// Creates an invite that launches an activity in a voice channel, returning its URL
+///
+/// The bot needs the Create Invite permission in the channel
+pub async fn create_activity_invite(
+    http: &serenity::Http,
+    channel_id: ChannelId,
+    activity: &Activity,
+) -> Result<String, Error> {
+    let invite = channel_id
+        .create_invite(
+            http,
+            CreateInvite::new()
+                .max_age(24 * 60 * 60)
+                .target_type(InviteTargetType::EmbeddedApplication)
+                .target_application_id(activity.application_id),
+        )
+        .await?;
+
+    Ok(format!("https://discord.gg/{}", invite.code))
+}
+
+/// Returns why ``user_id`` lacks ``permission`` in a channel, or None if they have it
+fn _missing(
+    cache: &serenity::Cache,
+    guild_id: GuildId,
+    user_id: UserId,
+    roles: &[RoleId],
+    channel_id: ChannelId,
+    permission: Permissions,
+    name: &'static str,
+) -> Option<String> {
+    // Without a cached guild, let discord decide
+    let inputs = PermissionInputs::from_cache(cache, guild_id, user_id, roles, Some(channel_id))?;
+
+    let explanation = inputs.explain(permission, name);
+
+    (!explanation.allowed).then_some(explanation.reason)
+}
+
+/// Lets the user pick an activity and replies with an invite launching it, can be plugged into your bots ``/activity`` command
+///
+/// If ``channel`` is None, the voice channel the user is connected to is used
+pub async fn activity<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    activities: &[Activity],
+    channel: Option<ChannelId>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Activities can only be started in a server".into());
+    };
+
+    let cache = &ctx.serenity_context().cache;
+
+    let channel_id = match channel {
+        Some(channel_id) => channel_id,
+        None => {
+            let in_voice = cache.guild(guild_id).and_then(|g| {
+                g.voice_states
+                    .get(&ctx.author().id)
+                    .and_then(|v| v.channel_id)
+            });
+
+            in_voice.ok_or("Join a voice channel or pick one to start an activity")?
+        }
+    };
+
+    let author_roles = ctx
+        .author_member()
+        .await
+        .map(|m| m.roles.to_vec())
+        .unwrap_or_default();
+
+    if let Some(reason) = _missing(
+        cache,
+        guild_id,
+        ctx.author().id,
+        &author_roles,
+        channel_id,
+        Permissions::USE_EMBEDDED_ACTIVITIES,
+        "Use Activities",
+    ) {
+        return Err(format!(
+            "You need the Use Activities permission in <#{}> ({})",
+            channel_id, reason
+        )
+        .into());
+    }
+
+    let bot_id = cache.current_user().id;
+    let bot_roles = cache
+        .guild(guild_id)
+        .and_then(|g| g.members.get(&bot_id).map(|m| m.roles.to_vec()))
+        .unwrap_or_default();
+
+    if let Some(reason) = _missing(
+        cache,
+        guild_id,
+        bot_id,
+        &bot_roles,
+        channel_id,
+        Permissions::CREATE_INSTANT_INVITE,
+        "Create Invite",
+    ) {
+        return Err(format!(
+            "I need the Create Invite permission in <#{}> ({})",
+            channel_id, reason
+        )
+        .into());
+    }
+
+    let options = activities
+        .iter()
+        .take(25)
+        .enumerate()
+        .map(|(i, a)| CreateSelectMenuOption::new(a.name.clone(), i.to_string()))
+        .collect::<Vec<_>>();
+
+    let msg = ctx
+        .send(
+            CreateReply::default()
+                .content(format!("Pick an activity to start in <#{}>", channel_id))
+                .components(vec![CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new(
+                        "activity:select",
+                        CreateSelectMenuKind::String {
+                            options: options.into(),
+                        },
+                    )
+                    .placeholder("Pick an activity"),
+                )])
+                .ephemeral(true),
+        )
+        .await?
+        .into_message()
+        .await?;
+
+    let Some(item) = msg
+        .await_component_interaction(ctx.serenity_context().shard.clone())
+        .author_id(ctx.author().id)
+        .timeout(Duration::from_secs(60))
+        .await
+    else {
+        return Ok(());
+    };
+
+    let ComponentInteractionDataKind::StringSelect { values } = &item.data.kind else {
+        return Err("Internal error: Invalid interaction type".into());
+    };
+
+    let activity = values
+        .first()
+        .and_then(|v| v.parse::<usize>().ok())
+        .and_then(|i| activities.get(i))
+        .ok_or("Internal error: Unknown activity")?;
+
+    let url = create_activity_invite(ctx.http(), channel_id, activity).await?;
+
+    item.create_response(
+        ctx.http(),
+        CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content(format!("Press the button to start {}", activity.name))
+                .components(vec![CreateActionRow::Buttons(vec![
+                    CreateButton::new_link(url).label(format!("Join {}", activity.name)),
+                ])]),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
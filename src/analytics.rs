@@ -0,0 +1,129 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+/// Tracks how often each command is invoked
+///
+/// This is cheap to clone
+#[derive(Clone, Default)]
+pub struct UsageTracker {
+    counts: Arc<RwLock<HashMap<String, u64>>>,
+    /// Outcome counts by experiment, variant and outcome, see ``experiments::Experiments``
+    outcomes: Arc<RwLock<HashMap<(String, String, String), u64>>>,
+}
+
+impl UsageTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an invocation of a command, this should be called from your bots ``pre_command`` hook
+    pub async fn record<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+    ) {
+        let path = crate::cmdpath::invoked_path(ctx);
+
+        #[cfg(feature = "otel")]
+        crate::telemetry::command_counter().add(
+            1,
+            &[
+                opentelemetry::KeyValue::new("command", path.clone()),
+                // Distinguishes bots running in one process, see ``multibot::Supervisor``
+                opentelemetry::KeyValue::new(
+                    "bot",
+                    ctx.serenity_context().cache.current_user().id.to_string(),
+                ),
+            ],
+        );
+
+        self._count(&path).await;
+    }
+
+    /// Records an invocation of a command by its path, normalized with ``cmdpath::normalize``
+    pub async fn record_name(&self, path: &str) {
+        let path = crate::cmdpath::normalize(path);
+
+        #[cfg(feature = "otel")]
+        crate::telemetry::command_counter()
+            .add(1, &[opentelemetry::KeyValue::new("command", path.clone())]);
+
+        self._count(&path).await;
+    }
+
+    async fn _count(&self, qualified_name: &str) {
+        let mut counts = self.counts.write().await;
+
+        if let Some(count) = counts.get_mut(qualified_name) {
+            *count += 1;
+        } else {
+            counts.insert(qualified_name.to_string(), 1);
+        }
+    }
+
+    /// Returns the number of times a command has been invoked
+    pub async fn count(&self, qualified_name: &str) -> u64 {
+        self.counts
+            .read()
+            .await
+            .get(qualified_name)
+            .copied()
+            .unwrap_or_default()
+    }
+
+    /// Returns a copy of all invocation counts
+    pub async fn counts(&self) -> HashMap<String, u64> {
+        self.counts.read().await.clone()
+    }
+
+    /// Returns the ``n`` most used commands, most used first
+    pub async fn top(&self, n: usize) -> Vec<(String, u64)> {
+        let mut counts = self
+            .counts
+            .read()
+            .await
+            .iter()
+            .map(|(k, v)| (k.clone(), *v))
+            .collect::<Vec<_>>();
+
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(n);
+
+        counts
+    }
+
+    /// Records an outcome (such as ``exposure`` or ``completed``) of an experiment variant
+    pub async fn record_outcome(&self, experiment: &str, variant: &str, outcome: &str) {
+        #[cfg(feature = "otel")]
+        crate::telemetry::experiment_counter().add(
+            1,
+            &[
+                opentelemetry::KeyValue::new("experiment", experiment.to_string()),
+                opentelemetry::KeyValue::new("variant", variant.to_string()),
+                opentelemetry::KeyValue::new("outcome", outcome.to_string()),
+            ],
+        );
+
+        *self
+            .outcomes
+            .write()
+            .await
+            .entry((
+                experiment.to_string(),
+                variant.to_string(),
+                outcome.to_string(),
+            ))
+            .or_default() += 1;
+    }
+
+    /// Returns the outcome counts of an experiment by variant and outcome
+    pub async fn outcomes(&self, experiment: &str) -> HashMap<(String, String), u64> {
+        self.outcomes
+            .read()
+            .await
+            .iter()
+            .filter(|((e, _, _), _)| e == experiment)
+            .map(|((_, variant, outcome), count)| ((variant.clone(), outcome.clone()), *count))
+            .collect()
+    }
+}
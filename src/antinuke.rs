@@ -0,0 +1,313 @@
+use poise::serenity_prelude::{
+    self as serenity, audit_log::Action, ChannelAction, CreateMessage, EditMember, FullEvent,
+    GuildId, MemberAction, RoleAction, RoleId, Timestamp, UserId, WebhookAction,
+};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::auditlog::{self, AuditLogFilter};
+use crate::features::{Feature, FeatureMatrix};
+use crate::reason::Reason;
+use crate::Error;
+
+/// A destructive action watched by the anti-nuke
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NukeAction {
+    ChannelDelete,
+    RoleDelete,
+    Ban,
+    WebhookCreate,
+}
+
+impl NukeAction {
+    pub fn from_action(action: &Action) -> Option<Self> {
+        match action {
+            Action::Channel(ChannelAction::Delete) => Some(NukeAction::ChannelDelete),
+            Action::Role(RoleAction::Delete) => Some(NukeAction::RoleDelete),
+            Action::Member(MemberAction::BanAdd) => Some(NukeAction::Ban),
+            Action::Webhook(WebhookAction::Create) => Some(NukeAction::WebhookCreate),
+            _ => None,
+        }
+    }
+
+    pub fn to_action(self) -> Action {
+        match self {
+            NukeAction::ChannelDelete => Action::Channel(ChannelAction::Delete),
+            NukeAction::RoleDelete => Action::Role(RoleAction::Delete),
+            NukeAction::Ban => Action::Member(MemberAction::BanAdd),
+            NukeAction::WebhookCreate => Action::Webhook(WebhookAction::Create),
+        }
+    }
+}
+
+pub struct AntiNukeConfig {
+    /// How many of each action a single user may perform within ``window`` before the anti-nuke triggers
+    pub thresholds: HashMap<NukeAction, usize>,
+    pub window: Duration,
+    /// Whether to strip all (non-managed) roles from the perpetrator
+    pub strip_roles: bool,
+    /// Whether to DM the guild owner when the anti-nuke triggers
+    pub alert_owner: bool,
+    /// Users that are never acted against, the guild owner and the bot itself are always trusted
+    pub trusted: Vec<UserId>,
+    /// Notification preferences, owners who opted out of security alerts are not DMed
+    pub preferences: Option<crate::notify::Preferences>,
+}
+
+impl Default for AntiNukeConfig {
+    fn default() -> Self {
+        Self {
+            thresholds: HashMap::from([
+                (NukeAction::ChannelDelete, 3),
+                (NukeAction::RoleDelete, 3),
+                (NukeAction::Ban, 5),
+                (NukeAction::WebhookCreate, 5),
+            ]),
+            window: Duration::from_secs(30),
+            strip_roles: true,
+            alert_owner: true,
+            trusted: Vec::new(),
+            preferences: None,
+        }
+    }
+}
+
+/// A triggered anti-nuke incident
+#[derive(Debug, Clone)]
+pub struct NukeIncident {
+    pub guild_id: GuildId,
+    pub user_id: UserId,
+    pub action: NukeAction,
+    /// How many times the action was performed within the window
+    pub count: usize,
+    pub roles_stripped: bool,
+    pub owner_alerted: bool,
+}
+
+/// Watches audit log entries for mass destructive actions and responds to them
+///
+/// Requires the ``GUILD_MODERATION`` intent and the View Audit Log permission
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct AntiNuke {
+    config: Arc<AntiNukeConfig>,
+    actions: Arc<Mutex<HashMap<(GuildId, UserId, NukeAction), VecDeque<Instant>>>>,
+    /// Audit log entries of guilds that disabled ``Feature::AntiNuke`` are ignored, if set
+    pub features: Option<FeatureMatrix>,
+}
+
+impl AntiNuke {
+    pub fn new(config: AntiNukeConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            actions: Arc::new(Mutex::new(HashMap::new())),
+            features: None,
+        }
+    }
+
+    pub fn config(&self) -> &AntiNukeConfig {
+        &self.config
+    }
+
+    /// Records an action, returning how many times it was performed within the window if this exceeds the threshold
+    pub fn record(&self, guild_id: GuildId, user_id: UserId, action: NukeAction) -> Option<usize> {
+        let threshold = *self.config.thresholds.get(&action)?;
+
+        let now = Instant::now();
+        let mut actions = self.actions.lock().unwrap();
+        let times = actions.entry((guild_id, user_id, action)).or_default();
+
+        times.push_back(now);
+
+        while times
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > self.config.window)
+        {
+            times.pop_front();
+        }
+
+        if times.len() > threshold {
+            let count = times.len();
+            // Reset so a single burst only triggers once
+            times.clear();
+            Some(count)
+        } else {
+            None
+        }
+    }
+
+    /// Handles an audit log entry, returning the incident if the anti-nuke triggered
+    ///
+    /// This should be called from your bots event handler
+    pub async fn handle_event(
+        &self,
+        ctx: &serenity::Context,
+        event: &FullEvent,
+    ) -> Result<Option<NukeIncident>, Error> {
+        if !crate::features::allows(self.features.as_ref(), event, Feature::AntiNuke).await {
+            return Ok(None);
+        }
+
+        let FullEvent::GuildAuditLogEntryCreate { entry, guild_id } = event else {
+            return Ok(None);
+        };
+
+        let Some(action) = NukeAction::from_action(&entry.action) else {
+            return Ok(None);
+        };
+
+        let user_id = entry.user_id;
+
+        if user_id == ctx.cache.current_user().id || self.config.trusted.contains(&user_id) {
+            return Ok(None);
+        }
+
+        let Some(count) = self.record(*guild_id, user_id, action) else {
+            return Ok(None);
+        };
+
+        let guild = ctx.http.get_guild(*guild_id).await?;
+
+        let mut incident = NukeIncident {
+            guild_id: *guild_id,
+            user_id,
+            action,
+            count,
+            roles_stripped: false,
+            owner_alerted: false,
+        };
+
+        log::warn!(
+            "Anti-nuke triggered in {} by {}: {:?} x{} within {:?}",
+            guild_id,
+            user_id,
+            action,
+            count,
+            self.config.window
+        );
+
+        if user_id == guild.owner_id {
+            log::warn!(
+                "Perpetrator {} owns guild {}, not acting",
+                user_id,
+                guild_id
+            );
+            return Ok(Some(incident));
+        }
+
+        if self.config.strip_roles {
+            match self._strip_roles(&ctx.http, *guild_id, user_id).await {
+                Ok(()) => {
+                    incident.roles_stripped = true;
+                    log::warn!("Stripped roles of {} in {}", user_id, guild_id);
+                }
+                Err(e) => log::error!(
+                    "Failed to strip roles of {} in {}: {}",
+                    user_id,
+                    guild_id,
+                    e
+                ),
+            }
+        }
+
+        if self.config.alert_owner {
+            let mut alert = format!(
+                "**Anti-nuke triggered in {}**\n<@{}> ({}) performed {:?} {} times within {} seconds.{}",
+                guild.name,
+                user_id,
+                user_id,
+                action,
+                count,
+                self.config.window.as_secs(),
+                if incident.roles_stripped {
+                    " Their roles have been removed."
+                } else {
+                    ""
+                }
+            );
+
+            // The alert is still sent if the audit log cannot be read
+            let since = Timestamp::from_unix_timestamp(
+                Timestamp::now().unix_timestamp() - self.config.window.as_secs() as i64,
+            )
+            .unwrap_or_else(|_| Timestamp::now());
+
+            let filter = AuditLogFilter::new()
+                .action(action.to_action())
+                .actor(user_id)
+                .after(since)
+                .limit(10);
+
+            match auditlog::query(ctx, *guild_id, &filter).await {
+                Ok(entries) if !entries.is_empty() => {
+                    alert.push_str("\n\n**Recent actions**");
+
+                    for entry in entries {
+                        let _ = write!(
+                            alert,
+                            "\n{} {}",
+                            auditlog::action_name(&entry.action),
+                            entry
+                                .target_name
+                                .or_else(|| entry.target.map(|t| format!("``{}``", t)))
+                                .unwrap_or_default()
+                        );
+                    }
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to fetch audit log of {}: {}", guild_id, e),
+            }
+
+            incident.owner_alerted = crate::notify::dm(
+                &ctx.http,
+                self.config.preferences.as_ref(),
+                guild.owner_id,
+                crate::notify::NotifyCategory::Security,
+                CreateMessage::new().content(alert),
+            )
+            .await?;
+        }
+
+        Ok(Some(incident))
+    }
+
+    async fn _strip_roles(
+        &self,
+        http: &serenity::Http,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> Result<(), Error> {
+        let member = http.get_member(guild_id, user_id).await?;
+
+        // Managed roles cannot be removed, so they are kept
+        let managed = http
+            .get_guild_roles(guild_id)
+            .await?
+            .into_iter()
+            .filter(|r| r.managed)
+            .map(|r| r.id)
+            .collect::<Vec<_>>();
+
+        let keep = member
+            .roles
+            .iter()
+            .filter(|r| managed.contains(r))
+            .copied()
+            .collect::<Vec<RoleId>>();
+
+        guild_id
+            .edit_member(
+                http,
+                user_id,
+                EditMember::new()
+                    .roles(keep)
+                    .audit_log_reason(Reason::new("Anti-nuke triggered").as_str()),
+            )
+            .await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use axum::extract::{Path, Request, State};
+use axum::http::StatusCode;
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use poise::serenity_prelude as serenity;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::analytics::UsageTracker;
+use crate::shards::{ShardMonitor, ShardStatus};
+use crate::taskman::{RunFunction, Task};
+use crate::Error;
+
+/// A command as exposed by the API
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiCommand {
+    pub name: String,
+    pub category: Option<String>,
+    pub description: Option<String>,
+    pub subcommands: Vec<ApiCommand>,
+}
+
+impl ApiCommand {
+    fn from_command<Data>(command: &poise::Command<Data, Error>) -> Self {
+        Self {
+            name: command.name.to_string(),
+            category: command.category.clone(),
+            description: command.description.as_deref().map(str::to_string),
+            subcommands: command
+                .subcommands
+                .iter()
+                .filter(|c| !c.hide_in_help)
+                .map(Self::from_command)
+                .collect(),
+        }
+    }
+}
+
+/// State shared with the API handlers
+pub struct ApiState {
+    /// Requests must send this in the ``Authorization`` header
+    pub token: String,
+    pub ctx: serenity::Context,
+    pub monitor: Option<ShardMonitor>,
+    pub usage: Option<UsageTracker>,
+    pub commands: Vec<ApiCommand>,
+    tasks: HashMap<&'static str, RunFunction>,
+}
+
+impl ApiState {
+    pub fn new(token: impl Into<String>, ctx: serenity::Context) -> Self {
+        Self {
+            token: token.into(),
+            ctx,
+            monitor: None,
+            usage: None,
+            commands: Vec::new(),
+            tasks: HashMap::new(),
+        }
+    }
+
+    /// Sets the command list from the frameworks commands, grouped the same way as the help command
+    pub fn commands<Data>(mut self, commands: &[poise::Command<Data, Error>]) -> Self {
+        self.commands = crate::help::categorize(commands)
+            .into_values()
+            .flatten()
+            .filter(|c| !c.hide_in_help)
+            .map(ApiCommand::from_command)
+            .collect();
+        self
+    }
+
+    /// Allows a task to be triggered through ``POST /tasks/{name}``
+    pub fn task(mut self, task: Task) -> Self {
+        self.tasks.insert(task.name, task.run);
+        self
+    }
+}
+
+#[derive(Serialize)]
+struct _ApiError {
+    error: String,
+}
+
+fn _error(status: StatusCode, error: impl Into<String>) -> Response {
+    (
+        status,
+        Json(_ApiError {
+            error: error.into(),
+        }),
+    )
+        .into_response()
+}
+
+// Constant time comparison so the token cannot be guessed byte by byte through timing
+fn _token_matches(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+async fn _auth(State(state): State<Arc<ApiState>>, req: Request, next: Next) -> Response {
+    let authorized = req
+        .headers()
+        .get("Authorization")
+        .and_then(|h| h.to_str().ok())
+        .is_some_and(|h| _token_matches(h, &state.token));
+
+    if !authorized {
+        return _error(StatusCode::UNAUTHORIZED, "Invalid or missing token");
+    }
+
+    next.run(req).await
+}
+
+async fn _shards(State(state): State<Arc<ApiState>>) -> Json<Vec<ShardStatus>> {
+    Json(match &state.monitor {
+        Some(monitor) => monitor.snapshot().await,
+        None => Vec::new(),
+    })
+}
+
+#[derive(Serialize)]
+struct _Guilds {
+    guilds: usize,
+}
+
+async fn _guilds(State(state): State<Arc<ApiState>>) -> Json<_Guilds> {
+    Json(_Guilds {
+        guilds: state.ctx.cache.guild_count(),
+    })
+}
+
+async fn _commands(State(state): State<Arc<ApiState>>) -> Json<Vec<ApiCommand>> {
+    Json(state.commands.clone())
+}
+
+async fn _metrics(State(state): State<Arc<ApiState>>) -> Json<HashMap<String, u64>> {
+    Json(match &state.usage {
+        Some(usage) => usage.counts().await,
+        None => HashMap::new(),
+    })
+}
+
+async fn _run_task(State(state): State<Arc<ApiState>>, Path(name): Path<String>) -> Response {
+    let Some(run) = state.tasks.get(name.as_str()) else {
+        return _error(StatusCode::NOT_FOUND, format!("Unknown task {}", name));
+    };
+
+    log::info!("Running task {} from the API", name);
+
+    match run(&state.ctx).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => _error(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            crate::secrets::redact_error(&*e),
+        ),
+    }
+}
+
+/// Returns the API router, for mounting into an existing axum app
+///
+/// Endpoints: ``GET /shards``, ``GET /guilds``, ``GET /commands``, ``GET /metrics`` and ``POST /tasks/{name}``
+pub fn router(state: ApiState) -> Router {
+    let state = Arc::new(state);
+
+    Router::new()
+        .route("/shards", get(_shards))
+        .route("/guilds", get(_guilds))
+        .route("/commands", get(_commands))
+        .route("/metrics", get(_metrics))
+        .route("/tasks/:name", post(_run_task))
+        .layer(middleware::from_fn_with_state(state.clone(), _auth))
+        .with_state(state)
+}
+
+/// Serves the API on ``addr`` until the process exits
+pub async fn serve(addr: SocketAddr, state: ApiState) -> Result<(), Error> {
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+
+    log::info!("API listening on {}", addr);
+
+    axum::serve(listener, router(state)).await?;
+
+    Ok(())
+}
@@ -0,0 +1,480 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ActionRowComponent, ButtonStyle, ChannelId, ComponentInteraction,
+    CreateActionRow, CreateButton, CreateEmbed, CreateEmbedFooter, CreateInputText,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, CreateModal,
+    EditMember, GuildId, InputTextStyle, MessageId, ModalInteraction, Permissions, Timestamp,
+    UserId,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::cases::{Case, CaseAction, Cases, NewCase};
+use crate::notify::{self, NotifyCategory, Preferences};
+use crate::Error;
+
+/// Maximum length of an appeal
+const MAX_APPEAL_LENGTH: u16 = 1000;
+
+/// The status of an appeal
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum AppealStatus {
+    #[default]
+    Pending,
+    Approved,
+    Denied,
+}
+
+impl AppealStatus {
+    pub fn label(&self) -> &'static str {
+        match self {
+            AppealStatus::Pending => "Pending",
+            AppealStatus::Approved => "Approved",
+            AppealStatus::Denied => "Denied",
+        }
+    }
+
+    pub fn colour(&self) -> serenity::Colour {
+        match self {
+            AppealStatus::Pending => serenity::Colour::BLURPLE,
+            AppealStatus::Approved => serenity::Colour::DARK_GREEN,
+            AppealStatus::Denied => serenity::Colour::RED,
+        }
+    }
+}
+
+/// An appeal of a moderation case
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Appeal {
+    pub guild_id: GuildId,
+    pub case_number: u64,
+    pub user_id: UserId,
+    pub content: String,
+    pub status: AppealStatus,
+    pub reviewer: Option<UserId>,
+    /// The message in the staff channel
+    pub message_id: Option<MessageId>,
+    pub created_at: Timestamp,
+}
+
+/// Storage backend for appeals
+pub trait AppealStore: Send + Sync {
+    /// Returns the channel appeals of a guild are sent to
+    fn channel<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<Option<ChannelId>, Error>>;
+
+    /// Returns the appeal of a case
+    fn get<'a>(
+        &'a self,
+        guild_id: GuildId,
+        case_number: u64,
+    ) -> BoxFuture<'a, Result<Option<Appeal>, Error>>;
+
+    /// Adds or replaces an appeal
+    fn save<'a>(&'a self, appeal: &'a Appeal) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Returns the appeal button added to DM receipts of appealable cases
+pub fn appeal_components(case: &Case) -> Vec<CreateActionRow<'static>> {
+    vec![CreateActionRow::Buttons(vec![CreateButton::new(format!(
+        "appeal:open:{}:{}",
+        case.guild_id, case.number
+    ))
+    .label("Appeal")
+    .style(ButtonStyle::Primary)])]
+}
+
+fn _staff_components(appeal: &Appeal) -> Vec<CreateActionRow<'static>> {
+    if appeal.status != AppealStatus::Pending {
+        return Vec::new();
+    }
+
+    vec![CreateActionRow::Buttons(vec![
+        CreateButton::new(format!(
+            "appeal:approve:{}:{}",
+            appeal.guild_id, appeal.case_number
+        ))
+        .label("Approve")
+        .style(ButtonStyle::Success),
+        CreateButton::new(format!(
+            "appeal:deny:{}:{}",
+            appeal.guild_id, appeal.case_number
+        ))
+        .label("Deny")
+        .style(ButtonStyle::Danger),
+    ])]
+}
+
+fn _staff_embed(appeal: &Appeal, case: &Case) -> CreateEmbed<'static> {
+    let mut embed = CreateEmbed::default()
+        .title(format!(
+            "Appeal of case #{} | {}",
+            case.number,
+            case.action.label()
+        ))
+        .colour(appeal.status.colour())
+        .description(appeal.content.clone())
+        .field("User", format!("<@{}>", appeal.user_id), true)
+        .field("Status", appeal.status.label(), true)
+        .field("Original reason", case.reason.clone(), false)
+        .footer(CreateEmbedFooter::new(format!(
+            "User ID: {}",
+            appeal.user_id
+        )))
+        .timestamp(appeal.created_at);
+
+    if let Some(reviewer) = appeal.reviewer {
+        embed = embed.field("Reviewed by", format!("<@{}>", reviewer), true);
+    }
+
+    embed
+}
+
+fn _appealable(case: &Case, user_id: UserId) -> bool {
+    case.user_id == user_id && matches!(case.action, CaseAction::Timeout | CaseAction::Ban)
+}
+
+fn _parse(custom_id: &str) -> Option<(&str, GuildId, u64)> {
+    let mut parts = custom_id.strip_prefix("appeal:")?.split(':');
+
+    let action = parts.next()?;
+    let guild_id = parts.next()?.parse().ok()?;
+    let number = parts.next()?.parse().ok()?;
+
+    Some((action, guild_id, number))
+}
+
+async fn _respond_ephemeral(
+    http: &serenity::Http,
+    interaction: &ComponentInteraction,
+    content: &str,
+) -> Result<(), Error> {
+    interaction
+        .create_response(
+            http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Lets users appeal bans and timeouts from their DM receipt, routing appeals to a staff channel
+///
+/// Set ``Cases::appeals`` to add the appeal button to receipts. This is cheap to clone
+#[derive(Clone)]
+pub struct Appeals {
+    store: Arc<dyn AppealStore>,
+    cases: Cases,
+    /// Respected when DMing appeal outcomes, if set
+    pub preferences: Option<Preferences>,
+}
+
+impl Appeals {
+    pub fn new(store: Arc<dyn AppealStore>, cases: Cases) -> Self {
+        Self {
+            store,
+            cases,
+            preferences: None,
+        }
+    }
+
+    async fn _open(
+        &self,
+        http: &serenity::Http,
+        interaction: &ComponentInteraction,
+        guild_id: GuildId,
+        number: u64,
+    ) -> Result<(), Error> {
+        let appealable = self
+            .cases
+            .get(guild_id, number)
+            .await?
+            .is_some_and(|c| _appealable(&c, interaction.user.id));
+
+        if !appealable {
+            return _respond_ephemeral(http, interaction, "This case can not be appealed").await;
+        }
+
+        if self.store.get(guild_id, number).await?.is_some() {
+            return _respond_ephemeral(http, interaction, "You already appealed this case").await;
+        }
+
+        interaction
+            .create_response(
+                http,
+                CreateInteractionResponse::Modal(
+                    CreateModal::new(
+                        format!("appeal:submit:{}:{}", guild_id, number),
+                        format!("Appeal case #{}", number),
+                    )
+                    .components(vec![CreateActionRow::InputText(
+                        CreateInputText::new(
+                            InputTextStyle::Paragraph,
+                            "Why should this be lifted?",
+                            "content",
+                        )
+                        .max_length(MAX_APPEAL_LENGTH),
+                    )]),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn _review(
+        &self,
+        http: &serenity::Http,
+        interaction: &ComponentInteraction,
+        guild_id: GuildId,
+        number: u64,
+        approve: bool,
+    ) -> Result<(), Error> {
+        let (Some(mut appeal), Some(case)) = (
+            self.store.get(guild_id, number).await?,
+            self.cases.get(guild_id, number).await?,
+        ) else {
+            return _respond_ephemeral(http, interaction, "This appeal no longer exists").await;
+        };
+
+        let needed = match case.action {
+            CaseAction::Ban => Permissions::BAN_MEMBERS,
+            _ => Permissions::MODERATE_MEMBERS,
+        };
+
+        let allowed = interaction
+            .member
+            .as_ref()
+            .and_then(|m| m.permissions)
+            .is_some_and(|p| p.contains(needed));
+
+        if !allowed {
+            return _respond_ephemeral(
+                http,
+                interaction,
+                "You do not have permission to review this appeal",
+            )
+            .await;
+        }
+
+        if appeal.status != AppealStatus::Pending {
+            return _respond_ephemeral(http, interaction, "This appeal was already reviewed").await;
+        }
+
+        if approve {
+            self._lift(http, &case, interaction.user.id).await?;
+        }
+
+        appeal.status = if approve {
+            AppealStatus::Approved
+        } else {
+            AppealStatus::Denied
+        };
+        appeal.reviewer = Some(interaction.user.id);
+
+        self.store.save(&appeal).await?;
+
+        interaction
+            .create_response(
+                http,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(_staff_embed(&appeal, &case))
+                        .components(_staff_components(&appeal)),
+                ),
+            )
+            .await?;
+
+        notify::dm(
+            http,
+            self.preferences.as_ref(),
+            appeal.user_id,
+            NotifyCategory::Moderation,
+            CreateMessage::new().embed(
+                CreateEmbed::default()
+                    .title(format!(
+                        "Your appeal of case #{} was {}",
+                        case.number,
+                        appeal.status.label().to_lowercase()
+                    ))
+                    .colour(appeal.status.colour()),
+            ),
+        )
+        .await?;
+
+        Ok(())
+    }
+
+    // Lifts the punishment of an approved appeal, recording it as a case linked to the appealed one
+    async fn _lift(
+        &self,
+        http: &serenity::Http,
+        case: &Case,
+        moderator_id: UserId,
+    ) -> Result<(), Error> {
+        let action = match case.action {
+            CaseAction::Ban => CaseAction::Unban,
+            CaseAction::Timeout => {
+                case.guild_id
+                    .edit_member(
+                        http,
+                        case.user_id,
+                        EditMember::new()
+                            .enable_communication()
+                            .audit_log_reason("Appeal approved"),
+                    )
+                    .await?;
+
+                CaseAction::Note
+            }
+            _ => return Ok(()),
+        };
+
+        self.cases
+            .apply(
+                http,
+                NewCase {
+                    guild_id: case.guild_id,
+                    action,
+                    user_id: case.user_id,
+                    moderator_id,
+                    reason: format!("Appeal of case #{} approved", case.number),
+                    duration: None,
+                    related: vec![case.number],
+                },
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Handles appeal and review button presses, returning false if the interaction is not an appeal interaction
+    ///
+    /// This should be called from your bots event handler on every component interaction
+    pub async fn handle_interaction(
+        &self,
+        ctx: &serenity::Context,
+        interaction: &ComponentInteraction,
+    ) -> Result<bool, Error> {
+        let Some((action, guild_id, number)) = _parse(&interaction.data.custom_id) else {
+            return Ok(false);
+        };
+
+        match action {
+            "open" => self._open(&ctx.http, interaction, guild_id, number).await?,
+            "approve" | "deny" => {
+                self._review(
+                    &ctx.http,
+                    interaction,
+                    guild_id,
+                    number,
+                    action == "approve",
+                )
+                .await?
+            }
+            _ => return Ok(false),
+        }
+
+        Ok(true)
+    }
+
+    /// Handles submitted appeal modals, returning false if the interaction is not an appeal interaction
+    ///
+    /// This should be called from your bots event handler on every modal submit interaction
+    pub async fn handle_modal(
+        &self,
+        ctx: &serenity::Context,
+        interaction: &ModalInteraction,
+    ) -> Result<bool, Error> {
+        let Some(("submit", guild_id, number)) = _parse(&interaction.data.custom_id) else {
+            return Ok(false);
+        };
+
+        let content = interaction
+            .data
+            .components
+            .iter()
+            .flat_map(|row| row.components.iter())
+            .find_map(|c| match c {
+                ActionRowComponent::InputText(input) if input.custom_id == "content" => {
+                    input.value.as_deref().map(str::to_string)
+                }
+                _ => None,
+            })
+            .unwrap_or_default();
+
+        let reply = match self
+            ._submit(&ctx.http, interaction.user.id, guild_id, number, content)
+            .await
+        {
+            Ok(()) => "Your appeal was sent to the server staff".to_string(),
+            Err(e) => format!("Your appeal could not be sent: {}", e),
+        };
+
+        interaction
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new().content(reply),
+                ),
+            )
+            .await?;
+
+        Ok(true)
+    }
+
+    async fn _submit(
+        &self,
+        http: &serenity::Http,
+        user_id: UserId,
+        guild_id: GuildId,
+        number: u64,
+        content: String,
+    ) -> Result<(), Error> {
+        let Some(case) = self
+            .cases
+            .get(guild_id, number)
+            .await?
+            .filter(|c| _appealable(c, user_id))
+        else {
+            return Err("This case can not be appealed".into());
+        };
+
+        if self.store.get(guild_id, number).await?.is_some() {
+            return Err("You already appealed this case".into());
+        }
+
+        let Some(channel_id) = self.store.channel(guild_id).await? else {
+            return Err("This server does not accept appeals".into());
+        };
+
+        let mut appeal = Appeal {
+            guild_id,
+            case_number: number,
+            user_id,
+            content,
+            status: AppealStatus::Pending,
+            reviewer: None,
+            message_id: None,
+            created_at: Timestamp::now(),
+        };
+
+        let msg = channel_id
+            .send_message(
+                http,
+                CreateMessage::new()
+                    .embed(_staff_embed(&appeal, &case))
+                    .components(_staff_components(&appeal)),
+            )
+            .await?;
+
+        appeal.message_id = Some(msg.id);
+        self.store.save(&appeal).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,73 @@
+use std::fmt::Display;
+use strum::IntoEnumIterator;
+
+/// A slash command value that does not match any choice of its enum
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidChoice {
+    pub value: String,
+    pub expected: Vec<String>,
+}
+
+impl Display for InvalidChoice {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "``{}`` is not a valid choice, expected one of: {}",
+            self.value,
+            self.expected.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for InvalidChoice {}
+
+/// Returns the display names of every variant of an enum, in declaration order
+pub fn choice_names<T: IntoEnumIterator + Display>() -> Vec<String> {
+    T::iter().map(|v| v.to_string()).collect()
+}
+
+/// Returns slash command choices for every variant of an enum, named by its ``Display`` impl
+///
+/// The value of a choice is its index, use ``parse_choice`` to get the variant back
+pub fn choices_from<T: IntoEnumIterator + Display>() -> Vec<poise::CommandParameterChoice> {
+    T::iter()
+        .map(|v| poise::CommandParameterChoice {
+            name: v.to_string().into(),
+            localizations: Default::default(),
+            __non_exhaustive: (),
+        })
+        .collect()
+}
+
+/// Parses a choice back into its enum variant, from either its index or its (case-insensitive) name
+pub fn parse_choice<T: IntoEnumIterator + Display>(value: &str) -> Result<T, InvalidChoice> {
+    let value = value.trim();
+
+    if let Ok(index) = value.parse::<usize>() {
+        if let Some(variant) = T::iter().nth(index) {
+            return Ok(variant);
+        }
+    }
+
+    T::iter()
+        .find(|v| v.to_string().eq_ignore_ascii_case(value))
+        .ok_or_else(|| InvalidChoice {
+            value: value.to_string(),
+            expected: choice_names::<T>(),
+        })
+}
+
+/// Sets the choices of a commands parameter from an enum, returning false if there is no such parameter
+///
+/// Call this on the command before registering it, for example on ``my_command()``
+pub fn apply_choices<T: IntoEnumIterator + Display, Data, E>(
+    command: &mut poise::Command<Data, E>,
+    parameter: &str,
+) -> bool {
+    let Some(param) = command.parameters.iter_mut().find(|p| p.name == parameter) else {
+        return false;
+    };
+
+    param.choices = choices_from::<T>().into();
+    true
+}
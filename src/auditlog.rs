@@ -0,0 +1,247 @@
+use poise::serenity_prelude::{
+    self as serenity, audit_log::Action, AuditLogEntryId, CacheHttp, CreateEmbed, GuildId,
+    MemberAction, Timestamp, UserId,
+};
+use std::fmt::Write;
+
+use crate::leaderboard::resolve_names;
+use crate::Error;
+
+/// Number of entries Discord returns per audit log request by default
+const PAGE_SIZE: usize = 50;
+
+/// Number of entries shown per embed by ``render``
+pub const ENTRIES_PER_PAGE: usize = 10;
+
+/// Which audit log entries to fetch
+///
+/// Discord can only filter by a single action, so multiple actions are filtered after fetching
+#[derive(Debug, Clone)]
+pub struct AuditLogFilter {
+    /// Only entries with one of these actions, all actions if empty
+    pub actions: Vec<Action>,
+    /// Only entries performed by this user
+    pub actor: Option<UserId>,
+    /// Only entries targeting this id (a user, channel, role etc.)
+    pub target: Option<u64>,
+    /// Only entries created after this time, fetching stops at the first older entry
+    pub after: Option<Timestamp>,
+    /// Maximum number of entries returned, defaults to 50
+    pub limit: usize,
+    /// Maximum number of requests made, defaults to 5
+    pub max_pages: usize,
+}
+
+impl Default for AuditLogFilter {
+    fn default() -> Self {
+        Self {
+            actions: Vec::new(),
+            actor: None,
+            target: None,
+            after: None,
+            limit: PAGE_SIZE,
+            max_pages: 5,
+        }
+    }
+}
+
+impl AuditLogFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn action(mut self, action: Action) -> Self {
+        self.actions.push(action);
+        self
+    }
+
+    pub fn actor(mut self, actor: UserId) -> Self {
+        self.actor = Some(actor);
+        self
+    }
+
+    pub fn target(mut self, target: impl Into<u64>) -> Self {
+        self.target = Some(target.into());
+        self
+    }
+
+    pub fn after(mut self, after: Timestamp) -> Self {
+        self.after = Some(after);
+        self
+    }
+
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+}
+
+/// An audit log entry with the display names of its actor and (user) target
+#[derive(Debug, Clone)]
+pub struct AuditEntry {
+    pub id: AuditLogEntryId,
+    pub action: Action,
+    pub actor: UserId,
+    pub actor_name: Option<String>,
+    pub target: Option<u64>,
+    /// Only set for actions targeting members
+    pub target_name: Option<String>,
+    pub reason: Option<String>,
+    pub created_at: Timestamp,
+}
+
+/// Returns a readable name for an action, such as ``Channel Delete``
+pub fn action_name(action: &Action) -> String {
+    format!("{:?}", action).replace('(', " ").replace(')', "")
+}
+
+/// Returns true if the target of an action is a user
+fn _targets_user(action: &Action) -> bool {
+    matches!(
+        action,
+        Action::Member(
+            MemberAction::Kick
+                | MemberAction::BanAdd
+                | MemberAction::BanRemove
+                | MemberAction::Update
+                | MemberAction::RoleUpdate
+        )
+    )
+}
+
+/// Fetches the audit log entries of a guild matching a filter, newest first
+///
+/// Requires the View Audit Log permission. Actors and member targets are resolved to display names
+pub async fn query(
+    cache_http: impl CacheHttp,
+    guild_id: GuildId,
+    filter: &AuditLogFilter,
+) -> Result<Vec<AuditEntry>, Error> {
+    let action_type = match filter.actions.as_slice() {
+        [action] => Some(*action),
+        _ => None,
+    };
+
+    let after = filter.after.map(|t| t.unix_timestamp());
+
+    let mut entries = Vec::new();
+    let mut before = None;
+
+    'pages: for _ in 0..filter.max_pages {
+        let logs = guild_id
+            .audit_logs(cache_http.http(), action_type, filter.actor, before, None)
+            .await?;
+
+        for entry in logs.entries.iter() {
+            let created_at = entry.id.created_at();
+
+            if after.is_some_and(|after| created_at.unix_timestamp() < after) {
+                break 'pages;
+            }
+
+            let target = entry.target_id.map(|t| t.get());
+
+            if (!filter.actions.is_empty() && !filter.actions.contains(&entry.action))
+                || (filter.target.is_some() && filter.target != target)
+            {
+                continue;
+            }
+
+            entries.push(AuditEntry {
+                id: entry.id,
+                action: entry.action,
+                actor: entry.user_id,
+                actor_name: None,
+                target,
+                target_name: None,
+                reason: entry.reason.as_ref().map(|r| r.to_string()),
+                created_at,
+            });
+
+            if entries.len() >= filter.limit {
+                break 'pages;
+            }
+        }
+
+        match logs.entries.last() {
+            Some(last) if logs.entries.len() >= PAGE_SIZE => before = Some(last.id),
+            _ => break,
+        }
+    }
+
+    let mut users = entries.iter().map(|e| e.actor).collect::<Vec<_>>();
+
+    for entry in &entries {
+        if let Some(target) = entry.target.filter(|_| _targets_user(&entry.action)) {
+            users.push(UserId::new(target));
+        }
+    }
+
+    users.sort_unstable();
+    users.dedup();
+
+    let names = resolve_names(&cache_http, &users).await;
+
+    for entry in &mut entries {
+        entry.actor_name = names.get(&entry.actor).cloned();
+
+        if _targets_user(&entry.action) {
+            entry.target_name = entry
+                .target
+                .and_then(|t| names.get(&UserId::new(t)).cloned());
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Renders audit log entries into embed pages of ``ENTRIES_PER_PAGE`` entries
+pub fn render(entries: &[AuditEntry], title: &str) -> Vec<CreateEmbed<'static>> {
+    let pages = entries.len().div_ceil(ENTRIES_PER_PAGE).max(1);
+
+    (0..pages)
+        .map(|page| {
+            let mut desc = String::new();
+
+            for entry in entries
+                .iter()
+                .skip(page * ENTRIES_PER_PAGE)
+                .take(ENTRIES_PER_PAGE)
+            {
+                let _ = write!(
+                    desc,
+                    "**{}** <t:{}:R> by {}",
+                    action_name(&entry.action),
+                    entry.created_at.unix_timestamp(),
+                    entry.actor_name.as_deref().unwrap_or("Unknown user"),
+                );
+
+                match (&entry.target_name, entry.target) {
+                    (Some(name), _) => {
+                        let _ = write!(desc, " on {}", name);
+                    }
+                    (None, Some(target)) => {
+                        let _ = write!(desc, " on ``{}``", target);
+                    }
+                    (None, None) => {}
+                }
+
+                match &entry.reason {
+                    Some(reason) => {
+                        let _ = writeln!(desc, "\n{}\n", reason);
+                    }
+                    None => desc.push_str("\n\n"),
+                }
+            }
+
+            if desc.is_empty() {
+                desc.push_str("No audit log entries");
+            }
+
+            CreateEmbed::default()
+                .title(format!("{} ({}/{})", title, page + 1, pages))
+                .description(desc)
+                .colour(serenity::Colour::BLURPLE)
+        })
+        .collect()
+}
@@ -0,0 +1,463 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateEmbed, CreateMessage, FullEvent, GuildId, Message,
+    ReactionType, UserId,
+};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::cache::{Prunable, TtlCache};
+use crate::features::{Feature, FeatureMatrix};
+use crate::paginator::{PageSource, Paginator};
+use crate::templates::{Template, TemplateContext};
+use crate::Error;
+
+/// Id the auto-responder is registered under as a ``PageSource``
+pub const PAGE_SOURCE: &str = "autoresponder";
+
+/// Rules shown per page of ``autoresponder_list``
+const RULES_PER_PAGE: usize = 10;
+
+/// How a trigger is matched against a message, matching is case-insensitive
+#[derive(
+    poise::ChoiceParameter, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+pub enum TriggerKind {
+    /// The whole message equals the trigger
+    Exact,
+    /// The message contains the trigger
+    Contains,
+    /// The trigger is a regular expression
+    Regex,
+    /// The whole message matches the trigger, where ``*`` is any text and ``?`` any character
+    Wildcard,
+}
+
+/// What a rule does when triggered
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AutoResponse {
+    /// Replies with a ``templates::Template``, such as ``Hi {user.mention}, welcome to {guild.name}``
+    Message(String),
+    /// Reacts with an emoji
+    Reaction(String),
+}
+
+/// A single auto-responder rule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoResponderRule {
+    pub id: String,
+    pub guild_id: GuildId,
+    pub kind: TriggerKind,
+    pub trigger: String,
+    pub response: AutoResponse,
+    /// Channels the rule applies in, empty for every channel
+    pub channels: Vec<ChannelId>,
+    /// Minimum time between two responses of this rule in the same channel
+    pub cooldown: Duration,
+    pub created_by: UserId,
+}
+
+/// Storage backend for auto-responder rules
+pub trait AutoResponderStore: Send + Sync {
+    /// Lists the rules of a guild, in the order they are checked
+    fn rules<'a>(
+        &'a self,
+        guild_id: GuildId,
+    ) -> BoxFuture<'a, Result<Vec<AutoResponderRule>, Error>>;
+
+    /// Adds or replaces a rule
+    fn save<'a>(&'a self, rule: &'a AutoResponderRule) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Deletes a rule, returning whether it existed
+    fn delete<'a>(&'a self, guild_id: GuildId, id: &'a str) -> BoxFuture<'a, Result<bool, Error>>;
+}
+
+struct _CompiledRule {
+    rule: AutoResponderRule,
+    matcher: _Matcher,
+}
+
+enum _Matcher {
+    Exact(String),
+    Contains(String),
+    Regex(Regex),
+}
+
+impl _Matcher {
+    fn new(kind: TriggerKind, trigger: &str) -> Result<Self, Error> {
+        let regex = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .size_limit(1 << 16)
+                .build()
+                .map_err(|e| format!("Invalid trigger: {}", e))
+        };
+
+        Ok(match kind {
+            // Messages are trimmed before comparing, so the trigger must be too
+            TriggerKind::Exact => _Matcher::Exact(trigger.trim().to_lowercase()),
+            TriggerKind::Contains => _Matcher::Contains(trigger.to_lowercase()),
+            TriggerKind::Regex => _Matcher::Regex(regex(trigger)?),
+            TriggerKind::Wildcard => {
+                let pattern = regex::escape(trigger)
+                    .replace("\\*", ".*")
+                    .replace("\\?", ".");
+
+                _Matcher::Regex(regex(&format!("^{}$", pattern))?)
+            }
+        })
+    }
+
+    fn matches(&self, content: &str, lowercase: &str) -> bool {
+        match self {
+            _Matcher::Exact(trigger) => lowercase.trim() == trigger,
+            _Matcher::Contains(trigger) => lowercase.contains(trigger.as_str()),
+            _Matcher::Regex(regex) => regex.is_match(content),
+        }
+    }
+}
+
+/// Fills in the placeholders of a response template
+///
+/// Templates saved before placeholders were validated may not parse, those are sent as-is
+pub fn render_template(template: &str, msg: &Message, guild_name: Option<&str>) -> String {
+    match Template::parse(template) {
+        Ok(parsed) => parsed.render(&TemplateContext::from_message(msg, guild_name)),
+        Err(_) => template.to_string(),
+    }
+}
+
+/// Parses the emoji of a reaction rule, a unicode emoji or a custom emoji such as ``<:name:id>``
+fn _parse_reaction(emoji: &str) -> Result<ReactionType, Error> {
+    let emoji = emoji.trim();
+
+    if emoji.is_empty() || emoji.chars().any(char::is_whitespace) {
+        return Err(format!("Invalid reaction {}", emoji).into());
+    }
+
+    emoji
+        .parse::<ReactionType>()
+        .map_err(|_| format!("Invalid reaction {}", emoji).into())
+}
+
+/// Per-guild rules that reply to or react on matching messages
+///
+/// Compiled rules are cached per guild for an hour. This is cheap to clone
+#[derive(Clone)]
+pub struct AutoResponder {
+    store: Arc<dyn AutoResponderStore>,
+    /// Maximum number of rules per guild
+    pub max_rules: usize,
+    cache: TtlCache<GuildId, Arc<Vec<_CompiledRule>>>,
+    cooldowns: Arc<Mutex<HashMap<(String, ChannelId), Instant>>>,
+    /// Rules do not fire in guilds that disabled ``Feature::AutoResponder``, if set
+    pub features: Option<FeatureMatrix>,
+}
+
+impl AutoResponder {
+    pub fn new(store: Arc<dyn AutoResponderStore>, max_rules: usize) -> Self {
+        Self {
+            store,
+            max_rules,
+            cache: TtlCache::new("autoresponder", Duration::from_secs(60 * 60), 10_000),
+            cooldowns: Arc::new(Mutex::new(HashMap::new())),
+            features: None,
+        }
+    }
+
+    async fn _rules(&self, guild_id: GuildId) -> Result<Arc<Vec<_CompiledRule>>, Error> {
+        if let Some(rules) = self.cache.get(&guild_id).await {
+            return Ok(rules);
+        }
+
+        let rules = self
+            .store
+            .rules(guild_id)
+            .await?
+            .into_iter()
+            .filter_map(|rule| match _Matcher::new(rule.kind, &rule.trigger) {
+                Ok(matcher) => Some(_CompiledRule { rule, matcher }),
+                Err(e) => {
+                    log::warn!("Skipping auto-responder rule {}: {}", rule.id, e);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let rules = Arc::new(rules);
+        self.cache.insert(guild_id, rules.clone()).await;
+
+        Ok(rules)
+    }
+
+    /// Returns the rules of a guild
+    pub async fn rules(&self, guild_id: GuildId) -> Result<Vec<AutoResponderRule>, Error> {
+        Ok(self
+            ._rules(guild_id)
+            .await?
+            .iter()
+            .map(|r| r.rule.clone())
+            .collect())
+    }
+
+    /// Validates and adds a rule, failing if the guild is at the rule limit
+    pub async fn add(&self, rule: AutoResponderRule) -> Result<(), Error> {
+        _Matcher::new(rule.kind, &rule.trigger)?;
+
+        if let AutoResponse::Reaction(emoji) = &rule.response {
+            _parse_reaction(emoji)?;
+        }
+
+        if self._rules(rule.guild_id).await?.len() >= self.max_rules {
+            return Err(
+                format!("Servers can have at most {} auto-responses", self.max_rules).into(),
+            );
+        }
+
+        self.store.save(&rule).await?;
+        self.cache.remove(&rule.guild_id).await;
+
+        Ok(())
+    }
+
+    /// Removes a rule, returning whether it existed
+    pub async fn remove(&self, guild_id: GuildId, id: &str) -> Result<bool, Error> {
+        let removed = self.store.delete(guild_id, id).await?;
+        self.cache.remove(&guild_id).await;
+
+        Ok(removed)
+    }
+
+    /// Returns the compiled rule cache, for example to add it to a ``MemoryGovernor``
+    pub fn cache(&self) -> Arc<dyn Prunable> {
+        Arc::new(self.cache.clone())
+    }
+
+    /// Responds to messages matching a rule, this should be called from your bots event handler
+    ///
+    /// Only the first matching rule that is not on cooldown responds
+    pub async fn handle_event(
+        &self,
+        ctx: &serenity::Context,
+        event: &FullEvent,
+    ) -> Result<(), Error> {
+        if !crate::features::allows(self.features.as_ref(), event, Feature::AutoResponder).await {
+            return Ok(());
+        }
+
+        let FullEvent::Message { new_message: msg } = event else {
+            return Ok(());
+        };
+
+        let Some(guild_id) = msg.guild_id else {
+            return Ok(());
+        };
+
+        if msg.author.bot() || msg.content.is_empty() {
+            return Ok(());
+        }
+
+        let rules = self._rules(guild_id).await?;
+        let lowercase = msg.content.to_lowercase();
+
+        for compiled in rules.iter() {
+            let rule = &compiled.rule;
+
+            if !rule.channels.is_empty() && !rule.channels.contains(&msg.channel_id) {
+                continue;
+            }
+
+            if !compiled.matcher.matches(&msg.content, &lowercase) {
+                continue;
+            }
+
+            {
+                let mut cooldowns = self.cooldowns.lock().await;
+                let key = (rule.id.clone(), msg.channel_id);
+
+                if cooldowns
+                    .get(&key)
+                    .is_some_and(|last| last.elapsed() < rule.cooldown)
+                {
+                    continue;
+                }
+
+                cooldowns.insert(key, Instant::now());
+            }
+
+            match &rule.response {
+                AutoResponse::Message(template) => {
+                    let guild_name = ctx.cache.guild(guild_id).map(|g| g.name.to_string());
+
+                    crate::send::send_message(
+                        &ctx.http,
+                        msg.channel_id,
+                        CreateMessage::new()
+                            .content(render_template(template, msg, guild_name.as_deref()))
+                            .reference_message(msg),
+                    )
+                    .await?;
+                }
+                AutoResponse::Reaction(emoji) => {
+                    msg.react(&ctx.http, _parse_reaction(emoji)?).await?;
+                }
+            }
+
+            break;
+        }
+
+        Ok(())
+    }
+}
+
+impl PageSource for AutoResponder {
+    fn page_count<'a>(&'a self, args: &'a str) -> BoxFuture<'a, Result<usize, Error>> {
+        Box::pin(async move {
+            let guild_id = args.parse::<GuildId>()?;
+            let rules = self._rules(guild_id).await?;
+
+            Ok(rules.len().div_ceil(RULES_PER_PAGE).max(1))
+        })
+    }
+
+    fn render<'a>(
+        &'a self,
+        args: &'a str,
+        page: usize,
+    ) -> BoxFuture<'a, Result<CreateEmbed<'static>, Error>> {
+        Box::pin(async move {
+            let guild_id = args.parse::<GuildId>()?;
+            let rules = self._rules(guild_id).await?;
+
+            let mut desc = String::new();
+
+            for compiled in rules
+                .iter()
+                .skip(page * RULES_PER_PAGE)
+                .take(RULES_PER_PAGE)
+            {
+                let rule = &compiled.rule;
+
+                let response = match &rule.response {
+                    AutoResponse::Message(template) => template.chars().take(50).collect(),
+                    AutoResponse::Reaction(emoji) => format!("react {}", emoji),
+                };
+
+                let _ = writeln!(
+                    desc,
+                    "``{}`` {:?} ``{}`` → {}",
+                    rule.id, rule.kind, rule.trigger, response
+                );
+            }
+
+            if desc.is_empty() {
+                desc.push_str("No auto-responses yet");
+            }
+
+            Ok(CreateEmbed::default()
+                .title(format!(
+                    "Auto-responses ({}/{})",
+                    rules.len(),
+                    self.max_rules
+                ))
+                .description(desc))
+        })
+    }
+}
+
+/// Trait for bot data that holds an ``AutoResponder``
+pub trait HasAutoResponder {
+    fn autoresponder(&self) -> &AutoResponder;
+}
+
+/// Adds an auto-response, can be plugged into your bots ``/autoresponder add`` command
+///
+/// Permission checks (such as Manage Server) should be set on the command itself
+#[allow(clippy::too_many_arguments)]
+pub async fn autoresponder_add<Data: HasAutoResponder + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    kind: TriggerKind,
+    trigger: String,
+    response: String,
+    react: bool,
+    channel: Option<ChannelId>,
+    cooldown_secs: Option<u64>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Auto-responses can only be managed in a server".into());
+    };
+
+    if !react {
+        if let Err(errors) = crate::templates::validate(&response) {
+            return Err(format!(
+                "Invalid response template:\n{}",
+                crate::templates::format_errors(&errors)
+            )
+            .into());
+        }
+    }
+
+    let rule = AutoResponderRule {
+        id: crate::crypto::gen_random(8),
+        guild_id,
+        kind,
+        trigger,
+        response: if react {
+            AutoResponse::Reaction(response.trim().to_string())
+        } else {
+            AutoResponse::Message(response)
+        },
+        channels: channel.into_iter().collect(),
+        cooldown: Duration::from_secs(cooldown_secs.unwrap_or(10)),
+        created_by: ctx.author().id,
+    };
+
+    let id = rule.id.clone();
+
+    let data = ctx.data();
+    data.autoresponder().add(rule).await?;
+
+    ctx.say(format!("Added auto-response ``{}``", id)).await?;
+
+    Ok(())
+}
+
+/// Removes an auto-response, can be plugged into your bots ``/autoresponder remove`` command
+pub async fn autoresponder_remove<Data: HasAutoResponder + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    id: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Auto-responses can only be managed in a server".into());
+    };
+
+    let data = ctx.data();
+
+    if data.autoresponder().remove(guild_id, &id).await? {
+        ctx.say(format!("Removed auto-response ``{}``", id)).await?;
+    } else {
+        ctx.say(format!("No auto-response with id ``{}``", id))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Lists the auto-responses of the current guild as a paginated message, can be plugged into your bots ``/autoresponder list`` command
+///
+/// The ``AutoResponder`` must be registered on the paginator under ``PAGE_SOURCE``
+pub async fn autoresponder_list<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    paginator: &Paginator,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Auto-responses can only be managed in a server".into());
+    };
+
+    paginator.send(ctx, PAGE_SOURCE, guild_id.to_string()).await
+}
@@ -0,0 +1,285 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{self as serenity, FullEvent, GuildId, Timestamp, UserId};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cache::TtlCache;
+use crate::i18n::tr;
+use crate::Error;
+
+/// What a blacklist entry applies to
+#[derive(
+    poise::ChoiceParameter, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+pub enum BlacklistKind {
+    User,
+    Guild,
+}
+
+/// A single blacklist entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BlacklistEntry {
+    pub kind: BlacklistKind,
+    /// The id of the user or guild
+    pub id: u64,
+    pub reason: String,
+    pub added_by: UserId,
+    pub added_at: Timestamp,
+}
+
+/// Storage backend for the blacklist
+pub trait BlacklistStore: Send + Sync {
+    /// Returns the entry for a user or guild, if blacklisted
+    fn get<'a>(
+        &'a self,
+        kind: BlacklistKind,
+        id: u64,
+    ) -> BoxFuture<'a, Result<Option<BlacklistEntry>, Error>>;
+
+    /// Adds or replaces an entry
+    fn add<'a>(&'a self, entry: &'a BlacklistEntry) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Removes an entry, returning whether it existed
+    fn remove<'a>(&'a self, kind: BlacklistKind, id: u64) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Lists all entries of a kind
+    fn list<'a>(&'a self, kind: BlacklistKind)
+        -> BoxFuture<'a, Result<Vec<BlacklistEntry>, Error>>;
+}
+
+/// Global user/guild blacklist with an in-memory cache in front of a ``BlacklistStore``
+///
+/// Lookups (including misses) are cached for 10 minutes, so entries added or removed by other
+/// processes take effect within that time. This is cheap to clone
+#[derive(Clone)]
+pub struct Blacklist {
+    store: Arc<dyn BlacklistStore>,
+    /// If true, blacklisted users are ignored without being told why
+    pub silent: bool,
+    cache: TtlCache<(BlacklistKind, u64), Option<BlacklistEntry>>,
+}
+
+impl Blacklist {
+    pub fn new(store: Arc<dyn BlacklistStore>, silent: bool) -> Self {
+        Self {
+            store,
+            silent,
+            cache: TtlCache::new("blacklist", Duration::from_secs(60 * 10), 100_000),
+        }
+    }
+
+    /// Returns the blacklist entry for a user or guild, if any
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get(&self, kind: BlacklistKind, id: u64) -> Result<Option<BlacklistEntry>, Error> {
+        if let Some(entry) = self.cache.get(&(kind, id)).await {
+            return Ok(entry);
+        }
+
+        let entry = self.store.get(kind, id).await?;
+
+        self.cache.insert((kind, id), entry.clone()).await;
+
+        Ok(entry)
+    }
+
+    /// Adds an entry to the blacklist
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(kind = ?entry.kind, id = entry.id)))]
+    pub async fn add(&self, entry: BlacklistEntry) -> Result<(), Error> {
+        self.store.add(&entry).await?;
+        self.cache.insert((entry.kind, entry.id), Some(entry)).await;
+
+        Ok(())
+    }
+
+    /// Removes an entry from the blacklist, returning whether it existed
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn remove(&self, kind: BlacklistKind, id: u64) -> Result<bool, Error> {
+        let removed = self.store.remove(kind, id).await?;
+        self.cache.insert((kind, id), None).await;
+
+        Ok(removed)
+    }
+
+    /// Drops all cached entries
+    pub async fn invalidate_all(&self) {
+        self.cache.clear().await;
+    }
+
+    /// Returns the lookup cache, for example to add it to a ``MemoryGovernor``
+    pub fn cache(&self) -> &TtlCache<(BlacklistKind, u64), Option<BlacklistEntry>> {
+        &self.cache
+    }
+}
+
+/// Trait for bot data that holds a ``Blacklist``
+pub trait HasBlacklist {
+    fn blacklist(&self) -> &Blacklist;
+}
+
+/// Ready-made ``command_check`` that rejects blacklisted users and guilds
+///
+/// Use as ``command_check: Some(botox::blacklist::command_check::<Data>)``
+pub fn command_check<Data: HasBlacklist + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> BoxFuture<'_, Result<bool, crate::Error>> {
+    Box::pin(async move {
+        let data = ctx.data();
+        let bl = data.blacklist().clone();
+
+        let mut entry = bl.get(BlacklistKind::User, ctx.author().id.get()).await?;
+
+        if entry.is_none() {
+            if let Some(guild_id) = ctx.guild_id() {
+                entry = bl.get(BlacklistKind::Guild, guild_id.get()).await?;
+            }
+        }
+
+        match entry {
+            None => Ok(true),
+            Some(_) if bl.silent => Ok(false),
+            Some(entry) => {
+                let key = match entry.kind {
+                    BlacklistKind::User => "blacklist.user",
+                    BlacklistKind::Guild => "blacklist.guild",
+                };
+
+                Err(tr(ctx, key, &[("reason", &entry.reason)]).into())
+            }
+        }
+    })
+}
+
+/// Leaves guilds that are blacklisted as soon as they become available
+///
+/// This should be called from your bots event handler
+pub async fn handle_event(
+    ctx: &serenity::Context,
+    event: &FullEvent,
+    bl: &Blacklist,
+) -> Result<(), Error> {
+    if let FullEvent::GuildCreate { guild, .. } = event {
+        if let Some(entry) = bl.get(BlacklistKind::Guild, guild.id.get()).await? {
+            log::info!(
+                "Leaving blacklisted guild {} ({}): {}",
+                guild.name,
+                guild.id,
+                entry.reason
+            );
+
+            guild.id.leave(&ctx.http).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks whether a guild is blacklisted, useful for guilds the bot was already in
+pub async fn is_guild_blacklisted(bl: &Blacklist, guild_id: GuildId) -> Result<bool, Error> {
+    Ok(bl
+        .get(BlacklistKind::Guild, guild_id.get())
+        .await?
+        .is_some())
+}
+
+fn _kind_label<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    kind: BlacklistKind,
+) -> String {
+    match kind {
+        BlacklistKind::User => tr(ctx, "blacklist.kind_user", &[]),
+        BlacklistKind::Guild => tr(ctx, "blacklist.kind_guild", &[]),
+    }
+}
+
+/// Adds a blacklist entry, can be plugged into an owner-only ``/blacklist add`` command
+pub async fn blacklist_add<Data: HasBlacklist + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    kind: BlacklistKind,
+    id: u64,
+    reason: String,
+) -> Result<(), Error> {
+    let data = ctx.data();
+
+    data.blacklist()
+        .add(BlacklistEntry {
+            kind,
+            id,
+            reason,
+            added_by: ctx.author().id,
+            added_at: Timestamp::now(),
+        })
+        .await?;
+
+    ctx.say(tr(
+        ctx,
+        "blacklist.added",
+        &[("kind", &_kind_label(ctx, kind)), ("id", &id.to_string())],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Removes a blacklist entry, can be plugged into an owner-only ``/blacklist remove`` command
+pub async fn blacklist_remove<Data: HasBlacklist + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    kind: BlacklistKind,
+    id: u64,
+) -> Result<(), Error> {
+    let data = ctx.data();
+
+    let key = if data.blacklist().remove(kind, id).await? {
+        "blacklist.removed"
+    } else {
+        "blacklist.not_listed"
+    };
+
+    ctx.say(tr(
+        ctx,
+        key,
+        &[("kind", &_kind_label(ctx, kind)), ("id", &id.to_string())],
+    ))
+    .await?;
+
+    Ok(())
+}
+
+/// Lists all blacklist entries of a kind, can be plugged into an owner-only ``/blacklist list`` command
+pub async fn blacklist_list<Data: HasBlacklist + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    kind: BlacklistKind,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let entries = data.blacklist().store.list(kind).await?;
+
+    if entries.is_empty() {
+        ctx.say(tr(ctx, "blacklist.empty", &[])).await?;
+        return Ok(());
+    }
+
+    let mut msg = String::new();
+
+    for entry in entries {
+        let _ = writeln!(
+            msg,
+            "``{}`` - {} (by <@{}>, <t:{}:R>)",
+            entry.id,
+            entry.reason,
+            entry.added_by,
+            entry.added_at.unix_timestamp()
+        );
+    }
+
+    ctx.send(
+        CreateReply::default().attachment(serenity::CreateAttachment::bytes(
+            msg.into_bytes(),
+            "blacklist.txt",
+        )),
+    )
+    .await?;
+
+    Ok(())
+}
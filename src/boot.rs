@@ -0,0 +1,333 @@
+use poise::serenity_prelude::{
+    self as serenity, ApplicationFlags, ApplicationId, CurrentUser, FullEvent, GatewayIntents,
+    GuildId, ShardId,
+};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch, Mutex};
+
+use crate::taskman::Task;
+use crate::Error;
+
+/// Privileged intents and the application flags that enable them (full and limited)
+const PRIVILEGED_INTENTS: &[(GatewayIntents, &str, ApplicationFlags, ApplicationFlags)] = &[
+    (
+        GatewayIntents::GUILD_MEMBERS,
+        "Server Members",
+        ApplicationFlags::GATEWAY_GUILD_MEMBERS,
+        ApplicationFlags::GATEWAY_GUILD_MEMBERS_LIMITED,
+    ),
+    (
+        GatewayIntents::GUILD_PRESENCES,
+        "Presence",
+        ApplicationFlags::GATEWAY_PRESENCE,
+        ApplicationFlags::GATEWAY_PRESENCE_LIMITED,
+    ),
+    (
+        GatewayIntents::MESSAGE_CONTENT,
+        "Message Content",
+        ApplicationFlags::GATEWAY_MESSAGE_CONTENT,
+        ApplicationFlags::GATEWAY_MESSAGE_CONTENT_LIMITED,
+    ),
+];
+
+/// The result of ``preflight``
+#[derive(Debug, Clone)]
+pub struct PreflightReport {
+    /// The bot user the token belongs to
+    pub user: CurrentUser,
+    pub application_id: ApplicationId,
+    /// Privileged intents that were requested but are not enabled in the developer portal,
+    /// connecting with these will fail with a disallowed intents close code
+    pub missing_intents: Vec<&'static str>,
+    /// Privileged intents that are only enabled in limited mode (bots in under 100 servers)
+    pub limited_intents: Vec<&'static str>,
+    /// Problems that will not stop the bot from connecting
+    pub warnings: Vec<String>,
+}
+
+impl PreflightReport {
+    /// Returns true if the shards can connect with the requested intents
+    pub fn is_ok(&self) -> bool {
+        self.missing_intents.is_empty()
+    }
+
+    /// Logs the report, errors for missing intents and warnings for everything else
+    pub fn log(&self) {
+        log::info!(
+            "Preflight: logged in as {} ({}), application {}",
+            self.user.name,
+            self.user.id,
+            self.application_id
+        );
+
+        for intent in &self.missing_intents {
+            log::error!(
+                "Preflight: the {} intent is requested but not enabled in the developer portal",
+                intent
+            );
+        }
+
+        for intent in &self.limited_intents {
+            log::warn!(
+                "Preflight: the {} intent is only enabled in limited mode, it must be approved once the bot is in 100 servers",
+                intent
+            );
+        }
+
+        for warning in &self.warnings {
+            log::warn!("Preflight: {}", warning);
+        }
+    }
+}
+
+/// Validates a bot token and the requested intents before shards connect
+///
+/// Checks the token against ``/users/@me``, compares privileged intents with what the application
+/// has enabled and warns about requesting guild messages without ``MESSAGE_CONTENT`` (prefix
+/// commands then only work in DMs and when the bot is mentioned)
+pub async fn preflight(token: &str, intents: GatewayIntents) -> Result<PreflightReport, Error> {
+    let http = serenity::Http::new(token);
+
+    let user = match http.get_current_user().await {
+        Ok(user) => user,
+        Err(serenity::Error::Http(e)) if e.status_code().map(|s| s.as_u16()) == Some(401) => {
+            return Err("Invalid bot token".into());
+        }
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut warnings = Vec::new();
+
+    if !user.bot() {
+        warnings.push("The token does not belong to a bot account".to_string());
+    }
+
+    let app = http.get_current_application_info().await?;
+    let flags = app.flags.unwrap_or_default();
+
+    let mut missing_intents = Vec::new();
+    let mut limited_intents = Vec::new();
+
+    for (intent, name, full, limited) in PRIVILEGED_INTENTS {
+        if !intents.contains(*intent) {
+            continue;
+        }
+
+        if flags.contains(*full) {
+            continue;
+        }
+
+        if flags.contains(*limited) {
+            limited_intents.push(*name);
+        } else {
+            missing_intents.push(*name);
+        }
+    }
+
+    if intents.contains(GatewayIntents::GUILD_MESSAGES)
+        && !intents.contains(GatewayIntents::MESSAGE_CONTENT)
+    {
+        warnings.push(
+            "GUILD_MESSAGES is requested without MESSAGE_CONTENT, prefix commands will only work in DMs and when the bot is mentioned".to_string(),
+        );
+    }
+
+    Ok(PreflightReport {
+        user,
+        application_id: app.id,
+        missing_intents,
+        limited_intents,
+        warnings,
+    })
+}
+
+/// Cache warm-up progress, see ``ReadyTracker::subscribe``
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReadyEvent {
+    Progress {
+        cached: usize,
+        expected: usize,
+        shards_ready: usize,
+        shards_total: usize,
+    },
+    /// Every shard is ready and every guild has been cached, sent once
+    FullyReady,
+}
+
+#[derive(Default)]
+struct _ReadyState {
+    /// Number of shards launched by this process, see ``ReadyTracker::with_shards``
+    launched: Option<usize>,
+    /// Total shard count from Ready, used when ``launched`` is not set
+    shards_total: usize,
+    shards: HashMap<ShardId, HashSet<GuildId>>,
+    cached: HashSet<GuildId>,
+    /// Last reported progress, in tenths
+    reported: usize,
+}
+
+impl _ReadyState {
+    fn expected(&self) -> usize {
+        self.shards.values().map(|g| g.len()).sum()
+    }
+
+    /// Number of expected guilds that have been cached, guilds joined after Ready are not counted
+    fn cached(&self) -> usize {
+        self.shards
+            .values()
+            .flatten()
+            .filter(|g| self.cached.contains(g))
+            .count()
+    }
+
+    fn shards_total(&self) -> usize {
+        self.launched.unwrap_or(self.shards_total)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.shards_total() > 0
+            && self.shards.len() >= self.shards_total()
+            && self.cached() >= self.expected()
+    }
+}
+
+/// Tracks Ready and GuildCreate events across shards to report cache warm-up progress
+///
+/// Tasks that need a warm cache (such as digests) can be wrapped with ``ReadyTracker::gate``.
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct ReadyTracker {
+    state: Arc<Mutex<_ReadyState>>,
+    ready: watch::Sender<bool>,
+    events: broadcast::Sender<ReadyEvent>,
+}
+
+impl Default for ReadyTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ReadyTracker {
+    /// Expects every shard of the bot to be launched by this process, use ``with_shards`` when
+    /// shards are split across processes
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(_ReadyState::default())),
+            ready: watch::channel(false).0,
+            events: broadcast::channel(64).0,
+        }
+    }
+
+    /// Expects only ``shards`` shards to become ready, such as the size of a clusters shard range
+    pub fn with_shards(shards: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(_ReadyState {
+                launched: Some(shards),
+                ..Default::default()
+            })),
+            ready: watch::channel(false).0,
+            events: broadcast::channel(64).0,
+        }
+    }
+
+    /// Returns a receiver of progress updates and the final ``FullyReady``
+    pub fn subscribe(&self) -> broadcast::Receiver<ReadyEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns true once every guild has been cached
+    pub fn is_ready(&self) -> bool {
+        *self.ready.borrow()
+    }
+
+    /// Waits until every guild has been cached
+    pub async fn wait(&self) {
+        let mut rx = self.ready.subscribe();
+        let _ = rx.wait_for(|ready| *ready).await;
+    }
+
+    /// Returns the number of cached and expected guilds
+    pub async fn progress(&self) -> (usize, usize) {
+        let state = self.state.lock().await;
+        (state.cached(), state.expected())
+    }
+
+    /// Records Ready and GuildCreate events, this should be called from your bots event handler
+    pub async fn handle_event(&self, ctx: &serenity::Context, event: &FullEvent) {
+        if self.is_ready() {
+            return;
+        }
+
+        let mut state = self.state.lock().await;
+
+        match event {
+            FullEvent::Ready { data_about_bot } => {
+                state.shards_total = data_about_bot.shard.map_or(1, |s| s.total.get() as usize);
+
+                state.shards.insert(
+                    ctx.shard_id,
+                    data_about_bot.guilds.iter().map(|g| g.id).collect(),
+                );
+            }
+            FullEvent::GuildCreate { guild, .. } => {
+                state.cached.insert(guild.id);
+            }
+            // Guilds in an outage or left during startup would otherwise never arrive
+            FullEvent::GuildDelete { incomplete, .. } => {
+                for guilds in state.shards.values_mut() {
+                    guilds.remove(&incomplete.id);
+                }
+            }
+            _ => return,
+        }
+
+        let (cached, expected) = (state.cached(), state.expected());
+        let tenths = (cached * 10).checked_div(expected).unwrap_or(10);
+
+        // Reported every 10% and whenever a shard becomes ready
+        if tenths > state.reported || matches!(event, FullEvent::Ready { .. }) {
+            state.reported = tenths;
+
+            log::info!(
+                "{}/{} guilds cached ({}/{} shards ready)",
+                cached,
+                expected,
+                state.shards.len(),
+                state.shards_total()
+            );
+
+            let _ = self.events.send(ReadyEvent::Progress {
+                cached,
+                expected,
+                shards_ready: state.shards.len(),
+                shards_total: state.shards_total(),
+            });
+        }
+
+        if state.is_complete() {
+            log::info!("Cache warm-up complete, {} guilds cached", cached);
+
+            self.ready.send_replace(true);
+            let _ = self.events.send(ReadyEvent::FullyReady);
+        }
+    }
+
+    /// Wraps a task so its runs are skipped until every guild has been cached
+    pub fn gate(&self, mut task: Task) -> Task {
+        let tracker = self.clone();
+        let run = task.run;
+
+        task.run = Box::new(move |ctx| {
+            if !tracker.is_ready() {
+                log::debug!("Skipping a task run, the cache is still warming up");
+                return Box::pin(async { Ok(()) });
+            }
+
+            run(ctx)
+        });
+
+        task
+    }
+}
@@ -0,0 +1,278 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{self as serenity, FullEvent, GatewayIntents};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::shards::ShardMonitor;
+use crate::taskman::Task;
+use crate::Error;
+
+/// A hook run on every gateway event, in the order hooks were added
+pub type EventHook = Box<
+    dyn Send
+        + Sync
+        + for<'a> Fn(&'a serenity::Context, &'a FullEvent) -> BoxFuture<'a, Result<(), Error>>,
+>;
+
+/// Default error handler, replies to failed commands with their (redacted) error and defers
+/// everything else to poise's builtin handler
+pub fn on_error<Data: Send + Sync + 'static>(
+    error: poise::FrameworkError<'_, Data, Error>,
+) -> BoxFuture<'_, ()> {
+    Box::pin(async move {
+        match error {
+            poise::FrameworkError::Command { error, ctx, .. } => {
+                let msg = crate::secrets::redact_error(&*error);
+
+                log::error!("Error in command {}: {}", ctx.command().qualified_name, msg);
+
+                if let Err(e) = ctx.say(msg).await {
+                    log::error!("Failed to send error message: {}", e);
+                }
+            }
+            error => {
+                if let Err(e) = poise::builtins::on_error(error).await {
+                    log::error!("Error while handling error: {}", e);
+                }
+            }
+        }
+    })
+}
+
+struct _Handler {
+    tasks: Mutex<Option<Vec<Task>>>,
+    shards: Option<ShardMonitor>,
+    hooks: Vec<EventHook>,
+}
+
+#[serenity::async_trait]
+impl serenity::EventHandler for _Handler {
+    async fn dispatch(&self, ctx: &serenity::Context, event: &FullEvent) {
+        if let Some(shards) = &self.shards {
+            shards.handle_event(ctx, event).await;
+        }
+
+        if let FullEvent::Ready { .. } = event {
+            // Tasks are started once, on the first shard to become ready
+            if let Some(tasks) = self.tasks.lock().await.take() {
+                let ctx = ctx.clone();
+                tokio::spawn(async move {
+                    crate::taskman::start_all_tasks(tasks, ctx).await;
+                });
+            }
+        }
+
+        for hook in &self.hooks {
+            if let Err(e) = hook(ctx, event).await {
+                log::error!("Event hook failed: {}", crate::secrets::redact_error(&*e));
+            }
+        }
+    }
+}
+
+/// Builds a ``Bot``, wiring poise, serenity and the crates subsystems together
+pub struct BotBuilder<Data: Send + Sync + 'static> {
+    name: Option<String>,
+    token: Option<String>,
+    intents: GatewayIntents,
+    data: Option<Arc<Data>>,
+    options: poise::FrameworkOptions<Data, Error>,
+    commands: Vec<poise::Command<Data, Error>>,
+    prefix: Option<String>,
+    tasks: Vec<Task>,
+    hooks: Vec<EventHook>,
+    shards: Option<ShardMonitor>,
+    preflight: bool,
+}
+
+impl<Data: Send + Sync + 'static> BotBuilder<Data> {
+    /// Names the bot, its tasks and logs are labelled with this when running several bots in one process
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    pub fn token(mut self, token: impl Into<String>) -> Self {
+        self.token = Some(token.into());
+        self
+    }
+
+    /// Sets the gateway intents, defaults to ``GatewayIntents::non_privileged()``
+    pub fn intents(mut self, intents: GatewayIntents) -> Self {
+        self.intents = intents;
+        self
+    }
+
+    /// Sets the bot data, available through ``ctx.data()``
+    pub fn data(mut self, data: Data) -> Self {
+        self.data = Some(Arc::new(data));
+        self
+    }
+
+    /// Sets bot data shared with other bots in the same process, see ``multibot::Supervisor``
+    pub fn shared_data(mut self, data: Arc<Data>) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    /// Replaces the base framework options, commands and the prefix set on the builder take precedence
+    ///
+    /// This is where poise hooks such as ``pre_command`` (for ``UsageTracker::record``) go
+    pub fn options(mut self, options: poise::FrameworkOptions<Data, Error>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Adds commands, such as your bots help command
+    pub fn commands(
+        mut self,
+        commands: impl IntoIterator<Item = poise::Command<Data, Error>>,
+    ) -> Self {
+        self.commands.extend(commands);
+        self
+    }
+
+    /// Sets the static prefix for prefix commands
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = Some(prefix.into());
+        self
+    }
+
+    /// Adds a background task, tasks are started once the first shard is ready
+    pub fn task(mut self, task: Task) -> Self {
+        self.tasks.push(task);
+        self
+    }
+
+    /// Adds a hook run on every gateway event
+    pub fn on_event(
+        mut self,
+        hook: impl Send
+            + Sync
+            + 'static
+            + for<'a> Fn(&'a serenity::Context, &'a FullEvent) -> BoxFuture<'a, Result<(), Error>>,
+    ) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Feeds every event to a ``ShardMonitor``
+    pub fn shard_monitor(mut self, shards: ShardMonitor) -> Self {
+        self.shards = Some(shards);
+        self
+    }
+
+    /// Runs ``boot::preflight`` before connecting, failing the build if privileged intents are missing
+    pub fn preflight(mut self, preflight: bool) -> Self {
+        self.preflight = preflight;
+        self
+    }
+
+    pub async fn build(self) -> Result<Bot, Error> {
+        let token = self.token.ok_or("A bot token is required")?;
+        let data = self.data.ok_or("Bot data is required")?;
+
+        if self.preflight {
+            let report = crate::boot::preflight(&token, self.intents).await?;
+            report.log();
+
+            if !report.is_ok() {
+                return Err(format!(
+                    "Preflight failed, enable these intents in the developer portal: {}",
+                    report.missing_intents.join(", ")
+                )
+                .into());
+            }
+        }
+
+        let mut options = self.options;
+        options.commands.extend(self.commands);
+
+        if let Some(prefix) = self.prefix {
+            options.prefix_options.prefix = Some(prefix.into());
+        }
+
+        let mut tasks = self.tasks;
+
+        if let Some(name) = &self.name {
+            for task in &mut tasks {
+                // Task names are static, this runs once per task for the lifetime of the process
+                task.name = Box::leak(format!("{}/{}", name, task.name).into_boxed_str());
+            }
+        }
+
+        let handler = _Handler {
+            tasks: Mutex::new(Some(tasks)),
+            shards: self.shards,
+            hooks: self.hooks,
+        };
+
+        let client = serenity::ClientBuilder::new(&token, self.intents)
+            .framework(poise::Framework::new(options))
+            .event_handler(handler)
+            .data(data as _)
+            .await?;
+
+        Ok(Bot {
+            name: self.name,
+            client,
+        })
+    }
+}
+
+/// A ready to run bot, see ``Bot::builder``
+pub struct Bot {
+    name: Option<String>,
+    client: serenity::Client,
+}
+
+impl Bot {
+    /// Returns a builder with the crates default error handler and non-privileged intents
+    pub fn builder<Data: Send + Sync + 'static>() -> BotBuilder<Data> {
+        BotBuilder {
+            name: None,
+            token: None,
+            intents: GatewayIntents::non_privileged(),
+            data: None,
+            options: poise::FrameworkOptions {
+                on_error: on_error::<Data>,
+                ..Default::default()
+            },
+            commands: Vec::new(),
+            prefix: None,
+            tasks: Vec::new(),
+            hooks: Vec::new(),
+            shards: None,
+            preflight: true,
+        }
+    }
+
+    /// Returns the name set with ``BotBuilder::name``
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    /// Returns the underlying serenity client
+    pub fn client(&mut self) -> &mut serenity::Client {
+        &mut self.client
+    }
+
+    /// Connects every shard, shutting them down cleanly on Ctrl+C
+    pub async fn run(mut self) -> Result<(), Error> {
+        let shard_manager = self.client.shard_manager.clone();
+
+        tokio::spawn(async move {
+            if let Err(e) = tokio::signal::ctrl_c().await {
+                log::error!("Failed to listen for Ctrl+C: {}", e);
+                return;
+            }
+
+            log::info!("Shutting down all shards");
+            shard_manager.shutdown_all().await;
+        });
+
+        self.client.start_autosharded().await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{self as serenity, ChannelId, CreateEmbed, GuildId, Permissions};
+use poise::CreateReply;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::permissions::{PermissionExplanation, PermissionInputs};
+
+/// Returns the display name of a single permission flag
+pub fn permission_name(permission: Permissions) -> &'static str {
+    permission
+        .get_permission_names()
+        .first()
+        .copied()
+        .unwrap_or("Unknown")
+}
+
+/// Bot permissions commands need, by qualified command name
+///
+/// Commands not in the registry fall back to their ``required_bot_permissions``
+#[derive(Debug, Clone, Default)]
+pub struct BotPermissions {
+    required: HashMap<String, Permissions>,
+}
+
+impl BotPermissions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Declares the permissions a command needs
+    pub fn require(mut self, command: impl Into<String>, permissions: Permissions) -> Self {
+        self.required.insert(command.into(), permissions);
+        self
+    }
+
+    /// Returns the permissions a command needs, from the registry or the command itself
+    pub fn required<Data>(&self, command: &poise::Command<Data, crate::Error>) -> Permissions {
+        self.required
+            .get(&*command.qualified_name)
+            .copied()
+            .unwrap_or(command.required_bot_permissions)
+    }
+}
+
+/// Explains which of ``required`` the bot is missing in a channel, returns None if the guild
+/// (or the bots member) is not cached
+pub fn missing(
+    cache: &serenity::Cache,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+    required: Permissions,
+) -> Option<Vec<PermissionExplanation>> {
+    let bot_id = cache.current_user().id;
+    let roles = cache.guild(guild_id)?.members.get(&bot_id)?.roles.to_vec();
+
+    let inputs = PermissionInputs::from_cache(cache, guild_id, bot_id, &roles, Some(channel_id))?;
+    let view = inputs.explain(Permissions::VIEW_CHANNEL, "View Channel");
+
+    Some(
+        required
+            .iter()
+            .map(|permission| {
+                let mut explanation = inputs.explain(permission, permission_name(permission));
+
+                if !view.allowed && permission != Permissions::VIEW_CHANNEL && explanation.allowed {
+                    explanation.allowed = false;
+                    explanation.reason = "Cannot view the channel".to_string();
+                }
+
+                explanation
+            })
+            .filter(|e| !e.allowed)
+            .collect(),
+    )
+}
+
+/// Trait for bot data that holds ``BotPermissions``
+pub trait HasBotPermissions {
+    fn bot_permissions(&self) -> &BotPermissions;
+}
+
+/// Ready-made ``command_check`` that stops commands the bot lacks permissions for in the current
+/// channel, replying with every missing permission and why it is missing
+///
+/// Use as ``command_check: Some(botox::botperms::command_check::<Data>)``. Guilds that are not
+/// cached are let through
+pub fn command_check<Data: HasBotPermissions + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> BoxFuture<'_, Result<bool, crate::Error>> {
+    Box::pin(async move {
+        let Some(guild_id) = ctx.guild_id() else {
+            return Ok(true);
+        };
+
+        let data = ctx.data();
+        let required = data.bot_permissions().required(ctx.command());
+
+        if required.is_empty() {
+            return Ok(true);
+        }
+
+        let Some(missing) = missing(
+            &ctx.serenity_context().cache,
+            guild_id,
+            ctx.channel_id(),
+            required,
+        ) else {
+            return Ok(true);
+        };
+
+        if missing.is_empty() {
+            return Ok(true);
+        }
+
+        let mut text = String::new();
+
+        for explanation in &missing {
+            let _ = writeln!(text, "**{}** - {}", explanation.name, explanation.reason);
+        }
+
+        let title = format!(
+            "I need {} more permission(s) in this channel for ``{}``",
+            missing.len(),
+            ctx.command().qualified_name
+        );
+
+        // Without Embed Links the embed would not show, so fall back to plain text
+        let can_embed = !missing
+            .iter()
+            .any(|e| e.permission == Permissions::EMBED_LINKS);
+
+        let reply = if can_embed {
+            CreateReply::default().embed(
+                CreateEmbed::default()
+                    .title(title)
+                    .description(text)
+                    .colour(serenity::Colour::RED),
+            )
+        } else {
+            CreateReply::default().content(format!("{}\n{}", title, text))
+        };
+
+        ctx.send(reply.ephemeral(true)).await?;
+
+        Ok(false)
+    })
+}
+
+/// Lists the permissions a command needs, one per line, for help pages
+pub fn describe(required: Permissions) -> Option<String> {
+    if required.is_empty() {
+        return None;
+    }
+
+    Some(
+        required
+            .iter()
+            .map(permission_name)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    )
+}
@@ -0,0 +1,319 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateAttachment, EditWebhookMessage, ExecuteWebhook, FullEvent,
+    Message, MessageId,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::{Mutex, RwLock};
+
+use crate::features::{Feature, FeatureMatrix};
+use crate::mimic::Mimic;
+use crate::sanitize::{clean, MentionPolicy, SanitizeLevel};
+use crate::Error;
+
+/// Maximum size of an attachment that is re-uploaded, larger attachments are linked instead
+pub const MAX_MIRRORED_ATTACHMENT_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Configuration of a single bridge
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BridgeConfig {
+    pub id: String,
+    /// Channels whose messages are mirrored to each other, possibly across guilds
+    pub channels: Vec<ChannelId>,
+    /// Sanitizer level applied to mirrored content
+    pub sanitize: SanitizeLevel,
+    /// Whether attachments are re-uploaded (up to ``MAX_MIRRORED_ATTACHMENT_SIZE``) rather than linked
+    pub mirror_attachments: bool,
+    /// Whether messages from bots are ignored
+    pub ignore_bots: bool,
+}
+
+/// Storage backend for bridge configuration
+pub trait BridgeStore: Send + Sync {
+    /// Lists all bridges
+    fn list<'a>(&'a self) -> BoxFuture<'a, Result<Vec<BridgeConfig>, Error>>;
+
+    /// Saves a bridge, creating it if it does not already exist
+    fn save<'a>(&'a self, config: &'a BridgeConfig) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Deletes a bridge, returning whether it existed
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<bool, Error>>;
+}
+
+#[derive(Default)]
+struct MirrorMap {
+    /// Original message -> mirrored copies
+    copies: HashMap<MessageId, Vec<(ChannelId, MessageId)>>,
+    order: VecDeque<MessageId>,
+}
+
+/// Mirrors messages, edits and deletes between channels using managed webhooks
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct Bridge {
+    store: Arc<dyn BridgeStore>,
+    mimic: Mimic,
+    client: reqwest::Client,
+    configs: Arc<RwLock<Option<Vec<BridgeConfig>>>>,
+    mirrors: Arc<Mutex<MirrorMap>>,
+    /// Number of original messages whose copies are remembered for edits and deletes
+    pub max_tracked: usize,
+    /// Messages from guilds that disabled ``Feature::Bridge`` are not mirrored, if set
+    pub features: Option<FeatureMatrix>,
+}
+
+impl Bridge {
+    pub fn new(store: Arc<dyn BridgeStore>, mimic: Mimic) -> Self {
+        Self {
+            store,
+            mimic,
+            client: reqwest::Client::new(),
+            configs: Arc::new(RwLock::new(None)),
+            mirrors: Arc::new(Mutex::new(MirrorMap::default())),
+            max_tracked: 5000,
+            features: None,
+        }
+    }
+
+    /// Returns all bridges, loading them from the store on first use
+    pub async fn configs(&self) -> Result<Vec<BridgeConfig>, Error> {
+        if let Some(configs) = self.configs.read().await.as_ref() {
+            return Ok(configs.clone());
+        }
+
+        let configs = self.store.list().await?;
+        *self.configs.write().await = Some(configs.clone());
+
+        Ok(configs)
+    }
+
+    /// Creates or updates a bridge
+    pub async fn save(&self, config: BridgeConfig) -> Result<(), Error> {
+        if config.channels.len() < 2 {
+            return Err("A bridge needs at least two channels".into());
+        }
+
+        self.store.save(&config).await?;
+        self.reload().await
+    }
+
+    /// Deletes a bridge, returning whether it existed
+    pub async fn delete(&self, id: &str) -> Result<bool, Error> {
+        let deleted = self.store.delete(id).await?;
+        self.reload().await?;
+
+        Ok(deleted)
+    }
+
+    /// Reloads all bridges from the store
+    pub async fn reload(&self) -> Result<(), Error> {
+        let configs = self.store.list().await?;
+        *self.configs.write().await = Some(configs);
+
+        Ok(())
+    }
+
+    async fn _attachments(
+        &self,
+        msg: &Message,
+        config: &BridgeConfig,
+    ) -> (Vec<CreateAttachment<'static>>, Vec<String>) {
+        let mut files = Vec::new();
+        let mut links = Vec::new();
+
+        for attachment in &msg.attachments {
+            if !config.mirror_attachments
+                || u64::from(attachment.size) > MAX_MIRRORED_ATTACHMENT_SIZE
+            {
+                links.push(attachment.url.to_string());
+                continue;
+            }
+
+            let bytes = match self.client.get(attachment.url.as_str()).send().await {
+                Ok(res) => res.bytes().await,
+                Err(e) => Err(e),
+            };
+
+            match bytes {
+                Ok(bytes) => files.push(CreateAttachment::bytes(
+                    bytes.to_vec(),
+                    attachment.filename.to_string(),
+                )),
+                Err(e) => {
+                    log::warn!("Failed to download attachment {}: {}", attachment.url, e);
+                    links.push(attachment.url.to_string());
+                }
+            }
+        }
+
+        (files, links)
+    }
+
+    fn _content(msg: &Message, config: &BridgeConfig, links: &[String]) -> String {
+        let mut content = clean(&msg.content, config.sanitize);
+
+        for link in links {
+            if !content.is_empty() {
+                content.push('\n');
+            }
+            content.push_str(link);
+        }
+
+        content
+    }
+
+    async fn _track(&self, original: MessageId, copies: Vec<(ChannelId, MessageId)>) {
+        let mut mirrors = self.mirrors.lock().await;
+
+        mirrors.copies.insert(original, copies);
+        mirrors.order.push_back(original);
+
+        while mirrors.order.len() > self.max_tracked {
+            if let Some(old) = mirrors.order.pop_front() {
+                mirrors.copies.remove(&old);
+            }
+        }
+    }
+
+    async fn _on_message(&self, http: &serenity::Http, msg: &Message) -> Result<(), Error> {
+        // Never mirror webhook messages, this also stops the bridge from mirroring its own copies
+        if msg.webhook_id.is_some() {
+            return Ok(());
+        }
+
+        let mut copies = Vec::new();
+
+        for config in self.configs().await? {
+            if !config.channels.contains(&msg.channel_id)
+                || (config.ignore_bots && msg.author.bot())
+            {
+                continue;
+            }
+
+            let (files, links) = self._attachments(msg, &config).await;
+            let content = Self::_content(msg, &config, &links);
+
+            if content.is_empty() && files.is_empty() {
+                continue;
+            }
+
+            for channel_id in config.channels.iter().filter(|c| **c != msg.channel_id) {
+                let builder = ExecuteWebhook::new()
+                    .username(msg.author.display_name())
+                    .avatar_url(msg.author.face())
+                    .content(content.as_str())
+                    .files(files.clone())
+                    .allowed_mentions(MentionPolicy::none().build());
+
+                match self.mimic.execute(http, *channel_id, builder).await {
+                    Ok(copy) => copies.push((*channel_id, copy.id)),
+                    Err(e) => log::warn!(
+                        "Bridge {} failed to mirror message to {}: {}",
+                        config.id,
+                        channel_id,
+                        e
+                    ),
+                }
+            }
+        }
+
+        if !copies.is_empty() {
+            self._track(msg.id, copies).await;
+        }
+
+        Ok(())
+    }
+
+    async fn _on_edit(&self, http: &serenity::Http, msg: &Message) -> Result<(), Error> {
+        let Some(copies) = self.mirrors.lock().await.copies.get(&msg.id).cloned() else {
+            return Ok(());
+        };
+
+        let Some(config) = self
+            .configs()
+            .await?
+            .into_iter()
+            .find(|c| c.channels.contains(&msg.channel_id))
+        else {
+            return Ok(());
+        };
+
+        let links = msg
+            .attachments
+            .iter()
+            .filter(|_| !config.mirror_attachments)
+            .map(|a| a.url.to_string())
+            .collect::<Vec<_>>();
+        let content = Self::_content(msg, &config, &links);
+
+        for (channel_id, message_id) in copies {
+            let webhook = self.mimic.webhook(http, channel_id).await?;
+
+            if let Err(e) = webhook
+                .edit_message(
+                    http,
+                    message_id,
+                    EditWebhookMessage::new()
+                        .content(content.as_str())
+                        .allowed_mentions(MentionPolicy::none().build()),
+                )
+                .await
+            {
+                log::warn!(
+                    "Bridge failed to edit mirrored message {}: {}",
+                    message_id,
+                    e
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn _on_delete(&self, http: &serenity::Http, message_id: MessageId) -> Result<(), Error> {
+        let copies = {
+            let mut mirrors = self.mirrors.lock().await;
+            mirrors.order.retain(|m| *m != message_id);
+            mirrors.copies.remove(&message_id)
+        };
+
+        for (channel_id, copy_id) in copies.unwrap_or_default() {
+            let webhook = self.mimic.webhook(http, channel_id).await?;
+
+            if let Err(e) = webhook.delete_message(http, None, copy_id).await {
+                log::warn!(
+                    "Bridge failed to delete mirrored message {}: {}",
+                    copy_id,
+                    e
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mirrors new messages, edits and deletes, this should be called from your bots event handler
+    ///
+    /// Edits are only mirrored when serenity provides the updated message, which requires the message cache
+    pub async fn handle_event(
+        &self,
+        ctx: &serenity::Context,
+        event: &FullEvent,
+    ) -> Result<(), Error> {
+        if !crate::features::allows(self.features.as_ref(), event, Feature::Bridge).await {
+            return Ok(());
+        }
+
+        match event {
+            FullEvent::Message { new_message } => self._on_message(&ctx.http, new_message).await,
+            FullEvent::MessageUpdate { new: Some(new), .. } => self._on_edit(&ctx.http, new).await,
+            FullEvent::MessageDelete {
+                deleted_message_id, ..
+            } => self._on_delete(&ctx.http, *deleted_message_id).await,
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,297 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateAttachment, CreateEmbed, CreateMessage, GuildId,
+};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::sanitize::MentionPolicy;
+use crate::templates::{EmbedTemplate, TemplateContext};
+use crate::Error;
+
+/// Progress of a broadcast, saved after every guild so an interrupted broadcast can resume
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BroadcastCheckpoint {
+    /// The id of the broadcast, such as a version number
+    pub id: String,
+    /// Guilds the announcement was already sent to
+    pub sent: Vec<GuildId>,
+    /// Guilds the announcement failed to send to, with the error
+    pub failed: Vec<(GuildId, String)>,
+}
+
+impl BroadcastCheckpoint {
+    /// Returns true if a guild was already handled, successfully or not
+    pub fn is_done(&self, guild_id: GuildId) -> bool {
+        self.sent.contains(&guild_id) || self.failed.iter().any(|(g, _)| *g == guild_id)
+    }
+}
+
+/// Storage backend for announcement channels and broadcast checkpoints
+pub trait BroadcastStore: Send + Sync {
+    /// Returns the channel announcements are sent to in a guild, if configured
+    fn channel<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<Option<ChannelId>, Error>>;
+
+    /// Sets or clears the announcement channel of a guild
+    fn set_channel<'a>(
+        &'a self,
+        guild_id: GuildId,
+        channel_id: Option<ChannelId>,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    fn checkpoint<'a>(
+        &'a self,
+        id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<BroadcastCheckpoint>, Error>>;
+
+    fn save_checkpoint<'a>(
+        &'a self,
+        checkpoint: &'a BroadcastCheckpoint,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Decides which guilds receive a broadcast
+pub type GuildFilter = Arc<dyn Fn(GuildId) -> bool + Send + Sync>;
+
+/// The outcome of a broadcast
+#[derive(Debug, Clone, Default)]
+pub struct BroadcastReport {
+    pub sent: usize,
+    /// Guilds skipped because they were already handled by an earlier, interrupted run
+    pub resumed: usize,
+    /// Guilds without an announcement channel
+    pub unconfigured: usize,
+    pub failed: Vec<(GuildId, String)>,
+}
+
+impl BroadcastReport {
+    /// Renders the report as plain text, one line per failed guild
+    pub fn to_text(&self) -> String {
+        let mut text = format!(
+            "Sent: {}\nAlready sent (resumed): {}\nNo announcement channel: {}\nFailed: {}\n",
+            self.sent,
+            self.resumed,
+            self.unconfigured,
+            self.failed.len()
+        );
+
+        for (guild_id, error) in &self.failed {
+            let _ = writeln!(text, "{} - {}", guild_id, error);
+        }
+
+        text
+    }
+}
+
+fn _matches(filter: Option<&GuildFilter>, guild_id: GuildId) -> bool {
+    match filter {
+        Some(filter) => filter(guild_id),
+        None => true,
+    }
+}
+
+/// Sends announcements (such as changelogs) to the configured channel of every guild
+pub struct Broadcaster {
+    store: Arc<dyn BroadcastStore>,
+    /// Delay between sends, defaults to 2 seconds
+    pub pace: Duration,
+}
+
+impl Broadcaster {
+    pub fn new(store: Arc<dyn BroadcastStore>) -> Self {
+        Self {
+            store,
+            pace: Duration::from_secs(2),
+        }
+    }
+
+    /// Returns the guilds (and their announcement channel) a broadcast would be sent to
+    pub async fn targets(
+        &self,
+        guilds: &[GuildId],
+        filter: Option<&GuildFilter>,
+    ) -> Result<Vec<(GuildId, ChannelId)>, Error> {
+        let mut targets = Vec::new();
+
+        for guild_id in guilds {
+            if !_matches(filter, *guild_id) {
+                continue;
+            }
+
+            if let Some(channel_id) = self.store.channel(*guild_id).await? {
+                targets.push((*guild_id, channel_id));
+            }
+        }
+
+        Ok(targets)
+    }
+
+    /// Sends an announcement to every matching guild, one at a time with ``pace`` between sends
+    ///
+    /// Progress is checkpointed under ``id``, so calling this again with the same id after a crash
+    /// only sends to the remaining guilds
+    pub async fn run(
+        &self,
+        http: &serenity::Http,
+        id: &str,
+        embed: CreateEmbed<'static>,
+        guilds: &[GuildId],
+        filter: Option<&GuildFilter>,
+    ) -> Result<BroadcastReport, Error> {
+        self._run(http, id, guilds, filter, |_| Ok(embed.clone()))
+            .await
+    }
+
+    /// Like ``run``, but renders an embed template per guild so ``{guild.name}`` and
+    /// ``{guild.id}`` are filled in
+    pub async fn run_template(
+        &self,
+        http: &serenity::Http,
+        cache: &serenity::Cache,
+        id: &str,
+        template: &EmbedTemplate,
+        guilds: &[GuildId],
+        filter: Option<&GuildFilter>,
+    ) -> Result<BroadcastReport, Error> {
+        self._run(http, id, guilds, filter, |guild_id| {
+            let name = cache.guild(guild_id).map(|g| g.name.to_string());
+            template.render(&TemplateContext::for_guild(guild_id, name.as_deref()))
+        })
+        .await
+    }
+
+    async fn _run(
+        &self,
+        http: &serenity::Http,
+        id: &str,
+        guilds: &[GuildId],
+        filter: Option<&GuildFilter>,
+        embed: impl Fn(GuildId) -> Result<CreateEmbed<'static>, Error>,
+    ) -> Result<BroadcastReport, Error> {
+        let mut checkpoint =
+            self.store
+                .checkpoint(id)
+                .await?
+                .unwrap_or_else(|| BroadcastCheckpoint {
+                    id: id.to_string(),
+                    ..Default::default()
+                });
+
+        let mut report = BroadcastReport::default();
+        let targets = self.targets(guilds, filter).await?;
+
+        report.unconfigured =
+            guilds.iter().filter(|g| _matches(filter, **g)).count() - targets.len();
+
+        for (guild_id, channel_id) in targets {
+            if checkpoint.is_done(guild_id) {
+                report.resumed += 1;
+                continue;
+            }
+
+            let msg = match embed(guild_id) {
+                Ok(embed) => CreateMessage::new()
+                    .embed(embed)
+                    .allowed_mentions(MentionPolicy::none().build()),
+                Err(e) => {
+                    report.failed.push((guild_id, e.to_string()));
+                    checkpoint.failed.push((guild_id, e.to_string()));
+                    self.store.save_checkpoint(&checkpoint).await?;
+                    continue;
+                }
+            };
+
+            match channel_id.send_message(http, msg).await {
+                Ok(_) => {
+                    report.sent += 1;
+                    checkpoint.sent.push(guild_id);
+                }
+                Err(e) => {
+                    log::warn!("Broadcast {} failed for {}: {}", id, guild_id, e);
+                    report.failed.push((guild_id, e.to_string()));
+                    checkpoint.failed.push((guild_id, e.to_string()));
+                }
+            }
+
+            self.store.save_checkpoint(&checkpoint).await?;
+
+            tokio::time::sleep(self.pace).await;
+        }
+
+        Ok(report)
+    }
+}
+
+/// Lists the channels a broadcast would be sent to without sending anything, can be plugged into
+/// an owner-only ``/broadcast dry-run`` command
+pub async fn broadcast_dry_run<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    broadcaster: &Broadcaster,
+    filter: Option<&GuildFilter>,
+) -> Result<(), Error> {
+    let guilds = ctx.serenity_context().cache.guilds();
+    let targets = broadcaster.targets(&guilds, filter).await?;
+
+    let mut text = String::new();
+
+    for (guild_id, channel_id) in &targets {
+        let name = ctx
+            .serenity_context()
+            .cache
+            .guild(*guild_id)
+            .map(|g| g.name.to_string())
+            .unwrap_or_default();
+
+        let _ = writeln!(text, "{} ({}) -> {}", name, guild_id, channel_id);
+    }
+
+    ctx.send(
+        CreateReply::default()
+            .content(format!(
+                "Would send to {} of {} guilds",
+                targets.len(),
+                guilds.len()
+            ))
+            .attachment(CreateAttachment::bytes(text.into_bytes(), "targets.txt")),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Sends an announcement to every configured guild and replies with the report, can be plugged
+/// into an owner-only ``/broadcast send`` command
+pub async fn broadcast_send<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    broadcaster: &Broadcaster,
+    id: &str,
+    embed: CreateEmbed<'static>,
+    filter: Option<&GuildFilter>,
+) -> Result<(), Error> {
+    ctx.defer().await?;
+
+    let guilds = ctx.serenity_context().cache.guilds();
+    let report = broadcaster
+        .run(ctx.http(), id, embed, &guilds, filter)
+        .await?;
+
+    ctx.send(
+        CreateReply::default()
+            .content(format!(
+                "Broadcast ``{}`` sent to {} guilds ({} failed)",
+                id,
+                report.sent,
+                report.failed.len()
+            ))
+            .attachment(CreateAttachment::bytes(
+                report.to_text().into_bytes(),
+                "broadcast.txt",
+            )),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -1,8 +1,17 @@
+use futures::future::BoxFuture;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
 
 use serenity::all::{Cache, CacheHttp, Http};
 use serenity::all::{GuildId, UserId};
 
+use crate::taskman::Task;
+
 /// Helper function to check if a member is on a server, returning a boolean
 pub async fn member_on_guild(
     cache_http: impl CacheHttp,
@@ -72,3 +81,453 @@ impl From<serenity::all::Context> for CacheHttpImpl {
         }
     }
 }
+
+/// Approximate size of a cached member, excluding roles
+const MEMBER_SIZE: usize = 512;
+
+/// A cache whose memory usage can be estimated and reduced by ``MemoryGovernor``
+pub trait Prunable: Send + Sync {
+    /// Name of the cache, used in logs and metrics
+    fn name(&self) -> &str;
+
+    /// Returns the approximate memory used by the cache in bytes
+    fn approx_bytes<'a>(&'a self) -> BoxFuture<'a, usize>;
+
+    /// Evicts least recently used entries until about ``bytes`` are freed, returning the number of entries evicted
+    fn prune<'a>(&'a self, bytes: usize) -> BoxFuture<'a, usize>;
+}
+
+/// Reports the approximate size of serenitys member cache
+///
+/// serenity does not allow evicting single members, so this only counts towards the budget
+pub struct SerenityCacheUsage {
+    pub cache: Arc<Cache>,
+}
+
+impl Prunable for SerenityCacheUsage {
+    fn name(&self) -> &str {
+        "serenity"
+    }
+
+    fn approx_bytes<'a>(&'a self) -> BoxFuture<'a, usize> {
+        Box::pin(async move {
+            self.cache
+                .guilds()
+                .into_iter()
+                .filter_map(|id| self.cache.guild(id).map(|g| g.members.len()))
+                .sum::<usize>()
+                * MEMBER_SIZE
+        })
+    }
+
+    fn prune<'a>(&'a self, _bytes: usize) -> BoxFuture<'a, usize> {
+        Box::pin(async move { 0 })
+    }
+}
+
+/// The result of a ``MemoryGovernor`` check
+#[derive(Debug, Clone, Default)]
+pub struct GovernorReport {
+    /// Approximate bytes used before pruning
+    pub used: usize,
+    pub budget: usize,
+    /// Entries evicted per cache
+    pub evicted: Vec<(String, usize)>,
+}
+
+/// Keeps the approximate memory usage of caches under a budget by pruning the largest caches first
+pub struct MemoryGovernor {
+    /// Budget in bytes
+    pub budget: usize,
+    caches: Vec<Arc<dyn Prunable>>,
+}
+
+impl MemoryGovernor {
+    pub fn new(budget: usize) -> Self {
+        Self {
+            budget,
+            caches: Vec::new(),
+        }
+    }
+
+    /// Adds a cache to monitor
+    pub fn cache(mut self, cache: Arc<dyn Prunable>) -> Self {
+        self.caches.push(cache);
+        self
+    }
+
+    /// Measures every cache and prunes until usage is back under budget
+    pub async fn check(&self) -> GovernorReport {
+        let mut usage = Vec::with_capacity(self.caches.len());
+
+        for cache in &self.caches {
+            usage.push((cache.clone(), cache.approx_bytes().await));
+        }
+
+        let used = usage.iter().map(|(_, bytes)| bytes).sum::<usize>();
+
+        let mut report = GovernorReport {
+            used,
+            budget: self.budget,
+            evicted: Vec::new(),
+        };
+
+        if used <= self.budget {
+            return report;
+        }
+
+        let mut excess = used - self.budget;
+
+        usage.sort_by(|a, b| b.1.cmp(&a.1));
+
+        for (cache, bytes) in usage {
+            if excess == 0 {
+                break;
+            }
+
+            let target = excess.min(bytes);
+            let evicted = cache.prune(target).await;
+
+            if evicted == 0 {
+                continue;
+            }
+
+            #[cfg(feature = "otel")]
+            crate::telemetry::cache_eviction_counter().add(
+                evicted as u64,
+                &[opentelemetry::KeyValue::new(
+                    "cache",
+                    cache.name().to_string(),
+                )],
+            );
+
+            excess = excess.saturating_sub(bytes.saturating_sub(cache.approx_bytes().await));
+            report.evicted.push((cache.name().to_string(), evicted));
+        }
+
+        if excess > 0 {
+            log::warn!(
+                "Cache usage still {} bytes over the {} byte budget after pruning",
+                excess,
+                self.budget
+            );
+        }
+
+        report
+    }
+}
+
+/// Returns a task that checks cache memory usage every ``interval``
+pub fn memory_governor_task(governor: MemoryGovernor, interval: Duration) -> Task {
+    let governor = Arc::new(governor);
+
+    Task {
+        name: "memory_governor",
+        description: "Prunes caches that exceed the memory budget",
+        enabled: true,
+        duration: interval,
+        run: Box::new(move |_ctx| {
+            let governor = governor.clone();
+            Box::pin(async move {
+                let report = governor.check().await;
+
+                for (cache, evicted) in &report.evicted {
+                    log::info!("Evicted {} entries from the {} cache", evicted, cache);
+                }
+
+                Ok(())
+            })
+        }),
+    }
+}
+
+/// Default number of shards of a ``TtlCache``
+const TTL_CACHE_SHARDS: usize = 16;
+
+/// Share of a full ``TtlCache`` shard evicted at once, so the eviction scan runs once per this
+/// many inserts rather than on every insert
+const TTL_CACHE_EVICT_DIVISOR: usize = 8;
+
+struct _TtlEntry<V> {
+    value: V,
+    inserted: Instant,
+    expires: Instant,
+    /// Tick of the last access, for least recently used eviction
+    used: AtomicU64,
+}
+
+/// An in-memory cache with a time to live per entry and a maximum capacity
+///
+/// Entries are spread over shards so lookups of different keys rarely wait on each other. Once a
+/// shard is full, expired entries are dropped first and then the least recently used eighth of
+/// the shard, so full caches don't scan their shard on every insert. Hits
+/// and misses are counted (and exported to metrics with the ``otel`` feature). This is cheap to clone
+pub struct TtlCache<K, V> {
+    name: Arc<str>,
+    shards: Arc<[RwLock<HashMap<K, _TtlEntry<V>>>]>,
+    hasher: RandomState,
+    ttl: Duration,
+    shard_capacity: usize,
+    tick: Arc<AtomicU64>,
+    hits: Arc<AtomicU64>,
+    misses: Arc<AtomicU64>,
+}
+
+impl<K, V> Clone for TtlCache<K, V> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            shards: self.shards.clone(),
+            hasher: self.hasher.clone(),
+            ttl: self.ttl,
+            shard_capacity: self.shard_capacity,
+            tick: self.tick.clone(),
+            hits: self.hits.clone(),
+            misses: self.misses.clone(),
+        }
+    }
+}
+
+impl<K, V> TtlCache<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    /// Creates a cache holding at most about ``capacity`` entries for ``ttl`` each
+    ///
+    /// ``name`` is used in logs and metrics
+    pub fn new(name: impl Into<String>, ttl: Duration, capacity: usize) -> Self {
+        let shards = TTL_CACHE_SHARDS.min(capacity.max(1));
+
+        Self {
+            name: name.into().into(),
+            shards: (0..shards).map(|_| RwLock::new(HashMap::new())).collect(),
+            hasher: RandomState::new(),
+            ttl,
+            shard_capacity: capacity.div_ceil(shards).max(1),
+            tick: Arc::new(AtomicU64::new(0)),
+            hits: Arc::new(AtomicU64::new(0)),
+            misses: Arc::new(AtomicU64::new(0)),
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The time to live of entries inserted with ``insert``
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    fn _shard(&self, key: &K) -> &RwLock<HashMap<K, _TtlEntry<V>>> {
+        &self.shards[self.hasher.hash_one(key) as usize % self.shards.len()]
+    }
+
+    /// Evicts the ``count`` least recently used entries of a shard, returning how many were evicted
+    fn _evict_lru(shard: &mut HashMap<K, _TtlEntry<V>>, count: usize) -> usize {
+        if count == 0 || shard.is_empty() {
+            return 0;
+        }
+
+        if count >= shard.len() {
+            let evicted = shard.len();
+            shard.clear();
+            return evicted;
+        }
+
+        let mut used = shard
+            .values()
+            .map(|e| e.used.load(Ordering::Relaxed))
+            .collect::<Vec<_>>();
+
+        // Ticks are unique, so everything up to the count-th smallest is exactly count entries
+        let cutoff = *used.select_nth_unstable(count - 1).1;
+
+        let before = shard.len();
+        shard.retain(|_, e| e.used.load(Ordering::Relaxed) > cutoff);
+        before - shard.len()
+    }
+
+    fn _record(&self, hit: bool) {
+        if hit {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+        }
+
+        #[cfg(feature = "otel")]
+        crate::telemetry::cache_lookup_counter().add(
+            1,
+            &[
+                opentelemetry::KeyValue::new("cache", self.name.to_string()),
+                opentelemetry::KeyValue::new("result", if hit { "hit" } else { "miss" }),
+            ],
+        );
+    }
+
+    /// Returns an entry and how long ago it was inserted, if it has not expired
+    pub async fn get_with_age(&self, key: &K) -> Option<(V, Duration)> {
+        let now = Instant::now();
+
+        let found = self
+            ._shard(key)
+            .read()
+            .await
+            .get(key)
+            .filter(|e| e.expires > now)
+            .map(|e| {
+                e.used
+                    .store(self.tick.fetch_add(1, Ordering::Relaxed), Ordering::Relaxed);
+                (e.value.clone(), now.duration_since(e.inserted))
+            });
+
+        self._record(found.is_some());
+
+        found
+    }
+
+    /// Returns an entry if it has not expired
+    pub async fn get(&self, key: &K) -> Option<V> {
+        self.get_with_age(key).await.map(|(value, _)| value)
+    }
+
+    /// Inserts an entry with the default time to live
+    pub async fn insert(&self, key: K, value: V) {
+        self.insert_with_ttl(key, value, self.ttl).await
+    }
+
+    /// Inserts an entry that expires after ``ttl``, evicting another entry if the shard is full
+    pub async fn insert_with_ttl(&self, key: K, value: V, ttl: Duration) {
+        let now = Instant::now();
+        let mut shard = self._shard(&key).write().await;
+
+        if !shard.contains_key(&key) && shard.len() >= self.shard_capacity {
+            shard.retain(|_, e| e.expires > now);
+
+            if shard.len() >= self.shard_capacity {
+                let target =
+                    self.shard_capacity - (self.shard_capacity / TTL_CACHE_EVICT_DIVISOR).max(1);
+                let count = shard.len() - target;
+
+                Self::_evict_lru(&mut shard, count);
+            }
+        }
+
+        shard.insert(
+            key,
+            _TtlEntry {
+                value,
+                inserted: now,
+                expires: now + ttl,
+                used: AtomicU64::new(self.tick.fetch_add(1, Ordering::Relaxed)),
+            },
+        );
+    }
+
+    /// Removes an entry, returning it if it had not expired
+    pub async fn remove(&self, key: &K) -> Option<V> {
+        self._shard(key)
+            .write()
+            .await
+            .remove(key)
+            .filter(|e| e.expires > Instant::now())
+            .map(|e| e.value)
+    }
+
+    /// Keeps only the unexpired entries matching a predicate
+    pub async fn retain(&self, mut f: impl FnMut(&K, &V) -> bool) {
+        let now = Instant::now();
+
+        for shard in self.shards.iter() {
+            shard
+                .write()
+                .await
+                .retain(|k, e| e.expires > now && f(k, &e.value));
+        }
+    }
+
+    /// Drops expired entries, returning how many were dropped
+    pub async fn purge_expired(&self) -> usize {
+        let now = Instant::now();
+        let mut purged = 0;
+
+        for shard in self.shards.iter() {
+            let mut shard = shard.write().await;
+            let before = shard.len();
+            shard.retain(|_, e| e.expires > now);
+            purged += before - shard.len();
+        }
+
+        purged
+    }
+
+    pub async fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.write().await.clear();
+        }
+    }
+
+    /// Returns the number of entries, including expired entries not dropped yet
+    pub async fn len(&self) -> usize {
+        let mut len = 0;
+
+        for shard in self.shards.iter() {
+            len += shard.read().await.len();
+        }
+
+        len
+    }
+
+    pub async fn is_empty(&self) -> bool {
+        self.len().await == 0
+    }
+
+    /// Returns the number of hits and misses since the cache was created
+    pub fn stats(&self) -> (u64, u64) {
+        (
+            self.hits.load(Ordering::Relaxed),
+            self.misses.load(Ordering::Relaxed),
+        )
+    }
+}
+
+impl<K, V> Prunable for TtlCache<K, V>
+where
+    K: Hash + Eq + Clone + Send + Sync + 'static,
+    V: Clone + Send + Sync + 'static,
+{
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Only counts the inline size of keys and values, not data they point to
+    fn approx_bytes<'a>(&'a self) -> BoxFuture<'a, usize> {
+        Box::pin(async move {
+            self.len().await * (std::mem::size_of::<K>() + std::mem::size_of::<_TtlEntry<V>>())
+        })
+    }
+
+    fn prune<'a>(&'a self, bytes: usize) -> BoxFuture<'a, usize> {
+        Box::pin(async move {
+            let mut evicted = self.purge_expired().await;
+            let entry_size =
+                (std::mem::size_of::<K>() + std::mem::size_of::<_TtlEntry<V>>()).max(1);
+
+            // Spread the evictions over the shards rather than emptying the first one
+            let per_shard = (bytes / entry_size)
+                .saturating_sub(evicted)
+                .div_ceil(self.shards.len());
+
+            if per_shard == 0 {
+                return evicted;
+            }
+
+            for shard in self.shards.iter() {
+                evicted += Self::_evict_lru(&mut *shard.write().await, per_shard);
+            }
+
+            evicted
+        })
+    }
+}
@@ -0,0 +1,131 @@
+use poise::serenity_prelude::{CreateEmbedFooter, GuildId};
+use poise::CreateReply;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cache::TtlCache;
+use crate::Error;
+
+/// Maximum number of replies kept by a ``CachedReply``
+const MAX_ENTRIES: usize = 1000;
+
+/// Returns the cache key of an invocation, such as the command arguments
+pub type KeyFn<Data> =
+    Box<dyn Send + Sync + for<'a> Fn(poise::Context<'a, Data, crate::Error>) -> String>;
+
+/// Caches the replies of an expensive read-only command, see ``cached``
+///
+/// Keys are scoped per guild and command, so identical invocations in different guilds never share a
+/// reply. This is cheap to clone
+pub struct CachedReply<Data> {
+    key_fn: Arc<KeyFn<Data>>,
+    entries: TtlCache<String, CreateReply<'static>>,
+}
+
+impl<Data> Clone for CachedReply<Data> {
+    fn clone(&self) -> Self {
+        Self {
+            key_fn: self.key_fn.clone(),
+            entries: self.entries.clone(),
+        }
+    }
+}
+
+/// Wraps a command body so identical invocations within ``ttl`` get the cached reply
+///
+/// Cached replies are marked with how long ago they were made
+pub fn cached<Data: Send + Sync + 'static>(
+    key_fn: impl Send + Sync + 'static + for<'a> Fn(poise::Context<'a, Data, crate::Error>) -> String,
+    ttl: Duration,
+) -> CachedReply<Data> {
+    CachedReply {
+        key_fn: Arc::new(Box::new(key_fn)),
+        entries: TtlCache::new("cached_replies", ttl, MAX_ENTRIES),
+    }
+}
+
+fn _guild_prefix(guild_id: Option<GuildId>) -> String {
+    match guild_id {
+        Some(guild_id) => format!("{}:", guild_id),
+        None => "dm:".to_string(),
+    }
+}
+
+fn _mark_cached(mut reply: CreateReply<'static>, age: Duration) -> CreateReply<'static> {
+    let note = format!("Cached {} seconds ago", age.as_secs());
+
+    match reply.embeds.pop() {
+        Some(embed) => {
+            reply
+                .embeds
+                .push(embed.footer(CreateEmbedFooter::new(note)));
+            reply
+        }
+        None => {
+            let content = reply.content.take().unwrap_or_default();
+            reply.content(format!("{}\n*{}*", content, note))
+        }
+    }
+}
+
+impl<Data: Send + Sync + 'static> CachedReply<Data> {
+    fn _key(&self, ctx: poise::Context<'_, Data, crate::Error>) -> String {
+        format!(
+            "{}{}:{}",
+            _guild_prefix(ctx.guild_id()),
+            ctx.command().qualified_name,
+            (self.key_fn)(ctx)
+        )
+    }
+
+    /// Replies with the cached reply of this invocation, or runs ``body`` and caches its reply
+    pub async fn reply<F, Fut>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+        body: F,
+    ) -> Result<(), Error>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<CreateReply<'static>, Error>>,
+    {
+        let key = self._key(ctx);
+
+        if let Some((reply, age)) = self.entries.get_with_age(&key).await {
+            ctx.send(_mark_cached(reply, age)).await?;
+            return Ok(());
+        }
+
+        let reply = body().await?;
+        self.entries.insert(key, reply.clone()).await;
+
+        ctx.send(reply).await?;
+
+        Ok(())
+    }
+
+    /// Drops the cached reply of an invocation, for example after the data it shows changed
+    pub async fn invalidate(&self, ctx: poise::Context<'_, Data, crate::Error>) {
+        let key = self._key(ctx);
+        self.entries.remove(&key).await;
+    }
+
+    /// Drops every cached reply of a guild, None drops replies cached in DMs
+    pub async fn invalidate_guild(&self, guild_id: Option<GuildId>) {
+        let prefix = _guild_prefix(guild_id);
+
+        self.entries
+            .retain(|key, _| !key.starts_with(&prefix))
+            .await;
+    }
+
+    /// Returns the underlying cache, for example to add it to a ``MemoryGovernor``
+    pub fn cache(&self) -> &TtlCache<String, CreateReply<'static>> {
+        &self.entries
+    }
+
+    /// Drops every cached reply
+    pub async fn clear(&self) {
+        self.entries.clear().await;
+    }
+}
@@ -0,0 +1,601 @@
+use chrono_tz::Tz;
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, CreateAttachment, CreateEmbed, CreateEmbedFooter, CreateMessage, EditMember,
+    GuildId, Timestamp, UserId,
+};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::appeals::appeal_components;
+use crate::logrouter::{LogCategory, LogEntry, LogRouter};
+use crate::notify::{self, NotifyCategory, Preferences};
+use crate::paginator::{ExportFormat, PageSource, Paginator};
+use crate::privacy::DataHolder;
+use crate::reason::Reason;
+use crate::time::{to_chrono, GuildClock};
+use crate::Error;
+
+/// Id ``Cases`` is registered under on the paginator
+pub const PAGE_SOURCE: &str = "cases";
+
+/// Cases shown per page of ``case_history``
+const CASES_PER_PAGE: usize = 5;
+
+/// Maximum length of an embed field value
+const MAX_FIELD_VALUE: usize = 1024;
+
+/// Maximum length of an embed description
+const MAX_DESCRIPTION: usize = 4096;
+
+fn _truncate(text: &str, max: usize) -> String {
+    if text.chars().count() <= max {
+        return text.to_string();
+    }
+
+    let mut out = text.chars().take(max - 1).collect::<String>();
+    out.push('…');
+    out
+}
+
+/// A moderation action
+#[derive(
+    poise::ChoiceParameter, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+pub enum CaseAction {
+    Warn,
+    Timeout,
+    Kick,
+    Ban,
+    Unban,
+    Note,
+}
+
+impl CaseAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CaseAction::Warn => "Warn",
+            CaseAction::Timeout => "Timeout",
+            CaseAction::Kick => "Kick",
+            CaseAction::Ban => "Ban",
+            CaseAction::Unban => "Unban",
+            CaseAction::Note => "Note",
+        }
+    }
+
+    /// Past tense used in DM receipts, such as ``You were banned``
+    fn verb(&self) -> &'static str {
+        match self {
+            CaseAction::Warn => "warned",
+            CaseAction::Timeout => "timed out",
+            CaseAction::Kick => "kicked",
+            CaseAction::Ban => "banned",
+            CaseAction::Unban => "unbanned",
+            CaseAction::Note => "noted",
+        }
+    }
+
+    fn colour(&self) -> serenity::Colour {
+        match self {
+            CaseAction::Warn => serenity::Colour::GOLD,
+            CaseAction::Timeout => serenity::Colour::ORANGE,
+            CaseAction::Kick | CaseAction::Ban => serenity::Colour::RED,
+            CaseAction::Unban => serenity::Colour::DARK_GREEN,
+            CaseAction::Note => serenity::Colour::LIGHT_GREY,
+        }
+    }
+}
+
+/// A moderation case
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Case {
+    pub guild_id: GuildId,
+    /// Increments per guild, starting at 1
+    pub number: u64,
+    pub action: CaseAction,
+    pub user_id: UserId,
+    pub moderator_id: UserId,
+    pub reason: String,
+    /// Length of a timeout or temporary ban, in seconds
+    pub duration: Option<u64>,
+    /// Numbers of related cases, such as the warns that escalated into this case
+    pub related: Vec<u64>,
+    pub created_at: Timestamp,
+    pub edited_at: Option<Timestamp>,
+}
+
+impl Case {
+    /// Renders the case as an embed, with times shown in ``tz``
+    pub fn embed(&self, tz: Tz) -> CreateEmbed<'static> {
+        let mut embed = CreateEmbed::default()
+            .title(format!("Case #{} | {}", self.number, self.action.label()))
+            .colour(self.action.colour())
+            .field("User", format!("<@{}>", self.user_id), true)
+            .field("Moderator", format!("<@{}>", self.moderator_id), true)
+            .field("Reason", _truncate(&self.reason, MAX_FIELD_VALUE), false)
+            .timestamp(self.created_at);
+
+        if let Some(duration) = self.duration {
+            embed = embed.field(
+                "Until",
+                format!(
+                    "<t:{}:f>",
+                    self.created_at.unix_timestamp() + duration as i64
+                ),
+                true,
+            );
+        }
+
+        if !self.related.is_empty() {
+            embed = embed.field(
+                "Related",
+                self.related
+                    .iter()
+                    .map(|n| format!("#{}", n))
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                true,
+            );
+        }
+
+        if let Some(edited_at) = self.edited_at {
+            embed = embed.footer(CreateEmbedFooter::new(format!(
+                "Reason edited {}",
+                to_chrono(edited_at)
+                    .with_timezone(&tz)
+                    .format("%Y-%m-%d %H:%M %Z")
+            )));
+        }
+
+        embed
+    }
+}
+
+/// Storage backend for moderation cases
+pub trait CaseStore: Send + Sync {
+    /// Atomically increments and returns the next case number of a guild
+    fn next_number<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<u64, Error>>;
+
+    /// Returns a case by number
+    fn get<'a>(
+        &'a self,
+        guild_id: GuildId,
+        number: u64,
+    ) -> BoxFuture<'a, Result<Option<Case>, Error>>;
+
+    /// Adds or replaces a case
+    fn save<'a>(&'a self, case: &'a Case) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Returns the cases of a user in a guild, newest first
+    fn for_user<'a>(
+        &'a self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> BoxFuture<'a, Result<Vec<Case>, Error>>;
+
+    /// Returns the cases of a user in every guild
+    fn all_for_user<'a>(&'a self, user_id: UserId) -> BoxFuture<'a, Result<Vec<Case>, Error>>;
+
+    /// Deletes the cases of a user in every guild, returning the number of cases deleted
+    fn erase_user<'a>(&'a self, user_id: UserId) -> BoxFuture<'a, Result<usize, Error>>;
+
+    /// Deletes cases created before ``before``, returning the number of cases deleted
+    fn prune_before<'a>(&'a self, before: Timestamp) -> BoxFuture<'a, Result<usize, Error>>;
+}
+
+/// A moderation action to record as a case
+#[derive(Debug, Clone)]
+pub struct NewCase {
+    pub guild_id: GuildId,
+    pub action: CaseAction,
+    pub user_id: UserId,
+    pub moderator_id: UserId,
+    pub reason: String,
+    pub duration: Option<Duration>,
+    pub related: Vec<u64>,
+}
+
+/// Moderation case system, records actions with per-guild case numbers, DMs receipts and logs cases
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct Cases {
+    store: Arc<dyn CaseStore>,
+    /// Cases are logged to the ``Moderation`` category, if set
+    pub log_router: Option<LogRouter>,
+    /// Respected when DMing receipts, if set
+    pub preferences: Option<Preferences>,
+    /// DM users a receipt of actions taken against them
+    pub dm_receipts: bool,
+    /// Adds an appeal button to ban and timeout receipts, see ``appeals``
+    pub appeals: bool,
+    /// Used to show case times in the guilds timezone, UTC otherwise
+    pub clock: Option<GuildClock>,
+}
+
+impl Cases {
+    pub fn new(store: Arc<dyn CaseStore>) -> Self {
+        Self {
+            store,
+            log_router: None,
+            preferences: None,
+            dm_receipts: true,
+            appeals: false,
+            clock: None,
+        }
+    }
+
+    /// Renders a case as an embed in the timezone of its guild
+    pub async fn embed(&self, case: &Case) -> CreateEmbed<'static> {
+        let tz = match &self.clock {
+            Some(clock) => clock.timezone(case.guild_id).await.unwrap_or_else(|e| {
+                log::warn!("Failed to get the timezone of {}: {}", case.guild_id, e);
+                Tz::UTC
+            }),
+            None => Tz::UTC,
+        };
+
+        case.embed(tz)
+    }
+
+    /// Returns a case by number
+    pub async fn get(&self, guild_id: GuildId, number: u64) -> Result<Option<Case>, Error> {
+        self.store.get(guild_id, number).await
+    }
+
+    /// Returns the cases of a user in a guild, newest first
+    pub async fn history(&self, guild_id: GuildId, user_id: UserId) -> Result<Vec<Case>, Error> {
+        self.store.for_user(guild_id, user_id).await
+    }
+
+    /// Records a case without taking any action, DMing a receipt and logging it
+    pub async fn record(&self, http: &serenity::Http, new: NewCase) -> Result<Case, Error> {
+        let case = self._case(new).await?;
+
+        self.store.save(&case).await?;
+
+        if self.dm_receipts && case.action != CaseAction::Note {
+            self._receipt(http, &case).await?;
+        }
+
+        self._log(&case).await;
+
+        Ok(case)
+    }
+
+    /// Builds a case with the next case number, without saving it
+    async fn _case(&self, new: NewCase) -> Result<Case, Error> {
+        Ok(Case {
+            guild_id: new.guild_id,
+            number: self.store.next_number(new.guild_id).await?,
+            action: new.action,
+            user_id: new.user_id,
+            moderator_id: new.moderator_id,
+            reason: new.reason,
+            duration: new.duration.map(|d| d.as_secs()),
+            related: new.related,
+            created_at: Timestamp::now(),
+            edited_at: None,
+        })
+    }
+
+    async fn _log(&self, case: &Case) {
+        if let Some(router) = &self.log_router {
+            router
+                .log(
+                    case.guild_id,
+                    LogCategory::Moderation,
+                    LogEntry::new(
+                        format!("Case #{} | {}", case.number, case.action.label()),
+                        format!(
+                            "<@{}> by <@{}>: {}",
+                            case.user_id, case.moderator_id, case.reason
+                        ),
+                    ),
+                )
+                .await;
+        }
+    }
+
+    async fn _receipt(
+        &self,
+        http: &serenity::Http,
+        case: &Case,
+    ) -> Result<Option<serenity::Message>, Error> {
+        let guild_name = case
+            .guild_id
+            .to_partial_guild(http)
+            .await
+            .map(|g| g.name.to_string())
+            .unwrap_or_else(|_| "a server".to_string());
+
+        let embed = CreateEmbed::default()
+            .title(format!("You were {} in {}", case.action.verb(), guild_name))
+            .colour(case.action.colour())
+            .field("Reason", _truncate(&case.reason, MAX_FIELD_VALUE), false)
+            .footer(CreateEmbedFooter::new(format!("Case #{}", case.number)))
+            .timestamp(case.created_at);
+
+        let mut msg = CreateMessage::new().embed(embed);
+
+        if self.appeals && matches!(case.action, CaseAction::Timeout | CaseAction::Ban) {
+            msg = msg.components(appeal_components(case));
+        }
+
+        notify::dm_message(
+            http,
+            self.preferences.as_ref(),
+            case.user_id,
+            NotifyCategory::Moderation,
+            msg,
+        )
+        .await
+    }
+
+    /// Takes a moderation action and records it as a case
+    ///
+    /// The receipt is sent before kicks and bans, while the bot still shares a server with the user.
+    /// If the action fails, the receipt is deleted again and no case is saved or logged
+    pub async fn apply(&self, http: &serenity::Http, new: NewCase) -> Result<Case, Error> {
+        let reason = Reason::new(format!("{} (by {})", new.reason, new.moderator_id));
+
+        match new.action {
+            CaseAction::Timeout => {
+                let duration = new.duration.ok_or("Timeouts need a duration")?;
+                let until = Timestamp::from_unix_timestamp(
+                    Timestamp::now().unix_timestamp() + duration.as_secs() as i64,
+                )?;
+
+                new.guild_id
+                    .edit_member(
+                        http,
+                        new.user_id,
+                        EditMember::new()
+                            .disable_communication_until(until)
+                            .audit_log_reason(reason.as_str()),
+                    )
+                    .await?;
+            }
+            CaseAction::Kick | CaseAction::Ban => {
+                let case = self._case(new.clone()).await?;
+
+                let receipt = if self.dm_receipts {
+                    self._receipt(http, &case).await?
+                } else {
+                    None
+                };
+
+                let result = if new.action == CaseAction::Kick {
+                    new.guild_id
+                        .kick_with_reason(http, new.user_id, reason.as_str())
+                        .await
+                } else {
+                    new.guild_id
+                        .ban_with_reason(http, new.user_id, 0, reason.as_str())
+                        .await
+                };
+
+                if let Err(e) = result {
+                    if let Some(receipt) = receipt {
+                        if let Err(e) = receipt.delete(http, None).await {
+                            log::warn!("Failed to retract receipt of case #{}: {}", case.number, e);
+                        }
+                    }
+
+                    return Err(e.into());
+                }
+
+                self.store.save(&case).await?;
+                self._log(&case).await;
+
+                return Ok(case);
+            }
+            CaseAction::Unban => new.guild_id.unban(http, new.user_id).await?,
+            CaseAction::Warn | CaseAction::Note => {}
+        }
+
+        self.record(http, new).await
+    }
+
+    /// Changes the reason of a case, returning the updated case if it exists
+    pub async fn edit_reason(
+        &self,
+        guild_id: GuildId,
+        number: u64,
+        reason: String,
+    ) -> Result<Option<Case>, Error> {
+        let Some(mut case) = self.store.get(guild_id, number).await? else {
+            return Ok(None);
+        };
+
+        case.reason = reason;
+        case.edited_at = Some(Timestamp::now());
+
+        self.store.save(&case).await?;
+
+        Ok(Some(case))
+    }
+
+    fn _args(args: &str) -> Result<(GuildId, UserId), Error> {
+        let (guild_id, user_id) = args.split_once(':').ok_or("Invalid case history args")?;
+
+        Ok((guild_id.parse()?, user_id.parse()?))
+    }
+}
+
+impl PageSource for Cases {
+    fn page_count<'a>(&'a self, args: &'a str) -> BoxFuture<'a, Result<usize, Error>> {
+        Box::pin(async move {
+            let (guild_id, user_id) = Self::_args(args)?;
+            let cases = self.history(guild_id, user_id).await?;
+
+            Ok(cases.len().div_ceil(CASES_PER_PAGE).max(1))
+        })
+    }
+
+    fn render<'a>(
+        &'a self,
+        args: &'a str,
+        page: usize,
+    ) -> BoxFuture<'a, Result<CreateEmbed<'static>, Error>> {
+        Box::pin(async move {
+            let (guild_id, user_id) = Self::_args(args)?;
+            let cases = self.history(guild_id, user_id).await?;
+
+            let mut desc = String::new();
+
+            for case in cases
+                .iter()
+                .skip(page * CASES_PER_PAGE)
+                .take(CASES_PER_PAGE)
+            {
+                let _ = writeln!(
+                    desc,
+                    "**#{} {}** <t:{}:d> by <@{}>\n{}\n",
+                    case.number,
+                    case.action.label(),
+                    case.created_at.unix_timestamp(),
+                    case.moderator_id,
+                    case.reason
+                );
+            }
+
+            if desc.is_empty() {
+                desc.push_str("No cases");
+            }
+
+            Ok(CreateEmbed::default()
+                .title(format!("Cases of {} ({})", user_id, cases.len()))
+                .description(_truncate(&desc, MAX_DESCRIPTION)))
+        })
+    }
+}
+
+impl DataHolder for Cases {
+    fn name(&self) -> &str {
+        "cases"
+    }
+
+    fn export_user<'a>(
+        &'a self,
+        user_id: UserId,
+    ) -> BoxFuture<'a, Result<serde_json::Value, Error>> {
+        Box::pin(async move {
+            Ok(serde_json::to_value(
+                self.store.all_for_user(user_id).await?,
+            )?)
+        })
+    }
+
+    fn erase_user<'a>(&'a self, user_id: UserId) -> BoxFuture<'a, Result<usize, Error>> {
+        self.store.erase_user(user_id)
+    }
+
+    fn prune_before<'a>(&'a self, before: Timestamp) -> BoxFuture<'a, Result<usize, Error>> {
+        self.store.prune_before(before)
+    }
+}
+
+/// Trait for bot data that holds ``Cases``
+pub trait HasCases {
+    fn cases(&self) -> &Cases;
+}
+
+/// Shows a case, can be plugged into your bots ``/case view`` command
+pub async fn case_view<Data: HasCases + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    number: u64,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Cases can only be viewed in a server".into());
+    };
+
+    let data = ctx.data();
+    let cases = data.cases();
+
+    match cases.get(guild_id, number).await? {
+        Some(case) => {
+            ctx.send(CreateReply::default().embed(cases.embed(&case).await))
+                .await?;
+        }
+        None => {
+            ctx.say(format!("Case #{} does not exist", number)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Changes the reason of a case, can be plugged into your bots ``/case edit reason`` command
+pub async fn case_edit_reason<Data: HasCases + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    number: u64,
+    reason: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Cases can only be edited in a server".into());
+    };
+
+    let data = ctx.data();
+    let cases = data.cases();
+
+    match cases.edit_reason(guild_id, number, reason).await? {
+        Some(case) => {
+            ctx.send(CreateReply::default().embed(cases.embed(&case).await))
+                .await?;
+        }
+        None => {
+            ctx.say(format!("Case #{} does not exist", number)).await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Shows the case history of a user as a paginated message, can be plugged into your bots ``/case history`` command
+///
+/// The ``Cases`` must be registered on the paginator under ``PAGE_SOURCE``. If ``export`` is set,
+/// moderators can download the whole history in that format
+pub async fn case_history<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    paginator: &Paginator,
+    user: serenity::User,
+    export: Option<ExportFormat>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Cases can only be viewed in a server".into());
+    };
+
+    paginator
+        .send_exportable(
+            ctx,
+            PAGE_SOURCE,
+            format!("{}:{}", guild_id, user.id),
+            export,
+        )
+        .await
+}
+
+/// Exports the case history of a user as JSON, can be plugged into your bots ``/case export`` command
+pub async fn case_export<Data: HasCases + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    user: serenity::User,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Cases can only be exported in a server".into());
+    };
+
+    let data = ctx.data();
+    let cases = data.cases().history(guild_id, user.id).await?;
+
+    ctx.send(CreateReply::default().attachment(CreateAttachment::bytes(
+        serde_json::to_vec_pretty(&cases)?,
+        format!("cases-{}.json", user.id),
+    )))
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,355 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{self as serenity, ChannelId, GuildId, Permissions};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cache::TtlCache;
+use crate::i18n::tr;
+use crate::Error;
+
+/// Per-guild channel allowlist/denylist, entries may be channels or categories
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChannelRestriction {
+    /// If non-empty, commands may only be used in these channels (or channels in these categories)
+    pub allowed: Vec<ChannelId>,
+    /// Commands may never be used in these channels (or channels in these categories)
+    pub denied: Vec<ChannelId>,
+    /// Whether members with Manage Server bypass the restriction
+    pub bypass_managers: bool,
+}
+
+impl ChannelRestriction {
+    /// Returns whether a channel is usable given the channel and its ancestors (parent channel, category)
+    pub fn allows(&self, channel_id: ChannelId, ancestors: &[ChannelId]) -> bool {
+        let matches = |list: &[ChannelId]| {
+            list.contains(&channel_id) || ancestors.iter().any(|a| list.contains(a))
+        };
+
+        if matches(&self.denied) {
+            return false;
+        }
+
+        self.allowed.is_empty() || matches(&self.allowed)
+    }
+}
+
+/// Storage backend for channel restrictions
+pub trait ChannelRestrictionStore: Send + Sync {
+    /// Returns the restriction of a guild, if any
+    fn get<'a>(
+        &'a self,
+        guild_id: GuildId,
+    ) -> BoxFuture<'a, Result<Option<ChannelRestriction>, Error>>;
+
+    /// Sets the restriction of a guild
+    fn set<'a>(
+        &'a self,
+        guild_id: GuildId,
+        restriction: &'a ChannelRestriction,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Channel restrictions with an in-memory cache in front of a ``ChannelRestrictionStore``
+///
+/// Restrictions are cached for an hour. This is cheap to clone
+#[derive(Clone)]
+pub struct ChannelRestrictions {
+    store: Arc<dyn ChannelRestrictionStore>,
+    cache: TtlCache<GuildId, Option<ChannelRestriction>>,
+}
+
+impl ChannelRestrictions {
+    pub fn new(store: Arc<dyn ChannelRestrictionStore>) -> Self {
+        Self {
+            store,
+            cache: TtlCache::new("channel_restrictions", Duration::from_secs(60 * 60), 50_000),
+        }
+    }
+
+    /// Returns the restriction of a guild, if any
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get(&self, guild_id: GuildId) -> Result<Option<ChannelRestriction>, Error> {
+        if let Some(r) = self.cache.get(&guild_id).await {
+            return Ok(r);
+        }
+
+        let r = self.store.get(guild_id).await?;
+
+        self.cache.insert(guild_id, r.clone()).await;
+
+        Ok(r)
+    }
+
+    /// Sets the restriction of a guild, updating the cache
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, restriction)))]
+    pub async fn set(
+        &self,
+        guild_id: GuildId,
+        restriction: ChannelRestriction,
+    ) -> Result<(), Error> {
+        self.store.set(guild_id, &restriction).await?;
+        self.cache.insert(guild_id, Some(restriction)).await;
+
+        Ok(())
+    }
+
+    /// Drops the cached restriction of a guild
+    pub async fn invalidate(&self, guild_id: GuildId) {
+        self.cache.remove(&guild_id).await;
+    }
+
+    /// Returns the restriction cache, for example to add it to a ``MemoryGovernor``
+    pub fn cache(&self) -> &TtlCache<GuildId, Option<ChannelRestriction>> {
+        &self.cache
+    }
+}
+
+/// Trait for bot data that holds ``ChannelRestrictions``
+pub trait HasChannelRestrictions {
+    fn channel_restrictions(&self) -> &ChannelRestrictions;
+}
+
+/// Returns the parent channel and category of a channel from the cache, closest first
+pub fn channel_ancestors(
+    cache: &serenity::Cache,
+    guild_id: GuildId,
+    channel_id: ChannelId,
+) -> Vec<ChannelId> {
+    let Some(guild) = cache.guild(guild_id) else {
+        return Vec::new();
+    };
+
+    let mut ancestors = Vec::new();
+
+    let mut parent = guild
+        .channels
+        .get(&channel_id)
+        .and_then(|c| c.parent_id)
+        .or_else(|| {
+            guild
+                .threads
+                .iter()
+                .find(|t| t.id == channel_id)
+                .and_then(|t| t.parent_id)
+        });
+
+    // Threads are at most two levels deep (thread -> channel -> category)
+    while let Some(id) = parent {
+        if ancestors.contains(&id) || ancestors.len() >= 2 {
+            break;
+        }
+
+        ancestors.push(id);
+        parent = guild.channels.get(&id).and_then(|c| c.parent_id);
+    }
+
+    ancestors
+}
+
+/// Returns the guild permissions of the command author, if they can be determined
+pub async fn author_permissions<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> Option<Permissions> {
+    let member = ctx.author_member().await?;
+
+    if let Some(permissions) = member.permissions {
+        return Some(permissions);
+    }
+
+    let guild = ctx.guild()?;
+    Some(guild.member_permissions(&member))
+}
+
+/// Check that allows or denies commands based on the guilds channel restrictions
+///
+/// Use as ``command_check: Some(botox::checks::channel_restrictions::<Data>)`` or in a commands ``checks``
+pub fn channel_restrictions<Data: HasChannelRestrictions + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> BoxFuture<'_, Result<bool, crate::Error>> {
+    Box::pin(async move {
+        let Some(guild_id) = ctx.guild_id() else {
+            return Ok(true);
+        };
+
+        let data = ctx.data();
+        let Some(restriction) = data.channel_restrictions().get(guild_id).await? else {
+            return Ok(true);
+        };
+
+        let ancestors = channel_ancestors(
+            ctx.serenity_context().cache.as_ref(),
+            guild_id,
+            ctx.channel_id(),
+        );
+
+        if restriction.allows(ctx.channel_id(), &ancestors) {
+            return Ok(true);
+        }
+
+        if restriction.bypass_managers {
+            if let Some(permissions) = author_permissions(ctx).await {
+                if permissions.manage_guild() {
+                    return Ok(true);
+                }
+            }
+        }
+
+        if restriction.allowed.is_empty() {
+            Err(tr(ctx, "checks.channel_denied", &[]).into())
+        } else {
+            let channels = restriction
+                .allowed
+                .iter()
+                .map(|c| format!("<#{}>", c))
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            Err(tr(
+                ctx,
+                "checks.channel_allowed_only",
+                &[("channels", &channels)],
+            )
+            .into())
+        }
+    })
+}
+
+/// Which list a channel restriction command operates on
+#[derive(poise::ChoiceParameter, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RestrictionList {
+    Allow,
+    Deny,
+}
+
+async fn _edit_restriction<Data: HasChannelRestrictions + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    f: impl FnOnce(&mut ChannelRestriction) -> String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err(tr(ctx, "checks.restrict_server_only", &[]).into());
+    };
+
+    let data = ctx.data();
+    let restrictions = data.channel_restrictions();
+
+    let mut restriction = restrictions.get(guild_id).await?.unwrap_or_default();
+    let msg = f(&mut restriction);
+
+    restrictions.set(guild_id, restriction).await?;
+
+    ctx.say(msg).await?;
+
+    Ok(())
+}
+
+/// Adds a channel or category to a list, can be plugged into your bots ``/restrict add`` command
+///
+/// Permission checks (such as Manage Server) should be set on the command itself
+pub async fn restrict_add<Data: HasChannelRestrictions + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    list: RestrictionList,
+    channel_id: ChannelId,
+) -> Result<(), Error> {
+    _edit_restriction(ctx, |r| {
+        let (list, key) = match list {
+            RestrictionList::Allow => (&mut r.allowed, "checks.allowlist"),
+            RestrictionList::Deny => (&mut r.denied, "checks.denylist"),
+        };
+
+        let (channel, name) = (format!("<#{}>", channel_id), tr(ctx, key, &[]));
+        let args = [("channel", channel.as_str()), ("list", name.as_str())];
+
+        if list.contains(&channel_id) {
+            return tr(ctx, "checks.already_listed", &args);
+        }
+
+        list.push(channel_id);
+        tr(ctx, "checks.listed", &args)
+    })
+    .await
+}
+
+/// Removes a channel or category from a list, can be plugged into your bots ``/restrict remove`` command
+pub async fn restrict_remove<Data: HasChannelRestrictions + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    list: RestrictionList,
+    channel_id: ChannelId,
+) -> Result<(), Error> {
+    _edit_restriction(ctx, |r| {
+        let (list, key) = match list {
+            RestrictionList::Allow => (&mut r.allowed, "checks.allowlist"),
+            RestrictionList::Deny => (&mut r.denied, "checks.denylist"),
+        };
+
+        let (channel, name) = (format!("<#{}>", channel_id), tr(ctx, key, &[]));
+        let args = [("channel", channel.as_str()), ("list", name.as_str())];
+
+        let len = list.len();
+        list.retain(|c| *c != channel_id);
+
+        if list.len() == len {
+            tr(ctx, "checks.not_listed", &args)
+        } else {
+            tr(ctx, "checks.unlisted", &args)
+        }
+    })
+    .await
+}
+
+/// Toggles whether members with Manage Server bypass the restriction, can be plugged into your bots ``/restrict bypass`` command
+pub async fn restrict_bypass<Data: HasChannelRestrictions + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    enabled: bool,
+) -> Result<(), Error> {
+    _edit_restriction(ctx, |r| {
+        r.bypass_managers = enabled;
+
+        if enabled {
+            tr(ctx, "checks.bypass_on", &[])
+        } else {
+            tr(ctx, "checks.bypass_off", &[])
+        }
+    })
+    .await
+}
+
+/// Shows the current channel restrictions, can be plugged into your bots ``/restrict list`` command
+pub async fn restrict_list<Data: HasChannelRestrictions + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err(tr(ctx, "checks.restrict_server_only", &[]).into());
+    };
+
+    let data = ctx.data();
+    let restriction = data
+        .channel_restrictions()
+        .get(guild_id)
+        .await?
+        .unwrap_or_default();
+
+    let fmt_list = |list: &[ChannelId]| {
+        if list.is_empty() {
+            tr(ctx, "checks.none", &[])
+        } else {
+            list.iter()
+                .map(|c| format!("<#{}>", c))
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+    };
+
+    ctx.say(tr(
+        ctx,
+        "checks.restrict_list",
+        &[
+            ("allowed", &fmt_list(&restriction.allowed)),
+            ("denied", &fmt_list(&restriction.denied)),
+            ("bypass", &restriction.bypass_managers.to_string()),
+        ],
+    ))
+    .await?;
+
+    Ok(())
+}
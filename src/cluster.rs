@@ -0,0 +1,866 @@
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::{mpsc, oneshot, Mutex, RwLock};
+
+use crate::shards::{ShardMonitor, ShardStatus};
+use crate::taskman::Task;
+use crate::Error;
+
+/// The shard range assigned to a cluster
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Assignment {
+    pub cluster_id: u32,
+    /// First shard of the cluster
+    pub shard_start: u32,
+    /// Last shard of the cluster (inclusive)
+    pub shard_end: u32,
+    /// Total number of shards across all clusters
+    pub shard_total: u32,
+}
+
+/// Stats reported by a cluster
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ClusterStats {
+    pub cluster_id: u32,
+    pub guilds: u64,
+    pub shards: Vec<ShardStatus>,
+    /// Whether every shard of the cluster is connected
+    pub ready: bool,
+}
+
+/// A message of the coordinator/worker protocol, sent as one JSON object per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum ClusterMessage {
+    /// Worker -> coordinator, sent once on connect. ``secret`` must match the secret of the
+    /// coordinator and ``cluster_id`` is the preferred cluster, used when reconnecting after a restart
+    Hello {
+        secret: String,
+        cluster_id: Option<u32>,
+    },
+    /// Coordinator -> worker, reply to ``Hello``
+    Assign(Assignment),
+    /// Worker -> coordinator, periodic stats
+    Stats(ClusterStats),
+    /// Worker -> coordinator, asks for the stats of all clusters
+    StatsRequest { nonce: u64 },
+    /// Coordinator -> worker, reply to ``StatsRequest``
+    StatsResponse {
+        nonce: u64,
+        clusters: Vec<ClusterStats>,
+    },
+    /// Coordinator -> worker, the worker should shut down so it can be restarted
+    Restart,
+    /// Coordinator -> worker, a fatal protocol error
+    Error { message: String },
+    /// Worker -> coordinator, a call to one (``target``) or all workers
+    RpcRequest {
+        id: u64,
+        origin: u32,
+        target: Option<u32>,
+        method: String,
+        payload: serde_json::Value,
+    },
+    /// Coordinator -> worker, the number of workers an ``RpcRequest`` was sent to
+    RpcDispatched { id: u64, recipients: u32 },
+    /// Coordinator -> worker, a call to handle
+    RpcCall {
+        id: u64,
+        origin: u32,
+        method: String,
+        payload: serde_json::Value,
+    },
+    /// Worker -> coordinator -> worker, the result of handling an ``RpcCall``
+    RpcReply {
+        id: u64,
+        origin: u32,
+        cluster_id: u32,
+        result: Result<serde_json::Value, String>,
+    },
+}
+
+async fn _write_message(
+    writer: &mut (impl AsyncWriteExt + Unpin),
+    msg: &ClusterMessage,
+) -> Result<(), Error> {
+    let mut line = serde_json::to_vec(msg)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    Ok(())
+}
+
+/// Spawns a task writing all messages from the returned sender to ``writer``
+fn _spawn_writer(
+    mut writer: impl AsyncWriteExt + Unpin + Send + 'static,
+) -> mpsc::UnboundedSender<ClusterMessage> {
+    let (tx, mut rx) = mpsc::unbounded_channel::<ClusterMessage>();
+
+    tokio::spawn(async move {
+        while let Some(msg) = rx.recv().await {
+            if let Err(e) = _write_message(&mut writer, &msg).await {
+                log::error!("Failed to write cluster message: {}", e);
+                break;
+            }
+        }
+    });
+
+    tx
+}
+
+/// Cluster layout used by the coordinator
+#[derive(Debug, Clone, Copy)]
+pub struct ClusterConfig {
+    pub clusters: u32,
+    pub shards_per_cluster: u32,
+}
+
+impl ClusterConfig {
+    /// Checks that the layout has at least one shard and that the shard total fits in a u32
+    pub fn validate(&self) -> Result<(), Error> {
+        if self.clusters == 0 {
+            return Err("clusters must be at least 1".into());
+        }
+
+        if self.shards_per_cluster == 0 {
+            return Err("shards_per_cluster must be at least 1".into());
+        }
+
+        if self.clusters.checked_mul(self.shards_per_cluster).is_none() {
+            return Err("clusters * shards_per_cluster is too large".into());
+        }
+
+        Ok(())
+    }
+}
+
+struct WorkerHandle {
+    tx: mpsc::UnboundedSender<ClusterMessage>,
+    stats: Option<ClusterStats>,
+    /// Unique id of the connection, used to tell a restarted worker apart from the old one
+    connection: u64,
+}
+
+/// Compares two secrets without returning early on the first mismatching byte
+fn _secret_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+/// Assigns shard ranges to worker processes and aggregates their stats
+///
+/// Workers must present the shared secret passed to ``Coordinator::new`` in their ``Hello``.
+/// The protocol is plain TCP, so the coordinator should still only be bound to a private
+/// interface (such as ``127.0.0.1`` or an internal network), never a public one.
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct Coordinator {
+    config: ClusterConfig,
+    secret: Arc<str>,
+    workers: Arc<Mutex<HashMap<u32, WorkerHandle>>>,
+    next_connection: Arc<AtomicU64>,
+}
+
+impl Coordinator {
+    /// ``secret`` is the shared secret workers must connect with, see ``Worker::connect``
+    ///
+    /// Errors if the config is invalid, see ``ClusterConfig::validate``
+    pub fn new(config: ClusterConfig, secret: impl Into<String>) -> Result<Self, Error> {
+        config.validate()?;
+
+        Ok(Self {
+            config,
+            secret: secret.into().into(),
+            workers: Arc::new(Mutex::new(HashMap::new())),
+            next_connection: Arc::new(AtomicU64::new(0)),
+        })
+    }
+
+    /// Returns the shard range of a cluster
+    pub fn assignment(&self, cluster_id: u32) -> Assignment {
+        Assignment {
+            cluster_id,
+            shard_start: cluster_id * self.config.shards_per_cluster,
+            shard_end: (cluster_id + 1) * self.config.shards_per_cluster - 1,
+            shard_total: self.config.clusters * self.config.shards_per_cluster,
+        }
+    }
+
+    /// Accepts worker connections forever
+    ///
+    /// ``addr`` should be a private interface, see ``Coordinator``
+    pub async fn serve(self, addr: impl ToSocketAddrs) -> Result<(), Error> {
+        let listener = TcpListener::bind(addr).await?;
+
+        loop {
+            let (stream, peer) = listener.accept().await?;
+
+            let coordinator = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = coordinator._handle_conn(stream).await {
+                    log::error!("Cluster connection from {} failed: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    async fn _handle_conn(&self, stream: TcpStream) -> Result<(), Error> {
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+
+        let Some(line) = lines.next_line().await? else {
+            return Ok(());
+        };
+
+        let ClusterMessage::Hello { secret, cluster_id } = serde_json::from_str(&line)? else {
+            _write_message(
+                &mut write,
+                &ClusterMessage::Error {
+                    message: "Expected hello".to_string(),
+                },
+            )
+            .await?;
+            return Err("Worker did not send hello".into());
+        };
+
+        if !_secret_eq(&secret, &self.secret) {
+            _write_message(
+                &mut write,
+                &ClusterMessage::Error {
+                    message: "Invalid secret".to_string(),
+                },
+            )
+            .await?;
+            return Err("Worker sent an invalid secret".into());
+        }
+
+        let connection = self.next_connection.fetch_add(1, Ordering::Relaxed);
+        let tx = _spawn_writer(write);
+
+        let cluster_id = {
+            let mut workers = self.workers.lock().await;
+
+            // A worker asking for its previous cluster is reconnecting after a restart. Its old
+            // handle is only replaced once that connection is gone, otherwise two live workers
+            // would run the same shards, so the worker gets a free cluster instead
+            let free = |c: &u32| !workers.get(c).is_some_and(|w| !w.tx.is_closed());
+
+            let cluster_id = cluster_id
+                .filter(|c| *c < self.config.clusters && free(c))
+                .or_else(|| (0..self.config.clusters).find(free));
+
+            let Some(cluster_id) = cluster_id else {
+                let _ = tx.send(ClusterMessage::Error {
+                    message: "All clusters are already assigned".to_string(),
+                });
+                return Err("No free cluster for worker".into());
+            };
+
+            let old = workers.insert(
+                cluster_id,
+                WorkerHandle {
+                    tx: tx.clone(),
+                    stats: None,
+                    connection,
+                },
+            );
+
+            if old.is_some() {
+                log::info!("Replacing closed connection of cluster {}", cluster_id);
+            }
+
+            cluster_id
+        };
+
+        log::info!("Cluster {} connected", cluster_id);
+
+        tx.send(ClusterMessage::Assign(self.assignment(cluster_id)))?;
+
+        let res = self._read_loop(cluster_id, &tx, &mut lines).await;
+
+        // Only remove the handle if it has not already been replaced by a newer connection
+        let mut workers = self.workers.lock().await;
+        if workers.get(&cluster_id).map(|w| w.connection) == Some(connection) {
+            workers.remove(&cluster_id);
+        }
+
+        log::info!("Cluster {} disconnected", cluster_id);
+
+        res
+    }
+
+    async fn _read_loop(
+        &self,
+        cluster_id: u32,
+        tx: &mpsc::UnboundedSender<ClusterMessage>,
+        lines: &mut tokio::io::Lines<BufReader<tokio::net::tcp::OwnedReadHalf>>,
+    ) -> Result<(), Error> {
+        while let Some(line) = lines.next_line().await? {
+            let msg = match serde_json::from_str(&line) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    log::warn!("Invalid message from cluster {}: {}", cluster_id, e);
+                    continue;
+                }
+            };
+
+            match msg {
+                ClusterMessage::Stats(stats) => {
+                    if let Some(worker) = self.workers.lock().await.get_mut(&cluster_id) {
+                        worker.stats = Some(stats);
+                    }
+                }
+                ClusterMessage::StatsRequest { nonce } => {
+                    tx.send(ClusterMessage::StatsResponse {
+                        nonce,
+                        clusters: self.stats().await,
+                    })?;
+                }
+                ClusterMessage::RpcRequest {
+                    id,
+                    origin,
+                    target,
+                    method,
+                    payload,
+                } => {
+                    let workers = self.workers.lock().await;
+
+                    let targets = workers
+                        .iter()
+                        .filter(|(c, _)| target.map_or(true, |t| t == **c))
+                        .map(|(_, w)| &w.tx)
+                        .collect::<Vec<_>>();
+
+                    tx.send(ClusterMessage::RpcDispatched {
+                        id,
+                        recipients: targets.len() as u32,
+                    })?;
+
+                    for target in targets {
+                        let _ = target.send(ClusterMessage::RpcCall {
+                            id,
+                            origin,
+                            method: method.clone(),
+                            payload: payload.clone(),
+                        });
+                    }
+                }
+                msg @ ClusterMessage::RpcReply { origin, .. } => {
+                    if let Some(worker) = self.workers.lock().await.get(&origin) {
+                        let _ = worker.tx.send(msg);
+                    }
+                }
+                msg => {
+                    log::warn!("Unexpected message from cluster {}: {:?}", cluster_id, msg);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the last reported stats of every connected cluster, sorted by cluster id
+    pub async fn stats(&self) -> Vec<ClusterStats> {
+        let mut stats = self
+            .workers
+            .lock()
+            .await
+            .values()
+            .filter_map(|w| w.stats.clone())
+            .collect::<Vec<_>>();
+
+        stats.sort_by_key(|s| s.cluster_id);
+
+        stats
+    }
+
+    /// Returns the total guild count across all clusters
+    pub async fn total_guilds(&self) -> u64 {
+        self.stats().await.iter().map(|s| s.guilds).sum()
+    }
+
+    /// Asks a cluster to restart, returning false if it is not connected
+    pub async fn restart(&self, cluster_id: u32) -> Result<bool, Error> {
+        let workers = self.workers.lock().await;
+
+        let Some(worker) = workers.get(&cluster_id) else {
+            return Ok(false);
+        };
+
+        worker.tx.send(ClusterMessage::Restart)?;
+
+        Ok(true)
+    }
+
+    /// Restarts every cluster one at a time, waiting up to ``timeout`` for each cluster to
+    /// reconnect and report ready before moving on to the next
+    pub async fn rolling_restart(&self, timeout: Duration) -> Result<(), Error> {
+        for cluster_id in 0..self.config.clusters {
+            let old_connection = self
+                .workers
+                .lock()
+                .await
+                .get(&cluster_id)
+                .map(|w| w.connection);
+
+            if old_connection.is_none() {
+                log::warn!("Cluster {} is not connected, skipping restart", cluster_id);
+                continue;
+            }
+
+            log::info!("Restarting cluster {}", cluster_id);
+            self.restart(cluster_id).await?;
+
+            let ready = tokio::time::timeout(timeout, async {
+                loop {
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+
+                    let workers = self.workers.lock().await;
+                    if let Some(worker) = workers.get(&cluster_id) {
+                        if Some(worker.connection) != old_connection
+                            && worker.stats.as_ref().is_some_and(|s| s.ready)
+                        {
+                            return;
+                        }
+                    }
+                }
+            })
+            .await;
+
+            if ready.is_err() {
+                return Err(format!(
+                    "Cluster {} did not become ready within {:?}, aborting rolling restart",
+                    cluster_id, timeout
+                )
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A typed RPC method that can be called on other clusters
+pub trait RpcMethod: Serialize + DeserializeOwned + Send + 'static {
+    /// Unique name of the method
+    const NAME: &'static str;
+    type Response: Serialize + DeserializeOwned + Send + 'static;
+}
+
+/// Finds which cluster has a guild in its cache, responds with its cluster id if it does
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FindGuild {
+    pub guild_id: serenity::all::GuildId,
+}
+
+impl RpcMethod for FindGuild {
+    const NAME: &'static str = "botox:find_guild";
+    type Response = Option<u32>;
+}
+
+/// Returns cache stats of a cluster
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStats;
+
+/// Response to ``CacheStats``
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheStatsResponse {
+    pub cluster_id: u32,
+    pub guilds: usize,
+}
+
+impl RpcMethod for CacheStats {
+    const NAME: &'static str = "botox:cache_stats";
+    type Response = CacheStatsResponse;
+}
+
+type RpcHandler = Box<
+    dyn Send + Sync + Fn(serde_json::Value) -> BoxFuture<'static, Result<serde_json::Value, Error>>,
+>;
+
+enum RpcEvent {
+    Dispatched(u32),
+    Reply(u32, Result<serde_json::Value, String>),
+}
+
+/// The aggregated responses to an RPC call
+#[derive(Debug)]
+pub struct RpcResults<T> {
+    /// The response of each cluster that replied, by cluster id
+    pub responses: Vec<(u32, Result<T, String>)>,
+    /// Whether the timeout was hit before every cluster replied
+    pub timed_out: bool,
+}
+
+/// Typed request/response calls between clusters, routed through the coordinator
+pub struct Rpc {
+    cluster_id: u32,
+    tx: mpsc::UnboundedSender<ClusterMessage>,
+    handlers: RwLock<HashMap<String, RpcHandler>>,
+    pending: Mutex<HashMap<u64, mpsc::UnboundedSender<RpcEvent>>>,
+    next_id: AtomicU64,
+}
+
+impl Rpc {
+    /// Registers the handler for a method, replacing any existing handler
+    pub async fn register<M: RpcMethod>(
+        &self,
+        handler: impl Send + Sync + 'static + Fn(M) -> BoxFuture<'static, Result<M::Response, Error>>,
+    ) {
+        let handler = Arc::new(handler);
+
+        self.handlers.write().await.insert(
+            M::NAME.to_string(),
+            Box::new(move |payload| {
+                let handler = handler.clone();
+                Box::pin(async move {
+                    let req: M = serde_json::from_value(payload)?;
+                    let res = handler(req).await?;
+                    Ok(serde_json::to_value(res)?)
+                })
+            }),
+        );
+    }
+
+    /// Registers handlers for the built-in ``FindGuild`` and ``CacheStats`` methods
+    pub async fn register_builtins(&self, cache: Arc<serenity::all::Cache>) {
+        let cluster_id = self.cluster_id;
+
+        let find_cache = cache.clone();
+        self.register::<FindGuild>(move |req| {
+            let found = find_cache.guild(req.guild_id).is_some();
+            Box::pin(async move { Ok(found.then_some(cluster_id)) })
+        })
+        .await;
+
+        self.register::<CacheStats>(move |_| {
+            let res = CacheStatsResponse {
+                cluster_id,
+                guilds: cache.guild_count(),
+            };
+            Box::pin(async move { Ok(res) })
+        })
+        .await;
+    }
+
+    async fn _call(
+        &self,
+        target: Option<u32>,
+        method: &str,
+        payload: serde_json::Value,
+        timeout: Duration,
+    ) -> Result<RpcResults<serde_json::Value>, Error> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, mut rx) = mpsc::unbounded_channel();
+
+        self.pending.lock().await.insert(id, tx);
+
+        self.tx.send(ClusterMessage::RpcRequest {
+            id,
+            origin: self.cluster_id,
+            target,
+            method: method.to_string(),
+            payload,
+        })?;
+
+        let mut expected = None;
+        let mut responses = Vec::new();
+
+        let collect = async {
+            while expected.map_or(true, |e| responses.len() < e as usize) {
+                match rx.recv().await {
+                    Some(RpcEvent::Dispatched(n)) => expected = Some(n),
+                    Some(RpcEvent::Reply(cluster_id, res)) => responses.push((cluster_id, res)),
+                    None => break,
+                }
+            }
+        };
+
+        let timed_out = tokio::time::timeout(timeout, collect).await.is_err();
+
+        self.pending.lock().await.remove(&id);
+
+        responses.sort_by_key(|(c, _)| *c);
+
+        Ok(RpcResults {
+            responses,
+            timed_out,
+        })
+    }
+
+    /// Calls a method on every cluster (including this one), waiting up to ``timeout`` for replies
+    pub async fn call_all<M: RpcMethod>(
+        &self,
+        req: &M,
+        timeout: Duration,
+    ) -> Result<RpcResults<M::Response>, Error> {
+        let results = self
+            ._call(None, M::NAME, serde_json::to_value(req)?, timeout)
+            .await?;
+
+        Ok(RpcResults {
+            responses: results
+                .responses
+                .into_iter()
+                .map(|(c, res)| {
+                    (
+                        c,
+                        res.and_then(|v| serde_json::from_value(v).map_err(|e| e.to_string())),
+                    )
+                })
+                .collect(),
+            timed_out: results.timed_out,
+        })
+    }
+
+    /// Calls a method on a single cluster, waiting up to ``timeout`` for its reply
+    pub async fn call_one<M: RpcMethod>(
+        &self,
+        cluster_id: u32,
+        req: &M,
+        timeout: Duration,
+    ) -> Result<M::Response, Error> {
+        let results = self
+            ._call(
+                Some(cluster_id),
+                M::NAME,
+                serde_json::to_value(req)?,
+                timeout,
+            )
+            .await?;
+
+        match results.responses.into_iter().next() {
+            Some((_, Ok(v))) => Ok(serde_json::from_value(v)?),
+            Some((_, Err(e))) => Err(e.into()),
+            None if results.timed_out => {
+                Err(format!("Cluster {} did not reply in time", cluster_id).into())
+            }
+            None => Err(format!("Cluster {} is not connected", cluster_id).into()),
+        }
+    }
+
+    async fn _handle(self: Arc<Self>, msg: ClusterMessage) {
+        match msg {
+            ClusterMessage::RpcDispatched { id, recipients } => {
+                if let Some(tx) = self.pending.lock().await.get(&id) {
+                    let _ = tx.send(RpcEvent::Dispatched(recipients));
+                }
+            }
+            ClusterMessage::RpcReply {
+                id,
+                cluster_id,
+                result,
+                ..
+            } => {
+                if let Some(tx) = self.pending.lock().await.get(&id) {
+                    let _ = tx.send(RpcEvent::Reply(cluster_id, result));
+                }
+            }
+            ClusterMessage::RpcCall {
+                id,
+                origin,
+                method,
+                payload,
+            } => {
+                let fut = self
+                    .handlers
+                    .read()
+                    .await
+                    .get(&method)
+                    .map(|handler| handler(payload));
+
+                let result = match fut {
+                    Some(fut) => fut.await.map_err(|e| e.to_string()),
+                    None => Err(format!("No handler for {}", method)),
+                };
+
+                let _ = self.tx.send(ClusterMessage::RpcReply {
+                    id,
+                    origin,
+                    cluster_id: self.cluster_id,
+                    result,
+                });
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Connection from a bot process to the coordinator
+pub struct Worker {
+    assignment: Assignment,
+    tx: mpsc::UnboundedSender<ClusterMessage>,
+    pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Vec<ClusterStats>>>>>,
+    nonce: AtomicU64,
+    restart: Mutex<mpsc::UnboundedReceiver<()>>,
+    rpc: Arc<Rpc>,
+}
+
+impl Worker {
+    /// Connects to the coordinator and waits for a shard range to be assigned
+    ///
+    /// ``secret`` must match the secret of the coordinator and ``cluster_id`` is the preferred
+    /// cluster, for example from a previous run
+    pub async fn connect(
+        addr: impl ToSocketAddrs,
+        secret: &str,
+        cluster_id: Option<u32>,
+    ) -> Result<Self, Error> {
+        let stream = TcpStream::connect(addr).await?;
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+
+        _write_message(
+            &mut write,
+            &ClusterMessage::Hello {
+                secret: secret.to_string(),
+                cluster_id,
+            },
+        )
+        .await?;
+
+        let assignment = match lines.next_line().await? {
+            Some(line) => match serde_json::from_str(&line)? {
+                ClusterMessage::Assign(assignment) => assignment,
+                ClusterMessage::Error { message } => return Err(message.into()),
+                msg => return Err(format!("Expected assignment, got {:?}", msg).into()),
+            },
+            None => return Err("Coordinator closed the connection".into()),
+        };
+
+        let tx = _spawn_writer(write);
+        let pending: Arc<Mutex<HashMap<u64, oneshot::Sender<Vec<ClusterStats>>>>> =
+            Arc::new(Mutex::new(HashMap::new()));
+        let (restart_tx, restart_rx) = mpsc::unbounded_channel();
+
+        let rpc = Arc::new(Rpc {
+            cluster_id: assignment.cluster_id,
+            tx: tx.clone(),
+            handlers: RwLock::new(HashMap::new()),
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(0),
+        });
+
+        let reader_pending = pending.clone();
+        let reader_rpc = rpc.clone();
+        tokio::spawn(async move {
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => break,
+                    Err(e) => {
+                        log::error!("Failed to read from coordinator: {}", e);
+                        break;
+                    }
+                };
+
+                match serde_json::from_str::<ClusterMessage>(&line) {
+                    Ok(ClusterMessage::StatsResponse { nonce, clusters }) => {
+                        if let Some(tx) = reader_pending.lock().await.remove(&nonce) {
+                            let _ = tx.send(clusters);
+                        }
+                    }
+                    Ok(ClusterMessage::Restart) => {
+                        let _ = restart_tx.send(());
+                    }
+                    Ok(
+                        msg @ (ClusterMessage::RpcDispatched { .. }
+                        | ClusterMessage::RpcReply { .. }
+                        | ClusterMessage::RpcCall { .. }),
+                    ) => {
+                        // Handlers may take a while, so don't block reading on them
+                        tokio::spawn(reader_rpc.clone()._handle(msg));
+                    }
+                    Ok(msg) => log::warn!("Unexpected message from coordinator: {:?}", msg),
+                    Err(e) => log::error!("Invalid message from coordinator: {}", e),
+                }
+            }
+
+            log::error!("Lost connection to the coordinator");
+        });
+
+        Ok(Self {
+            assignment,
+            tx,
+            pending,
+            nonce: AtomicU64::new(0),
+            restart: Mutex::new(restart_rx),
+            rpc,
+        })
+    }
+
+    /// Returns the RPC handle of this process, for registering handlers and calling other clusters
+    pub fn rpc(&self) -> &Arc<Rpc> {
+        &self.rpc
+    }
+
+    /// Returns the shard range assigned to this process
+    pub fn assignment(&self) -> &Assignment {
+        &self.assignment
+    }
+
+    /// Reports the stats of this cluster to the coordinator
+    pub fn report(&self, stats: ClusterStats) -> Result<(), Error> {
+        self.tx.send(ClusterMessage::Stats(stats))?;
+        Ok(())
+    }
+
+    /// Fetches the stats of every cluster from the coordinator
+    pub async fn cluster_stats(&self, timeout: Duration) -> Result<Vec<ClusterStats>, Error> {
+        let nonce = self.nonce.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+
+        self.pending.lock().await.insert(nonce, tx);
+        self.tx.send(ClusterMessage::StatsRequest { nonce })?;
+
+        match tokio::time::timeout(timeout, rx).await {
+            Ok(Ok(stats)) => Ok(stats),
+            Ok(Err(_)) => Err("Coordinator connection closed".into()),
+            Err(_) => {
+                self.pending.lock().await.remove(&nonce);
+                Err("Timed out waiting for cluster stats".into())
+            }
+        }
+    }
+
+    /// Resolves once the coordinator asks this process to restart
+    ///
+    /// The bot should shut down its shards and exit so its supervisor can start it again
+    pub async fn wait_for_restart(&self) {
+        let _ = self.restart.lock().await.recv().await;
+    }
+
+    /// Creates a task that periodically reports this clusters stats to the coordinator
+    pub fn stats_task(self: Arc<Self>, monitor: ShardMonitor, interval: Duration) -> Task {
+        Task {
+            name: "cluster_stats",
+            description: "Reports cluster stats to the coordinator",
+            enabled: true,
+            duration: interval,
+            run: Box::new(move |ctx| {
+                let worker = self.clone();
+                let monitor = monitor.clone();
+                Box::pin(async move {
+                    let shards = monitor.snapshot().await;
+
+                    worker.report(ClusterStats {
+                        cluster_id: worker.assignment.cluster_id,
+                        guilds: ctx.cache.guild_count() as u64,
+                        ready: !shards.is_empty() && shards.iter().all(|s| s.connected),
+                        shards,
+                    })
+                })
+            }),
+        }
+    }
+}
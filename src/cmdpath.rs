@@ -0,0 +1,109 @@
+use crate::Error;
+
+/// What ``walk`` should do after visiting a command
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visit {
+    Continue,
+    /// Do not visit the subcommands of this command
+    SkipChildren,
+    /// Stop walking entirely
+    Stop,
+}
+
+/// Returns the full path of the invoked command including its parents, such as ``config prefix set``
+pub fn invoked_path<Data: Send + Sync + 'static>(ctx: poise::Context<'_, Data, Error>) -> String {
+    ctx.command().qualified_name.to_string()
+}
+
+/// Returns the top-level command of the invoked command, such as ``config`` for ``config prefix set``
+pub fn root<Data: Send + Sync + 'static>(ctx: poise::Context<'_, Data, Error>) -> String {
+    ctx.command()
+        .qualified_name
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+/// Normalizes a user supplied path, accepting ``/config prefix set``, ``config.prefix.set`` and
+/// ``config/prefix/set`` as ``config prefix set``
+pub fn normalize(path: &str) -> String {
+    path.split(|c: char| c.is_whitespace() || c == '/' || c == '.')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Finds a command by path (see ``normalize``), matching names and aliases at every level
+pub fn find<'a, Data>(
+    commands: &'a [poise::Command<Data, Error>],
+    path: &str,
+) -> Option<&'a poise::Command<Data, Error>> {
+    let path = normalize(path);
+    let mut level = commands;
+    let mut found = None;
+
+    for segment in path.split(' ') {
+        let command = level.iter().find(|c| {
+            c.name.eq_ignore_ascii_case(segment)
+                || c.aliases.iter().any(|a| a.eq_ignore_ascii_case(segment))
+        })?;
+
+        found = Some(command);
+        level = &command.subcommands;
+    }
+
+    found
+}
+
+fn _walk<Data>(
+    commands: &[poise::Command<Data, Error>],
+    depth: usize,
+    visitor: &mut impl FnMut(&poise::Command<Data, Error>, usize) -> Visit,
+) -> bool {
+    for command in commands {
+        match visitor(command, depth) {
+            Visit::Stop => return false,
+            Visit::SkipChildren => {}
+            Visit::Continue => {
+                if !_walk(&command.subcommands, depth + 1, visitor) {
+                    return false;
+                }
+            }
+        }
+    }
+
+    true
+}
+
+/// Visits every command depth-first, parents before their subcommands
+///
+/// The visitor receives the command and its depth (0 for top-level commands). Returns false if
+/// the walk was stopped by the visitor
+pub fn walk<Data>(
+    commands: &[poise::Command<Data, Error>],
+    mut visitor: impl FnMut(&poise::Command<Data, Error>, usize) -> Visit,
+) -> bool {
+    _walk(commands, 0, &mut visitor)
+}
+
+/// Returns every command and subcommand, parents before their subcommands
+pub fn flatten<Data>(
+    commands: &[poise::Command<Data, Error>],
+) -> Vec<&poise::Command<Data, Error>> {
+    let mut all = Vec::new();
+
+    fn _push<'a, Data>(
+        commands: &'a [poise::Command<Data, Error>],
+        all: &mut Vec<&'a poise::Command<Data, Error>>,
+    ) {
+        for command in commands {
+            all.push(command);
+            _push(&command.subcommands, all);
+        }
+    }
+
+    _push(commands, &mut all);
+
+    all
+}
@@ -0,0 +1,112 @@
+use futures::{Stream, StreamExt};
+use poise::serenity_prelude::{
+    self as serenity, collector::ReactionCollector, Message, Reaction, ReactionType, UserId,
+};
+use std::time::Duration;
+
+/// Filters and limits for ``reactions``
+#[derive(Debug, Clone)]
+pub struct ReactionOptions {
+    /// Only collect reactions from this user, if set
+    pub author_id: Option<UserId>,
+    /// Only collect these emojis, empty for any emoji
+    pub emojis: Vec<ReactionType>,
+    /// Stop after this long without a collected reaction, the timer resets on every reaction
+    pub idle_timeout: Duration,
+    /// Stop after this long regardless of activity, if set
+    pub timeout: Option<Duration>,
+    /// Stop after collecting this many reactions, if set
+    pub max: Option<usize>,
+    pub ignore_bots: bool,
+}
+
+impl Default for ReactionOptions {
+    fn default() -> Self {
+        Self {
+            author_id: None,
+            emojis: Vec::new(),
+            idle_timeout: Duration::from_secs(60),
+            timeout: None,
+            max: None,
+            ignore_bots: true,
+        }
+    }
+}
+
+/// Returns a stream of reactions added to a message, ending on idle timeout, overall timeout or
+/// once ``max`` reactions were collected
+pub fn reactions(
+    shard: serenity::ShardMessenger,
+    msg: &Message,
+    opts: ReactionOptions,
+) -> impl Stream<Item = Reaction> + Send + 'static {
+    let ReactionOptions {
+        author_id,
+        emojis,
+        idle_timeout,
+        timeout,
+        max,
+        ignore_bots,
+    } = opts;
+
+    let mut collector =
+        ReactionCollector::new(shard)
+            .message_id(msg.id)
+            .filter(move |r: &Reaction| {
+                if author_id.is_some_and(|a| r.user_id != Some(a)) {
+                    return false;
+                }
+
+                if ignore_bots && r.member.as_ref().is_some_and(|m| m.user.bot()) {
+                    return false;
+                }
+
+                emojis.is_empty() || emojis.contains(&r.emoji)
+            });
+
+    if let Some(timeout) = timeout {
+        collector = collector.timeout(timeout);
+    }
+
+    futures::stream::unfold(
+        (Box::pin(collector.stream()), 0usize),
+        move |(mut inner, collected)| async move {
+            if max.is_some_and(|max| collected >= max) {
+                return None;
+            }
+
+            let reaction = tokio::time::timeout(idle_timeout, inner.next())
+                .await
+                .ok()??;
+
+            Some((reaction, (inner, collected + 1)))
+        },
+    )
+}
+
+/// Collects reactions into a vector, see ``reactions``
+pub async fn collect_reactions(
+    shard: serenity::ShardMessenger,
+    msg: &Message,
+    opts: ReactionOptions,
+) -> Vec<Reaction> {
+    reactions(shard, msg, opts).collect().await
+}
+
+/// Waits for a single matching reaction, returning None on timeout
+pub async fn await_reaction(
+    shard: serenity::ShardMessenger,
+    msg: &Message,
+    opts: ReactionOptions,
+) -> Option<Reaction> {
+    let mut stream = std::pin::pin!(reactions(
+        shard,
+        msg,
+        ReactionOptions {
+            max: Some(1),
+            ..opts
+        },
+    ));
+
+    stream.next().await
+}
@@ -0,0 +1,291 @@
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+
+use crate::Error;
+
+/// Environment variable ``load`` reads the environment name from, such as ``production``
+pub const ENVIRONMENT_VAR: &str = "BOTOX_ENV";
+
+/// A config file format, detected from the file extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigFormat {
+    Toml,
+    Yaml,
+    Json,
+}
+
+impl ConfigFormat {
+    pub fn from_path(path: &Path) -> Result<Self, Error> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some("toml") => Ok(ConfigFormat::Toml),
+            Some("yaml" | "yml") => Ok(ConfigFormat::Yaml),
+            Some("json") => Ok(ConfigFormat::Json),
+            _ => Err(format!("Unknown config format for {}", path.display()).into()),
+        }
+    }
+
+    /// Parses a document into a generic value so layers can be merged
+    pub fn parse(&self, text: &str) -> Result<serde_json::Value, Error> {
+        Ok(match self {
+            ConfigFormat::Toml => toml::from_str(text)?,
+            ConfigFormat::Yaml => serde_yaml::from_str(text)?,
+            ConfigFormat::Json => serde_json::from_str(text)?,
+        })
+    }
+}
+
+/// Replaces ``${VAR}`` and ``${VAR:-default}`` in a string with environment variables
+///
+/// ``$${`` is left as a literal ``${``. Fails on unset variables without a default
+pub fn interpolate(text: &str) -> Result<String, Error> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(start) = rest.find('$') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            out.push_str("${");
+            rest = escaped;
+            continue;
+        }
+
+        let Some(body) = rest.strip_prefix("${") else {
+            out.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+
+        let end = body.find('}').ok_or("Unterminated ${")?;
+
+        let (name, default) = match body[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&body[..end], None),
+        };
+
+        match (std::env::var(name), default) {
+            (Ok(value), _) => out.push_str(&value),
+            (Err(_), Some(default)) => out.push_str(default),
+            (Err(_), None) => {
+                return Err(format!("Environment variable {} is not set", name).into())
+            }
+        }
+
+        rest = &body[end + 1..];
+    }
+
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+/// Runs ``interpolate`` on every string in a parsed document
+///
+/// Only values are interpolated, so comments and keys are left alone and substituted values
+/// can't break the syntax of the file
+pub fn interpolate_value(value: &mut serde_json::Value) -> Result<(), Error> {
+    match value {
+        serde_json::Value::String(s) => *s = interpolate(s)?,
+        serde_json::Value::Array(items) => {
+            for item in items {
+                interpolate_value(item)?;
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for item in map.values_mut() {
+                interpolate_value(item)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+/// Merges ``overlay`` into ``base``, tables are merged key by key and everything else is replaced
+pub fn merge(base: &mut serde_json::Value, overlay: serde_json::Value) {
+    match (base, overlay) {
+        (serde_json::Value::Object(base), serde_json::Value::Object(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// Returns the environment specific overlay of a config file, ``config.toml`` becomes ``config.production.toml``
+pub fn overlay_path(path: &Path, environment: &str) -> PathBuf {
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let file_name = match path.extension() {
+        Some(ext) => format!("{}.{}.{}", stem, environment, ext.to_string_lossy()),
+        None => format!("{}.{}", stem, environment),
+    };
+
+    path.with_file_name(file_name)
+}
+
+type Validator<T> = Box<dyn Fn(&T) -> Result<(), Error>>;
+
+/// Loads layered config files
+///
+/// Layers are read in order (base, explicit overlays, then the environment overlay if it exists),
+/// parsed, interpolated, merged and deserialized, then every validation hook is run
+pub struct ConfigLoader<T> {
+    path: PathBuf,
+    overlays: Vec<PathBuf>,
+    environment: Option<String>,
+    validators: Vec<Validator<T>>,
+}
+
+impl<T: DeserializeOwned> ConfigLoader<T> {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            overlays: Vec::new(),
+            environment: None,
+            validators: Vec::new(),
+        }
+    }
+
+    /// Adds an overlay that must exist, may be in a different format to the base
+    pub fn overlay(mut self, path: impl Into<PathBuf>) -> Self {
+        self.overlays.push(path.into());
+        self
+    }
+
+    /// Sets the environment, whose overlay (see ``overlay_path``) is applied last if it exists
+    pub fn environment(mut self, environment: impl Into<String>) -> Self {
+        self.environment = Some(environment.into());
+        self
+    }
+
+    /// Adds a validation hook, run after the config is deserialized
+    pub fn validate(mut self, validator: impl Fn(&T) -> Result<(), Error> + 'static) -> Self {
+        self.validators.push(Box::new(validator));
+        self
+    }
+
+    fn _read(path: &Path) -> Result<serde_json::Value, Error> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+
+        let mut value = ConfigFormat::from_path(path)?
+            .parse(&text)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+
+        interpolate_value(&mut value).map_err(|e| format!("{} in {}", e, path.display()))?;
+
+        Ok(value)
+    }
+
+    pub fn load(self) -> Result<T, Error> {
+        let mut value = Self::_read(&self.path)?;
+
+        for overlay in &self.overlays {
+            merge(&mut value, Self::_read(overlay)?);
+        }
+
+        if let Some(environment) = &self.environment {
+            let path = overlay_path(&self.path, environment);
+
+            if path.exists() {
+                merge(&mut value, Self::_read(&path)?);
+            }
+        }
+
+        let config: T = serde_json::from_value(value)
+            .map_err(|e| format!("Invalid config in {}: {}", self.path.display(), e))?;
+
+        for validator in &self.validators {
+            validator(&config)?;
+        }
+
+        Ok(config)
+    }
+}
+
+/// Loads a config file, applying the overlay of the environment named by ``BOTOX_ENV`` if set
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> Result<T, Error> {
+    let mut loader = ConfigLoader::new(path.as_ref());
+
+    if let Ok(environment) = std::env::var(ENVIRONMENT_VAR) {
+        loader = loader.environment(environment);
+    }
+
+    loader.load()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn interpolate_variables_and_defaults() {
+        std::env::set_var("BOTOX_TEST_INTERPOLATE", "value");
+
+        assert_eq!(
+            interpolate("a ${BOTOX_TEST_INTERPOLATE} b").unwrap(),
+            "a value b"
+        );
+        assert_eq!(
+            interpolate("${BOTOX_TEST_INTERPOLATE_UNSET:-fallback}").unwrap(),
+            "fallback"
+        );
+        assert_eq!(interpolate("costs $5").unwrap(), "costs $5");
+        assert_eq!(
+            interpolate("$${BOTOX_TEST_INTERPOLATE}").unwrap(),
+            "${BOTOX_TEST_INTERPOLATE}"
+        );
+
+        assert!(interpolate("${BOTOX_TEST_INTERPOLATE_UNSET}").is_err());
+        assert!(interpolate("${BOTOX_TEST_INTERPOLATE").is_err());
+    }
+
+    #[test]
+    fn interpolate_value_only_touches_strings() {
+        std::env::set_var("BOTOX_TEST_INTERPOLATE_QUOTED", "say \"hi\"\nbye");
+
+        let mut value = ConfigFormat::Toml
+            .parse(
+                "# ${BOTOX_TEST_INTERPOLATE_UNSET} is not needed here\n\
+                 greeting = \"${BOTOX_TEST_INTERPOLATE_QUOTED}\"\n\
+                 count = 3\n\
+                 [nested]\n\
+                 list = [\"${BOTOX_TEST_INTERPOLATE_UNSET:-x}\"]\n",
+            )
+            .unwrap();
+
+        interpolate_value(&mut value).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "greeting": "say \"hi\"\nbye",
+                "count": 3,
+                "nested": { "list": ["x"] },
+            })
+        );
+    }
+
+    #[test]
+    fn merge_overlays_tables() {
+        let mut base = serde_json::json!({ "a": { "b": 1, "c": 2 }, "d": [1] });
+        merge(&mut base, serde_json::json!({ "a": { "c": 3 }, "d": [2] }));
+
+        assert_eq!(
+            base,
+            serde_json::json!({ "a": { "b": 1, "c": 3 }, "d": [2] })
+        );
+    }
+}
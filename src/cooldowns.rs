@@ -0,0 +1,196 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{GuildId, RoleId, Timestamp, UserId};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::i18n::tr;
+use crate::Error;
+
+/// How a cooldown is reduced for a member
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum CooldownBypass {
+    #[default]
+    None,
+    /// The cooldown is halved
+    Halve,
+    /// The cooldown does not apply
+    Bypass,
+}
+
+impl CooldownBypass {
+    fn apply(&self, duration: Duration) -> Duration {
+        match self {
+            CooldownBypass::None => duration,
+            CooldownBypass::Halve => duration / 2,
+            CooldownBypass::Bypass => Duration::ZERO,
+        }
+    }
+}
+
+/// A guilds cooldown bypass settings
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CooldownSettings {
+    /// Roles and the reduction members with them get, the strongest reduction wins
+    pub roles: HashMap<RoleId, CooldownBypass>,
+    /// Reduction for users (or guilds) with premium, see ``EntitlementProvider``
+    pub premium: CooldownBypass,
+}
+
+/// Storage backend for per-guild cooldown settings
+pub trait CooldownSettingsStore: Send + Sync {
+    fn get<'a>(
+        &'a self,
+        guild_id: GuildId,
+    ) -> BoxFuture<'a, Result<Option<CooldownSettings>, Error>>;
+}
+
+/// Decides whether a user has premium, for example from discord entitlements or a payment provider
+pub trait EntitlementProvider: Send + Sync {
+    /// Returns true if the user, or the guild the command is used in, has premium
+    fn is_premium<'a>(
+        &'a self,
+        guild_id: Option<GuildId>,
+        user_id: UserId,
+    ) -> BoxFuture<'a, Result<bool, Error>>;
+}
+
+/// Per-user command cooldowns that guilds can reduce for roles or premium members
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct Cooldowns {
+    store: Arc<dyn CooldownSettingsStore>,
+    /// Cooldowns by qualified command name, commands not listed have no cooldown
+    durations: Arc<HashMap<String, Duration>>,
+    /// Consulted for the ``premium`` reduction, if set
+    pub entitlements: Option<Arc<dyn EntitlementProvider>>,
+    last_used: Arc<Mutex<HashMap<(String, UserId), Instant>>>,
+}
+
+impl Cooldowns {
+    pub fn new(
+        store: Arc<dyn CooldownSettingsStore>,
+        durations: HashMap<String, Duration>,
+    ) -> Self {
+        Self {
+            store,
+            durations: Arc::new(durations),
+            entitlements: None,
+            last_used: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Returns the strongest reduction that applies to a member
+    pub async fn bypass(
+        &self,
+        guild_id: Option<GuildId>,
+        user_id: UserId,
+        roles: &[RoleId],
+    ) -> Result<CooldownBypass, Error> {
+        let settings = match guild_id {
+            Some(guild_id) => self.store.get(guild_id).await?.unwrap_or_default(),
+            None => CooldownSettings::default(),
+        };
+
+        let mut bypass = roles
+            .iter()
+            .filter_map(|r| settings.roles.get(r))
+            .copied()
+            .max()
+            .unwrap_or_default();
+
+        if settings.premium > bypass {
+            if let Some(entitlements) = &self.entitlements {
+                if entitlements.is_premium(guild_id, user_id).await? {
+                    bypass = settings.premium;
+                }
+            }
+        }
+
+        Ok(bypass)
+    }
+
+    /// Returns how long until a user may use a command again, starting the cooldown if they may use it now
+    pub fn hit(&self, command: &str, user_id: UserId, cooldown: Duration) -> Option<Duration> {
+        if cooldown.is_zero() {
+            return None;
+        }
+
+        let mut last_used = self.last_used.lock().unwrap();
+        let key = (command.to_string(), user_id);
+
+        if let Some(last) = last_used.get(&key) {
+            let elapsed = last.elapsed();
+
+            if elapsed < cooldown {
+                return Some(cooldown - elapsed);
+            }
+        }
+
+        last_used.insert(key, Instant::now());
+
+        None
+    }
+
+    /// Drops expired entries, call this periodically on bots with many users
+    pub fn cleanup(&self) {
+        let longest = self.durations.values().max().copied().unwrap_or_default();
+
+        self.last_used
+            .lock()
+            .unwrap()
+            .retain(|_, last| last.elapsed() < longest);
+    }
+}
+
+/// Trait for bot data that holds ``Cooldowns``
+pub trait HasCooldowns {
+    fn cooldowns(&self) -> &Cooldowns;
+}
+
+/// Ready-made ``command_check`` enforcing ``Cooldowns``
+///
+/// The error shows when the command can be used again as a relative timestamp. Use as
+/// ``command_check: Some(botox::cooldowns::command_check::<Data>)``
+pub fn command_check<Data: HasCooldowns + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> BoxFuture<'_, Result<bool, crate::Error>> {
+    Box::pin(async move {
+        let data = ctx.data();
+        let cooldowns = data.cooldowns();
+        let command = &ctx.command().qualified_name;
+
+        let Some(cooldown) = cooldowns.durations.get(&**command).copied() else {
+            return Ok(true);
+        };
+
+        let roles = match ctx.guild_id() {
+            Some(_) => ctx
+                .author_member()
+                .await
+                .map(|m| m.roles.to_vec())
+                .unwrap_or_default(),
+            None => Vec::new(),
+        };
+
+        let bypass = cooldowns
+            .bypass(ctx.guild_id(), ctx.author().id, &roles)
+            .await?;
+
+        match cooldowns.hit(command, ctx.author().id, bypass.apply(cooldown)) {
+            None => Ok(true),
+            Some(remaining) => {
+                let at = Timestamp::now().unix_timestamp() + remaining.as_secs_f64().ceil() as i64;
+
+                Err(tr(
+                    ctx,
+                    "cooldown.active",
+                    &[("when", &format!("<t:{}:R>", at))],
+                )
+                .into())
+            }
+        }
+    })
+}
@@ -0,0 +1,159 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude as serenity;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Runs remembered per command by ``Deferral::Auto``
+const AUTO_SAMPLES: usize = 5;
+
+/// How a command defers its response, set as a commands ``custom_data`` or in ``Deferrals::overrides``
+///
+/// Deferring a prefix command shows the typing indicator instead
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Deferral {
+    /// The command responds on its own
+    #[default]
+    Never,
+    /// Defer before the command runs
+    Always,
+    /// Defer ephemerally before the command runs, so the response is only visible to the user
+    AlwaysEphemeral,
+    /// Defer before the command runs once one of its recent runs took longer than
+    /// ``Deferrals::auto_threshold``
+    ///
+    /// poise gives no way to defer from outside a running command, so rather than deferring on a
+    /// timer this learns which commands are slow
+    Auto,
+}
+
+/// Applies each commands ``Deferral`` from ``pre_command``, replacing ``ctx.defer()`` calls in commands
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct Deferrals {
+    /// Used for commands without a ``Deferral`` in their ``custom_data`` or ``overrides``
+    pub default: Deferral,
+    /// Deferrals by qualified command name, for commands whose ``custom_data`` is used for
+    /// something else (such as ``help::LongHelp``)
+    pub overrides: HashMap<String, Deferral>,
+    /// How long a run must take for ``Deferral::Auto`` to start deferring, defaults to 2 seconds
+    pub auto_threshold: Duration,
+    started: Arc<Mutex<HashMap<u64, Instant>>>,
+    timings: Arc<Mutex<HashMap<String, VecDeque<Duration>>>>,
+}
+
+impl Default for Deferrals {
+    fn default() -> Self {
+        Self {
+            default: Deferral::Never,
+            overrides: HashMap::new(),
+            auto_threshold: Duration::from_secs(2),
+            started: Arc::default(),
+            timings: Arc::default(),
+        }
+    }
+}
+
+impl Deferrals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the deferral of a command from its ``custom_data``, ``overrides`` or ``default``, in that order
+    pub fn strategy<Data>(&self, command: &poise::Command<Data, crate::Error>) -> Deferral {
+        if let Some(deferral) = command.custom_data.downcast_ref::<Deferral>() {
+            return *deferral;
+        }
+
+        self.overrides
+            .get(&*command.qualified_name)
+            .copied()
+            .unwrap_or(self.default)
+    }
+
+    /// Returns true if a ``Deferral::Auto`` command has recently been slow
+    pub fn is_slow(&self, qualified_name: &str) -> bool {
+        self.timings
+            .lock()
+            .unwrap()
+            .get(qualified_name)
+            .is_some_and(|runs| runs.iter().any(|d| *d >= self.auto_threshold))
+    }
+
+    /// Defers the invocation if its command asks to, this should be called from your bots ``pre_command`` hook
+    pub async fn start<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+    ) -> Result<(), serenity::Error> {
+        let command = ctx.command();
+        let deferral = self.strategy(command);
+
+        if deferral == Deferral::Auto {
+            self.started
+                .lock()
+                .unwrap()
+                .insert(ctx.id(), Instant::now());
+        }
+
+        match deferral {
+            Deferral::Never => Ok(()),
+            Deferral::Always => ctx.defer().await,
+            Deferral::AlwaysEphemeral => ctx.defer_ephemeral().await,
+            Deferral::Auto if self.is_slow(&command.qualified_name) => ctx.defer().await,
+            Deferral::Auto => Ok(()),
+        }
+    }
+
+    /// Records how long a ``Deferral::Auto`` invocation took, this should be called from your bots
+    /// ``post_command`` hook and ``on_error`` handler
+    pub fn finish<Data: Send + Sync + 'static>(&self, ctx: poise::Context<'_, Data, crate::Error>) {
+        let Some(started) = self.started.lock().unwrap().remove(&ctx.id()) else {
+            return;
+        };
+
+        let mut timings = self.timings.lock().unwrap();
+        let runs = timings
+            .entry(ctx.command().qualified_name.to_string())
+            .or_default();
+
+        if runs.len() == AUTO_SAMPLES {
+            runs.pop_front();
+        }
+
+        runs.push_back(started.elapsed());
+    }
+}
+
+/// Trait for bot data that holds ``Deferrals``
+pub trait HasDeferrals {
+    fn deferrals(&self) -> &Deferrals;
+}
+
+/// Ready-made ``pre_command`` hook applying ``Deferrals``
+///
+/// Use as ``pre_command: botox::defer::pre_command::<Data>``, or call ``Deferrals::start`` from
+/// your own hook
+pub fn pre_command<Data: HasDeferrals + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> BoxFuture<'_, ()> {
+    Box::pin(async move {
+        let data = ctx.data();
+
+        if let Err(e) = data.deferrals().start(ctx).await {
+            log::warn!("Failed to defer {}: {}", ctx.command().qualified_name, e);
+        }
+    })
+}
+
+/// Ready-made ``post_command`` hook recording ``Deferral::Auto`` timings
+///
+/// Use as ``post_command: botox::defer::post_command::<Data>``
+pub fn post_command<Data: HasDeferrals + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> BoxFuture<'_, ()> {
+    Box::pin(async move {
+        let data = ctx.data();
+        data.deferrals().finish(ctx);
+    })
+}
@@ -0,0 +1,1017 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, FullEvent, GatewayIntents, GuildId, Permissions, Timestamp,
+};
+use poise::CreateReply;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::auditlog::{self, AuditLogFilter};
+use crate::cache::Prunable;
+use crate::cases::{CaseAction, Cases};
+use crate::embeds::FieldPacker;
+use crate::namelog::{NameKind, NameLog};
+use crate::paginator::{EmbedPages, Paginator, EMBED_PAGES_SOURCE};
+use crate::permissions::{PermissionExplanation, PermissionInputs, RELEVANT_PERMISSIONS};
+use crate::sanitize::escape_markdown;
+use crate::Error;
+
+/// A captured gateway payload
+#[derive(Debug, Clone, Serialize)]
+pub struct TappedPayload {
+    pub event_type: String,
+    pub guild_id: Option<GuildId>,
+    pub received_at: Timestamp,
+    pub payload: serde_json::Value,
+}
+
+/// Opt-in capture of raw gateway payloads into a bounded ring buffer, for debugging event-shape issues
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct GatewayTap {
+    enabled: Arc<AtomicBool>,
+    capacity: usize,
+    buffer: Arc<Mutex<VecDeque<TappedPayload>>>,
+}
+
+impl GatewayTap {
+    /// Creates a new tap holding at most ``capacity`` payloads, the tap starts disabled
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            enabled: Arc::new(AtomicBool::new(false)),
+            capacity,
+            buffer: Arc::new(Mutex::new(VecDeque::with_capacity(capacity))),
+        }
+    }
+
+    pub fn enable(&self) {
+        self.enabled.store(true, Ordering::Relaxed);
+    }
+
+    /// Disables the tap, already captured payloads are kept
+    pub fn disable(&self) {
+        self.enabled.store(false, Ordering::Relaxed);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Captures a payload if the tap is enabled, this should be called from your bots raw event handler
+    pub fn record<T: Serialize>(&self, event_type: &str, guild_id: Option<GuildId>, payload: &T) {
+        if !self.is_enabled() || self.capacity == 0 {
+            return;
+        }
+
+        let mut payload = match serde_json::to_value(payload) {
+            Ok(payload) => payload,
+            Err(e) => {
+                log::warn!(
+                    "GatewayTap failed to serialize {} payload: {}",
+                    event_type,
+                    e
+                );
+                return;
+            }
+        };
+
+        // Payloads are dumped to Discord, so keep registered secrets out of them
+        crate::secrets::secrets().redact_json(&mut payload);
+
+        let mut buffer = self.buffer.lock().unwrap();
+
+        if buffer.len() >= self.capacity {
+            buffer.pop_front();
+        }
+
+        buffer.push_back(TappedPayload {
+            event_type: event_type.to_string(),
+            guild_id,
+            received_at: Timestamp::now(),
+            payload,
+        });
+    }
+
+    /// Returns up to ``limit`` of the most recent payloads matching the filters, oldest first
+    pub fn recent(
+        &self,
+        event_type: Option<&str>,
+        guild_id: Option<GuildId>,
+        limit: usize,
+    ) -> Vec<TappedPayload> {
+        let buffer = self.buffer.lock().unwrap();
+
+        let mut payloads = buffer
+            .iter()
+            .rev()
+            .filter(|p| event_type.map_or(true, |t| p.event_type.eq_ignore_ascii_case(t)))
+            .filter(|p| guild_id.map_or(true, |g| p.guild_id == Some(g)))
+            .take(limit)
+            .cloned()
+            .collect::<Vec<_>>();
+
+        payloads.reverse();
+
+        payloads
+    }
+
+    /// Drops all captured payloads
+    pub fn clear(&self) {
+        self.buffer.lock().unwrap().clear();
+    }
+}
+
+/// Dumps recent gateway payloads as an attached file, can be plugged into an owner-only command
+pub async fn gateway_dump<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    tap: &GatewayTap,
+    event_type: Option<String>,
+    guild_id: Option<GuildId>,
+    limit: Option<usize>,
+) -> Result<(), Error> {
+    let payloads = tap.recent(event_type.as_deref(), guild_id, limit.unwrap_or(50));
+
+    if payloads.is_empty() {
+        ctx.say(if tap.is_enabled() {
+            "No matching payloads captured"
+        } else {
+            "No matching payloads captured, the gateway tap is disabled"
+        })
+        .await?;
+        return Ok(());
+    }
+
+    let json = serde_json::to_vec_pretty(&payloads)?;
+
+    ctx.send(
+        CreateReply::default()
+            .content(format!("{} payload(s)", payloads.len()))
+            .attachment(serenity::CreateAttachment::bytes(json, "gateway.json")),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Shows the stats of every cluster, can be plugged into an owner-only command
+pub async fn cluster_stats<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    worker: &crate::cluster::Worker,
+) -> Result<(), Error> {
+    let clusters = worker
+        .cluster_stats(std::time::Duration::from_secs(10))
+        .await?;
+
+    let mut embed = serenity::CreateEmbed::default()
+        .title("Cluster stats")
+        .description(format!(
+            "**Clusters:** {}\n**Guilds:** {}\n**This cluster:** {}",
+            clusters.len(),
+            clusters.iter().map(|c| c.guilds).sum::<u64>(),
+            worker.assignment().cluster_id
+        ));
+
+    for cluster in clusters.iter().take(25) {
+        let connected = cluster.shards.iter().filter(|s| s.connected).count();
+
+        embed = embed.field(
+            format!("Cluster {}", cluster.cluster_id),
+            format!(
+                "{} guilds\n{}/{} shards connected\n{}",
+                cluster.guilds,
+                connected,
+                cluster.shards.len(),
+                if cluster.ready { "Ready" } else { "Starting" }
+            ),
+            true,
+        );
+    }
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
+
+/// Every gateway intent and its name
+const INTENTS: &[(GatewayIntents, &str)] = &[
+    (GatewayIntents::GUILDS, "GUILDS"),
+    (GatewayIntents::GUILD_MEMBERS, "GUILD_MEMBERS"),
+    (GatewayIntents::GUILD_MODERATION, "GUILD_MODERATION"),
+    (
+        GatewayIntents::GUILD_EMOJIS_AND_STICKERS,
+        "GUILD_EMOJIS_AND_STICKERS",
+    ),
+    (GatewayIntents::GUILD_INTEGRATIONS, "GUILD_INTEGRATIONS"),
+    (GatewayIntents::GUILD_WEBHOOKS, "GUILD_WEBHOOKS"),
+    (GatewayIntents::GUILD_INVITES, "GUILD_INVITES"),
+    (GatewayIntents::GUILD_VOICE_STATES, "GUILD_VOICE_STATES"),
+    (GatewayIntents::GUILD_PRESENCES, "GUILD_PRESENCES"),
+    (GatewayIntents::GUILD_MESSAGES, "GUILD_MESSAGES"),
+    (
+        GatewayIntents::GUILD_MESSAGE_REACTIONS,
+        "GUILD_MESSAGE_REACTIONS",
+    ),
+    (GatewayIntents::GUILD_MESSAGE_TYPING, "GUILD_MESSAGE_TYPING"),
+    (GatewayIntents::DIRECT_MESSAGES, "DIRECT_MESSAGES"),
+    (
+        GatewayIntents::DIRECT_MESSAGE_REACTIONS,
+        "DIRECT_MESSAGE_REACTIONS",
+    ),
+    (
+        GatewayIntents::DIRECT_MESSAGE_TYPING,
+        "DIRECT_MESSAGE_TYPING",
+    ),
+    (GatewayIntents::MESSAGE_CONTENT, "MESSAGE_CONTENT"),
+    (
+        GatewayIntents::GUILD_SCHEDULED_EVENTS,
+        "GUILD_SCHEDULED_EVENTS",
+    ),
+    (
+        GatewayIntents::AUTO_MODERATION_CONFIGURATION,
+        "AUTO_MODERATION_CONFIGURATION",
+    ),
+    (
+        GatewayIntents::AUTO_MODERATION_EXECUTION,
+        "AUTO_MODERATION_EXECUTION",
+    ),
+];
+
+/// Privileged intents, these need approval once a bot is in 100 servers
+const PRIVILEGED: GatewayIntents = GatewayIntents::GUILD_MEMBERS
+    .union(GatewayIntents::GUILD_PRESENCES)
+    .union(GatewayIntents::MESSAGE_CONTENT);
+
+/// Returns the intents needed to receive an event
+///
+/// ``MESSAGE_CONTENT`` is not included, consuming a message does not mean reading its content
+fn _event_intents(event: &FullEvent) -> GatewayIntents {
+    let messages = |guild: bool| {
+        if guild {
+            GatewayIntents::GUILD_MESSAGES
+        } else {
+            GatewayIntents::DIRECT_MESSAGES
+        }
+    };
+
+    match event {
+        FullEvent::Message { new_message } => messages(new_message.guild_id.is_some()),
+        FullEvent::MessageUpdate { event, .. } => messages(event.guild_id.is_some()),
+        FullEvent::MessageDelete { guild_id, .. } => messages(guild_id.is_some()),
+        FullEvent::MessageDeleteBulk { .. } => GatewayIntents::GUILD_MESSAGES,
+        FullEvent::GuildMemberAddition { .. }
+        | FullEvent::GuildMemberRemoval { .. }
+        | FullEvent::GuildMemberUpdate { .. }
+        | FullEvent::GuildMembersChunk { .. } => GatewayIntents::GUILD_MEMBERS,
+        FullEvent::PresenceUpdate { .. } => GatewayIntents::GUILD_PRESENCES,
+        FullEvent::VoiceStateUpdate { .. } => GatewayIntents::GUILD_VOICE_STATES,
+        FullEvent::ReactionAdd { add_reaction } => {
+            if add_reaction.guild_id.is_some() {
+                GatewayIntents::GUILD_MESSAGE_REACTIONS
+            } else {
+                GatewayIntents::DIRECT_MESSAGE_REACTIONS
+            }
+        }
+        FullEvent::ReactionRemove { removed_reaction } => {
+            if removed_reaction.guild_id.is_some() {
+                GatewayIntents::GUILD_MESSAGE_REACTIONS
+            } else {
+                GatewayIntents::DIRECT_MESSAGE_REACTIONS
+            }
+        }
+        FullEvent::ReactionRemoveAll { .. } | FullEvent::ReactionRemoveEmoji { .. } => {
+            GatewayIntents::GUILD_MESSAGE_REACTIONS
+        }
+        FullEvent::TypingStart { event } => {
+            if event.guild_id.is_some() {
+                GatewayIntents::GUILD_MESSAGE_TYPING
+            } else {
+                GatewayIntents::DIRECT_MESSAGE_TYPING
+            }
+        }
+        FullEvent::GuildBanAddition { .. } | FullEvent::GuildBanRemoval { .. } => {
+            GatewayIntents::GUILD_MODERATION
+        }
+        FullEvent::GuildAuditLogEntryCreate { .. } => GatewayIntents::GUILD_MODERATION,
+        FullEvent::GuildEmojisUpdate { .. } | FullEvent::GuildStickersUpdate { .. } => {
+            GatewayIntents::GUILD_EMOJIS_AND_STICKERS
+        }
+        FullEvent::GuildIntegrationsUpdate { .. }
+        | FullEvent::IntegrationCreate { .. }
+        | FullEvent::IntegrationUpdate { .. }
+        | FullEvent::IntegrationDelete { .. } => GatewayIntents::GUILD_INTEGRATIONS,
+        FullEvent::WebhookUpdate { .. } => GatewayIntents::GUILD_WEBHOOKS,
+        FullEvent::InviteCreate { .. } | FullEvent::InviteDelete { .. } => {
+            GatewayIntents::GUILD_INVITES
+        }
+        FullEvent::GuildScheduledEventCreate { .. }
+        | FullEvent::GuildScheduledEventUpdate { .. }
+        | FullEvent::GuildScheduledEventDelete { .. }
+        | FullEvent::GuildScheduledEventUserAdd { .. }
+        | FullEvent::GuildScheduledEventUserRemove { .. } => GatewayIntents::GUILD_SCHEDULED_EVENTS,
+        FullEvent::AutoModRuleCreate { .. }
+        | FullEvent::AutoModRuleUpdate { .. }
+        | FullEvent::AutoModRuleDelete { .. } => GatewayIntents::AUTO_MODERATION_CONFIGURATION,
+        FullEvent::AutoModActionExecution { .. } => GatewayIntents::AUTO_MODERATION_EXECUTION,
+        FullEvent::GuildCreate { .. }
+        | FullEvent::GuildDelete { .. }
+        | FullEvent::GuildUpdate { .. }
+        | FullEvent::GuildRoleCreate { .. }
+        | FullEvent::GuildRoleUpdate { .. }
+        | FullEvent::GuildRoleDelete { .. }
+        | FullEvent::ChannelCreate { .. }
+        | FullEvent::ChannelUpdate { .. }
+        | FullEvent::ChannelDelete { .. }
+        | FullEvent::ChannelPinsUpdate { .. }
+        | FullEvent::ThreadCreate { .. }
+        | FullEvent::ThreadUpdate { .. }
+        | FullEvent::ThreadDelete { .. }
+        | FullEvent::StageInstanceCreate { .. }
+        | FullEvent::StageInstanceUpdate { .. }
+        | FullEvent::StageInstanceDelete { .. } => GatewayIntents::GUILDS,
+        _ => GatewayIntents::empty(),
+    }
+}
+
+/// Counts received and consumed gateway events per intent over a sampling window
+///
+/// Consumers mark events they acted on with ``consumed``, so intents whose events are received but
+/// never used show up in ``intent_report``. Consumers that read message content must say so with
+/// ``mark``, as only they know whether they do. This is cheap to clone
+#[derive(Clone)]
+pub struct IntentUsage {
+    started: Arc<Mutex<Instant>>,
+    received: Arc<Mutex<HashMap<&'static str, u64>>>,
+    consumed: Arc<Mutex<HashMap<&'static str, u64>>>,
+}
+
+impl Default for IntentUsage {
+    fn default() -> Self {
+        Self {
+            started: Arc::new(Mutex::new(Instant::now())),
+            received: Arc::default(),
+            consumed: Arc::default(),
+        }
+    }
+}
+
+impl IntentUsage {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn _count(counts: &Mutex<HashMap<&'static str, u64>>, intents: GatewayIntents) {
+        let mut counts = counts.lock().unwrap();
+
+        for (intent, name) in INTENTS {
+            if intents.contains(*intent) {
+                *counts.entry(*name).or_default() += 1;
+            }
+        }
+    }
+
+    /// Counts a received event, this should be called from your bots event handler
+    pub fn received(&self, event: &FullEvent) {
+        let mut intents = _event_intents(event);
+
+        // Every message event may carry content, so they all count as received for it
+        if intents.intersects(GatewayIntents::GUILD_MESSAGES | GatewayIntents::DIRECT_MESSAGES) {
+            intents |= GatewayIntents::MESSAGE_CONTENT;
+        }
+
+        Self::_count(&self.received, intents);
+    }
+
+    /// Marks an event as used by a subscriber
+    pub fn consumed(&self, event: &FullEvent) {
+        Self::_count(&self.consumed, _event_intents(event));
+    }
+
+    /// Marks intents as used, such as ``MESSAGE_CONTENT`` by a subscriber that read a messages content
+    pub fn mark(&self, intents: GatewayIntents) {
+        Self::_count(&self.consumed, intents);
+    }
+
+    /// Marks a command invocation, prefix commands need the message intents
+    pub fn command<Data: Send + Sync + 'static>(&self, ctx: poise::Context<'_, Data, Error>) {
+        if let poise::Context::Prefix(pctx) = ctx {
+            let intents = if pctx.msg.guild_id.is_some() {
+                GatewayIntents::GUILD_MESSAGES | GatewayIntents::MESSAGE_CONTENT
+            } else {
+                GatewayIntents::DIRECT_MESSAGES | GatewayIntents::MESSAGE_CONTENT
+            };
+
+            Self::_count(&self.consumed, intents);
+        }
+    }
+
+    /// Starts a new sampling window
+    pub fn reset(&self) {
+        *self.started.lock().unwrap() = Instant::now();
+        self.received.lock().unwrap().clear();
+        self.consumed.lock().unwrap().clear();
+    }
+}
+
+/// An enabled intent that went unused during the sampling window
+#[derive(Debug, Clone)]
+pub struct UnusedIntent {
+    pub name: &'static str,
+    pub privileged: bool,
+    /// Number of events received for the intent, which were all ignored
+    pub received: u64,
+}
+
+/// The result of ``intent_report``
+#[derive(Debug, Clone)]
+pub struct IntentReport {
+    pub window: Duration,
+    pub unused: Vec<UnusedIntent>,
+}
+
+/// Reports enabled intents that no subscriber or command consumed during the sampling window
+///
+/// Privileged intents are listed first, they are the most worth trimming. ``GUILDS`` is never
+/// reported, as serenity's cache depends on it
+pub fn intent_report(usage: &IntentUsage, enabled: GatewayIntents) -> IntentReport {
+    let received = usage.received.lock().unwrap().clone();
+    let consumed = usage.consumed.lock().unwrap();
+
+    let mut unused = INTENTS
+        .iter()
+        .filter(|(intent, name)| {
+            *intent != GatewayIntents::GUILDS
+                && enabled.contains(*intent)
+                && !consumed.contains_key(name)
+        })
+        .map(|(intent, name)| UnusedIntent {
+            name,
+            privileged: PRIVILEGED.contains(*intent),
+            received: received.get(name).copied().unwrap_or_default(),
+        })
+        .collect::<Vec<_>>();
+
+    unused.sort_by_key(|u| !u.privileged);
+
+    IntentReport {
+        window: usage.started.lock().unwrap().elapsed(),
+        unused,
+    }
+}
+
+/// Shows enabled intents that appear unused, can be plugged into an owner-only command
+pub async fn intents<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    usage: &IntentUsage,
+    enabled: GatewayIntents,
+) -> Result<(), Error> {
+    let report = intent_report(usage, enabled);
+
+    if report.unused.is_empty() {
+        ctx.say(format!(
+            "Every enabled intent was used in the last {} minutes",
+            report.window.as_secs() / 60
+        ))
+        .await?;
+        return Ok(());
+    }
+
+    let mut desc = String::new();
+
+    for intent in &report.unused {
+        let _ = writeln!(
+            desc,
+            "``{}``{} - {} event(s) received",
+            intent.name,
+            if intent.privileged {
+                " (privileged)"
+            } else {
+                ""
+            },
+            intent.received
+        );
+    }
+
+    ctx.send(
+        CreateReply::default().embed(
+            serenity::CreateEmbed::default()
+                .title(format!(
+                    "Unused intents (last {} minutes)",
+                    report.window.as_secs() / 60
+                ))
+                .description(desc),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// A backend that can be pinged by ``ping``, such as a database
+pub trait PingProbe: Send + Sync {
+    /// The name shown in the ping breakdown
+    fn name(&self) -> &str;
+
+    /// Performs a round-trip to the backend
+    fn ping<'a>(&'a self) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Shows gateway, REST and (optionally) database latency, can be plugged into your bots ``/ping`` command
+pub async fn ping<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    probe: Option<&dyn PingProbe>,
+) -> Result<(), Error> {
+    let gateway = ctx.ping().await;
+
+    let start = Instant::now();
+    let handle = ctx.say("Pinging...").await?;
+    let rest = start.elapsed();
+
+    let mut embed = serenity::CreateEmbed::default()
+        .title("Pong!")
+        .colour(serenity::Colour::BLURPLE)
+        .field(
+            format!("Gateway (shard {})", ctx.serenity_context().shard_id),
+            if gateway.is_zero() {
+                "Not measured yet".to_string()
+            } else {
+                format!("{}ms", gateway.as_millis())
+            },
+            true,
+        )
+        .field("REST", format!("{}ms", rest.as_millis()), true);
+
+    if let Some(probe) = probe {
+        let start = Instant::now();
+
+        let value = match probe.ping().await {
+            Ok(()) => format!("{}ms", start.elapsed().as_millis()),
+            Err(e) => format!("Failed: {}", e),
+        };
+
+        embed = embed.field(probe.name().to_string(), value, true);
+    }
+
+    handle
+        .edit(ctx, CreateReply::default().content("").embed(embed))
+        .await?;
+
+    Ok(())
+}
+
+/// Common bot actions and the permissions they need
+const BOT_ACTIONS: &[(&str, Permissions)] = &[
+    (
+        "Send messages",
+        Permissions::VIEW_CHANNEL.union(Permissions::SEND_MESSAGES),
+    ),
+    ("Send embeds", Permissions::EMBED_LINKS),
+    ("Upload files", Permissions::ATTACH_FILES),
+    ("Delete messages", Permissions::MANAGE_MESSAGES),
+    ("Assign roles", Permissions::MANAGE_ROLES),
+    ("Create webhooks", Permissions::MANAGE_WEBHOOKS),
+    ("Kick members", Permissions::KICK_MEMBERS),
+    ("Ban members", Permissions::BAN_MEMBERS),
+    ("Timeout members", Permissions::MODERATE_MEMBERS),
+];
+
+fn _permcheck_embeds(
+    title: &str,
+    explanations: &[PermissionExplanation],
+) -> Vec<serenity::CreateEmbed<'static>> {
+    FieldPacker::new(title)
+        .fields(explanations.iter().map(|e| {
+            (
+                format!("{} {}", if e.allowed { "✅" } else { "❌" }, e.name),
+                e.reason.clone(),
+            )
+        }))
+        .pack()
+}
+
+/// Explains a users permissions in a channel and flags what the bot itself cannot do there, can be plugged into your bots ``/permcheck`` command
+///
+/// The ``EmbedPages`` must be registered on the paginator under ``EMBED_PAGES_SOURCE``
+pub async fn permcheck<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    paginator: &Paginator,
+    pages: &EmbedPages,
+    user: serenity::User,
+    channel: Option<serenity::GuildChannel>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Permissions can only be checked in a server".into());
+    };
+
+    let channel_id = channel.map(|c| c.id).unwrap_or(ctx.channel_id());
+    let bot_id = ctx.cache().current_user().id;
+
+    let member = guild_id.member(ctx.http(), user.id).await?;
+    let bot_member = guild_id.member(ctx.http(), bot_id).await?;
+
+    let cache = ctx.cache();
+
+    let (Some(inputs), Some(bot_inputs)) = (
+        PermissionInputs::from_cache(cache, guild_id, user.id, &member.roles, Some(channel_id))
+            .map(|i| i.with_locale(ctx.locale())),
+        PermissionInputs::from_cache(cache, guild_id, bot_id, &bot_member.roles, Some(channel_id))
+            .map(|i| i.with_locale(ctx.locale())),
+    ) else {
+        return Err("This server is not cached yet, try again shortly".into());
+    };
+
+    let mut embeds = _permcheck_embeds(
+        &format!("Permissions of {} in <#{}>", user.name, channel_id),
+        &inputs.explain_all(),
+    );
+
+    let bot_permissions = bot_inputs.effective();
+    let mut problems = String::new();
+
+    for (action, needed) in BOT_ACTIONS {
+        if !bot_permissions.contains(*needed) {
+            let _ = writeln!(
+                problems,
+                "❌ **{}** - missing {}",
+                action,
+                *needed - bot_permissions
+            );
+        }
+    }
+
+    if user.id != bot_id
+        && user.id != inputs.owner_id
+        && inputs.highest_position() >= bot_inputs.highest_position()
+    {
+        let _ = writeln!(
+            problems,
+            "❌ **Moderate {}** - their highest role is not below the bots highest role",
+            user.name
+        );
+    }
+
+    embeds.push(
+        serenity::CreateEmbed::default()
+            .title("What the bot cannot do here")
+            .description(if problems.is_empty() {
+                "Nothing, the bot has every permission it commonly needs".to_string()
+            } else {
+                problems
+            }),
+    );
+
+    let args = pages.add(embeds).await;
+    paginator
+        .send_ephemeral(ctx, EMBED_PAGES_SOURCE, args)
+        .await
+}
+
+/// Shows recent audit log entries, optionally by a single user, can be plugged into your bots ``/auditlog`` command
+///
+/// The ``EmbedPages`` must be registered on the paginator under ``EMBED_PAGES_SOURCE``
+pub async fn audit_log<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    paginator: &Paginator,
+    pages: &EmbedPages,
+    user: Option<serenity::User>,
+    limit: Option<usize>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("The audit log can only be viewed in a server".into());
+    };
+
+    ctx.defer_ephemeral().await?;
+
+    let mut filter = AuditLogFilter::new().limit(limit.unwrap_or(50).clamp(1, 100));
+
+    if let Some(user) = &user {
+        filter = filter.actor(user.id);
+    }
+
+    let entries = auditlog::query(ctx.serenity_context(), guild_id, &filter).await?;
+
+    let title = match &user {
+        Some(user) => format!("Audit log of {}", user.name),
+        None => "Audit log".to_string(),
+    };
+
+    let embeds = auditlog::render(&entries, &title);
+
+    let args = pages.add(embeds).await;
+    paginator
+        .send_ephemeral(ctx, EMBED_PAGES_SOURCE, args)
+        .await
+}
+
+/// Permissions worth calling out in ``whois``
+const STAFF_PERMISSIONS: Permissions = Permissions::MANAGE_MESSAGES
+    .union(Permissions::MANAGE_CHANNELS)
+    .union(Permissions::MANAGE_ROLES)
+    .union(Permissions::MANAGE_WEBHOOKS)
+    .union(Permissions::KICK_MEMBERS)
+    .union(Permissions::BAN_MEMBERS)
+    .union(Permissions::MODERATE_MEMBERS);
+
+/// Number of past names shown by ``whois``
+const WHOIS_NAMES: usize = 5;
+
+/// Summarizes a users account, membership, permissions, cases and past names, can be plugged into your bots ``/whois`` command
+///
+/// Cases and name history are only shown if their trackers are passed. The ``EmbedPages`` must be
+/// registered on the paginator under ``EMBED_PAGES_SOURCE``
+pub async fn whois<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    paginator: &Paginator,
+    pages: &EmbedPages,
+    user: serenity::User,
+    cases: Option<&Cases>,
+    names: Option<&NameLog>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Users can only be inspected in a server".into());
+    };
+
+    let mut packer = FieldPacker::new(format!("Who is {}", user.name))
+        .colour(serenity::Colour::BLURPLE)
+        .field("ID", format!("``{}``", user.id), true)
+        .field(
+            "Account created",
+            format!("<t:{}:R>", user.id.created_at().unix_timestamp()),
+            true,
+        );
+
+    match guild_id.member(ctx.http(), user.id).await {
+        Ok(member) => {
+            if let Some(joined_at) = member.joined_at {
+                packer = packer.field(
+                    "Joined",
+                    format!("<t:{}:R>", joined_at.unix_timestamp()),
+                    true,
+                );
+            }
+
+            // A few mentions per line, so the packer splits between mentions rather than inside one
+            let roles = member
+                .roles
+                .chunks(5)
+                .map(|line| {
+                    line.iter()
+                        .map(|r| format!("<@&{}>", r))
+                        .collect::<Vec<_>>()
+                        .join(" ")
+                })
+                .collect::<Vec<_>>()
+                .join("\n");
+
+            packer = packer.field(
+                format!("Roles ({})", member.roles.len()),
+                if roles.is_empty() {
+                    "None".to_string()
+                } else {
+                    roles
+                },
+                false,
+            );
+
+            let inputs =
+                PermissionInputs::from_cache(ctx.cache(), guild_id, user.id, &member.roles, None);
+
+            if let Some(inputs) = inputs {
+                let is_admin = inputs
+                    .roles
+                    .iter()
+                    .any(|(_, _, p, _)| p.contains(Permissions::ADMINISTRATOR))
+                    || inputs.everyone.contains(Permissions::ADMINISTRATOR);
+
+                let summary = if inputs.owner_id == user.id {
+                    "Server owner".to_string()
+                } else if is_admin {
+                    "Administrator".to_string()
+                } else {
+                    let effective = inputs.effective() & STAFF_PERMISSIONS;
+
+                    let names = RELEVANT_PERMISSIONS
+                        .iter()
+                        .filter(|(p, _)| effective.contains(*p))
+                        .map(|(_, name)| *name)
+                        .collect::<Vec<_>>();
+
+                    if names.is_empty() {
+                        "No staff permissions".to_string()
+                    } else {
+                        names.join(", ")
+                    }
+                };
+
+                packer = packer.field("Permissions", summary, false);
+            }
+        }
+        Err(_) => packer = packer.field("Member", "Not in this server", true),
+    }
+
+    if let Some(cases) = cases {
+        let history = cases.history(guild_id, user.id).await?;
+        let warns = history
+            .iter()
+            .filter(|c| c.action == CaseAction::Warn)
+            .count();
+
+        packer = packer.field(
+            "Cases",
+            format!("{} total, {} warning(s)", history.len(), warns),
+            true,
+        );
+    }
+
+    if let Some(names) = names {
+        let past = names
+            .names(user.id)
+            .await?
+            .into_iter()
+            .filter(|e| match e.kind {
+                NameKind::Nickname(g) => g == guild_id,
+                _ => true,
+            })
+            .filter_map(|e| e.name)
+            .take(WHOIS_NAMES)
+            .map(|n| escape_markdown(&n))
+            .collect::<Vec<_>>();
+
+        if !past.is_empty() {
+            packer = packer.field("Past names", past.join(", "), false);
+        }
+    }
+
+    let embeds = packer.pack();
+
+    let args = pages.add(embeds).await;
+    paginator
+        .send_ephemeral(ctx, EMBED_PAGES_SOURCE, args)
+        .await
+}
+
+fn _started() -> &'static Instant {
+    static STARTED: OnceLock<Instant> = OnceLock::new();
+    STARTED.get_or_init(Instant::now)
+}
+
+/// Marks the process as started, call this early in ``main`` so ``uptime`` is accurate
+///
+/// Otherwise uptime is counted from the first call to ``uptime`` or ``about``
+pub fn mark_started() {
+    _started();
+}
+
+/// Returns how long the process has been running, see ``mark_started``
+pub fn uptime() -> Duration {
+    _started().elapsed()
+}
+
+/// Returns the resident memory of the process in bytes
+///
+/// This is read from ``/proc/self/status``, so it is only available on Linux and always None
+/// on other platforms
+pub fn rss_bytes() -> Option<u64> {
+    #[cfg(target_os = "linux")]
+    {
+        let status = std::fs::read_to_string("/proc/self/status").ok()?;
+
+        status
+            .lines()
+            .find_map(|l| l.strip_prefix("VmRSS:"))
+            .and_then(|v| v.trim().trim_end_matches("kB").trim().parse::<u64>().ok())
+            .map(|kb| kb * 1024)
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
+
+/// Returns the serenity version from its user agent
+fn _serenity_version() -> &'static str {
+    serenity::constants::USER_AGENT
+        .rsplit(", ")
+        .next()
+        .unwrap_or_default()
+        .trim_end_matches(')')
+}
+
+/// Optional crate features compiled in
+fn _features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+
+    if cfg!(feature = "redis") {
+        features.push("redis");
+    }
+    if cfg!(feature = "postgres") {
+        features.push("postgres");
+    }
+    if cfg!(feature = "api") {
+        features.push("api");
+    }
+    if cfg!(feature = "config") {
+        features.push("config");
+    }
+    if cfg!(feature = "tracing") {
+        features.push("tracing");
+    }
+    if cfg!(feature = "otel") {
+        features.push("otel");
+    }
+
+    features
+}
+
+fn _duration(d: Duration) -> String {
+    let secs = d.as_secs();
+    let (days, hours, minutes) = (secs / 86400, secs / 3600 % 24, secs / 60 % 60);
+
+    match (days, hours) {
+        (0, 0) => format!("{}m {}s", minutes, secs % 60),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h {}m", days, hours, minutes),
+    }
+}
+
+/// Shows uptime, resource usage, cache sizes and versions, can be plugged into your bots ``/about`` command
+///
+/// ``caches`` are the crates caches to report approximate sizes of, such as a ``MessageLog``
+pub async fn about<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    caches: &[Arc<dyn Prunable>],
+) -> Result<(), Error> {
+    let cache = &ctx.serenity_context().cache;
+    let guilds = cache.guilds();
+    let members = guilds
+        .iter()
+        .filter_map(|id| cache.guild(*id).map(|g| g.members.len()))
+        .sum::<usize>();
+
+    let metrics = tokio::runtime::Handle::current().metrics();
+
+    let mut cache_sizes = format!("{} guilds\n{} members", guilds.len(), members);
+
+    for c in caches {
+        let _ = write!(
+            cache_sizes,
+            "\n{}: {:.1} MiB",
+            c.name(),
+            c.approx_bytes().await as f64 / (1024.0 * 1024.0)
+        );
+    }
+
+    let features = _features();
+
+    let embed = serenity::CreateEmbed::default()
+        .title(format!("About {}", cache.current_user().name))
+        .colour(serenity::Colour::BLURPLE)
+        .field("Uptime", _duration(uptime()), true)
+        .field(
+            "Memory",
+            match rss_bytes() {
+                Some(rss) => format!("{:.1} MiB", rss as f64 / (1024.0 * 1024.0)),
+                None => "Unavailable".to_string(),
+            },
+            true,
+        )
+        .field(
+            "Tasks",
+            format!(
+                "{} tasks on {} workers",
+                metrics.num_alive_tasks(),
+                metrics.num_workers()
+            ),
+            true,
+        )
+        .field("Shards", cache.shard_count().to_string(), true)
+        .field("Cache", cache_sizes, true)
+        .field(
+            "Versions",
+            format!(
+                "botox {}\nserenity {}\n{}",
+                env!("CARGO_PKG_VERSION"),
+                _serenity_version(),
+                env!("BOTOX_RUSTC_VERSION")
+            ),
+            true,
+        )
+        .field(
+            "Features",
+            if features.is_empty() {
+                "None".to_string()
+            } else {
+                features.join(", ")
+            },
+            true,
+        );
+
+    ctx.send(CreateReply::default().embed(embed)).await?;
+
+    Ok(())
+}
@@ -0,0 +1,182 @@
+use poise::serenity_prelude::{Embed, Message};
+
+use crate::sanitize::escape_markdown;
+
+/// Maximum length of a diff, the limit of an embed description
+pub const MAX_DIFF_LENGTH: usize = 4096;
+
+/// Word-level diff with removed words struck through and added words in bold, all content is escaped
+pub fn word_diff(old: &str, new: &str) -> String {
+    let old = old.split_whitespace().collect::<Vec<_>>();
+    let new = new.split_whitespace().collect::<Vec<_>>();
+
+    // Longest common subsequence table
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut out = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            out.push(escape_markdown(old[i]));
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            out.push(format!("**{}**", escape_markdown(new[j])));
+            j += 1;
+        } else {
+            out.push(format!("~~{}~~", escape_markdown(old[i])));
+            i += 1;
+        }
+    }
+
+    out.join(" ")
+}
+
+/// Truncates a diff to ``max`` characters without cutting a word's markup in half
+pub fn truncate(diff: &str, max: usize) -> String {
+    if diff.chars().count() <= max {
+        return diff.to_string();
+    }
+
+    let mut out = String::new();
+    let mut len = 0;
+
+    for word in diff.split(' ') {
+        let word_len = word.chars().count() + 1;
+
+        // Keep room for the separator and ellipsis
+        if len + word_len + 1 > max {
+            break;
+        }
+
+        if !out.is_empty() {
+            out.push(' ');
+        }
+
+        out.push_str(word);
+        len += word_len;
+    }
+
+    out.push('…');
+    out
+}
+
+fn _push_change(out: &mut Vec<String>, label: &str, old: Option<&str>, new: Option<&str>) {
+    let (old, new) = (old.unwrap_or_default(), new.unwrap_or_default());
+
+    if old != new {
+        out.push(format!("**{}:** {}", label, word_diff(old, new)));
+    }
+}
+
+/// Returns the changed parts of two embeds, one line per changed title, description, footer or field
+pub fn embed_diff(old: &Embed, new: &Embed) -> Vec<String> {
+    let mut out = Vec::new();
+
+    _push_change(
+        &mut out,
+        "Title",
+        old.title.as_deref(),
+        new.title.as_deref(),
+    );
+    _push_change(
+        &mut out,
+        "Description",
+        old.description.as_deref(),
+        new.description.as_deref(),
+    );
+    _push_change(
+        &mut out,
+        "Footer",
+        old.footer.as_ref().map(|f| &*f.text),
+        new.footer.as_ref().map(|f| &*f.text),
+    );
+
+    for field in &new.fields {
+        let old_value = old
+            .fields
+            .iter()
+            .find(|f| f.name == field.name)
+            .map(|f| &*f.value);
+
+        _push_change(&mut out, &field.name, old_value, Some(&field.value));
+    }
+
+    for field in &old.fields {
+        if !new.fields.iter().any(|f| f.name == field.name) {
+            _push_change(&mut out, &field.name, Some(&field.value), None);
+        }
+    }
+
+    out
+}
+
+/// Returns a markdown diff of the content and embeds of two versions of a message, at most ``MAX_DIFF_LENGTH`` long
+///
+/// Empty if nothing visible changed
+pub fn message_diff(old: &Message, new: &Message) -> String {
+    let mut out = Vec::new();
+
+    _push_change(&mut out, "Content", Some(&old.content), Some(&new.content));
+
+    for i in 0..old.embeds.len().max(new.embeds.len()) {
+        let changes = match (old.embeds.get(i), new.embeds.get(i)) {
+            (Some(old), Some(new)) => embed_diff(old, new),
+            (None, Some(_)) => vec!["*Added*".to_string()],
+            (Some(_), None) => vec!["*Removed*".to_string()],
+            (None, None) => continue,
+        };
+
+        if !changes.is_empty() {
+            out.push(format!("__Embed {}__", i + 1));
+            out.extend(changes);
+        }
+    }
+
+    truncate(&out.join("\n"), MAX_DIFF_LENGTH)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn word_diff_marks_changes() {
+        assert_eq!(word_diff("a b", "a c"), "a **c** ~~b~~");
+        assert_eq!(word_diff("same", "same"), "same");
+    }
+
+    #[test]
+    fn truncate_keeps_short_diffs() {
+        assert_eq!(truncate("**a** b", 10), "**a** b");
+    }
+
+    #[test]
+    fn truncate_does_not_split_markup() {
+        let diff = "**aaa** ~~bbb~~ ccc";
+        let truncated = truncate(diff, 12);
+
+        assert_eq!(truncated, "**aaa**…");
+        assert!(truncated.chars().count() <= 12);
+
+        let long = vec!["~~word~~"; 1000].join(" ");
+        let truncated = truncate(&long, MAX_DIFF_LENGTH);
+
+        assert!(truncated.chars().count() <= MAX_DIFF_LENGTH);
+        assert!(truncated
+            .trim_end_matches('…')
+            .split(' ')
+            .all(|w| w == "~~word~~"));
+    }
+}
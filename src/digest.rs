@@ -0,0 +1,281 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateEmbed, CreateEmbedFooter, CreateMessage, FullEvent, GuildId,
+    Timestamp,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::analytics::UsageTracker;
+use crate::resilience::{retry, RetryPolicy};
+use crate::taskman::Task;
+use crate::time::GuildClock;
+use crate::Error;
+
+/// How often a digest is posted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DigestPeriod {
+    Daily,
+    Weekly,
+}
+
+impl DigestPeriod {
+    pub fn duration(&self) -> Duration {
+        match self {
+            DigestPeriod::Daily => Duration::from_secs(24 * 60 * 60),
+            DigestPeriod::Weekly => Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DigestPeriod::Daily => "Daily",
+            DigestPeriod::Weekly => "Weekly",
+        }
+    }
+}
+
+/// A guilds digest configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DigestSubscription {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub period: DigestPeriod,
+    pub last_sent: Option<Timestamp>,
+}
+
+impl DigestSubscription {
+    pub fn is_due(&self, now: Timestamp) -> bool {
+        match self.last_sent {
+            None => true,
+            Some(last) => {
+                now.unix_timestamp() - last.unix_timestamp()
+                    >= self.period.duration().as_secs() as i64
+            }
+        }
+    }
+}
+
+/// Storage backend for digest subscriptions
+pub trait DigestStore: Send + Sync {
+    /// Lists all digest subscriptions
+    fn subscriptions<'a>(&'a self) -> BoxFuture<'a, Result<Vec<DigestSubscription>, Error>>;
+
+    /// Records that a digest was posted for a guild
+    fn mark_sent<'a>(
+        &'a self,
+        guild_id: GuildId,
+        at: Timestamp,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// A section of a digest
+pub trait DigestProvider: Send + Sync {
+    /// Returns the field (name and value) to add to a guilds digest, or None to skip it
+    fn section<'a>(
+        &'a self,
+        guild_id: GuildId,
+        period: DigestPeriod,
+    ) -> BoxFuture<'a, Result<Option<(String, String)>, Error>>;
+}
+
+/// Digest section listing the most used commands
+///
+/// Command usage is tracked bot-wide, so every guild sees the same list
+pub struct CommandUsageDigest {
+    pub usage: UsageTracker,
+    pub top: usize,
+}
+
+impl DigestProvider for CommandUsageDigest {
+    fn section<'a>(
+        &'a self,
+        _guild_id: GuildId,
+        _period: DigestPeriod,
+    ) -> BoxFuture<'a, Result<Option<(String, String)>, Error>> {
+        Box::pin(async move {
+            let top = self.usage.top(self.top).await;
+
+            if top.is_empty() {
+                return Ok(None);
+            }
+
+            let mut value = String::new();
+
+            for (i, (name, count)) in top.iter().enumerate() {
+                let _ = writeln!(value, "{}. ``{}`` - {}", i + 1, name, count);
+            }
+
+            Ok(Some(("Top commands".to_string(), value)))
+        })
+    }
+}
+
+/// Tracks joins and leaves per guild between digests
+///
+/// This is cheap to clone
+#[derive(Clone, Default)]
+pub struct MemberGrowth {
+    counts: Arc<Mutex<HashMap<GuildId, (u64, u64)>>>,
+}
+
+impl MemberGrowth {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Counts joins and leaves, this should be called from your bots event handler
+    pub async fn handle_event(&self, event: &FullEvent) {
+        let (guild_id, join) = match event {
+            FullEvent::GuildMemberAddition { new_member } => (new_member.guild_id, true),
+            FullEvent::GuildMemberRemoval { guild_id, .. } => (*guild_id, false),
+            _ => return,
+        };
+
+        let mut counts = self.counts.lock().await;
+        let entry = counts.entry(guild_id).or_default();
+
+        if join {
+            entry.0 += 1;
+        } else {
+            entry.1 += 1;
+        }
+    }
+
+    /// Returns and resets the joins and leaves of a guild
+    pub async fn take(&self, guild_id: GuildId) -> (u64, u64) {
+        self.counts
+            .lock()
+            .await
+            .remove(&guild_id)
+            .unwrap_or_default()
+    }
+}
+
+impl DigestProvider for MemberGrowth {
+    fn section<'a>(
+        &'a self,
+        guild_id: GuildId,
+        _period: DigestPeriod,
+    ) -> BoxFuture<'a, Result<Option<(String, String)>, Error>> {
+        Box::pin(async move {
+            let (joins, leaves) = self.take(guild_id).await;
+
+            Ok(Some((
+                "Member growth".to_string(),
+                format!(
+                    "📥 {} joined | 📤 {} left | net {:+}",
+                    joins,
+                    leaves,
+                    joins as i64 - leaves as i64
+                ),
+            )))
+        })
+    }
+}
+
+/// Composes and posts digests
+pub struct Digest {
+    store: Arc<dyn DigestStore>,
+    providers: Vec<Box<dyn DigestProvider>>,
+    /// Used to title digests with the date in the guilds timezone, UTC otherwise
+    pub clock: Option<GuildClock>,
+    /// How posting a digest is retried before it is skipped until the next check
+    pub retry: RetryPolicy,
+}
+
+impl Digest {
+    pub fn new(store: Arc<dyn DigestStore>) -> Self {
+        Self {
+            store,
+            providers: Vec::new(),
+            clock: None,
+            retry: RetryPolicy::default(),
+        }
+    }
+
+    /// Adds a provider, sections appear in the order providers are added
+    pub fn provider(mut self, provider: impl DigestProvider + 'static) -> Self {
+        self.providers.push(Box::new(provider));
+        self
+    }
+
+    /// Composes the digest embed of a guild
+    pub async fn compose(
+        &self,
+        guild_id: GuildId,
+        period: DigestPeriod,
+    ) -> Result<CreateEmbed<'static>, Error> {
+        let date = match &self.clock {
+            Some(clock) => clock.format(Timestamp::now(), guild_id, "%Y-%m-%d").await?,
+            None => chrono::Utc::now().format("%Y-%m-%d").to_string(),
+        };
+
+        let mut embed = CreateEmbed::default()
+            .title(format!("{} digest for {}", period.label(), date))
+            .colour(serenity::Colour::BLURPLE)
+            .footer(CreateEmbedFooter::new(format!("Guild {}", guild_id)))
+            .timestamp(Timestamp::now());
+
+        for provider in &self.providers {
+            match provider.section(guild_id, period).await {
+                Ok(Some((name, value))) => embed = embed.field(name, value, false),
+                Ok(None) => {}
+                Err(e) => log::warn!("Digest provider failed for {}: {}", guild_id, e),
+            }
+        }
+
+        Ok(embed)
+    }
+
+    /// Posts every digest that is due
+    pub async fn run(&self, http: &serenity::Http) -> Result<(), Error> {
+        let now = Timestamp::now();
+
+        for sub in self.store.subscriptions().await? {
+            if !sub.is_due(now) {
+                continue;
+            }
+
+            let embed = &self.compose(sub.guild_id, sub.period).await?;
+            let channel_id = sub.channel_id;
+
+            let sent = retry(&self.retry, || async move {
+                channel_id
+                    .send_message(http, CreateMessage::new().embed(embed.clone()))
+                    .await
+                    .map_err(Error::from)
+            })
+            .await;
+
+            if let Err(e) = sent {
+                log::warn!("Failed to post digest for {}: {}", sub.guild_id, e);
+                continue;
+            }
+
+            self.store.mark_sent(sub.guild_id, now).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns a task that posts due digests, checking every ``check_interval``
+pub fn digest_task(digest: Digest, check_interval: Duration) -> Task {
+    let digest = Arc::new(digest);
+
+    Task {
+        name: "digest",
+        description: "Posts scheduled digest reports",
+        enabled: true,
+        duration: check_interval,
+        run: Box::new(move |ctx| {
+            let digest = digest.clone();
+            Box::pin(async move { digest.run(&ctx.http).await })
+        }),
+    }
+}
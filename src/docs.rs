@@ -0,0 +1,181 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::CreateEmbed;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::embeds::MAX_DESCRIPTION;
+use crate::paginator::{PageSource, Paginator};
+use crate::Error;
+
+/// Id ``Docs`` is registered under on the paginator
+pub const PAGE_SOURCE: &str = "docs";
+
+/// Title of the page holding text before the first heading
+const OVERVIEW_TITLE: &str = "Overview";
+
+/// A page of a markdown document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocPage {
+    pub title: String,
+    pub body: String,
+}
+
+/// Splits a body into chunks of at most ``max`` characters, on line boundaries where possible
+fn _split(body: &str, max: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut len = 0;
+
+    for line in body.split_inclusive('\n') {
+        let line_len = line.chars().count();
+
+        if len + line_len > max && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+            len = 0;
+        }
+
+        if line_len > max {
+            // A single line longer than a page, hard split it
+            for part in line.chars().collect::<Vec<_>>().chunks(max) {
+                chunks.push(part.iter().collect());
+            }
+
+            continue;
+        }
+
+        current.push_str(line);
+        len += line_len;
+    }
+
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Splits a markdown document into pages, one per heading with the heading as the page title
+///
+/// Text before the first heading becomes an overview page and sections too long for an embed
+/// continue on following pages
+pub fn paginate_markdown(text: &str) -> Vec<DocPage> {
+    let mut sections: Vec<(String, String)> = vec![(OVERVIEW_TITLE.to_string(), String::new())];
+    let mut in_code_block = false;
+
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+
+        if !in_code_block {
+            if let Some(heading) = line.strip_prefix('#') {
+                sections.push((
+                    heading.trim_start_matches('#').trim().to_string(),
+                    String::new(),
+                ));
+                continue;
+            }
+        }
+
+        if let Some((_, body)) = sections.last_mut() {
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+
+    let mut pages = Vec::new();
+
+    for (title, body) in sections {
+        let chunks = _split(body.trim(), MAX_DESCRIPTION);
+
+        if chunks.is_empty() && title != OVERVIEW_TITLE {
+            pages.push(DocPage {
+                title,
+                body: String::new(),
+            });
+            continue;
+        }
+
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            pages.push(DocPage {
+                title: if i == 0 {
+                    title.clone()
+                } else {
+                    format!("{} (cont.)", title)
+                },
+                body: chunk.trim().to_string(),
+            });
+        }
+    }
+
+    pages
+}
+
+/// Named markdown documents (such as rules, a FAQ or a changelog) rendered as paginated embeds
+///
+/// This is cheap to clone
+#[derive(Clone, Default)]
+pub struct Docs {
+    docs: Arc<RwLock<HashMap<String, Vec<DocPage>>>>,
+}
+
+impl Docs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds or replaces a document
+    pub fn set(&self, name: impl Into<String>, markdown: &str) {
+        self.docs
+            .write()
+            .unwrap()
+            .insert(name.into(), paginate_markdown(markdown));
+    }
+
+    /// Returns the pages of a document
+    pub fn pages(&self, name: &str) -> Option<Vec<DocPage>> {
+        self.docs.read().unwrap().get(name).cloned()
+    }
+}
+
+impl PageSource for Docs {
+    fn page_count<'a>(&'a self, args: &'a str) -> BoxFuture<'a, Result<usize, Error>> {
+        Box::pin(async move {
+            let pages = self.pages(args).ok_or("Unknown document")?;
+            Ok(pages.len().max(1))
+        })
+    }
+
+    fn render<'a>(
+        &'a self,
+        args: &'a str,
+        page: usize,
+    ) -> BoxFuture<'a, Result<CreateEmbed<'static>, Error>> {
+        Box::pin(async move {
+            let pages = self.pages(args).ok_or("Unknown document")?;
+
+            let Some(page) = pages.get(page) else {
+                return Ok(CreateEmbed::default().description("This document is empty"));
+            };
+
+            let mut embed = CreateEmbed::default().title(page.title.clone());
+
+            if !page.body.is_empty() {
+                embed = embed.description(page.body.clone());
+            }
+
+            Ok(embed)
+        })
+    }
+}
+
+/// Shows a document as a paginated message, can be plugged into your bots ``/rules``, ``/faq`` or ``/changelog`` commands
+///
+/// The ``Docs`` must be registered on the paginator under ``PAGE_SOURCE``
+pub async fn show_doc<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    paginator: &Paginator,
+    name: &str,
+) -> Result<(), Error> {
+    paginator.send(ctx, PAGE_SOURCE, name).await
+}
@@ -0,0 +1,235 @@
+use poise::serenity_prelude::{self as serenity, CreateEmbed};
+
+/// Maximum length of an embed title
+pub const MAX_TITLE: usize = 256;
+/// Maximum length of an embed description
+pub const MAX_DESCRIPTION: usize = 4096;
+/// Maximum number of fields in an embed
+pub const MAX_FIELDS: usize = 25;
+/// Maximum length of a field name
+pub const MAX_FIELD_NAME: usize = 256;
+/// Maximum length of a field value
+pub const MAX_FIELD_VALUE: usize = 1024;
+/// Maximum total length of an embeds text
+///
+/// Discord applies this to every embed of a message combined, so send large packs over several messages
+pub const MAX_EMBED_TOTAL: usize = 6000;
+
+/// Space kept free in each embed for the page counter appended to the title
+const PAGE_SUFFIX_RESERVE: usize = 16;
+
+/// Maximum number of embeds in a message
+pub const MAX_EMBEDS: usize = 10;
+
+/// Shown in place of empty names and values, which Discord rejects
+const EMPTY: &str = "\u{200b}";
+
+fn _truncate(s: &str, max: usize) -> String {
+    if s.chars().count() <= max {
+        return s.to_string();
+    }
+
+    let mut out = s.chars().take(max - 1).collect::<String>();
+    out.push('…');
+    out
+}
+
+/// Splits a value into chunks of at most ``max`` characters, on line boundaries where possible
+fn _split_value(value: &str, max: usize) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+
+    for line in value.split_inclusive('\n') {
+        let line_len = line.chars().count();
+
+        if current_len + line_len > max && !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+
+        if line_len <= max {
+            current.push_str(line);
+            current_len += line_len;
+            continue;
+        }
+
+        // A single line longer than a field, hard split it
+        let chars = line.chars().collect::<Vec<_>>();
+
+        for part in chars.chunks(max) {
+            if current_len + part.len() > max {
+                chunks.push(std::mem::take(&mut current));
+                current_len = 0;
+            }
+
+            current.extend(part);
+            current_len += part.len();
+        }
+    }
+
+    if !current.is_empty() || chunks.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Packs any number of fields into as many embeds as needed
+///
+/// Each embed holds at most 25 fields and stays under the 6000 character limit. Values over 1024
+/// characters are split across continued fields and long names are truncated
+pub struct FieldPacker {
+    title: String,
+    description: Option<String>,
+    colour: Option<serenity::Colour>,
+    fields: Vec<(String, String, bool)>,
+}
+
+impl FieldPacker {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            description: None,
+            colour: None,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Sets the colour of every embed
+    pub fn colour(mut self, colour: impl Into<serenity::Colour>) -> Self {
+        self.colour = Some(colour.into());
+        self
+    }
+
+    /// Sets the description of the first embed, long descriptions are truncated
+    pub fn description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(_truncate(&description.into(), MAX_DESCRIPTION));
+        self
+    }
+
+    /// Adds a field
+    pub fn field(
+        mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+        inline: bool,
+    ) -> Self {
+        self.fields.push((name.into(), value.into(), inline));
+        self
+    }
+
+    /// Adds several non-inline fields
+    pub fn fields<N: Into<String>, V: Into<String>>(
+        mut self,
+        fields: impl IntoIterator<Item = (N, V)>,
+    ) -> Self {
+        self.fields.extend(
+            fields
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into(), false)),
+        );
+        self
+    }
+
+    /// Packs the fields into embeds, titled with a page counter if there is more than one
+    pub fn pack(self) -> Vec<CreateEmbed<'static>> {
+        self._pack().into_iter().map(|(_, embed)| embed).collect()
+    }
+
+    /// Packs the fields into embeds grouped into messages, each message holding as many embeds
+    /// as fit in the combined size limit
+    pub fn pack_messages(self) -> Vec<Vec<CreateEmbed<'static>>> {
+        let mut messages: Vec<Vec<CreateEmbed<'static>>> = Vec::new();
+        let mut used = 0;
+
+        for (len, embed) in self._pack() {
+            match messages.last_mut() {
+                Some(message) if message.len() < MAX_EMBEDS && used + len <= MAX_EMBED_TOTAL => {
+                    message.push(embed);
+                    used += len;
+                }
+                _ => {
+                    messages.push(vec![embed]);
+                    used = len;
+                }
+            }
+        }
+
+        messages
+    }
+
+    /// Packs the fields into embeds along with an upper bound of each embeds total length
+    fn _pack(self) -> Vec<(usize, CreateEmbed<'static>)> {
+        let title = _truncate(&self.title, MAX_TITLE - PAGE_SUFFIX_RESERVE);
+        let budget = MAX_EMBED_TOTAL - title.chars().count() - PAGE_SUFFIX_RESERVE;
+
+        let mut pages: Vec<Vec<(String, String, bool)>> = vec![Vec::new()];
+        let mut used = self.description.as_ref().map_or(0, |d| d.chars().count());
+        let mut lens = vec![used];
+
+        for (name, value, inline) in self.fields {
+            let name = if name.is_empty() {
+                EMPTY.to_string()
+            } else {
+                _truncate(&name, MAX_FIELD_NAME)
+            };
+
+            for (i, chunk) in _split_value(&value, MAX_FIELD_VALUE)
+                .into_iter()
+                .enumerate()
+            {
+                let name = if i == 0 {
+                    name.clone()
+                } else {
+                    _truncate(&format!("{} (cont.)", name), MAX_FIELD_NAME)
+                };
+
+                let value = if chunk.trim().is_empty() {
+                    EMPTY.to_string()
+                } else {
+                    chunk
+                };
+
+                let len = name.chars().count() + value.chars().count();
+                let page = pages.last_mut().unwrap();
+
+                if !page.is_empty() && (page.len() >= MAX_FIELDS || used + len > budget) {
+                    pages.push(Vec::new());
+                    lens.push(0);
+                    used = 0;
+                }
+
+                used += len;
+                pages.last_mut().unwrap().push((name, value, inline));
+                *lens.last_mut().unwrap() = used;
+            }
+        }
+
+        let total = pages.len();
+        let title_len = title.chars().count() + PAGE_SUFFIX_RESERVE;
+
+        pages
+            .into_iter()
+            .zip(lens)
+            .enumerate()
+            .map(|(i, (fields, len))| {
+                let mut embed = CreateEmbed::default().title(if total > 1 {
+                    format!("{} ({}/{})", title, i + 1, total)
+                } else {
+                    title.clone()
+                });
+
+                if let Some(colour) = self.colour {
+                    embed = embed.colour(colour);
+                }
+
+                if let (0, Some(description)) = (i, &self.description) {
+                    embed = embed.description(description.clone());
+                }
+
+                (title_len + len, embed.fields(fields))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,273 @@
+use poise::serenity_prelude::{
+    self as serenity, CreateAttachment, CreateSticker, Emoji, EmojiId, GuildId, Message, Sticker,
+    StickerId,
+};
+
+use crate::Error;
+
+/// Maximum size of an emoji image
+pub const MAX_EMOJI_SIZE: usize = 256 * 1024;
+
+/// Maximum size of a sticker file
+pub const MAX_STICKER_SIZE: usize = 512 * 1024;
+
+/// Where to import an emoji or sticker from
+#[derive(Debug, Clone)]
+pub enum EmojiSource {
+    /// A custom emoji, for example parsed from a message or listed from another guild
+    Custom {
+        id: EmojiId,
+        name: String,
+        animated: bool,
+    },
+    /// An image at a URL, uploaded as an emoji
+    Url { url: String, name: String },
+    /// A sticker, for example from a message
+    Sticker { id: StickerId, name: String },
+}
+
+/// The created emoji or sticker
+#[derive(Debug, Clone)]
+pub enum Imported {
+    Emoji(Emoji),
+    Sticker(Sticker),
+}
+
+/// Progress of a bulk import
+#[derive(Debug, Clone, Copy)]
+pub struct ImportProgress {
+    pub done: usize,
+    pub total: usize,
+    pub failed: usize,
+}
+
+/// Parses all custom emojis (``<:name:id>`` and ``<a:name:id>``) in a text
+pub fn parse_custom_emojis(text: &str) -> Vec<EmojiSource> {
+    let mut sources = Vec::new();
+
+    for part in text.split('<').skip(1) {
+        let Some(end) = part.find('>') else {
+            continue;
+        };
+
+        let mut fields = part[..end].split(':');
+
+        let (animated, name, id) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(""), Some(name), Some(id)) => (false, name, id),
+            (Some("a"), Some(name), Some(id)) => (true, name, id),
+            _ => continue,
+        };
+
+        let Ok(id) = id.parse::<u64>() else {
+            continue;
+        };
+
+        if id == 0 || name.is_empty() {
+            continue;
+        }
+
+        sources.push(EmojiSource::Custom {
+            id: EmojiId::new(id),
+            name: name.to_string(),
+            animated,
+        });
+    }
+
+    sources
+}
+
+/// Returns every custom emoji and sticker in a message
+pub fn sources_from_message(msg: &Message) -> Vec<EmojiSource> {
+    let mut sources = parse_custom_emojis(&msg.content);
+
+    for sticker in &msg.sticker_items {
+        sources.push(EmojiSource::Sticker {
+            id: sticker.id,
+            name: sticker.name.to_string(),
+        });
+    }
+
+    sources
+}
+
+/// Returns every custom emoji of another guild
+pub async fn sources_from_guild(
+    http: &serenity::Http,
+    guild_id: GuildId,
+) -> Result<Vec<EmojiSource>, Error> {
+    Ok(guild_id
+        .emojis(http)
+        .await?
+        .into_iter()
+        .map(|e| EmojiSource::Custom {
+            id: e.id,
+            name: e.name.to_string(),
+            animated: e.animated,
+        })
+        .collect())
+}
+
+/// Strips a name down to the characters discord allows (alphanumerics and underscores, 2-32 long)
+pub fn sanitize_name(name: &str) -> String {
+    let mut name = name
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '_')
+        .take(32)
+        .collect::<String>();
+
+    while name.len() < 2 {
+        name.push('_');
+    }
+
+    name
+}
+
+/// Returns ``name`` or, if taken, ``name_2``, ``name_3``... keeping within the 32 character limit
+pub fn unique_name(name: &str, taken: &[String]) -> String {
+    let name = sanitize_name(name);
+
+    if !taken.iter().any(|t| t.eq_ignore_ascii_case(&name)) {
+        return name;
+    }
+
+    for i in 2.. {
+        let suffix = format!("_{}", i);
+        let candidate = format!("{}{}", &name[..name.len().min(32 - suffix.len())], suffix);
+
+        if !taken.iter().any(|t| t.eq_ignore_ascii_case(&candidate)) {
+            return candidate;
+        }
+    }
+
+    unreachable!()
+}
+
+fn _validate_image(bytes: &[u8], max_size: usize, what: &str) -> Result<&'static str, Error> {
+    if bytes.len() > max_size {
+        return Err(format!(
+            "{} is too large ({} KB, max {} KB)",
+            what,
+            bytes.len() / 1024,
+            max_size / 1024
+        )
+        .into());
+    }
+
+    let format = match bytes {
+        [0x89, b'P', b'N', b'G', ..] => "png",
+        [0xFF, 0xD8, 0xFF, ..] => "jpg",
+        [b'G', b'I', b'F', b'8', ..] => "gif",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "webp",
+        _ => return Err(format!("{} must be a PNG, JPEG, GIF or WebP image", what).into()),
+    };
+
+    Ok(format)
+}
+
+async fn _download(url: &str) -> Result<Vec<u8>, Error> {
+    let res = reqwest::get(url).await?.error_for_status()?;
+    Ok(res.bytes().await?.to_vec())
+}
+
+async fn _taken_names(http: &serenity::Http, guild_id: GuildId) -> Result<Vec<String>, Error> {
+    Ok(guild_id
+        .emojis(http)
+        .await?
+        .into_iter()
+        .map(|e| e.name.to_string())
+        .collect())
+}
+
+async fn _import(
+    http: &serenity::Http,
+    guild_id: GuildId,
+    source: &EmojiSource,
+    taken: &mut Vec<String>,
+) -> Result<Imported, Error> {
+    let (url, name) = match source {
+        EmojiSource::Custom { id, name, animated } => (
+            format!(
+                "https://cdn.discordapp.com/emojis/{}.{}",
+                id,
+                if *animated { "gif" } else { "png" }
+            ),
+            name,
+        ),
+        EmojiSource::Url { url, name } => (url.clone(), name),
+        EmojiSource::Sticker { id, name } => {
+            let bytes =
+                _download(&format!("https://media.discordapp.net/stickers/{}.png", id)).await?;
+
+            _validate_image(&bytes, MAX_STICKER_SIZE, "Sticker")?;
+
+            let sticker = guild_id
+                .create_sticker(
+                    http,
+                    CreateSticker::new(
+                        sanitize_name(name),
+                        CreateAttachment::bytes(bytes, format!("{}.png", id)),
+                    )
+                    .tags(name.clone()),
+                )
+                .await?;
+
+            return Ok(Imported::Sticker(sticker));
+        }
+    };
+
+    let bytes = _download(&url).await?;
+    let format = _validate_image(&bytes, MAX_EMOJI_SIZE, "Emoji")?;
+
+    let name = unique_name(name, taken);
+    let image = CreateAttachment::bytes(bytes, format!("{}.{}", name, format)).to_base64();
+
+    let emoji = guild_id.create_emoji(http, &name, &image).await?;
+
+    taken.push(name);
+
+    Ok(Imported::Emoji(emoji))
+}
+
+/// Downloads and uploads a single emoji or sticker to a guild
+pub async fn import(
+    http: &serenity::Http,
+    guild_id: GuildId,
+    source: &EmojiSource,
+) -> Result<Imported, Error> {
+    let mut taken = _taken_names(http, guild_id).await?;
+    _import(http, guild_id, source, &mut taken).await
+}
+
+/// Imports many emojis and stickers, calling ``progress`` after each one
+///
+/// Failures do not stop the import, the result of every source is returned in order
+pub async fn import_bulk(
+    http: &serenity::Http,
+    guild_id: GuildId,
+    sources: &[EmojiSource],
+    progress: impl Fn(ImportProgress),
+) -> Result<Vec<Result<Imported, String>>, Error> {
+    let mut taken = _taken_names(http, guild_id).await?;
+    let mut results = Vec::with_capacity(sources.len());
+    let mut failed = 0;
+
+    for source in sources {
+        let res = _import(http, guild_id, source, &mut taken)
+            .await
+            .map_err(|e| e.to_string());
+
+        if res.is_err() {
+            failed += 1;
+        }
+
+        results.push(res);
+
+        progress(ImportProgress {
+            done: results.len(),
+            total: sources.len(),
+            failed,
+        });
+    }
+
+    Ok(results)
+}
@@ -0,0 +1,322 @@
+use poise::serenity_prelude::{self as serenity, CreateEmbed};
+use poise::CreateReply;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::analytics::UsageTracker;
+use crate::Error;
+
+/// Outcome recorded every time a variant is shown, used as the denominator of conversion rates
+pub const EXPOSURE: &str = "exposure";
+
+/// What an experiment assigns to variants
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssignBy {
+    /// Everyone in a guild sees the same variant, users in DMs are assigned individually
+    Guild,
+    User,
+}
+
+/// A variant of an experiment, such as different copy or a different flow
+#[derive(Debug, Clone)]
+pub struct Variant {
+    pub name: String,
+    /// Relative share of units assigned to this variant
+    pub weight: u32,
+}
+
+/// An A/B experiment on a command
+#[derive(Debug, Clone)]
+pub struct Experiment {
+    pub name: String,
+    /// Qualified name of the command the experiment runs on
+    pub command: String,
+    pub assign_by: AssignBy,
+    pub variants: Vec<Variant>,
+}
+
+impl Experiment {
+    pub fn new(name: impl Into<String>, command: impl Into<String>, assign_by: AssignBy) -> Self {
+        Self {
+            name: name.into(),
+            command: command.into(),
+            assign_by,
+            variants: Vec::new(),
+        }
+    }
+
+    /// Adds a variant, variants with a weight of 0 are never assigned
+    pub fn variant(mut self, name: impl Into<String>, weight: u32) -> Self {
+        self.variants.push(Variant {
+            name: name.into(),
+            weight,
+        });
+        self
+    }
+
+    /// Assigns a guild or user id to a variant
+    ///
+    /// The same id always gets the same variant for as long as the variants and weights do not
+    /// change, and different experiments split ids independently
+    pub fn assign(&self, id: u64) -> Option<&Variant> {
+        let total = self.variants.iter().map(|v| v.weight as u64).sum::<u64>();
+
+        if total == 0 {
+            return None;
+        }
+
+        let mut bucket = _fmix64(_fnv1a(&self.name, id)) % total;
+
+        for variant in &self.variants {
+            if bucket < variant.weight as u64 {
+                return Some(variant);
+            }
+
+            bucket -= variant.weight as u64;
+        }
+
+        None
+    }
+}
+
+/// FNV-1a over the experiment name and id, stable across restarts and Rust versions unlike
+/// ``DefaultHasher``
+fn _fnv1a(name: &str, id: u64) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+
+    for byte in name.bytes().chain(id.to_le_bytes()) {
+        hash ^= byte as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+
+    hash
+}
+
+/// The murmur3 finalizer, FNV-1a alone has poorly distributed low bits which would skew ``% total``
+fn _fmix64(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51afd7ed558ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ceb9fe1a85ec53);
+    hash ^= hash >> 33;
+    hash
+}
+
+/// Performance of one variant of an experiment
+#[derive(Debug, Clone)]
+pub struct VariantReport {
+    pub variant: String,
+    pub exposures: u64,
+    /// Counts of every other outcome recorded for the variant
+    pub outcomes: HashMap<String, u64>,
+}
+
+impl VariantReport {
+    /// Returns the share of exposures that led to an outcome
+    pub fn rate(&self, outcome: &str) -> f64 {
+        if self.exposures == 0 {
+            return 0.0;
+        }
+
+        self.outcomes.get(outcome).copied().unwrap_or_default() as f64 / self.exposures as f64
+    }
+}
+
+/// Runs experiments on commands, recording exposures and outcomes to a ``UsageTracker``
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct Experiments {
+    experiments: Arc<HashMap<String, Experiment>>,
+    tracker: UsageTracker,
+}
+
+impl Experiments {
+    pub fn new(experiments: Vec<Experiment>, tracker: UsageTracker) -> Self {
+        Self {
+            experiments: Arc::new(
+                experiments
+                    .into_iter()
+                    .map(|e| (e.name.clone(), e))
+                    .collect(),
+            ),
+            tracker,
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Experiment> {
+        self.experiments.get(name)
+    }
+
+    /// Returns the names of every experiment
+    pub fn names(&self) -> Vec<&str> {
+        let mut names = self
+            .experiments
+            .keys()
+            .map(|n| n.as_str())
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names
+    }
+
+    fn _unit<Data: Send + Sync + 'static>(
+        experiment: &Experiment,
+        ctx: poise::Context<'_, Data, crate::Error>,
+    ) -> u64 {
+        match (experiment.assign_by, ctx.guild_id()) {
+            (AssignBy::Guild, Some(guild_id)) => guild_id.get(),
+            _ => ctx.author().id.get(),
+        }
+    }
+
+    /// Returns the variant the invoker is in without recording an exposure
+    pub fn peek<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+        experiment: &str,
+    ) -> Option<&str> {
+        let experiment = self.experiments.get(experiment)?;
+
+        experiment
+            .assign(Self::_unit(experiment, ctx))
+            .map(|v| v.name.as_str())
+    }
+
+    /// Returns the variant the invoker is in and records an exposure, call this once per
+    /// invocation where the variant is shown
+    ///
+    /// Returns None for unknown experiments (or ones without weighted variants), callers should
+    /// fall back to the default flow
+    pub async fn variant<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+        experiment: &str,
+    ) -> Option<&str> {
+        let variant = self.peek(ctx, experiment)?;
+
+        self.tracker
+            .record_outcome(experiment, variant, EXPOSURE)
+            .await;
+
+        Some(variant)
+    }
+
+    /// Records an outcome (such as ``completed`` or ``cancelled``) for the invokers variant
+    pub async fn record<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+        experiment: &str,
+        outcome: &str,
+    ) {
+        if let Some(variant) = self.peek(ctx, experiment) {
+            self.tracker
+                .record_outcome(experiment, variant, outcome)
+                .await;
+        }
+    }
+
+    /// Returns the performance of every variant of an experiment, in declaration order
+    pub async fn report(&self, experiment: &str) -> Option<Vec<VariantReport>> {
+        let experiment = self.experiments.get(experiment)?;
+        let outcomes = self.tracker.outcomes(&experiment.name).await;
+
+        Some(
+            experiment
+                .variants
+                .iter()
+                .map(|variant| {
+                    let mut report = VariantReport {
+                        variant: variant.name.clone(),
+                        exposures: 0,
+                        outcomes: HashMap::new(),
+                    };
+
+                    for ((v, outcome), count) in &outcomes {
+                        if *v != variant.name {
+                            continue;
+                        }
+
+                        if outcome == EXPOSURE {
+                            report.exposures = *count;
+                        } else {
+                            report.outcomes.insert(outcome.clone(), *count);
+                        }
+                    }
+
+                    report
+                })
+                .collect(),
+        )
+    }
+}
+
+/// Trait for bot data that holds ``Experiments``
+pub trait HasExperiments {
+    fn experiments(&self) -> &Experiments;
+}
+
+/// Shows how each variant of an experiment performs, can be plugged into an owner-only command
+///
+/// Without an experiment, every experiment is listed
+pub async fn experiment_report<Data: HasExperiments + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    experiment: Option<String>,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let experiments = data.experiments();
+
+    let Some(name) = experiment else {
+        let names = experiments.names();
+
+        ctx.say(if names.is_empty() {
+            "No experiments are running".to_string()
+        } else {
+            format!("Running experiments: {}", names.join(", "))
+        })
+        .await?;
+
+        return Ok(());
+    };
+
+    let (Some(info), Some(reports)) = (experiments.get(&name), experiments.report(&name).await)
+    else {
+        return Err(format!("Unknown experiment {}", name).into());
+    };
+
+    let mut embed = CreateEmbed::default()
+        .title(format!("Experiment {}", info.name))
+        .description(format!(
+            "On ``{}``, assigned by {}",
+            info.command,
+            match info.assign_by {
+                AssignBy::Guild => "guild",
+                AssignBy::User => "user",
+            }
+        ))
+        .colour(serenity::Colour::BLURPLE);
+
+    for report in reports {
+        let mut value = format!("{} exposures", report.exposures);
+
+        let mut outcomes = report.outcomes.iter().collect::<Vec<_>>();
+        outcomes.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (outcome, count) in outcomes {
+            let _ = write!(
+                value,
+                "\n{}: {} ({:.1}%)",
+                outcome,
+                count,
+                report.rate(outcome) * 100.0
+            );
+        }
+
+        embed = embed.field(report.variant, value, true);
+    }
+
+    ctx.send(CreateReply::default().embed(embed).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,163 @@
+use futures::{Stream, StreamExt};
+use poise::serenity_prelude::{
+    self as serenity, CommandOptionType, CreateCommandOption, GuildId, UserId,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::Error;
+
+/// The manifest schema version, bumped on breaking changes to the manifest format
+pub const MANIFEST_SCHEMA_VERSION: u32 = 1;
+
+/// A description of every command of a bot, for web dashboards
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandManifest {
+    pub schema_version: u32,
+    pub commands: Vec<ManifestCommand>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestCommand {
+    pub name: String,
+    pub qualified_name: String,
+    pub description: Option<String>,
+    pub category: Option<String>,
+    pub slash: bool,
+    pub prefix: bool,
+    /// The name of the context menu entry, if this is a context menu command
+    pub context_menu_name: Option<String>,
+    pub parameters: Vec<ManifestParameter>,
+    /// Permission bitflags
+    pub default_member_permissions: u64,
+    pub required_permissions: u64,
+    pub required_bot_permissions: u64,
+    pub owners_only: bool,
+    pub guild_only: bool,
+    pub dm_only: bool,
+    pub nsfw_only: bool,
+    pub subcommands: Vec<ManifestCommand>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestParameter {
+    pub name: String,
+    pub description: Option<String>,
+    pub required: bool,
+    /// The slash command option type, such as ``string``, ``integer`` or ``user``
+    pub kind: String,
+    /// Fixed choices, empty if the parameter accepts any value
+    pub choices: Vec<String>,
+    pub autocomplete: bool,
+}
+
+fn _option_kind(kind: u64) -> &'static str {
+    match kind {
+        3 => "string",
+        4 => "integer",
+        5 => "boolean",
+        6 => "user",
+        7 => "channel",
+        8 => "role",
+        9 => "mentionable",
+        10 => "number",
+        11 => "attachment",
+        _ => "unknown",
+    }
+}
+
+fn _parameter<Data>(param: &poise::CommandParameter<Data, Error>) -> ManifestParameter {
+    // poise only exposes the type through a builder callback, so apply it and read the type back
+    let kind = param
+        .type_setter
+        .and_then(|setter| {
+            let option = setter(CreateCommandOption::new(
+                CommandOptionType::String,
+                param.name.clone(),
+                "-",
+            ));
+
+            serde_json::to_value(option).ok()?.get("type")?.as_u64()
+        })
+        .map(_option_kind)
+        .unwrap_or("unknown");
+
+    ManifestParameter {
+        name: param.name.to_string(),
+        description: param.description.as_deref().map(str::to_string),
+        required: param.required,
+        kind: kind.to_string(),
+        choices: param.choices.iter().map(|c| c.name.to_string()).collect(),
+        autocomplete: param.autocomplete_callback.is_some(),
+    }
+}
+
+fn _command<Data>(command: &poise::Command<Data, Error>) -> ManifestCommand {
+    ManifestCommand {
+        name: command.name.to_string(),
+        qualified_name: command.qualified_name.to_string(),
+        description: command.description.as_deref().map(str::to_string),
+        category: command.category.clone(),
+        slash: command.slash_action.is_some(),
+        prefix: command.prefix_action.is_some(),
+        context_menu_name: command.context_menu_name.as_deref().map(str::to_string),
+        parameters: command.parameters.iter().map(_parameter).collect(),
+        default_member_permissions: command.default_member_permissions.bits(),
+        required_permissions: command.required_permissions.bits(),
+        required_bot_permissions: command.required_bot_permissions.bits(),
+        owners_only: command.owners_only,
+        guild_only: command.guild_only,
+        dm_only: command.dm_only,
+        nsfw_only: command.nsfw_only,
+        subcommands: command.subcommands.iter().map(_command).collect(),
+    }
+}
+
+/// Builds a manifest of commands, hidden commands are left out
+pub fn command_manifest<Data>(commands: &[poise::Command<Data, Error>]) -> CommandManifest {
+    CommandManifest {
+        schema_version: MANIFEST_SCHEMA_VERSION,
+        commands: commands
+            .iter()
+            .filter(|c| !c.hide_in_help)
+            .map(_command)
+            .collect(),
+    }
+}
+
+/// A row of ``guild_census``
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuildCensusRow {
+    pub guild_id: GuildId,
+    pub name: String,
+    pub member_count: u64,
+    pub owner_id: UserId,
+    /// Guild feature flags, such as ``COMMUNITY`` or ``VERIFIED``
+    pub features: Vec<String>,
+    pub shard_id: u32,
+}
+
+/// Streams a row per cached guild matching ``filter``, for ingestion by external analytics
+///
+/// Only the guild ids are collected upfront. Each row is built from the cache when the stream is
+/// polled, so a slow consumer holds back the export instead of rows piling up in memory
+pub fn guild_census(
+    cache: Arc<serenity::Cache>,
+    shard_count: u32,
+    filter: impl Fn(&GuildCensusRow) -> bool + Send + 'static,
+) -> impl Stream<Item = GuildCensusRow> + Send + 'static {
+    let guild_ids = cache.guilds();
+
+    futures::stream::iter(guild_ids).filter_map(move |guild_id| {
+        let row = cache.guild(guild_id).map(|g| GuildCensusRow {
+            guild_id,
+            name: g.name.to_string(),
+            member_count: g.member_count,
+            owner_id: g.owner_id,
+            features: g.features.iter().map(|f| f.to_string()).collect(),
+            shard_id: ((guild_id.get() >> 22) % u64::from(shard_count.max(1))) as u32,
+        });
+
+        std::future::ready(row.filter(|row| filter(row)))
+    })
+}
@@ -0,0 +1,304 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ComponentInteraction, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseMessage, FullEvent, GuildId, Permissions,
+};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cache::TtlCache;
+use crate::Error;
+
+/// A subsystem that can be turned off per guild
+#[derive(
+    poise::ChoiceParameter, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+pub enum Feature {
+    MessageLog,
+    WordFilter,
+    LinkGuard,
+    AutoResponder,
+    AntiRaid,
+    AntiNuke,
+    RolePersist,
+    Bridge,
+    MentionResponder,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 9] = [
+        Feature::MessageLog,
+        Feature::WordFilter,
+        Feature::LinkGuard,
+        Feature::AutoResponder,
+        Feature::AntiRaid,
+        Feature::AntiNuke,
+        Feature::RolePersist,
+        Feature::Bridge,
+        Feature::MentionResponder,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Feature::MessageLog => "Message log",
+            Feature::WordFilter => "Word filter",
+            Feature::LinkGuard => "Link guard",
+            Feature::AutoResponder => "Auto responder",
+            Feature::AntiRaid => "Anti-raid",
+            Feature::AntiNuke => "Anti-nuke",
+            Feature::RolePersist => "Role persistence",
+            Feature::Bridge => "Channel bridge",
+            Feature::MentionResponder => "Mention replies",
+        }
+    }
+
+    fn id(&self) -> &'static str {
+        match self {
+            Feature::MessageLog => "messagelog",
+            Feature::WordFilter => "wordfilter",
+            Feature::LinkGuard => "linkguard",
+            Feature::AutoResponder => "autoresponder",
+            Feature::AntiRaid => "antiraid",
+            Feature::AntiNuke => "antinuke",
+            Feature::RolePersist => "rolepersist",
+            Feature::Bridge => "bridge",
+            Feature::MentionResponder => "mention",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|f| f.id() == id)
+    }
+}
+
+/// Storage backend for per-guild feature toggles
+pub trait FeatureStore: Send + Sync {
+    /// Returns the features disabled in a guild, every other feature is enabled
+    fn disabled<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<Vec<Feature>, Error>>;
+
+    fn set_disabled<'a>(
+        &'a self,
+        guild_id: GuildId,
+        disabled: &'a [Feature],
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Per-guild map of enabled subsystems, consulted by the crates event handlers before acting
+///
+/// Set it as the ``features`` field of a subsystem to make it respect the toggles. Toggles are
+/// cached for an hour. This is cheap to clone
+#[derive(Clone)]
+pub struct FeatureMatrix {
+    store: Arc<dyn FeatureStore>,
+    cache: TtlCache<GuildId, Vec<Feature>>,
+}
+
+impl FeatureMatrix {
+    pub fn new(store: Arc<dyn FeatureStore>) -> Self {
+        Self {
+            store,
+            cache: TtlCache::new("features", Duration::from_secs(60 * 60), 50_000),
+        }
+    }
+
+    /// Returns the features disabled in a guild
+    pub async fn disabled(&self, guild_id: GuildId) -> Result<Vec<Feature>, Error> {
+        if let Some(disabled) = self.cache.get(&guild_id).await {
+            return Ok(disabled);
+        }
+
+        let disabled = self.store.disabled(guild_id).await?;
+
+        self.cache.insert(guild_id, disabled.clone()).await;
+
+        Ok(disabled)
+    }
+
+    pub async fn is_enabled(&self, guild_id: GuildId, feature: Feature) -> Result<bool, Error> {
+        Ok(!self.disabled(guild_id).await?.contains(&feature))
+    }
+
+    /// Enables or disables a feature in a guild
+    pub async fn set(
+        &self,
+        guild_id: GuildId,
+        feature: Feature,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        let mut disabled = self.disabled(guild_id).await?;
+
+        disabled.retain(|f| *f != feature);
+
+        if !enabled {
+            disabled.push(feature);
+        }
+
+        self.store.set_disabled(guild_id, &disabled).await?;
+        self.cache.insert(guild_id, disabled).await;
+
+        Ok(())
+    }
+
+    /// Drops the cached toggles of a guild
+    pub async fn invalidate(&self, guild_id: GuildId) {
+        self.cache.remove(&guild_id).await;
+    }
+
+    /// Returns the toggle cache, for example to add it to a ``MemoryGovernor``
+    pub fn cache(&self) -> &TtlCache<GuildId, Vec<Feature>> {
+        &self.cache
+    }
+}
+
+/// Returns the guild an event happened in, for the events the crates subsystems handle
+pub fn event_guild_id(event: &FullEvent) -> Option<GuildId> {
+    match event {
+        FullEvent::Message { new_message } => new_message.guild_id,
+        FullEvent::MessageUpdate { new: Some(new), .. } => new.guild_id,
+        FullEvent::MessageDelete { guild_id, .. } => *guild_id,
+        FullEvent::GuildMemberAddition { new_member } => Some(new_member.guild_id),
+        FullEvent::GuildMemberRemoval { guild_id, .. } => Some(*guild_id),
+        FullEvent::GuildAuditLogEntryCreate { guild_id, .. } => Some(*guild_id),
+        _ => None,
+    }
+}
+
+/// Returns whether a subsystem may act on an event
+///
+/// Events outside of a guild, or without a ``FeatureMatrix`` set, are always allowed. If the store
+/// fails the feature is treated as enabled so moderation does not silently stop
+pub async fn allows(features: Option<&FeatureMatrix>, event: &FullEvent, feature: Feature) -> bool {
+    let (Some(features), Some(guild_id)) = (features, event_guild_id(event)) else {
+        return true;
+    };
+
+    match features.is_enabled(guild_id, feature).await {
+        Ok(enabled) => enabled,
+        Err(e) => {
+            log::warn!(
+                "Failed to check feature {:?} in {}: {}",
+                feature,
+                guild_id,
+                e
+            );
+            true
+        }
+    }
+}
+
+/// Trait for bot data that holds a ``FeatureMatrix``
+pub trait HasFeatures {
+    fn features(&self) -> &FeatureMatrix;
+}
+
+fn _components(disabled: &[Feature]) -> Vec<CreateActionRow<'static>> {
+    Feature::ALL
+        .chunks(4)
+        .map(|row| {
+            CreateActionRow::Buttons(
+                row.iter()
+                    .map(|f| {
+                        CreateButton::new(format!("features:{}", f.id()))
+                            .label(f.label())
+                            .style(if disabled.contains(f) {
+                                serenity::ButtonStyle::Secondary
+                            } else {
+                                serenity::ButtonStyle::Success
+                            })
+                    })
+                    .collect(),
+            )
+        })
+        .collect()
+}
+
+const FEATURES_CONTENT: &str = "Toggle the features of this server, green features are enabled";
+
+/// Shows the guilds features as a grid of toggle buttons, can be plugged into your bots ``/features`` command
+///
+/// Button presses are handled by ``handle_interaction``
+pub async fn features<Data: HasFeatures + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Features can only be managed in a server".into());
+    };
+
+    let data = ctx.data();
+    let disabled = data.features().disabled(guild_id).await?;
+
+    ctx.send(
+        CreateReply::default()
+            .content(FEATURES_CONTENT)
+            .components(_components(&disabled))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Handles a feature toggle press, returning false if the interaction is not a feature interaction
+///
+/// Only members with ``MANAGE_GUILD`` may toggle features. This should be called from your bots event
+/// handler on every component interaction
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    features: &FeatureMatrix,
+) -> Result<bool, Error> {
+    let Some(feature) = interaction
+        .data
+        .custom_id
+        .strip_prefix("features:")
+        .and_then(Feature::from_id)
+    else {
+        return Ok(false);
+    };
+
+    let Some(guild_id) = interaction.guild_id else {
+        return Ok(true);
+    };
+
+    let allowed = interaction
+        .member
+        .as_ref()
+        .and_then(|m| m.permissions)
+        .is_some_and(|p| p.contains(Permissions::MANAGE_GUILD));
+
+    if !allowed {
+        interaction
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::Message(
+                    CreateInteractionResponseMessage::new()
+                        .content("You need the Manage Server permission to toggle features")
+                        .ephemeral(true),
+                ),
+            )
+            .await?;
+
+        return Ok(true);
+    }
+
+    let enabled = features.is_enabled(guild_id, feature).await?;
+
+    features.set(guild_id, feature, !enabled).await?;
+
+    let disabled = features.disabled(guild_id).await?;
+
+    interaction
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(FEATURES_CONTENT)
+                    .components(_components(&disabled)),
+            ),
+        )
+        .await?;
+
+    Ok(true)
+}
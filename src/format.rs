@@ -0,0 +1,88 @@
+use std::fmt::{Display, Write};
+
+/// Rough length of a rendered line, used to size buffers up front
+pub const LINE_ESTIMATE: usize = 64;
+
+/// Returns an empty buffer with room for ``lines`` lines of ``line_len`` bytes
+pub fn buffer(lines: usize, line_len: usize) -> String {
+    String::with_capacity(lines.saturating_mul(line_len))
+}
+
+/// Returns the number of characters ``n`` takes up when displayed, without formatting it
+pub fn int_width(n: i64) -> usize {
+    let mut width = if n < 0 { 2 } else { 1 };
+    let mut n = n.unsigned_abs();
+
+    while n >= 10 {
+        n /= 10;
+        width += 1;
+    }
+
+    width
+}
+
+/// How ``BufWriterExt::push_padded`` aligns text
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+/// Helpers for building text into a ``String`` in place, avoiding a ``format!`` allocation per line
+pub trait BufWriterExt {
+    /// Appends anything displayable, writing straight into the buffer
+    fn push_display(&mut self, value: impl Display) -> &mut Self;
+
+    /// Appends ``s`` padded with spaces to ``width`` characters
+    fn push_padded(&mut self, s: &str, width: usize, align: Align) -> &mut Self;
+
+    /// Appends ``count`` spaces
+    fn push_spaces(&mut self, count: usize) -> &mut Self;
+
+    /// Appends ``s`` with every ``from`` replaced by ``to``
+    fn push_replacing(&mut self, s: &str, from: char, to: char) -> &mut Self;
+
+    /// Appends the parts followed by a newline
+    fn push_line(&mut self, parts: &[&str]) -> &mut Self;
+}
+
+impl BufWriterExt for String {
+    fn push_display(&mut self, value: impl Display) -> &mut Self {
+        let _ = write!(self, "{}", value);
+        self
+    }
+
+    fn push_padded(&mut self, s: &str, width: usize, align: Align) -> &mut Self {
+        let padding = width.saturating_sub(s.chars().count());
+
+        match align {
+            Align::Left => {
+                self.push_str(s);
+                self.push_spaces(padding)
+            }
+            Align::Right => {
+                self.push_spaces(padding).push_str(s);
+                self
+            }
+        }
+    }
+
+    fn push_spaces(&mut self, count: usize) -> &mut Self {
+        self.extend(std::iter::repeat(' ').take(count));
+        self
+    }
+
+    fn push_replacing(&mut self, s: &str, from: char, to: char) -> &mut Self {
+        self.extend(s.chars().map(|c| if c == from { to } else { c }));
+        self
+    }
+
+    fn push_line(&mut self, parts: &[&str]) -> &mut Self {
+        for part in parts {
+            self.push_str(part);
+        }
+
+        self.push('\n');
+        self
+    }
+}
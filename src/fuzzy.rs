@@ -0,0 +1,272 @@
+use poise::serenity_prelude::{self as serenity, ChannelId, CommandId, CreateMessage, Message};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::i18n::t;
+use crate::Error;
+
+/// Levenshtein edit distance between two strings
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    let mut prev = (0..=b.len()).collect::<Vec<_>>();
+    let mut cur = vec![0; b.len() + 1];
+
+    for i in 1..=a.len() {
+        cur[0] = i;
+
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+
+        std::mem::swap(&mut prev, &mut cur);
+    }
+
+    prev[b.len()]
+}
+
+/// Jaro-Winkler similarity between two strings, from 0.0 (nothing in common) to 1.0 (equal)
+pub fn jaro_winkler(a: &str, b: &str) -> f64 {
+    let a = a.chars().collect::<Vec<_>>();
+    let b = b.chars().collect::<Vec<_>>();
+
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+
+    let mut a_matches = vec![false; a.len()];
+    let mut b_matches = vec![false; b.len()];
+    let mut matches = 0;
+
+    for i in 0..a.len() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(b.len());
+
+        for j in start..end {
+            if !b_matches[j] && a[i] == b[j] {
+                a_matches[i] = true;
+                b_matches[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+
+    if matches == 0 {
+        return 0.0;
+    }
+
+    let mut transpositions = 0;
+    let mut k = 0;
+
+    for i in 0..a.len() {
+        if !a_matches[i] {
+            continue;
+        }
+
+        while !b_matches[k] {
+            k += 1;
+        }
+
+        if a[i] != b[k] {
+            transpositions += 1;
+        }
+
+        k += 1;
+    }
+
+    let m = matches as f64;
+    let jaro =
+        (m / a.len() as f64 + m / b.len() as f64 + (m - transpositions as f64 / 2.0) / m) / 3.0;
+
+    let prefix = a
+        .iter()
+        .zip(b.iter())
+        .take(4)
+        .take_while(|(x, y)| x == y)
+        .count();
+
+    jaro + prefix as f64 * 0.1 * (1.0 - jaro)
+}
+
+/// Suggests close matches for unknown prefix commands
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct DidYouMean {
+    /// Minimum Jaro-Winkler similarity for a name to be suggested
+    pub min_similarity: f64,
+    /// Maximum number of suggestions shown
+    pub max_suggestions: usize,
+    /// Minimum time between suggestions in the same channel
+    pub cooldown: Duration,
+    last_sent: Arc<Mutex<HashMap<ChannelId, Instant>>>,
+    command_ids: Arc<Mutex<Option<HashMap<String, CommandId>>>>,
+}
+
+impl Default for DidYouMean {
+    fn default() -> Self {
+        Self {
+            min_similarity: 0.8,
+            max_suggestions: 3,
+            cooldown: Duration::from_secs(10),
+            last_sent: Arc::new(Mutex::new(HashMap::new())),
+            command_ids: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl DidYouMean {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the closest command names (matching names and aliases), best match first
+    pub fn suggest<U, E>(&self, commands: &[poise::Command<U, E>], input: &str) -> Vec<String> {
+        let input = input.to_lowercase();
+
+        let mut scored = commands
+            .iter()
+            .filter(|c| !c.hide_in_help)
+            .filter_map(|c| {
+                std::iter::once(&c.name)
+                    .chain(c.aliases.iter())
+                    .map(|n| {
+                        let n = n.to_lowercase();
+                        // Small typos in short names score low on Jaro-Winkler, so also accept a single edit
+                        let mut score = jaro_winkler(&input, &n);
+                        if levenshtein(&input, &n) <= 1 {
+                            score = score.max(self.min_similarity);
+                        }
+                        score
+                    })
+                    .fold(None, |best: Option<f64>, s| {
+                        Some(best.map_or(s, |b| b.max(s)))
+                    })
+                    .filter(|s| *s >= self.min_similarity)
+                    .map(|s| (c.name.to_string(), s))
+            })
+            .collect::<Vec<_>>();
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(self.max_suggestions);
+
+        scored.into_iter().map(|(n, _)| n).collect()
+    }
+
+    /// Drops the cached slash command ids, call after re-registering commands
+    pub async fn invalidate(&self) {
+        *self.command_ids.lock().await = None;
+    }
+
+    async fn _mention(&self, http: &serenity::Http, name: &str) -> String {
+        let mut ids = self.command_ids.lock().await;
+
+        if ids.is_none() {
+            match http.get_global_commands().await {
+                Ok(commands) => {
+                    *ids = Some(
+                        commands
+                            .into_iter()
+                            .map(|c| (c.name.to_string(), c.id))
+                            .collect(),
+                    )
+                }
+                Err(e) => log::warn!("Failed to fetch command ids for mentions: {}", e),
+            }
+        }
+
+        match ids.as_ref().and_then(|ids| ids.get(name)) {
+            Some(id) => format!("</{}:{}>", name, id),
+            None => format!("``{}``", name),
+        }
+    }
+
+    /// Replies with suggestions for an unknown prefix command, returning whether a reply was sent
+    ///
+    /// Call from your bots ``on_error`` handler on ``FrameworkError::UnknownCommand`` with its
+    /// ``msg`` and ``msg_content``. Prefix commands carry no locale, so the reply uses the ``en`` strings
+    pub async fn on_unknown_command<U, E>(
+        &self,
+        http: &serenity::Http,
+        commands: &[poise::Command<U, E>],
+        msg: &Message,
+        msg_content: &str,
+    ) -> Result<bool, Error> {
+        let Some(name) = msg_content.split_whitespace().next() else {
+            return Ok(false);
+        };
+
+        let suggestions = self.suggest(commands, name);
+
+        if suggestions.is_empty() {
+            return Ok(false);
+        }
+
+        {
+            let mut last_sent = self.last_sent.lock().await;
+
+            if last_sent
+                .get(&msg.channel_id)
+                .is_some_and(|t| t.elapsed() < self.cooldown)
+            {
+                return Ok(false);
+            }
+
+            last_sent.insert(msg.channel_id, Instant::now());
+        }
+
+        let mut mentions = Vec::with_capacity(suggestions.len());
+
+        for suggestion in &suggestions {
+            mentions.push(self._mention(http, suggestion).await);
+        }
+
+        crate::send::send_message(
+            http,
+            msg.channel_id,
+            CreateMessage::new()
+                .content(t(
+                    None,
+                    "fuzzy.did_you_mean",
+                    &[("commands", &mentions.join(", "))],
+                ))
+                .reference_message(msg),
+        )
+        .await?;
+
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn levenshtein_distances() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("ban", "ban"), 0);
+    }
+
+    #[test]
+    fn jaro_winkler_similarity() {
+        assert!((jaro_winkler("martha", "marhta") - 0.9611).abs() < 0.001);
+        assert_eq!(jaro_winkler("ban", "ban"), 1.0);
+        assert_eq!(jaro_winkler("", ""), 1.0);
+        assert_eq!(jaro_winkler("ban", ""), 0.0);
+        assert_eq!(jaro_winkler("abc", "xyz"), 0.0);
+        assert!(jaro_winkler("purge", "prune") < jaro_winkler("purge", "purg"));
+    }
+}
@@ -0,0 +1,656 @@
+use chrono::{DateTime, Datelike, Days, Months, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateMessage, CreateScheduledEvent, EditScheduledEvent, GuildId,
+    ScheduledEvent, ScheduledEventId, ScheduledEventStatus, ScheduledEventType, Timestamp,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::notify::{NotifyCategory, Preferences};
+use crate::taskman::Task;
+use crate::templates::{Template, TemplateContext};
+use crate::time::{to_chrono, GuildClock};
+use crate::Error;
+
+/// Upper bound on occurrences walked per call of ``Recurrence::between``, so a wide window can't stall the task
+const MAX_OCCURRENCES: u32 = 1000;
+
+/// How often a recurring event repeats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+}
+
+/// A recurrence rule, supporting the ``FREQ``, ``INTERVAL``, ``COUNT`` and ``UNTIL`` parts of RFC 5545 RRULEs
+///
+/// Occurrences are computed on the wall clock of the start's timezone, so events keep their local
+/// time across DST changes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recurrence {
+    pub freq: Frequency,
+    /// Repeat every ``interval`` days, weeks or months, at least 1
+    pub interval: u32,
+    /// Stop after this many occurrences, including the first
+    pub count: Option<u32>,
+    /// Stop after this wall clock time, in the timezone occurrences are computed in
+    pub until: Option<NaiveDateTime>,
+}
+
+impl Recurrence {
+    /// Parses a rule such as ``FREQ=WEEKLY;INTERVAL=2;COUNT=10``, a leading ``RRULE:`` is allowed
+    ///
+    /// ``UNTIL`` must be a local time such as ``20261231T235959``, it is interpreted in the guilds timezone
+    pub fn parse(rule: &str) -> Result<Self, Error> {
+        let rule = rule.trim();
+        let rule = rule.strip_prefix("RRULE:").unwrap_or(rule);
+
+        let mut freq = None;
+        let mut interval = 1;
+        let mut count = None;
+        let mut until = None;
+
+        for part in rule.split(';').filter(|p| !p.is_empty()) {
+            let Some((key, value)) = part.split_once('=') else {
+                return Err(format!("Invalid rule part ``{}``", part).into());
+            };
+
+            match key.to_ascii_uppercase().as_str() {
+                "FREQ" => {
+                    freq = Some(match value.to_ascii_uppercase().as_str() {
+                        "DAILY" => Frequency::Daily,
+                        "WEEKLY" => Frequency::Weekly,
+                        "MONTHLY" => Frequency::Monthly,
+                        _ => return Err(format!("Unsupported frequency ``{}``", value).into()),
+                    })
+                }
+                "INTERVAL" => {
+                    interval = value
+                        .parse::<u32>()
+                        .ok()
+                        .filter(|i| *i >= 1)
+                        .ok_or("``INTERVAL`` must be a positive number")?
+                }
+                "COUNT" => {
+                    count = Some(
+                        value
+                            .parse::<u32>()
+                            .ok()
+                            .filter(|c| *c >= 1)
+                            .ok_or("``COUNT`` must be a positive number")?,
+                    )
+                }
+                "UNTIL" => {
+                    until = Some(
+                        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+                            .map_err(|_| "``UNTIL`` must look like ``20261231T235959``")?,
+                    );
+                }
+                _ => return Err(format!("Unsupported rule part ``{}``", key).into()),
+            }
+        }
+
+        Ok(Self {
+            freq: freq.ok_or("The rule needs a ``FREQ``")?,
+            interval,
+            count,
+            until,
+        })
+    }
+
+    /// Returns the nth (0-based) occurrence, or None if the rule ends before it
+    ///
+    /// Occurrences falling into a DST gap are moved forward by an hour
+    pub fn nth(&self, start: DateTime<Tz>, n: u32) -> Option<DateTime<Tz>> {
+        if self.count.is_some_and(|c| n >= c) {
+            return None;
+        }
+
+        let steps = n.checked_mul(self.interval)?;
+        let local = start.naive_local();
+
+        let local = match self.freq {
+            Frequency::Daily => local.checked_add_days(Days::new(steps as u64))?,
+            Frequency::Weekly => local.checked_add_days(Days::new(steps as u64 * 7))?,
+            Frequency::Monthly => local.checked_add_months(Months::new(steps))?,
+        };
+
+        if self.until.is_some_and(|until| local > until) {
+            return None;
+        }
+
+        let tz = start.timezone();
+
+        tz.from_local_datetime(&local).earliest().or_else(|| {
+            tz.from_local_datetime(&(local + chrono::Duration::hours(1)))
+                .earliest()
+        })
+    }
+
+    /// Returns an occurrence index at or before the first occurrence after ``after``, so walks
+    /// don't have to start at the first occurrence
+    fn _first_index(&self, start: &DateTime<Tz>, after: &DateTime<Tz>) -> u32 {
+        let (start, after) = (start.naive_local(), after.naive_local());
+
+        if after <= start {
+            return 0;
+        }
+
+        let periods = match self.freq {
+            Frequency::Daily => (after - start).num_days(),
+            Frequency::Weekly => (after - start).num_days() / 7,
+            Frequency::Monthly => {
+                (after.year() - start.year()) as i64 * 12 + after.month() as i64
+                    - start.month() as i64
+            }
+        };
+
+        // One step back covers DST shifts and months of different lengths
+        (periods / self.interval as i64 - 1).clamp(0, u32::MAX as i64) as u32
+    }
+
+    /// Returns the occurrences after ``after`` up to and including ``until``
+    pub fn between(
+        &self,
+        start: DateTime<Tz>,
+        after: DateTime<Tz>,
+        until: DateTime<Tz>,
+    ) -> Vec<DateTime<Tz>> {
+        let mut occurrences = Vec::new();
+        let first = self._first_index(&start, &after);
+
+        for n in first..first.saturating_add(MAX_OCCURRENCES) {
+            let Some(occurrence) = self.nth(start, n) else {
+                break;
+            };
+
+            if occurrence > until {
+                break;
+            }
+
+            if occurrence > after {
+                occurrences.push(occurrence);
+            }
+        }
+
+        occurrences
+    }
+}
+
+/// Where a scheduled event takes place
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EventLocation {
+    Voice(ChannelId),
+    Stage(ChannelId),
+    /// Somewhere outside Discord, such as a URL or address
+    External(String),
+}
+
+/// What events are created with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EventTemplate {
+    /// A ``templates::Template``, so ``{guild.name}`` can be used
+    pub name: String,
+    pub description: Option<String>,
+    pub location: EventLocation,
+    pub duration: Duration,
+    /// DM interested users this long before the event starts
+    pub remind_before: Option<Duration>,
+}
+
+impl EventTemplate {
+    /// Checks the name and description placeholders
+    pub fn validate(&self) -> Result<(), Error> {
+        for text in std::iter::once(&self.name).chain(self.description.as_ref()) {
+            if let Err(errors) = crate::templates::validate(text) {
+                return Err(format!(
+                    "Invalid event template:\n{}",
+                    crate::templates::format_errors(&errors)
+                )
+                .into());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn _render(&self, guild_id: GuildId, guild_name: Option<&str>) -> (String, Option<String>) {
+        let ctx = TemplateContext::for_guild(guild_id, guild_name);
+        let render = |text: &str| match Template::parse(text) {
+            Ok(template) => template.render(&ctx),
+            Err(_) => text.to_string(),
+        };
+
+        (render(&self.name), self.description.as_deref().map(render))
+    }
+}
+
+/// An event that is created again on every occurrence of a recurrence
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecurringEvent {
+    pub id: String,
+    pub guild_id: GuildId,
+    pub template: EventTemplate,
+    /// The first occurrence
+    pub start: Timestamp,
+    pub recurrence: Recurrence,
+    /// The latest occurrence an event was created for
+    pub materialized_until: Option<Timestamp>,
+}
+
+/// A created event waiting for its reminder
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingReminder {
+    pub guild_id: GuildId,
+    pub event_id: ScheduledEventId,
+    pub name: String,
+    pub start: Timestamp,
+    pub remind_at: Timestamp,
+}
+
+/// Storage backend for recurring events and pending reminders
+pub trait GuildEventStore: Send + Sync {
+    fn recurring<'a>(&'a self) -> BoxFuture<'a, Result<Vec<RecurringEvent>, Error>>;
+
+    /// Saves a recurring event, creating it if it does not already exist
+    fn save_recurring<'a>(&'a self, event: &'a RecurringEvent) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Deletes a recurring event, returning whether it existed
+    fn delete_recurring<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<bool, Error>>;
+
+    fn reminders<'a>(&'a self) -> BoxFuture<'a, Result<Vec<PendingReminder>, Error>>;
+
+    fn save_reminder<'a>(
+        &'a self,
+        reminder: &'a PendingReminder,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    fn delete_reminder<'a>(
+        &'a self,
+        event_id: ScheduledEventId,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+fn _offset(ts: Timestamp, by: Duration, forward: bool) -> Result<Timestamp, Error> {
+    let secs = by.as_secs() as i64;
+    let unix = if forward {
+        ts.unix_timestamp() + secs
+    } else {
+        ts.unix_timestamp() - secs
+    };
+
+    Ok(Timestamp::from_unix_timestamp(unix)?)
+}
+
+/// Creates, updates and cancels Discord scheduled events, materializes recurring events and
+/// reminds interested users before events start
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct GuildEvents {
+    store: Arc<dyn GuildEventStore>,
+    /// How far ahead recurring events are created, defaults to 7 days
+    pub horizon: Duration,
+    /// Reminders are skipped for users who opted out of ``NotifyCategory::Reminders``
+    pub preferences: Option<Preferences>,
+    /// Recurring events repeat on the wall clock of the guilds timezone, UTC if unset
+    pub clock: Option<GuildClock>,
+}
+
+impl GuildEvents {
+    pub fn new(store: Arc<dyn GuildEventStore>) -> Self {
+        Self {
+            store,
+            horizon: Duration::from_secs(7 * 24 * 60 * 60),
+            preferences: None,
+            clock: None,
+        }
+    }
+
+    /// Creates a scheduled event from a template, scheduling its reminder if the template has one
+    pub async fn create(
+        &self,
+        http: &serenity::Http,
+        cache: &serenity::Cache,
+        guild_id: GuildId,
+        template: &EventTemplate,
+        start: Timestamp,
+    ) -> Result<ScheduledEvent, Error> {
+        let guild_name = cache.guild(guild_id).map(|g| g.name.to_string());
+        let (name, description) = template._render(guild_id, guild_name.as_deref());
+
+        let kind = match template.location {
+            EventLocation::Voice(_) => ScheduledEventType::Voice,
+            EventLocation::Stage(_) => ScheduledEventType::StageInstance,
+            EventLocation::External(_) => ScheduledEventType::External,
+        };
+
+        let mut builder = CreateScheduledEvent::new(kind, name.clone(), start).end_time(_offset(
+            start,
+            template.duration,
+            true,
+        )?);
+
+        if let Some(description) = description {
+            builder = builder.description(description);
+        }
+
+        builder = match &template.location {
+            EventLocation::Voice(channel_id) | EventLocation::Stage(channel_id) => {
+                builder.channel_id(*channel_id)
+            }
+            EventLocation::External(location) => builder.location(location.clone()),
+        };
+
+        let event = guild_id.create_scheduled_event(http, builder).await?;
+
+        if let Some(before) = template.remind_before {
+            self.store
+                .save_reminder(&PendingReminder {
+                    guild_id,
+                    event_id: event.id,
+                    name,
+                    start,
+                    remind_at: _offset(start, before, false)?,
+                })
+                .await?;
+        }
+
+        Ok(event)
+    }
+
+    /// Edits a scheduled event, the reminder of a rescheduled event is not moved
+    pub async fn update(
+        &self,
+        http: &serenity::Http,
+        guild_id: GuildId,
+        event_id: ScheduledEventId,
+        builder: EditScheduledEvent<'_>,
+    ) -> Result<ScheduledEvent, Error> {
+        Ok(guild_id
+            .edit_scheduled_event(http, event_id, builder)
+            .await?)
+    }
+
+    /// Cancels a scheduled event and drops its reminder
+    pub async fn cancel(
+        &self,
+        http: &serenity::Http,
+        guild_id: GuildId,
+        event_id: ScheduledEventId,
+    ) -> Result<(), Error> {
+        guild_id
+            .edit_scheduled_event(
+                http,
+                event_id,
+                EditScheduledEvent::new().status(ScheduledEventStatus::Canceled),
+            )
+            .await?;
+
+        self.store.delete_reminder(event_id).await
+    }
+
+    /// Adds a recurring event, its occurrences are created by ``run``
+    pub async fn add_recurring(
+        &self,
+        guild_id: GuildId,
+        template: EventTemplate,
+        start: Timestamp,
+        recurrence: Recurrence,
+    ) -> Result<String, Error> {
+        template.validate()?;
+
+        let event = RecurringEvent {
+            id: crate::crypto::gen_random(16),
+            guild_id,
+            template,
+            start,
+            recurrence,
+            materialized_until: None,
+        };
+
+        self.store.save_recurring(&event).await?;
+
+        Ok(event.id)
+    }
+
+    /// Stops a recurring event, events that were already created are kept
+    pub async fn remove_recurring(&self, id: &str) -> Result<bool, Error> {
+        self.store.delete_recurring(id).await
+    }
+
+    /// Creates the occurrences of every recurring event that start within ``horizon``
+    pub async fn materialize(
+        &self,
+        http: &serenity::Http,
+        cache: &serenity::Cache,
+    ) -> Result<(), Error> {
+        let now = Utc::now();
+        let until = now + chrono::Duration::from_std(self.horizon)?;
+
+        for mut recurring in self.store.recurring().await? {
+            let tz = match &self.clock {
+                Some(clock) => match clock.timezone(recurring.guild_id).await {
+                    Ok(tz) => tz,
+                    Err(e) => {
+                        log::warn!(
+                            "Failed to get the timezone of {}: {}",
+                            recurring.guild_id,
+                            e
+                        );
+                        continue;
+                    }
+                },
+                None => Tz::UTC,
+            };
+
+            let start = to_chrono(recurring.start).with_timezone(&tz);
+            let after = recurring
+                .materialized_until
+                .map(to_chrono)
+                .unwrap_or(DateTime::<Utc>::MIN_UTC)
+                .max(now)
+                .with_timezone(&tz);
+
+            let occurrences = recurring
+                .recurrence
+                .between(start, after, until.with_timezone(&tz));
+
+            if occurrences.is_empty() {
+                continue;
+            }
+
+            for occurrence in occurrences {
+                let at = Timestamp::from_unix_timestamp(occurrence.timestamp())?;
+
+                if let Err(e) = self
+                    .create(http, cache, recurring.guild_id, &recurring.template, at)
+                    .await
+                {
+                    log::warn!(
+                        "Failed to create occurrence of recurring event {}: {}",
+                        recurring.id,
+                        e
+                    );
+                    break;
+                }
+
+                recurring.materialized_until = Some(at);
+            }
+
+            self.store.save_recurring(&recurring).await?;
+        }
+
+        Ok(())
+    }
+
+    /// DMs interested users of every event whose reminder is due
+    pub async fn remind(&self, http: &serenity::Http) -> Result<(), Error> {
+        let now = Timestamp::now();
+
+        for reminder in self.store.reminders().await? {
+            if reminder.remind_at.unix_timestamp() > now.unix_timestamp() {
+                continue;
+            }
+
+            // Reminders are only sent once, even if fetching users fails (the event was deleted)
+            self.store.delete_reminder(reminder.event_id).await?;
+
+            let users = match reminder
+                .guild_id
+                .scheduled_event_users(http, reminder.event_id, None)
+                .await
+            {
+                Ok(users) => users,
+                Err(e) => {
+                    log::warn!(
+                        "Failed to fetch interested users of {}: {}",
+                        reminder.event_id,
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            let content = format!(
+                "⏰ **{}** starts <t:{}:R>",
+                reminder.name,
+                reminder.start.unix_timestamp()
+            );
+
+            for user in users {
+                crate::notify::dm(
+                    http,
+                    self.preferences.as_ref(),
+                    user.user.id,
+                    NotifyCategory::Reminders,
+                    CreateMessage::new().content(content.clone()),
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns a task that creates upcoming recurring events and sends due reminders, checking every
+/// ``check_interval``
+pub fn guild_events_task(events: GuildEvents, check_interval: Duration) -> Task {
+    Task {
+        name: "guild_events",
+        description: "Creates recurring scheduled events and sends event reminders",
+        enabled: true,
+        duration: check_interval,
+        run: Box::new(move |ctx| {
+            let events = events.clone();
+            Box::pin(async move {
+                events.materialize(&ctx.http, &ctx.cache).await?;
+                events.remind(&ctx.http).await
+            })
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{NaiveDate, Timelike};
+    use chrono_tz::Europe::London;
+
+    fn at(y: i32, m: u32, d: u32, h: u32, min: u32) -> DateTime<Tz> {
+        London.with_ymd_and_hms(y, m, d, h, min, 0).unwrap()
+    }
+
+    fn local(y: i32, m: u32, d: u32, h: u32, min: u32) -> NaiveDateTime {
+        NaiveDate::from_ymd_opt(y, m, d)
+            .unwrap()
+            .and_hms_opt(h, min, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn parse_rules() {
+        let rule = Recurrence::parse("RRULE:FREQ=WEEKLY;INTERVAL=2;COUNT=3").unwrap();
+        assert_eq!(rule.freq, Frequency::Weekly);
+        assert_eq!(rule.interval, 2);
+        assert_eq!(rule.count, Some(3));
+        assert_eq!(rule.until, None);
+
+        let rule = Recurrence::parse("freq=daily;until=20261231T235959").unwrap();
+        assert_eq!(
+            rule.until,
+            Some(local(2026, 12, 31, 23, 59) + chrono::Duration::seconds(59))
+        );
+
+        assert!(Recurrence::parse("INTERVAL=2").is_err());
+        assert!(Recurrence::parse("FREQ=YEARLY").is_err());
+        assert!(Recurrence::parse("FREQ=DAILY;INTERVAL=0").is_err());
+        assert!(Recurrence::parse("FREQ=DAILY;UNTIL=2026-12-31").is_err());
+    }
+
+    #[test]
+    fn nth_keeps_local_time_across_dst() {
+        let rule = Recurrence::parse("FREQ=WEEKLY").unwrap();
+        let next = rule.nth(at(2026, 3, 22, 10, 0), 1).unwrap();
+
+        assert_eq!(next.naive_local(), local(2026, 3, 29, 10, 0));
+        assert_eq!(next.with_timezone(&Utc).hour(), 9);
+    }
+
+    #[test]
+    fn nth_moves_dst_gap_forward() {
+        let rule = Recurrence::parse("FREQ=DAILY").unwrap();
+        let next = rule.nth(at(2026, 3, 28, 1, 30), 1).unwrap();
+
+        assert_eq!(next.naive_local(), local(2026, 3, 29, 2, 30));
+    }
+
+    #[test]
+    fn nth_clamps_to_the_end_of_the_month() {
+        let rule = Recurrence::parse("FREQ=MONTHLY").unwrap();
+        let next = rule.nth(at(2026, 1, 31, 12, 0), 1).unwrap();
+
+        assert_eq!(next.naive_local(), local(2026, 2, 28, 12, 0));
+    }
+
+    #[test]
+    fn nth_stops_at_count_and_until() {
+        let start = at(2026, 4, 1, 10, 0);
+
+        let rule = Recurrence::parse("FREQ=DAILY;COUNT=3").unwrap();
+        assert!(rule.nth(start, 2).is_some());
+        assert!(rule.nth(start, 3).is_none());
+
+        let rule = Recurrence::parse("FREQ=DAILY;UNTIL=20260405T000000").unwrap();
+        assert!(rule.nth(start, 3).is_some());
+        assert!(rule.nth(start, 4).is_none());
+    }
+
+    #[test]
+    fn between_skips_to_the_window() {
+        let rule = Recurrence::parse("FREQ=DAILY").unwrap();
+        let start = at(2026, 1, 1, 12, 0);
+
+        let occurrences = rule.between(start, at(2026, 3, 1, 0, 0), at(2026, 3, 3, 23, 0));
+        assert_eq!(
+            occurrences
+                .iter()
+                .map(|o| o.naive_local())
+                .collect::<Vec<_>>(),
+            vec![
+                local(2026, 3, 1, 12, 0),
+                local(2026, 3, 2, 12, 0),
+                local(2026, 3, 3, 12, 0),
+            ]
+        );
+
+        // ``after`` itself is excluded
+        let occurrences = rule.between(start, at(2026, 3, 1, 12, 0), at(2026, 3, 3, 23, 0));
+        assert_eq!(occurrences.len(), 2);
+    }
+}
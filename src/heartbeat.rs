@@ -0,0 +1,211 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::resilience::RetryPolicy;
+use crate::shards::{ShardMonitor, ShardStatus};
+use crate::taskman::Task;
+use crate::Error;
+
+/// How the heartbeat is sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeartbeatKind {
+    /// A GET request with ``status``, ``msg`` and ``ping`` query parameters (Uptime Kuma push monitors)
+    ///
+    /// ``ping`` is the average gateway latency in milliseconds, sent once ``shard_manager`` is set
+    /// and a latency has been measured
+    Push,
+    /// A POST request with a JSON ``HeartbeatPayload`` body
+    Json,
+}
+
+pub struct HeartbeatOptions {
+    /// The URL to send heartbeats to
+    pub url: String,
+    pub kind: HeartbeatKind,
+    /// How often to send a heartbeat
+    pub interval: Duration,
+    /// Request timeout for a single heartbeat
+    pub timeout: Duration,
+    /// Used to include shard health in the heartbeat, if set
+    pub monitor: Option<ShardMonitor>,
+    /// Used to include the gateway latency in the heartbeat, if set
+    pub shard_manager: Option<Arc<serenity::gateway::ShardManager>>,
+    /// Number of consecutive failures after which an alert is logged
+    pub alert_after: u32,
+    /// How a failed heartbeat is retried before it counts as a failure
+    pub retry: RetryPolicy,
+}
+
+impl HeartbeatOptions {
+    pub fn new(url: impl Into<String>, kind: HeartbeatKind) -> Self {
+        Self {
+            url: url.into(),
+            kind,
+            interval: Duration::from_secs(60),
+            timeout: Duration::from_secs(10),
+            monitor: None,
+            shard_manager: None,
+            alert_after: 3,
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+/// The body of a ``HeartbeatKind::Json`` heartbeat
+#[derive(Debug, Clone, Serialize)]
+pub struct HeartbeatPayload {
+    /// ``up`` if all shards are connected, ``degraded`` otherwise
+    pub status: &'static str,
+    pub guilds: usize,
+    pub shards: Vec<ShardStatus>,
+    /// Average gateway latency of the shards on this process in milliseconds, if measured
+    pub ping_ms: Option<u64>,
+}
+
+/// Periodically pushes a heartbeat to a status page
+pub struct Heartbeat {
+    opts: HeartbeatOptions,
+    client: reqwest::Client,
+    failures: AtomicU32,
+}
+
+impl Heartbeat {
+    pub fn new(opts: HeartbeatOptions) -> Result<Self, Error> {
+        let client = reqwest::Client::builder().timeout(opts.timeout).build()?;
+
+        Ok(Self {
+            opts,
+            client,
+            failures: AtomicU32::new(0),
+        })
+    }
+
+    /// Builds the payload for the current state of the bot
+    pub async fn payload(&self, ctx: &serenity::client::Context) -> HeartbeatPayload {
+        let shards = match &self.opts.monitor {
+            Some(monitor) => monitor.snapshot().await,
+            None => Vec::new(),
+        };
+
+        HeartbeatPayload {
+            status: if shards.iter().all(|s| s.connected) {
+                "up"
+            } else {
+                "degraded"
+            },
+            guilds: ctx.cache.guild_count(),
+            shards,
+            ping_ms: self._ping().await.map(|p| p.as_millis() as u64),
+        }
+    }
+
+    /// Returns the average gateway latency of the shards that have measured one
+    async fn _ping(&self) -> Option<Duration> {
+        let shard_manager = self.opts.shard_manager.as_ref()?;
+        let runners = shard_manager.runners.lock().await;
+
+        let latencies = runners
+            .values()
+            .filter_map(|r| r.latency)
+            .collect::<Vec<_>>();
+
+        if latencies.is_empty() {
+            return None;
+        }
+
+        Some(latencies.iter().sum::<Duration>() / latencies.len() as u32)
+    }
+
+    async fn _send(&self, payload: &HeartbeatPayload) -> Result<(), Error> {
+        let res = match self.opts.kind {
+            HeartbeatKind::Push => {
+                let connected = payload.shards.iter().filter(|s| s.connected).count();
+
+                let mut query = vec![
+                    // Push monitors treat any status other than ``up`` as down
+                    ("status", payload.status.to_string()),
+                    (
+                        "msg",
+                        format!(
+                            "{} ({}/{} shards connected)",
+                            payload.status,
+                            connected,
+                            payload.shards.len()
+                        ),
+                    ),
+                ];
+
+                if let Some(ping) = payload.ping_ms {
+                    query.push(("ping", ping.to_string()));
+                }
+
+                self.client.get(&self.opts.url).query(&query).send().await?
+            }
+            HeartbeatKind::Json => {
+                self.client
+                    .post(&self.opts.url)
+                    .json(payload)
+                    .send()
+                    .await?
+            }
+        };
+
+        res.error_for_status()?;
+
+        Ok(())
+    }
+
+    /// Sends a single heartbeat, logging an alert once ``alert_after`` consecutive heartbeats have failed
+    pub async fn beat(&self, ctx: &serenity::client::Context) -> Result<(), Error> {
+        let payload = self.payload(ctx).await;
+
+        match crate::resilience::retry(&self.opts.retry, || self._send(&payload)).await {
+            Ok(()) => {
+                let failures = self.failures.swap(0, Ordering::Relaxed);
+
+                if failures >= self.opts.alert_after {
+                    log::info!(
+                        "Heartbeat to {} recovered after {} failures",
+                        self.opts.url,
+                        failures
+                    );
+                }
+
+                Ok(())
+            }
+            Err(e) => {
+                let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+
+                if failures == self.opts.alert_after {
+                    log::error!(
+                        "ALERT: Heartbeat to {} has failed {} times in a row: {}",
+                        self.opts.url,
+                        failures,
+                        e
+                    );
+                }
+
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Creates a task that can be passed to ``taskman::start_all_tasks`` to send heartbeats
+pub fn heartbeat_task(opts: HeartbeatOptions) -> Result<Task, Error> {
+    let duration = opts.interval;
+    let heartbeat = Arc::new(Heartbeat::new(opts)?);
+
+    Ok(Task {
+        name: "heartbeat",
+        description: "Pushes a heartbeat to the configured status page",
+        enabled: true,
+        duration,
+        run: Box::new(move |ctx| {
+            let heartbeat = heartbeat.clone();
+            Box::pin(async move { heartbeat.beat(ctx).await })
+        }),
+    })
+}
@@ -5,12 +5,33 @@ use poise::serenity_prelude::{
     CreateActionRow, CreateButton, CreateEmbed, CreateSelectMenuOption, MessageId,
 };
 use poise::{Command, CreateReply};
+use std::collections::HashMap;
 use std::fmt::Write;
+use std::future::Future;
 
+use crate::cmdpath;
+use crate::embeds::FieldPacker;
+use crate::format::{buffer, LINE_ESTIMATE};
+use crate::i18n::{t, tr};
 use crate::Error;
 use std::sync::Arc;
 use std::time::Duration;
 
+/// Default number of commands whose checks and filters run at once while rendering help
+pub const DEFAULT_CHECK_CONCURRENCY: usize = 16;
+
+/// How commands are ordered within a help category
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortMode {
+    /// The order the commands were registered in
+    #[default]
+    Registration,
+    /// Alphabetical order by command name
+    Alphabetical,
+    /// Most invoked commands first, as counted by ``HelpOptions::usage``
+    MostUsed,
+}
+
 #[derive(Default)]
 pub struct HelpOptions<Data: Send + Sync + 'static, State: Send + Sync + Default> {
     /// State for the help command
@@ -33,22 +54,119 @@ pub struct HelpOptions<Data: Send + Sync + 'static, State: Send + Sync + Default
                 ) -> BoxFuture<'a, Result<bool, crate::Error>>,
         >,
     >,
+    /// How commands are ordered within a category
+    pub sort_mode: SortMode,
+    /// Usage tracker for ``SortMode::MostUsed``
+    pub usage: Option<crate::analytics::UsageTracker>,
+    /// How many commands have their checks and filters run at once, defaults to ``DEFAULT_CHECK_CONCURRENCY``
+    pub check_concurrency: Option<usize>,
+    /// Long help by qualified command name, for commands without a ``LongHelp`` in their ``custom_data``
+    pub long_help: HashMap<String, String>,
+    /// Bot permissions shown on command pages, commands fall back to their ``required_bot_permissions``
+    pub bot_permissions: Option<crate::botperms::BotPermissions>,
 }
 
-/// Struct to store embed data for the help command
-struct EmbedHelp {
-    category: String,
-    desc: String,
+/// Extended help shown on a commands own help page, set as a commands ``custom_data``
+///
+/// Markdown headings (such as ``## Usage``, ``## Notes`` or ``## Permissions``) start sections that
+/// are shown as separate fields, text before the first heading is added to the description
+#[derive(Debug, Clone)]
+pub struct LongHelp(pub String);
+
+/// Returns the long help of a command from its ``custom_data``, the registry or its ``help_text``, in that order
+pub fn long_help<'a, Data>(
+    command: &'a Command<Data, Error>,
+    registry: &'a HashMap<String, String>,
+) -> Option<&'a str> {
+    if let Some(LongHelp(text)) = command.custom_data.downcast_ref::<LongHelp>() {
+        return Some(text);
+    }
+
+    registry
+        .get(&*command.qualified_name)
+        .map(|s| s.as_str())
+        .or(command.help_text.as_deref())
 }
 
-async fn _embed_help<Data: Send + Sync + 'static, State: Send + Sync + Default>(
-    pctx: poise::Context<'_, Data, crate::Error>,
-    ctx: poise::FrameworkContext<'_, Data, crate::Error>,
-    prefix: &str,
-    ho: HelpOptions<Data, State>,
-) -> Result<Vec<EmbedHelp>, Error> {
+/// Returns the text of a markdown heading (``#`` to ``######`` followed by a space)
+fn _heading(line: &str) -> Option<&str> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+
+    line[level..].strip_prefix(' ').map(str::trim)
+}
+
+/// Splits long help into its introduction and ``(heading, body)`` sections
+///
+/// Lines in fenced code blocks and lines such as ``#channel`` are never headings
+pub fn help_sections(text: &str) -> (String, Vec<(String, String)>) {
+    let mut intro = String::new();
+    let mut sections: Vec<(String, String)> = Vec::new();
+    let mut in_code_block = false;
+
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_code_block = !in_code_block;
+        }
+
+        if !in_code_block {
+            if let Some(heading) = _heading(line) {
+                sections.push((heading.to_string(), String::new()));
+                continue;
+            }
+        }
+
+        let body = match sections.last_mut() {
+            Some((_, body)) => body,
+            None => &mut intro,
+        };
+
+        body.push_str(line);
+        body.push('\n');
+    }
+
+    (
+        intro.trim().to_string(),
+        sections
+            .into_iter()
+            .map(|(heading, body)| (heading, body.trim().to_string()))
+            .collect(),
+    )
+}
+
+/// Runs ``f`` on every item with at most ``limit`` futures in flight, returning the outputs in the order of ``items``
+///
+/// Help rendering uses this so slow (for example, database-backed) checks don't run one after another
+pub async fn run_bounded<T, R, F, Fut>(items: Vec<T>, limit: usize, f: F) -> Vec<R>
+where
+    F: Fn(T) -> Fut,
+    Fut: Future<Output = R>,
+{
+    let mut out = (0..items.len()).map(|_| None).collect::<Vec<Option<R>>>();
+
+    let mut stream = futures::stream::iter(items.into_iter().enumerate())
+        .map(|(i, item)| {
+            let fut = f(item);
+            async move { (i, fut.await) }
+        })
+        .buffer_unordered(limit.max(1));
+
+    while let Some((i, res)) = stream.next().await {
+        out[i] = Some(res);
+    }
+
+    out.into_iter().flatten().collect()
+}
+
+/// Groups commands by category, keeping the order categories and commands were registered in
+pub fn categorize<Data>(
+    commands: &[Command<Data, Error>],
+) -> indexmap::IndexMap<Option<String>, Vec<&Command<Data, Error>>> {
     let mut categories = indexmap::IndexMap::<Option<String>, Vec<&Command<Data, Error>>>::new();
-    for cmd in &ctx.options().commands {
+    for cmd in commands {
         // Check if category exists
         if categories.contains_key(&cmd.category) {
             categories.get_mut(&cmd.category).unwrap().push(cmd);
@@ -59,6 +177,97 @@ async fn _embed_help<Data: Send + Sync + 'static, State: Send + Sync + Default>(
         }
     }
 
+    categories
+}
+
+/// Struct to store embed data for the help command
+struct EmbedHelp {
+    category: String,
+    desc: String,
+}
+
+/// Returns None if a command is hidden from the user, otherwise whether each of its subcommands is shown
+async fn _visibility<Data: Send + Sync + 'static, State: Send + Sync + Default>(
+    pctx: poise::Context<'_, Data, crate::Error>,
+    ho: &HelpOptions<Data, State>,
+    command: &Command<Data, Error>,
+) -> Result<Option<Vec<bool>>, Error> {
+    if command.hide_in_help {
+        return Ok(None);
+    }
+
+    for check in command.checks.iter() {
+        // User may not run this command, errored checks are ignored
+        if let Ok(false) = check(pctx).await {
+            return Ok(None);
+        }
+    }
+
+    let mut subcommands = Vec::with_capacity(command.subcommands.len());
+
+    for subcmd in command.subcommands.iter() {
+        let visible = !subcmd.hide_in_help
+            && match &ho.filter {
+                Some(filter) => filter(&pctx, &ho.state, subcmd).await?,
+                None => true,
+            };
+
+        subcommands.push(visible);
+    }
+
+    Ok(Some(subcommands))
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(name = "help_render", skip_all))]
+async fn _embed_help<Data: Send + Sync + 'static, State: Send + Sync + Default>(
+    pctx: poise::Context<'_, Data, crate::Error>,
+    ctx: poise::FrameworkContext<'_, Data, crate::Error>,
+    prefix: &str,
+    ho: HelpOptions<Data, State>,
+) -> Result<Vec<EmbedHelp>, Error> {
+    let counts = match (&ho.sort_mode, &ho.usage) {
+        (SortMode::MostUsed, Some(usage)) => usage.counts().await,
+        _ => std::collections::HashMap::new(),
+    };
+
+    let categories = categorize(&ctx.options().commands)
+        .into_iter()
+        .map(|(category, mut commands)| {
+            match ho.sort_mode {
+                SortMode::Registration => {}
+                SortMode::Alphabetical => commands.sort_by(|a, b| a.name.cmp(&b.name)),
+                // Parent commands are rarely invoked directly, so they count their subcommands uses
+                SortMode::MostUsed => commands.sort_by_key(|c| {
+                    std::cmp::Reverse(
+                        std::iter::once(*c)
+                            .chain(crate::cmdpath::flatten(&c.subcommands))
+                            .filter_map(|c| counts.get(&*c.qualified_name))
+                            .sum::<u64>(),
+                    )
+                }),
+            }
+
+            (category, commands)
+        })
+        .collect::<Vec<_>>();
+
+    // Run every commands checks and filters up front, bounded by the concurrency limit
+    let all = categories
+        .iter()
+        .flat_map(|(_, commands)| commands.iter().copied())
+        .collect::<Vec<_>>();
+
+    let mut visibility = run_bounded(
+        all,
+        ho.check_concurrency.unwrap_or(DEFAULT_CHECK_CONCURRENCY),
+        |command| _visibility(pctx, &ho, command),
+    )
+    .await
+    .into_iter();
+
+    let no_description = format!("*{}*", tr(pctx, "help.no_description", &[]));
+    let subcommands_label = tr(pctx, "help.subcommands", &[]);
+
     let mut help_arr = Vec::new();
 
     for (category, commands) in categories {
@@ -69,35 +278,13 @@ async fn _embed_help<Data: Send + Sync + 'static, State: Send + Sync + Default>(
                 category
             }
         }
-        .unwrap_or("Uncategorized".to_string());
+        .unwrap_or_else(|| tr(pctx, "help.uncategorized", &[]));
 
-        let mut menu = "".to_string();
+        let mut menu = buffer(commands.len() * 2, LINE_ESTIMATE);
         for command in commands {
-            if command.hide_in_help {
+            let Some(subcommands) = visibility.next().unwrap_or(Ok(None))? else {
                 continue;
-            }
-
-            let mut flag = true;
-
-            for check in command.checks.iter() {
-                let res = check(pctx).await;
-
-                // User may not run this command
-                if res.is_err() {
-                    continue;
-                }
-
-                let res = res.unwrap();
-
-                if !res {
-                    flag = false;
-                    break;
-                }
-            }
-
-            if !flag {
-                continue;
-            }
+            };
 
             let _ = writeln!(
                 menu,
@@ -106,34 +293,30 @@ async fn _embed_help<Data: Send + Sync + 'static, State: Send + Sync + Default>(
                 desc = command
                     .description
                     .as_deref()
-                    .unwrap_or("*No description available yet*")
+                    .unwrap_or(no_description.as_str())
             );
 
-            if command.context_menu_action.is_some() {
+            if let Some(action) = &command.context_menu_action {
                 let _ = writeln!(
                     menu,
-                    "*This command is a context menu command of type {type:#?}*",
-                    r#type = command.context_menu_action.unwrap()
+                    "*{}*",
+                    tr(
+                        pctx,
+                        "help.context_menu",
+                        &[("type", &format!("{:#?}", action))]
+                    )
                 );
                 continue;
             }
 
             if !command.subcommands.is_empty() {
-                let _ = writeln!(menu, "**Subcommands**",);
+                let _ = writeln!(menu, "**{}**", subcommands_label);
 
-                for subcmd in command.subcommands.iter() {
-                    if subcmd.hide_in_help {
+                for (subcmd, visible) in command.subcommands.iter().zip(subcommands) {
+                    if !visible {
                         continue;
                     }
 
-                    if let Some(filter) = &ho.filter {
-                        let res = filter(&pctx, &ho.state, subcmd).await?;
-        
-                        if !res {
-                            continue;
-                        }
-                    }        
-
                     let _ = writeln!(
                         menu,
                         "/{cmd_name} {subcmd_name} | {prefix}{cmd_name} {subcmd_name} - {desc}",
@@ -143,15 +326,15 @@ async fn _embed_help<Data: Send + Sync + 'static, State: Send + Sync + Default>(
                         desc = subcmd
                             .description
                             .as_deref()
-                            .unwrap_or("*No description available yet*")
+                            .unwrap_or(no_description.as_str())
                     );
                 }
             }
         }
 
         help_arr.push(EmbedHelp {
-            category: cat_name.to_string(),
-            desc: menu.clone(),
+            category: cat_name,
+            desc: menu,
         });
     }
 
@@ -197,23 +380,31 @@ fn _create_reply<'a>(
     index: usize,
     prev_disabled: bool,
     next_disabled: bool,
+    locale: Option<&str>,
 ) -> CreateReply<'a> {
     CreateReply::default()
         .embed(
             CreateEmbed::default()
-                .title(format!("{} (Page {})", data.category, index + 1))
+                .title(t(
+                    locale,
+                    "help.page",
+                    &[
+                        ("category", &data.category),
+                        ("page", &(index + 1).to_string()),
+                    ],
+                ))
                 .description(&data.desc),
         )
         .components(vec![
             CreateActionRow::Buttons(vec![
                 CreateButton::new("hnav:".to_string() + &(index - 1).to_string())
-                    .label("Previous")
+                    .label(t(locale, "help.previous", &[]))
                     .disabled(prev_disabled),
                 CreateButton::new("hnav:cancel")
-                    .label("Cancel")
+                    .label(t(locale, "help.cancel", &[]))
                     .style(serenity::ButtonStyle::Danger),
                 CreateButton::new("hnav:".to_string() + &(index + 1).to_string())
-                    .label("Next")
+                    .label(t(locale, "help.next", &[]))
                     .disabled(next_disabled),
             ]),
             CreateActionRow::SelectMenu(_create_select_menu(l_data, index)),
@@ -227,6 +418,7 @@ async fn _help_send_index<Data: Send + Sync + 'static>(
     l_data: &[EmbedHelp],
     index: usize,
     interaction: Option<Arc<ComponentInteraction>>,
+    locale: Option<&str>,
 ) -> Result<Option<serenity::Message>, crate::Error> {
     let next_disabled = index >= l_data.len() - 1;
 
@@ -244,8 +436,15 @@ async fn _help_send_index<Data: Send + Sync + 'static>(
                         .edit_message(
                             http,
                             old_msg.message_id,
-                            _create_reply(data, l_data, index, prev_disabled, next_disabled)
-                                .to_prefix_edit(serenity::EditMessage::new()),
+                            _create_reply(
+                                data,
+                                l_data,
+                                index,
+                                prev_disabled,
+                                next_disabled,
+                                locale,
+                            )
+                            .to_prefix_edit(serenity::EditMessage::new()),
                         )
                         .await?;
                 } else {
@@ -254,10 +453,17 @@ async fn _help_send_index<Data: Send + Sync + 'static>(
                     interaction
                         .edit_response(
                             http,
-                            _create_reply(data, l_data, index, prev_disabled, next_disabled)
-                                .to_slash_initial_response_edit(
-                                    poise::serenity_prelude::EditInteractionResponse::new(),
-                                ),
+                            _create_reply(
+                                data,
+                                l_data,
+                                index,
+                                prev_disabled,
+                                next_disabled,
+                                locale,
+                            )
+                            .to_slash_initial_response_edit(
+                                poise::serenity_prelude::EditInteractionResponse::new(),
+                            ),
                         )
                         .await?;
                 }
@@ -273,6 +479,7 @@ async fn _help_send_index<Data: Send + Sync + 'static>(
                         index,
                         prev_disabled,
                         next_disabled,
+                        locale,
                     ))
                     .await?
                     .into_message()
@@ -287,6 +494,10 @@ async fn _help_send_index<Data: Send + Sync + 'static>(
 }
 
 /// Simple help command that can be plugged into your bot
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(skip_all, fields(command = ?command, user_id = %ctx.author().id))
+)]
 pub async fn help<Data: Send + Sync + 'static, State: Send + Sync + Default>(
     ctx: poise::Context<'_, Data, crate::Error>,
     command: Option<String>,
@@ -295,72 +506,102 @@ pub async fn help<Data: Send + Sync + 'static, State: Send + Sync + Default>(
 ) -> Result<(), Error> {
     if let Some(cmd) = command {
         // They just want the parameters for a specific command
-        for botcmd in &ctx.framework().options().commands {
-            if botcmd.name == cmd {
-                let params_str = botcmd
-                    .parameters
-                    .iter()
-                    .map(|p| {
-                        format!(
-                            "{} - {}",
-                            p.name,
-                            p.description
-                                .as_deref()
-                                .unwrap_or("No description available yet")
-                        )
-                    })
-                    .collect::<Vec<String>>()
-                    .join("\n");
-
-                let mut embed = CreateEmbed::default()
-                    .title(format!("Help for {}", botcmd.name))
-                    .description(
-                        botcmd
-                            .description
-                            .as_deref()
-                            .unwrap_or("No description available yet"),
+        let no_description = tr(ctx, "help.no_description", &[]);
+
+        if let Some(botcmd) = cmdpath::find(&ctx.framework().options().commands, &cmd) {
+            let params_str = botcmd
+                .parameters
+                .iter()
+                .map(|p| {
+                    format!(
+                        "{} - {}",
+                        p.name,
+                        p.description.as_deref().unwrap_or(no_description.as_str())
                     )
-                    .field("Parameters", params_str, false);
-
-                for subcmd in botcmd.subcommands.iter() {
-                    embed = embed.field(
-                        subcmd.name.clone(),
-                        format!(
-                            "{}\n{}",
-                            subcmd
-                                .description
-                                .as_deref()
-                                .unwrap_or("No description available yet"),
-                            subcmd
-                                .parameters
-                                .iter()
-                                .map(|p| format!(
-                                    "*{}* - {}",
-                                    p.name.as_str(),
-                                    p.description
-                                        .as_deref()
-                                        .unwrap_or("No description available yet")
-                                ))
-                                .collect::<Vec<String>>()
-                                .join("\n")
-                        ),
-                        false,
-                    );
-                }
+                })
+                .collect::<Vec<String>>()
+                .join("\n");
+
+            let mut description = botcmd
+                .description
+                .as_deref()
+                .unwrap_or(no_description.as_str())
+                .to_string();
+
+            let (intro, sections) = long_help(botcmd, &ho.long_help)
+                .map(help_sections)
+                .unwrap_or_default();
+
+            if !intro.is_empty() {
+                description.push_str("\n\n");
+                description.push_str(&intro);
+            }
 
-                ctx.send(CreateReply::default().embed(embed)).await?;
+            let mut packer = FieldPacker::new(tr(
+                ctx,
+                "help.title",
+                &[("command", &botcmd.qualified_name)],
+            ))
+            .description(description)
+            .field(tr(ctx, "help.parameters", &[]), params_str, false);
 
-                return Ok(());
+            let bot_permissions = match &ho.bot_permissions {
+                Some(registry) => registry.required(botcmd),
+                None => botcmd.required_bot_permissions,
+            };
+
+            if let Some(permissions) = crate::botperms::describe(bot_permissions) {
+                packer = packer.field(tr(ctx, "help.bot_permissions", &[]), permissions, false);
+            }
+
+            for subcmd in botcmd.subcommands.iter() {
+                packer = packer.field(
+                    subcmd.name.clone(),
+                    format!(
+                        "{}\n{}",
+                        subcmd
+                            .description
+                            .as_deref()
+                            .unwrap_or(no_description.as_str()),
+                        subcmd
+                            .parameters
+                            .iter()
+                            .map(|p| format!(
+                                "*{}* - {}",
+                                p.name.as_str(),
+                                p.description.as_deref().unwrap_or(no_description.as_str())
+                            ))
+                            .collect::<Vec<String>>()
+                            .join("\n")
+                    ),
+                    false,
+                );
+            }
+
+            // Usually a single reply, the size limit applies to a messages embeds combined
+            for embeds in packer.fields(sections).pack_messages() {
+                ctx.send(CreateReply::default().embeds(embeds)).await?;
             }
+
+            return Ok(());
         }
 
-        ctx.say("Command not found!").await?;
+        ctx.say(tr(ctx, "help.not_found", &[])).await?;
         return Ok(());
     }
 
     let eh = _embed_help(ctx, ctx.framework(), prefix, ho).await?;
 
-    let msg = _help_send_index(Some(ctx), None, &ctx.serenity_context().http, &eh, 0, None).await?;
+    let msg = _help_send_index(
+        Some(ctx),
+        None,
+        &ctx.serenity_context().http,
+        &eh,
+        0,
+        None,
+        ctx.locale(),
+    )
+    .await?;
 
     if let Some(msg) = msg {
         // Create a collector
@@ -408,6 +649,7 @@ pub async fn help<Data: Send + Sync + 'static, State: Send + Sync + Default>(
                     &eh,
                     value,
                     Some(Arc::new(item.clone())),
+                    ctx.locale(),
                 )
                 .await?;
 
@@ -428,6 +670,7 @@ pub async fn help<Data: Send + Sync + 'static, State: Send + Sync + Default>(
                     &eh,
                     id,
                     Some(Arc::new(item.clone())),
+                    ctx.locale(),
                 )
                 .await?;
             }
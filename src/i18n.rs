@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+
+/// English defaults for every user-facing string the crate emits
+///
+/// Placeholders are written as ``{name}``. Downstream bots can translate these keys with ``set_translations``
+pub const DEFAULTS: &[(&str, &str)] = &[
+    ("help.uncategorized", "Uncategorized"),
+    ("help.no_description", "No description available yet"),
+    (
+        "help.context_menu",
+        "This command is a context menu command of type {type}",
+    ),
+    ("help.subcommands", "Subcommands"),
+    ("help.parameters", "Parameters"),
+    ("help.bot_permissions", "Bot permissions"),
+    ("help.title", "Help for {command}"),
+    ("help.page", "{category} (Page {page})"),
+    ("help.previous", "Previous"),
+    ("help.cancel", "Cancel"),
+    ("help.next", "Next"),
+    ("help.not_found", "Command not found!"),
+    ("prefix.empty", "Prefix cannot be empty"),
+    (
+        "prefix.too_long",
+        "Prefix cannot be longer than {max} characters",
+    ),
+    ("prefix.whitespace", "Prefix cannot contain whitespace"),
+    (
+        "prefix.server_only",
+        "Prefixes can only be changed in a server",
+    ),
+    ("prefix.set", "Prefix set to ``{prefix}``"),
+    ("prefix.reset_to", "Prefix reset to ``{prefix}``"),
+    ("prefix.reset", "Prefix reset"),
+    (
+        "blacklist.user",
+        "You are blacklisted from using this bot: {reason}",
+    ),
+    (
+        "blacklist.guild",
+        "This server is blacklisted from using this bot: {reason}",
+    ),
+    ("blacklist.kind_user", "User"),
+    ("blacklist.kind_guild", "Guild"),
+    ("blacklist.added", "Blacklisted {kind} ``{id}``"),
+    (
+        "blacklist.removed",
+        "Removed {kind} ``{id}`` from the blacklist",
+    ),
+    ("blacklist.not_listed", "{kind} ``{id}`` is not blacklisted"),
+    ("blacklist.empty", "No entries found"),
+    (
+        "checks.channel_denied",
+        "Commands cannot be used in this channel",
+    ),
+    (
+        "checks.channel_allowed_only",
+        "Commands can only be used in: {channels}",
+    ),
+    (
+        "checks.restrict_server_only",
+        "Channel restrictions can only be changed in a server",
+    ),
+    ("checks.allowlist", "allowlist"),
+    ("checks.denylist", "denylist"),
+    (
+        "checks.already_listed",
+        "{channel} is already on the {list}",
+    ),
+    ("checks.listed", "Added {channel} to the {list}"),
+    ("checks.not_listed", "{channel} is not on the {list}"),
+    ("checks.unlisted", "Removed {channel} from the {list}"),
+    (
+        "checks.bypass_on",
+        "Members with Manage Server now bypass channel restrictions",
+    ),
+    (
+        "checks.bypass_off",
+        "Members with Manage Server no longer bypass channel restrictions",
+    ),
+    ("checks.none", "None"),
+    (
+        "checks.restrict_list",
+        "**Allowlist:** {allowed}\n**Denylist:** {denied}\n**Managers bypass:** {bypass}",
+    ),
+    (
+        "suggestions.server_only",
+        "Suggestions can only be made in a server",
+    ),
+    (
+        "suggestions.no_channel",
+        "This server has not configured a suggestion channel yet",
+    ),
+    (
+        "suggestions.posted",
+        "Your suggestion has been posted: {link}",
+    ),
+    ("suggestions.missing", "This suggestion no longer exists"),
+    ("suggestions.closed", "Voting on this suggestion has closed"),
+    ("suggestions.title", "Suggestion ({status})"),
+    ("suggestions.author", "Author"),
+    ("suggestions.votes", "Votes"),
+    ("suggestions.pending", "Pending"),
+    ("suggestions.approved", "Approved"),
+    ("suggestions.denied", "Denied"),
+    ("suggestions.implemented", "Implemented"),
+    ("suggestions.approve", "Approve"),
+    ("suggestions.deny", "Deny"),
+    (
+        "suggestions.marked",
+        "Your suggestion has been marked as {status}",
+    ),
+    (
+        "suggestions.not_staff",
+        "You do not have permission to review suggestions",
+    ),
+    (
+        "limiter.busy",
+        "This command is busy right now, please try again shortly",
+    ),
+    (
+        "limiter.queued",
+        "This command is busy, you are #{position} in the queue",
+    ),
+    ("cooldown.active", "You can use this command again {when}"),
+    ("mention.title", "Hi, I'm {bot}!"),
+    ("mention.help", "Run {help} to see everything I can do"),
+    ("mention.prefix", "My prefix here is ``{prefix}``"),
+    ("mention.setup", "Set me up"),
+    (
+        "raid.server_only",
+        "Panic mode can only be toggled in a server",
+    ),
+    ("raid.enabled", "Panic mode enabled"),
+    ("raid.disabled", "Panic mode disabled"),
+    ("paginator.expired", "This menu has expired"),
+    (
+        "paginator.not_owner",
+        "Only the person who opened this menu can use it",
+    ),
+    ("fuzzy.did_you_mean", "Did you mean {commands}?"),
+    ("quote.invalid_link", "That is not a valid message link"),
+    ("quote.dm", "Messages from DMs cannot be quoted"),
+    (
+        "quote.not_member",
+        "You are not a member of the server that message is from",
+    ),
+    ("quote.not_found", "That message could not be found"),
+    (
+        "quote.bot_no_access",
+        "The bot cannot see the channel that message is from",
+    ),
+    (
+        "quote.no_access",
+        "You cannot view the channel that message is from",
+    ),
+    ("quote.attachments", "Attachments"),
+    ("quote.more_attachments", "and {count} more"),
+    ("quote.jump", "Jump to message"),
+    ("leaderboard.empty", "No entries yet"),
+    ("leaderboard.page", "Page {page}/{pages}"),
+    ("leaderboard.rank", "Your rank: #{rank} ({score} {label})"),
+    ("notify.levelups", "Level ups"),
+    ("notify.reminders", "Reminders"),
+    ("notify.moderation", "Moderation notices"),
+    ("notify.suggestions", "Suggestion updates"),
+    ("notify.security", "Security alerts"),
+    (
+        "notify.toggle",
+        "Toggle which DM notifications you receive, green categories are enabled",
+    ),
+    ("permissions.owner", "Server owner"),
+    ("permissions.admin_everyone", "Administrator via @everyone"),
+    ("permissions.admin_role", "Administrator via @{role}"),
+    ("permissions.granted_everyone", "Granted by @everyone"),
+    ("permissions.granted_role", "Granted by role @{role}"),
+    ("permissions.not_granted", "No role grants it"),
+    (
+        "permissions.denied_everyone",
+        "Denied by the @everyone overwrite",
+    ),
+    (
+        "permissions.allowed_everyone",
+        "Allowed by the @everyone overwrite",
+    ),
+    (
+        "permissions.denied_role",
+        "Denied by the overwrite for {role}",
+    ),
+    (
+        "permissions.allowed_role",
+        "Allowed by the overwrite for {role}",
+    ),
+    ("permissions.denied_member", "Denied by a member overwrite"),
+    (
+        "permissions.allowed_member",
+        "Allowed by a member overwrite",
+    ),
+    ("permissions.cannot_view", "Cannot view the channel"),
+    ("setup.step", "Setup ({step}/{total}): {name}"),
+    ("setup.log_channel", "Log channel"),
+    (
+        "setup.log_channel_description",
+        "Pick the channel the bot should send logs to",
+    ),
+    ("setup.log_channel_placeholder", "Pick a log channel"),
+    ("setup.log_channel_required", "A log channel must be picked"),
+    ("setup.mod_roles", "Moderator roles"),
+    (
+        "setup.mod_roles_description",
+        "Pick the roles that should be treated as moderators",
+    ),
+    ("setup.mod_roles_placeholder", "Pick moderator roles"),
+    (
+        "setup.mod_roles_required",
+        "At least {min} mod role(s) must be picked",
+    ),
+    ("setup.features", "Features"),
+    (
+        "setup.features_description",
+        "Pick the features you want to enable",
+    ),
+    ("setup.features_placeholder", "Pick features"),
+    ("setup.summary", "Setup: Summary"),
+    ("setup.none", "None"),
+    ("setup.cancel", "Cancel"),
+    ("setup.skip", "Skip"),
+    ("setup.confirm", "Confirm"),
+    ("setup.restart", "Start over"),
+    ("setup.error", "Error"),
+    ("setup.complete", "Setup complete"),
+    ("setup.cancelled", "Setup cancelled"),
+    (
+        "setup.timed_out",
+        "Setup timed out, run it again to continue",
+    ),
+    ("setup.server_only", "Setup can only be run in a server"),
+    ("setup.welcome_title", "Thanks for adding me!"),
+    (
+        "setup.welcome",
+        "A member with the Manage Server permission can press the button below to set me up",
+    ),
+    ("setup.start", "Start setup"),
+    (
+        "setup.missing_permission",
+        "You need the Manage Server permission to set up the bot",
+    ),
+];
+
+fn _translations() -> &'static RwLock<HashMap<String, HashMap<String, String>>> {
+    static TRANSLATIONS: OnceLock<RwLock<HashMap<String, HashMap<String, String>>>> =
+        OnceLock::new();
+
+    TRANSLATIONS.get_or_init(|| RwLock::new(HashMap::new()))
+}
+
+/// Sets the translations of a locale (such as ``de`` or ``pt-BR``), replacing any previous ones
+///
+/// Keys missing from ``strings`` fall back to the base language (``pt`` for ``pt-BR``) and then English.
+/// Setting ``en`` overrides the defaults, including for strings emitted without a known locale
+pub fn set_translations(locale: &str, strings: HashMap<String, String>) {
+    _translations()
+        .write()
+        .unwrap()
+        .insert(locale.to_string(), strings);
+}
+
+/// Returns the English defaults as a JSON object, as a starting point for translators
+pub fn export_defaults() -> String {
+    let map = DEFAULTS
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect::<std::collections::BTreeMap<_, _>>();
+
+    serde_json::to_string_pretty(&map).unwrap_or_default()
+}
+
+fn _fill(template: &str, args: &[(&str, &str)]) -> String {
+    let mut out = template.to_string();
+
+    for (name, value) in args {
+        out = out.replace(&format!("{{{}}}", name), value);
+    }
+
+    out
+}
+
+/// Returns a translated string, filling in ``{name}`` placeholders from ``args``
+pub fn t(locale: Option<&str>, key: &str, args: &[(&str, &str)]) -> String {
+    let locale = locale.unwrap_or("en");
+    let base = locale.split('-').next().unwrap_or(locale);
+
+    {
+        let translations = _translations().read().unwrap();
+
+        for locale in [locale, base, "en"] {
+            if let Some(template) = translations.get(locale).and_then(|s| s.get(key)) {
+                return _fill(template, args);
+            }
+        }
+    }
+
+    match DEFAULTS.iter().find(|(k, _)| *k == key) {
+        Some((_, template)) => _fill(template, args),
+        None => key.to_string(),
+    }
+}
+
+/// Returns a string translated to the locale of the invoking user
+pub fn tr<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    key: &str,
+    args: &[(&str, &str)],
+) -> String {
+    t(ctx.locale(), key, args)
+}
@@ -0,0 +1,95 @@
+use poise::serenity_prelude::{CreateCommand, InstallationContext, InteractionContext};
+use poise::CreateReply;
+
+use crate::Error;
+
+/// Where a command can be installed and used
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallScope {
+    /// Only when the bot is added to a server
+    Guild,
+    /// Only when a user installs the bot to their account, usable anywhere
+    User,
+    /// Both guild and user installs
+    Everywhere,
+}
+
+/// Marks a command as guild and/or user installable, including the contexts it may be used in
+///
+/// Use on the output of ``poise::builtins::create_application_commands`` before registering
+pub fn contexts(command: CreateCommand<'_>, scope: InstallScope) -> CreateCommand<'_> {
+    match scope {
+        InstallScope::Guild => command
+            .integration_types(vec![InstallationContext::Guild])
+            .contexts(vec![InteractionContext::Guild]),
+        InstallScope::User => command
+            .integration_types(vec![InstallationContext::User])
+            .contexts(vec![
+                InteractionContext::Guild,
+                InteractionContext::BotDm,
+                InteractionContext::PrivateChannel,
+            ]),
+        InstallScope::Everywhere => command
+            .integration_types(vec![InstallationContext::Guild, InstallationContext::User])
+            .contexts(vec![
+                InteractionContext::Guild,
+                InteractionContext::BotDm,
+                InteractionContext::PrivateChannel,
+            ]),
+    }
+}
+
+/// The context a command was invoked in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RuntimeContext {
+    /// In a server the bot is a member of
+    Guild,
+    /// In the bots DMs
+    BotDm,
+    /// Through a user install, in a server the bot is not in or in a DM/group DM between users
+    UserInstall,
+}
+
+/// Detects the context a command was invoked in
+///
+/// Prefix commands are always ``Guild`` or ``BotDm`` as they require the bot to see the message
+pub fn runtime_context<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> RuntimeContext {
+    let poise::Context::Application(actx) = ctx else {
+        return match ctx.guild_id() {
+            Some(_) => RuntimeContext::Guild,
+            None => RuntimeContext::BotDm,
+        };
+    };
+
+    match actx.interaction.context {
+        Some(InteractionContext::PrivateChannel) => RuntimeContext::UserInstall,
+        Some(InteractionContext::BotDm) => RuntimeContext::BotDm,
+        _ => match actx.interaction.guild_id {
+            // Guild interactions where the bot is not in the guild come from a user install
+            Some(guild_id) if ctx.cache().guild(guild_id).is_none() => RuntimeContext::UserInstall,
+            Some(_) => RuntimeContext::Guild,
+            None => RuntimeContext::BotDm,
+        },
+    }
+}
+
+/// Returns whether a command was invoked through a user install
+pub fn is_user_install<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> bool {
+    runtime_context(ctx) == RuntimeContext::UserInstall
+}
+
+/// Sends a reply, forcing it to be ephemeral in user install contexts so the bot does not post publicly where it was not invited
+pub async fn send<'a, Data: Send + Sync + 'static>(
+    ctx: poise::Context<'a, Data, crate::Error>,
+    mut reply: CreateReply<'_>,
+) -> Result<poise::ReplyHandle<'a>, Error> {
+    if is_user_install(ctx) {
+        reply = reply.ephemeral(true);
+    }
+
+    crate::send::send(ctx, reply).await
+}
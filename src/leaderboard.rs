@@ -0,0 +1,211 @@
+use futures::stream::{self, StreamExt};
+use poise::serenity_prelude::{
+    self as serenity, CacheHttp, CreateEmbed, CreateEmbedFooter, UserId,
+};
+use std::collections::HashMap;
+
+use crate::format::{buffer, int_width, Align, BufWriterExt, LINE_ESTIMATE};
+use crate::i18n::t;
+
+/// Maximum number of users fetched over HTTP at once when resolving names
+const MAX_CONCURRENT_FETCHES: usize = 5;
+
+/// A single ranked entry
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LeaderboardEntry {
+    pub user_id: UserId,
+    pub score: i64,
+}
+
+pub struct LeaderboardOptions {
+    pub title: String,
+    /// Entries shown per page
+    pub per_page: usize,
+    /// The user viewing the leaderboard, their rank is pinned in the footer
+    pub invoker: Option<UserId>,
+    /// Label shown after scores, such as ``xp``
+    pub score_label: String,
+    pub colour: serenity::Colour,
+    /// Locale the footer and empty notice are translated to
+    pub locale: Option<String>,
+}
+
+impl Default for LeaderboardOptions {
+    fn default() -> Self {
+        Self {
+            title: "Leaderboard".to_string(),
+            per_page: 10,
+            invoker: None,
+            score_label: String::new(),
+            colour: serenity::Colour::GOLD,
+            locale: None,
+        }
+    }
+}
+
+/// Width ranks are padded to
+const RANK_WIDTH: usize = 4;
+
+fn _push_rank(buf: &mut String, rank: usize) {
+    let medal = match rank {
+        1 => "🥇",
+        2 => "🥈",
+        3 => "🥉",
+        _ => {
+            buf.push('#');
+            buf.push_display(rank)
+                .push_spaces(RANK_WIDTH.saturating_sub(1 + int_width(rank as i64)));
+            return;
+        }
+    };
+
+    buf.push_padded(medal, RANK_WIDTH, Align::Left);
+}
+
+/// Resolves display names of users, from the cache first and over HTTP otherwise
+///
+/// Users that cannot be fetched are left out
+pub async fn resolve_names(
+    cache_http: impl CacheHttp,
+    user_ids: &[UserId],
+) -> HashMap<UserId, String> {
+    let mut names = HashMap::new();
+    let mut missing = Vec::new();
+
+    for user_id in user_ids {
+        let cached = cache_http
+            .cache()
+            .and_then(|c| c.user(*user_id).map(|u| u.display_name().to_string()));
+
+        match cached {
+            Some(name) => {
+                names.insert(*user_id, name);
+            }
+            None => missing.push(*user_id),
+        }
+    }
+
+    let http = cache_http.http();
+
+    let fetched = stream::iter(missing)
+        .map(|user_id| async move {
+            (
+                user_id,
+                http.get_user(user_id)
+                    .await
+                    .map(|u| u.display_name().to_string()),
+            )
+        })
+        .buffer_unordered(MAX_CONCURRENT_FETCHES)
+        .collect::<Vec<_>>()
+        .await;
+
+    for (user_id, name) in fetched {
+        match name {
+            Ok(name) => {
+                names.insert(user_id, name);
+            }
+            Err(e) => log::debug!("Failed to resolve name of {}: {}", user_id, e),
+        }
+    }
+
+    names
+}
+
+/// Renders ranked entries into embed pages
+///
+/// Entries are sorted by score, highest first. ``names`` maps users to display names, see ``resolve_names``
+pub fn render(
+    mut entries: Vec<LeaderboardEntry>,
+    names: &HashMap<UserId, String>,
+    opts: &LeaderboardOptions,
+) -> Vec<CreateEmbed<'static>> {
+    entries.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then_with(|| a.user_id.cmp(&b.user_id))
+    });
+
+    let per_page = opts.per_page.max(1);
+    let pages = entries.len().div_ceil(per_page).max(1);
+
+    let own_rank = opts
+        .invoker
+        .and_then(|id| entries.iter().position(|e| e.user_id == id))
+        .map(|i| (i + 1, entries[i].score));
+
+    let score_width = entries
+        .iter()
+        .map(|e| int_width(e.score))
+        .max()
+        .unwrap_or(1);
+
+    let locale = opts.locale.as_deref();
+    let mut embeds = Vec::with_capacity(pages);
+
+    for page in 0..pages {
+        let mut desc = buffer(per_page + 2, LINE_ESTIMATE);
+        desc.push_str("```\n");
+
+        for (i, entry) in entries
+            .iter()
+            .enumerate()
+            .skip(page * per_page)
+            .take(per_page)
+        {
+            _push_rank(&mut desc, i + 1);
+
+            desc.push_spaces(1 + score_width.saturating_sub(int_width(entry.score)))
+                .push_display(entry.score)
+                .push_spaces(1)
+                .push_str(&opts.score_label);
+            desc.push(' ');
+
+            match names.get(&entry.user_id) {
+                // Keep names from breaking out of the code block
+                Some(name) => desc.push_replacing(name, '`', '\''),
+                None => desc.push_display(entry.user_id),
+            }
+            .push('\n');
+        }
+
+        if entries.is_empty() {
+            desc.push_str(&t(locale, "leaderboard.empty", &[]));
+            desc.push('\n');
+        }
+
+        desc.push_str("```");
+
+        let mut footer = t(
+            locale,
+            "leaderboard.page",
+            &[
+                ("page", &(page + 1).to_string()),
+                ("pages", &pages.to_string()),
+            ],
+        );
+
+        if let Some((rank, score)) = own_rank {
+            footer.push_str(" • ");
+            footer.push_str(&t(
+                locale,
+                "leaderboard.rank",
+                &[
+                    ("rank", &rank.to_string()),
+                    ("score", &score.to_string()),
+                    ("label", &opts.score_label),
+                ],
+            ));
+        }
+
+        embeds.push(
+            CreateEmbed::default()
+                .title(opts.title.clone())
+                .colour(opts.colour)
+                .description(desc)
+                .footer(CreateEmbedFooter::new(footer)),
+        );
+    }
+
+    embeds
+}
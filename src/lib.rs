@@ -2,5 +2,92 @@ pub mod cache;
 pub mod help;
 pub mod crypto;
 pub mod taskman;
+pub mod suggestions;
+pub mod prefixes;
+pub mod blacklist;
+pub mod shards;
+pub mod heartbeat;
+pub mod analytics;
+pub mod setup;
+pub mod devtools;
+pub mod sanitize;
+pub mod send;
+pub mod checks;
+pub mod stores;
+#[cfg(feature = "tracing")]
+pub mod spans;
+#[cfg(feature = "otel")]
+pub mod telemetry;
+pub mod cluster;
+pub mod mimic;
+pub mod bridge;
+pub mod emoji;
+pub mod snapshot;
+pub mod antinuke;
+pub mod raid;
+pub mod digest;
+pub mod register;
+pub mod install;
+pub mod messagelog;
+pub mod namelog;
+pub mod rolepersist;
+pub mod notify;
+pub mod leaderboard;
+pub mod limiter;
+pub mod resilience;
+#[cfg(feature = "api")]
+pub mod api;
+pub mod export;
+pub mod permissions;
+pub mod reason;
+pub mod quote;
+pub mod schedperm;
+pub mod time;
+pub mod fuzzy;
+pub mod paginator;
+pub mod i18n;
+pub mod format;
+pub mod embeds;
+#[cfg(feature = "config")]
+pub mod config;
+pub mod secrets;
+pub mod boot;
+pub mod bot;
+pub mod plugins;
+pub mod autoresponder;
+pub mod wordfilter;
+pub mod linkguard;
+pub mod logrouter;
+pub mod cases;
+pub mod warnings;
+pub mod appeals;
+pub mod diff;
+pub mod random;
+pub mod multibot;
+pub mod args;
+pub mod cachedreply;
+pub mod docs;
+pub mod broadcast;
+pub mod features;
+pub mod mention;
+pub mod activities;
+pub mod collectors;
+pub mod privacy;
+pub mod roles;
+pub mod cooldowns;
+pub mod cmdpath;
+pub mod templates;
+pub mod defer;
+pub mod voicestats;
+pub mod guildevents;
+pub mod pins;
+pub mod botperms;
+pub mod screening;
+pub mod translate;
+pub mod auditlog;
+pub mod experiments;
+pub mod settings;
+
+pub use bot::Bot;
 
 type Error = Box<dyn std::error::Error + Send + Sync>;
@@ -0,0 +1,188 @@
+use poise::serenity_prelude::GuildId;
+use poise::CreateReply;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+use crate::i18n::tr;
+use crate::Error;
+
+/// What happens to invocations beyond the limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueueMode {
+    /// Wait for a slot, telling the user their position in the queue
+    Wait,
+    /// Fail immediately with a busy error
+    FailFast,
+}
+
+pub struct ConcurrencyConfig {
+    /// Maximum concurrent executions of a command (by qualified name) across the bot
+    pub per_command: HashMap<String, usize>,
+    /// Limit used for commands not in ``per_command``, None for unlimited
+    pub default_per_command: Option<usize>,
+    /// Maximum concurrent executions of a single command within a guild, None for unlimited
+    pub per_guild: Option<usize>,
+    pub mode: QueueMode,
+}
+
+impl Default for ConcurrencyConfig {
+    fn default() -> Self {
+        Self {
+            per_command: HashMap::new(),
+            default_per_command: None,
+            per_guild: None,
+            mode: QueueMode::Wait,
+        }
+    }
+}
+
+struct Slot {
+    semaphore: Arc<Semaphore>,
+    waiting: Arc<AtomicUsize>,
+}
+
+impl Slot {
+    fn new(limit: usize) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit)),
+            waiting: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+/// Held while a command executes, the slot is freed when this is dropped
+pub struct ConcurrencyPermit {
+    _command: Option<OwnedSemaphorePermit>,
+    _guild: Option<OwnedSemaphorePermit>,
+}
+
+/// Caps concurrent executions per command and per guild
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct CommandConcurrency {
+    config: Arc<ConcurrencyConfig>,
+    commands: Arc<Mutex<HashMap<String, Slot>>>,
+    guilds: Arc<Mutex<HashMap<(GuildId, String), Slot>>>,
+}
+
+// Decrements a waiting counter when dropped, so cancelled waits are not counted
+struct _Waiting(Arc<AtomicUsize>);
+
+impl Drop for _Waiting {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl CommandConcurrency {
+    pub fn new(config: ConcurrencyConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            commands: Arc::new(Mutex::new(HashMap::new())),
+            guilds: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn _command_slot(&self, command: &str) -> Option<(Arc<Semaphore>, Arc<AtomicUsize>)> {
+        let limit = self
+            .config
+            .per_command
+            .get(command)
+            .copied()
+            .or(self.config.default_per_command)?;
+
+        let mut commands = self.commands.lock().unwrap();
+        let slot = commands
+            .entry(command.to_string())
+            .or_insert_with(|| Slot::new(limit));
+
+        Some((slot.semaphore.clone(), slot.waiting.clone()))
+    }
+
+    fn _guild_slot(
+        &self,
+        guild_id: GuildId,
+        command: &str,
+    ) -> Option<(Arc<Semaphore>, Arc<AtomicUsize>)> {
+        let limit = self.config.per_guild?;
+
+        let mut guilds = self.guilds.lock().unwrap();
+        let slot = guilds
+            .entry((guild_id, command.to_string()))
+            .or_insert_with(|| Slot::new(limit));
+
+        Some((slot.semaphore.clone(), slot.waiting.clone()))
+    }
+
+    async fn _acquire<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+        slot: Option<(Arc<Semaphore>, Arc<AtomicUsize>)>,
+        notified: &mut bool,
+    ) -> Result<Option<OwnedSemaphorePermit>, Error> {
+        let Some((semaphore, waiting)) = slot else {
+            return Ok(None);
+        };
+
+        if let Ok(permit) = semaphore.clone().try_acquire_owned() {
+            return Ok(Some(permit));
+        }
+
+        if self.config.mode == QueueMode::FailFast {
+            return Err(tr(ctx, "limiter.busy", &[]).into());
+        }
+
+        let position = waiting.fetch_add(1, Ordering::Relaxed) + 1;
+        let _waiting = _Waiting(waiting);
+
+        if !*notified {
+            *notified = true;
+
+            ctx.send(
+                CreateReply::default()
+                    .content(tr(
+                        ctx,
+                        "limiter.queued",
+                        &[("position", &position.to_string())],
+                    ))
+                    .ephemeral(true),
+            )
+            .await?;
+        }
+
+        Ok(Some(semaphore.acquire_owned().await?))
+    }
+
+    /// Acquires a slot for the invoked command, waiting or failing as configured
+    ///
+    /// Call at the start of a command and hold the permit until it finishes, for slash commands
+    /// that wait this sends the queue notice as the initial response
+    pub async fn acquire<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+    ) -> Result<ConcurrencyPermit, Error> {
+        let command = &ctx.command().qualified_name;
+        let mut notified = false;
+
+        // Guild slot first, so a busy guild does not hold up a global slot while waiting
+        let guild = match ctx.guild_id() {
+            Some(guild_id) => {
+                self._acquire(ctx, self._guild_slot(guild_id, command), &mut notified)
+                    .await?
+            }
+            None => None,
+        };
+
+        let command = self
+            ._acquire(ctx, self._command_slot(command), &mut notified)
+            .await?;
+
+        Ok(ConcurrencyPermit {
+            _command: command,
+            _guild: guild,
+        })
+    }
+}
@@ -0,0 +1,286 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateEmbed, CreateMessage, FullEvent, GuildId, Timestamp,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::embeds::FieldPacker;
+use crate::mimic::is_not_found;
+use crate::taskman::Task;
+use crate::Error;
+
+/// Maximum number of entries queued per guild and category, older entries are dropped first
+const MAX_QUEUED: usize = 100;
+
+/// A category of log events, each can be routed to its own channel
+#[derive(
+    poise::ChoiceParameter, Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize,
+)]
+pub enum LogCategory {
+    Joins,
+    #[name = "Message edits"]
+    MessageEdits,
+    Moderation,
+    Voice,
+    Errors,
+}
+
+impl LogCategory {
+    pub fn label(&self) -> &'static str {
+        match self {
+            LogCategory::Joins => "Joins",
+            LogCategory::MessageEdits => "Message edits",
+            LogCategory::Moderation => "Moderation",
+            LogCategory::Voice => "Voice",
+            LogCategory::Errors => "Errors",
+        }
+    }
+
+    pub fn colour(&self) -> serenity::Colour {
+        match self {
+            LogCategory::Joins => serenity::Colour::DARK_GREEN,
+            LogCategory::MessageEdits => serenity::Colour::GOLD,
+            LogCategory::Moderation => serenity::Colour::ORANGE,
+            LogCategory::Voice => serenity::Colour::BLURPLE,
+            LogCategory::Errors => serenity::Colour::RED,
+        }
+    }
+}
+
+/// A guilds log channels
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LogRoutes {
+    pub channels: HashMap<LogCategory, ChannelId>,
+    /// Used when the channel of a category was deleted
+    pub fallback: Option<ChannelId>,
+}
+
+/// Storage backend for log routes
+pub trait LogRouteStore: Send + Sync {
+    /// Returns the log routes of a guild
+    fn routes<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<LogRoutes, Error>>;
+
+    /// Sets or, if ``channel_id`` is None, removes the channel of a category
+    fn set_route<'a>(
+        &'a self,
+        guild_id: GuildId,
+        category: LogCategory,
+        channel_id: Option<ChannelId>,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// A single log event
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    pub title: String,
+    pub description: String,
+    pub at: Timestamp,
+}
+
+impl LogEntry {
+    pub fn new(title: impl Into<String>, description: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            description: description.into(),
+            at: Timestamp::now(),
+        }
+    }
+}
+
+/// Routes log events to per-guild, per-category channels, batching bursts into combined embeds
+///
+/// Entries are queued by ``log`` and posted by ``flush``, see ``logrouter_task``. This is cheap to clone
+#[derive(Clone)]
+pub struct LogRouter {
+    store: Arc<dyn LogRouteStore>,
+    queue: Arc<Mutex<HashMap<(GuildId, LogCategory), Vec<LogEntry>>>>,
+}
+
+impl LogRouter {
+    pub fn new(store: Arc<dyn LogRouteStore>) -> Self {
+        Self {
+            store,
+            queue: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Queues a log entry, it is posted on the next ``flush``
+    pub async fn log(&self, guild_id: GuildId, category: LogCategory, entry: LogEntry) {
+        let mut queue = self.queue.lock().await;
+        let entries = queue.entry((guild_id, category)).or_default();
+
+        if entries.len() >= MAX_QUEUED {
+            entries.remove(0);
+        }
+
+        entries.push(entry);
+    }
+
+    /// Renders queued entries, a single entry gets its own embed and bursts are combined
+    pub fn render(category: LogCategory, entries: &[LogEntry]) -> Vec<CreateEmbed<'static>> {
+        if let [entry] = entries {
+            return vec![CreateEmbed::default()
+                .title(entry.title.clone())
+                .description(entry.description.clone())
+                .colour(category.colour())
+                .timestamp(entry.at)];
+        }
+
+        FieldPacker::new(format!("{} ({} events)", category.label(), entries.len()))
+            .colour(category.colour())
+            .fields(entries.iter().map(|e| {
+                (
+                    e.title.clone(),
+                    format!("<t:{}:T> {}", e.at.unix_timestamp(), e.description),
+                )
+            }))
+            .pack()
+    }
+
+    async fn _send(
+        http: &serenity::Http,
+        channel_id: ChannelId,
+        embeds: &[CreateEmbed<'static>],
+    ) -> Result<(), serenity::Error> {
+        for embed in embeds {
+            channel_id
+                .send_message(http, CreateMessage::new().embed(embed.clone()))
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    async fn _post(
+        &self,
+        http: &serenity::Http,
+        guild_id: GuildId,
+        category: LogCategory,
+        entries: Vec<LogEntry>,
+    ) -> Result<(), Error> {
+        let routes = self.store.routes(guild_id).await?;
+
+        let Some(channel_id) = routes.channels.get(&category).copied() else {
+            return Ok(());
+        };
+
+        let embeds = Self::render(category, &entries);
+
+        match Self::_send(http, channel_id, &embeds).await {
+            Err(e) if is_not_found(&e) => {
+                log::warn!(
+                    "{} log channel {} of {} is gone, using the fallback channel",
+                    category.label(),
+                    channel_id,
+                    guild_id
+                );
+
+                self.store.set_route(guild_id, category, None).await?;
+
+                if let Some(fallback) = routes.fallback {
+                    Self::_send(http, fallback, &embeds).await?;
+                }
+
+                Ok(())
+            }
+            res => Ok(res?),
+        }
+    }
+
+    /// Posts all queued entries
+    pub async fn flush(&self, http: &serenity::Http) -> Result<(), Error> {
+        let queued = std::mem::take(&mut *self.queue.lock().await);
+
+        for ((guild_id, category), entries) in queued {
+            if let Err(e) = self._post(http, guild_id, category, entries).await {
+                log::warn!(
+                    "Failed to post {} logs for {}: {}",
+                    category.label(),
+                    guild_id,
+                    e
+                );
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Removes routes to deleted channels, this should be called from your bots event handler
+    pub async fn handle_event(&self, event: &FullEvent) -> Result<(), Error> {
+        let FullEvent::ChannelDelete { channel, .. } = event else {
+            return Ok(());
+        };
+
+        let routes = self.store.routes(channel.guild_id).await?;
+
+        for (category, channel_id) in routes.channels {
+            if channel_id == channel.id {
+                self.store
+                    .set_route(channel.guild_id, category, None)
+                    .await?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Trait for bot data that holds a ``LogRouter``
+pub trait HasLogRouter {
+    fn log_router(&self) -> &LogRouter;
+}
+
+/// Sets or clears the channel of a log category, can be plugged into your bots ``/logs set`` command
+pub async fn logs_set<Data: HasLogRouter + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    category: LogCategory,
+    channel: Option<ChannelId>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Log channels can only be changed in a server".into());
+    };
+
+    let data = ctx.data();
+
+    data.log_router()
+        .store
+        .set_route(guild_id, category, channel)
+        .await?;
+
+    match channel {
+        Some(channel) => {
+            ctx.say(format!(
+                "{} logs will be sent to <#{}>",
+                category.label(),
+                channel
+            ))
+            .await?
+        }
+        None => {
+            ctx.say(format!("{} logs are now disabled", category.label()))
+                .await?
+        }
+    };
+
+    Ok(())
+}
+
+/// Returns a task that posts queued log entries every ``batch_window``
+///
+/// Events within one window are combined, so a longer window means fewer, larger messages
+pub fn logrouter_task(router: LogRouter, batch_window: Duration) -> Task {
+    Task {
+        name: "logrouter",
+        description: "Posts batched log entries",
+        enabled: true,
+        duration: batch_window,
+        run: Box::new(move |ctx| {
+            let router = router.clone();
+            Box::pin(async move { router.flush(&ctx.http).await })
+        }),
+    }
+}
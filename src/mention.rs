@@ -0,0 +1,168 @@
+use poise::serenity_prelude::{
+    self as serenity, CommandId, CreateActionRow, CreateButton, CreateEmbed, CreateMessage,
+    FullEvent, Message,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::features::{Feature, FeatureMatrix};
+use crate::i18n::t;
+use crate::prefixes::DynamicPrefix;
+use crate::sanitize::MentionPolicy;
+use crate::Error;
+
+/// Replies with a quickstart embed when the bot is mentioned without a command
+///
+/// Strings use the ``mention.*`` i18n keys, translated to the guilds preferred locale. This is cheap to clone
+#[derive(Clone)]
+pub struct MentionResponder {
+    /// Shows the guilds prefix, if set
+    pub prefix: Option<DynamicPrefix>,
+    /// Name of the slash command mentioned as the place to start, defaults to ``help``
+    pub help_command: String,
+    /// Whether to add a button starting the ``setup::SetupWizard``
+    pub setup_button: bool,
+    /// Link buttons (label and URL) such as a support server or website
+    pub links: Vec<(String, String)>,
+    pub colour: serenity::Colour,
+    /// The responder stays quiet in guilds that disabled ``Feature::MentionResponder``, if set
+    pub features: Option<FeatureMatrix>,
+    command_ids: Arc<Mutex<Option<HashMap<String, CommandId>>>>,
+}
+
+impl Default for MentionResponder {
+    fn default() -> Self {
+        Self {
+            prefix: None,
+            help_command: "help".to_string(),
+            setup_button: false,
+            links: Vec::new(),
+            colour: serenity::Colour::BLURPLE,
+            features: None,
+            command_ids: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+impl MentionResponder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    async fn _help_mention(&self, http: &serenity::Http) -> String {
+        let mut ids = self.command_ids.lock().await;
+
+        if ids.is_none() {
+            match http.get_global_commands().await {
+                Ok(commands) => {
+                    *ids = Some(
+                        commands
+                            .into_iter()
+                            .map(|c| (c.name.to_string(), c.id))
+                            .collect(),
+                    )
+                }
+                Err(e) => log::warn!("Failed to fetch command ids for mentions: {}", e),
+            }
+        }
+
+        match ids.as_ref().and_then(|ids| ids.get(&self.help_command)) {
+            Some(id) => format!("</{}:{}>", self.help_command, id),
+            None => format!("``/{}``", self.help_command),
+        }
+    }
+
+    /// Builds the quickstart message sent in reply to a bare mention
+    pub async fn quickstart(
+        &self,
+        ctx: &serenity::Context,
+        msg: &Message,
+    ) -> Result<CreateMessage<'static>, Error> {
+        let locale = msg
+            .guild_id
+            .and_then(|g| ctx.cache.guild(g).map(|g| g.preferred_locale.to_string()));
+        let locale = locale.as_deref();
+
+        let bot_name = ctx.cache.current_user().name.to_string();
+        let help = self._help_mention(&ctx.http).await;
+
+        let mut description = t(locale, "mention.help", &[("help", &help)]);
+
+        if let Some(prefix) = &self.prefix {
+            if let Some(prefix) = prefix.get(msg.guild_id).await? {
+                description.push('\n');
+                description.push_str(&t(locale, "mention.prefix", &[("prefix", &prefix)]));
+            }
+        }
+
+        let mut buttons = Vec::new();
+
+        if self.setup_button && msg.guild_id.is_some() {
+            buttons.push(
+                CreateButton::new("setup:start")
+                    .label(t(locale, "mention.setup", &[]))
+                    .style(serenity::ButtonStyle::Primary),
+            );
+        }
+
+        for (label, url) in self
+            .links
+            .iter()
+            .take(if buttons.is_empty() { 5 } else { 4 })
+        {
+            buttons.push(CreateButton::new_link(url.clone()).label(label.clone()));
+        }
+
+        let mut reply = CreateMessage::new()
+            .embed(
+                CreateEmbed::default()
+                    .title(t(locale, "mention.title", &[("bot", &bot_name)]))
+                    .description(description)
+                    .colour(self.colour),
+            )
+            .reference_message(msg)
+            .allowed_mentions(MentionPolicy::none().build());
+
+        if !buttons.is_empty() {
+            reply = reply.components(vec![CreateActionRow::Buttons(buttons)]);
+        }
+
+        Ok(reply)
+    }
+
+    /// Replies to messages consisting only of a mention of the bot
+    ///
+    /// This should be called from your bots event handler
+    pub async fn handle_event(
+        &self,
+        ctx: &serenity::Context,
+        event: &FullEvent,
+    ) -> Result<(), Error> {
+        let FullEvent::Message { new_message: msg } = event else {
+            return Ok(());
+        };
+
+        if msg.author.bot() {
+            return Ok(());
+        }
+
+        let bot_id = ctx.cache.current_user().id;
+        let content = msg.content.trim();
+
+        if content != format!("<@{}>", bot_id) && content != format!("<@!{}>", bot_id) {
+            return Ok(());
+        }
+
+        if !crate::features::allows(self.features.as_ref(), event, Feature::MentionResponder).await
+        {
+            return Ok(());
+        }
+
+        let reply = self.quickstart(ctx, msg).await?;
+
+        msg.channel_id.send_message(&ctx.http, reply).await?;
+
+        Ok(())
+    }
+}
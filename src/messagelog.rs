@@ -0,0 +1,460 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateEmbed, CreateEmbedFooter, CreateMessage, FullEvent, GuildId,
+    Message, MessageId, RoleId, Timestamp, UserId,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use crate::cache::Prunable;
+use crate::diff::{self, word_diff, MAX_DIFF_LENGTH};
+use crate::features::{Feature, FeatureMatrix};
+use crate::privacy::DataHolder;
+use crate::sanitize::escape_markdown;
+use crate::Error;
+
+/// Maximum length of message content shown in a log embed
+const MAX_LOGGED_CONTENT: usize = 1000;
+
+/// A guilds message log configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MessageLogConfig {
+    pub channel_id: ChannelId,
+    pub ignored_channels: Vec<ChannelId>,
+    /// Messages from members with any of these roles are not logged
+    pub ignored_roles: Vec<RoleId>,
+}
+
+/// Storage backend for message log configuration
+pub trait MessageLogStore: Send + Sync {
+    /// Returns the message log configuration of a guild, if enabled
+    fn config<'a>(
+        &'a self,
+        guild_id: GuildId,
+    ) -> BoxFuture<'a, Result<Option<MessageLogConfig>, Error>>;
+}
+
+/// What is remembered about a messages content
+#[derive(Debug, Clone)]
+pub enum CachedContent {
+    Full(String),
+    /// Only a hash, so edits can be detected without keeping the content around
+    Hash(u64),
+}
+
+#[derive(Debug, Clone)]
+pub struct CachedMessage {
+    pub channel_id: ChannelId,
+    pub author_id: UserId,
+    pub author_roles: Vec<RoleId>,
+    pub content: CachedContent,
+    pub created_at: Timestamp,
+}
+
+#[derive(Default)]
+struct MessageCache {
+    messages: HashMap<MessageId, CachedMessage>,
+    order: VecDeque<MessageId>,
+    /// Messages already logged by ``log_removal``, so their delete event is not logged again
+    removed: HashSet<MessageId>,
+}
+
+fn _hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn _truncate(text: &str) -> String {
+    if text.chars().count() <= MAX_LOGGED_CONTENT {
+        return text.to_string();
+    }
+
+    let mut text = text.chars().take(MAX_LOGGED_CONTENT).collect::<String>();
+    text.push('…');
+    text
+}
+
+fn _approx_size(msg: &CachedMessage) -> usize {
+    let content = match &msg.content {
+        CachedContent::Full(content) => content.len(),
+        CachedContent::Hash(_) => 0,
+    };
+
+    std::mem::size_of::<CachedMessage>()
+        + std::mem::size_of::<MessageId>() * 2
+        + content
+        + msg.author_roles.len() * std::mem::size_of::<RoleId>()
+}
+
+/// Logs message edits and deletes to a guilds log channel
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct MessageLog {
+    store: Arc<dyn MessageLogStore>,
+    cache: Arc<Mutex<MessageCache>>,
+    /// Maximum number of messages cached
+    pub max_cached: usize,
+    /// If true only a hash of message content is cached, deletes are logged without content and edits without a diff
+    pub hash_only: bool,
+    /// Guilds that disabled ``Feature::MessageLog`` are neither cached nor logged, if set
+    pub features: Option<FeatureMatrix>,
+}
+
+impl MessageLog {
+    pub fn new(store: Arc<dyn MessageLogStore>) -> Self {
+        Self {
+            store,
+            cache: Arc::new(Mutex::new(MessageCache::default())),
+            max_cached: 10000,
+            hash_only: false,
+            features: None,
+        }
+    }
+
+    async fn _remove_where(&self, f: impl Fn(&CachedMessage) -> bool) -> usize {
+        let mut cache = self.cache.lock().await;
+        let before = cache.messages.len();
+
+        cache.messages.retain(|_, m| !f(m));
+
+        let MessageCache {
+            messages, order, ..
+        } = &mut *cache;
+        order.retain(|id| messages.contains_key(id));
+
+        before - cache.messages.len()
+    }
+
+    async fn _cache(&self, msg: &Message) {
+        let content = if self.hash_only {
+            CachedContent::Hash(_hash(&msg.content))
+        } else {
+            CachedContent::Full(msg.content.to_string())
+        };
+
+        let author_roles = msg
+            .member
+            .as_ref()
+            .map(|m| m.roles.to_vec())
+            .unwrap_or_default();
+
+        let mut cache = self.cache.lock().await;
+
+        if cache.messages.contains_key(&msg.id) {
+            if let Some(cached) = cache.messages.get_mut(&msg.id) {
+                cached.content = content;
+            }
+
+            return;
+        }
+
+        cache.messages.insert(
+            msg.id,
+            CachedMessage {
+                channel_id: msg.channel_id,
+                author_id: msg.author.id,
+                author_roles,
+                content,
+                created_at: msg.timestamp,
+            },
+        );
+        cache.order.push_back(msg.id);
+
+        while cache.order.len() > self.max_cached {
+            if let Some(id) = cache.order.pop_front() {
+                cache.messages.remove(&id);
+            }
+        }
+    }
+
+    fn _ignored(config: &MessageLogConfig, channel_id: ChannelId, roles: &[RoleId]) -> bool {
+        config.ignored_channels.contains(&channel_id)
+            || roles.iter().any(|r| config.ignored_roles.contains(r))
+    }
+
+    async fn _on_edit(&self, http: &serenity::Http, msg: &Message) -> Result<(), Error> {
+        let Some(guild_id) = msg.guild_id else {
+            return Ok(());
+        };
+
+        if msg.author.bot() {
+            return Ok(());
+        }
+
+        let old = self
+            .cache
+            .lock()
+            .await
+            .messages
+            .get(&msg.id)
+            .map(|c| c.content.clone());
+
+        self._cache(msg).await;
+
+        let diff = match old {
+            Some(CachedContent::Full(old)) if old == *msg.content => return Ok(()),
+            Some(CachedContent::Hash(old)) if old == _hash(&msg.content) => return Ok(()),
+            // Embed unfurls also arrive as updates, but only content edits set the edit timestamp
+            None if msg.edited_timestamp.is_none() => return Ok(()),
+            Some(CachedContent::Full(old)) => word_diff(&_truncate(&old), &_truncate(&msg.content)),
+            _ => escape_markdown(&_truncate(&msg.content)),
+        };
+
+        // Escaping and diff markup can push the diff past the description limit
+        let diff = diff::truncate(&diff, MAX_DIFF_LENGTH);
+
+        let Some(config) = self.store.config(guild_id).await? else {
+            return Ok(());
+        };
+
+        let roles = msg
+            .member
+            .as_ref()
+            .map(|m| m.roles.to_vec())
+            .unwrap_or_default();
+
+        if Self::_ignored(&config, msg.channel_id, &roles) {
+            return Ok(());
+        }
+
+        config
+            .channel_id
+            .send_message(
+                http,
+                CreateMessage::new().embed(
+                    CreateEmbed::default()
+                        .title("Message edited")
+                        .colour(serenity::Colour::GOLD)
+                        .description(diff)
+                        .field("Author", format!("<@{}>", msg.author.id), true)
+                        .field("Channel", format!("<#{}>", msg.channel_id), true)
+                        .field("Message", msg.link(), false)
+                        .footer(CreateEmbedFooter::new(format!("ID: {}", msg.id)))
+                        .timestamp(Timestamp::now()),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    async fn _on_delete(
+        &self,
+        http: &serenity::Http,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> Result<(), Error> {
+        let cached = {
+            let mut cache = self.cache.lock().await;
+            cache.order.retain(|id| *id != message_id);
+
+            if cache.removed.remove(&message_id) {
+                return Ok(());
+            }
+
+            cache.messages.remove(&message_id)
+        };
+
+        let Some(config) = self.store.config(guild_id).await? else {
+            return Ok(());
+        };
+
+        let roles = cached
+            .as_ref()
+            .map(|c| c.author_roles.as_slice())
+            .unwrap_or_default();
+
+        if Self::_ignored(&config, channel_id, roles) {
+            return Ok(());
+        }
+
+        let mut embed = CreateEmbed::default()
+            .title("Message deleted")
+            .colour(serenity::Colour::RED)
+            .field("Channel", format!("<#{}>", channel_id), true)
+            .footer(CreateEmbedFooter::new(format!("ID: {}", message_id)))
+            .timestamp(Timestamp::now());
+
+        match cached {
+            Some(cached) => {
+                embed = embed
+                    .field("Author", format!("<@{}>", cached.author_id), true)
+                    .field(
+                        "Sent",
+                        format!("<t:{}:R>", cached.created_at.unix_timestamp()),
+                        true,
+                    );
+
+                if let CachedContent::Full(content) = cached.content {
+                    embed = embed.description(escape_markdown(&_truncate(&content)));
+                }
+            }
+            None => embed = embed.description("*Message was not cached*"),
+        }
+
+        config
+            .channel_id
+            .send_message(http, CreateMessage::new().embed(embed))
+            .await?;
+
+        Ok(())
+    }
+
+    /// Logs a message removed by the bot (for example, by ``wordfilter``) with the reason, the following delete is not logged again
+    pub async fn log_removal(
+        &self,
+        http: &serenity::Http,
+        msg: &Message,
+        reason: &str,
+    ) -> Result<(), Error> {
+        let Some(guild_id) = msg.guild_id else {
+            return Ok(());
+        };
+
+        {
+            let mut cache = self.cache.lock().await;
+
+            // Bounded by the message cache, delete events normally follow right away
+            if cache.removed.len() < self.max_cached {
+                cache.removed.insert(msg.id);
+            }
+        }
+
+        let Some(config) = self.store.config(guild_id).await? else {
+            return Ok(());
+        };
+
+        config
+            .channel_id
+            .send_message(
+                http,
+                CreateMessage::new().embed(
+                    CreateEmbed::default()
+                        .title("Message removed")
+                        .colour(serenity::Colour::DARK_RED)
+                        .description(escape_markdown(&_truncate(&msg.content)))
+                        .field("Author", format!("<@{}>", msg.author.id), true)
+                        .field("Channel", format!("<#{}>", msg.channel_id), true)
+                        .field("Reason", reason, false)
+                        .footer(CreateEmbedFooter::new(format!("ID: {}", msg.id)))
+                        .timestamp(Timestamp::now()),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Caches new messages and logs edits and deletes, this should be called from your bots event handler
+    ///
+    /// Requires the ``MESSAGE_CONTENT`` intent for content to be logged
+    pub async fn handle_event(
+        &self,
+        ctx: &serenity::Context,
+        event: &FullEvent,
+    ) -> Result<(), Error> {
+        if !crate::features::allows(self.features.as_ref(), event, Feature::MessageLog).await {
+            return Ok(());
+        }
+
+        match event {
+            FullEvent::Message { new_message } if new_message.guild_id.is_some() => {
+                self._cache(new_message).await;
+                Ok(())
+            }
+            FullEvent::MessageUpdate { new: Some(new), .. } => self._on_edit(&ctx.http, new).await,
+            FullEvent::MessageDelete {
+                channel_id,
+                deleted_message_id,
+                guild_id: Some(guild_id),
+            } => {
+                self._on_delete(&ctx.http, *guild_id, *channel_id, *deleted_message_id)
+                    .await
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+impl DataHolder for MessageLog {
+    fn name(&self) -> &str {
+        "messagelog"
+    }
+
+    fn export_user<'a>(
+        &'a self,
+        user_id: UserId,
+    ) -> BoxFuture<'a, Result<serde_json::Value, Error>> {
+        Box::pin(async move {
+            let cache = self.cache.lock().await;
+
+            let messages = cache
+                .messages
+                .iter()
+                .filter(|(_, m)| m.author_id == user_id)
+                .map(|(id, m)| {
+                    serde_json::json!({
+                        "id": id,
+                        "channel_id": m.channel_id,
+                        "content": match &m.content {
+                            CachedContent::Full(content) => Some(content.clone()),
+                            CachedContent::Hash(_) => None,
+                        },
+                        "created_at": m.created_at,
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            Ok(serde_json::Value::Array(messages))
+        })
+    }
+
+    fn erase_user<'a>(&'a self, user_id: UserId) -> BoxFuture<'a, Result<usize, Error>> {
+        Box::pin(async move { Ok(self._remove_where(|m| m.author_id == user_id).await) })
+    }
+
+    fn prune_before<'a>(&'a self, before: Timestamp) -> BoxFuture<'a, Result<usize, Error>> {
+        Box::pin(async move { Ok(self._remove_where(|m| m.created_at < before).await) })
+    }
+}
+
+impl Prunable for MessageLog {
+    fn name(&self) -> &str {
+        "messagelog"
+    }
+
+    fn approx_bytes<'a>(&'a self) -> BoxFuture<'a, usize> {
+        Box::pin(async move {
+            let cache = self.cache.lock().await;
+
+            cache.messages.values().map(_approx_size).sum()
+        })
+    }
+
+    fn prune<'a>(&'a self, bytes: usize) -> BoxFuture<'a, usize> {
+        Box::pin(async move {
+            let mut cache = self.cache.lock().await;
+            let (mut freed, mut evicted) = (0, 0);
+
+            while freed < bytes {
+                let Some(id) = cache.order.pop_front() else {
+                    break;
+                };
+
+                if let Some(msg) = cache.messages.remove(&id) {
+                    freed += _approx_size(&msg);
+                    evicted += 1;
+                }
+            }
+
+            evicted
+        })
+    }
+}
@@ -0,0 +1,135 @@
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateWebhook, ExecuteWebhook, Message, Webhook,
+};
+use std::time::Duration;
+
+use crate::cache::TtlCache;
+use crate::resilience::{retry, RetryPolicy};
+use crate::sanitize::MentionPolicy;
+use crate::Error;
+
+/// Name of the webhooks created and reused by ``Mimic``
+pub const MANAGED_WEBHOOK_NAME: &str = "botox";
+
+/// Returns true if a serenity error is a 404 (for example, because the webhook was deleted)
+pub(crate) fn is_not_found(err: &serenity::Error) -> bool {
+    match err {
+        serenity::Error::Http(e) => e.status_code().map(|s| s.as_u16()) == Some(404),
+        _ => false,
+    }
+}
+
+/// Sends messages impersonating a display name and avatar through managed webhooks
+///
+/// Webhooks are found or created once per channel and cached for a day. This is cheap to clone
+#[derive(Clone)]
+pub struct Mimic {
+    webhooks: TtlCache<ChannelId, Webhook>,
+    /// How a failed webhook execution is retried
+    pub retry: RetryPolicy,
+}
+
+impl Default for Mimic {
+    fn default() -> Self {
+        Self {
+            webhooks: TtlCache::new("webhooks", Duration::from_secs(60 * 60 * 24), 10_000),
+            retry: RetryPolicy::default(),
+        }
+    }
+}
+
+impl Mimic {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the managed webhook of a channel, creating it if needed
+    pub async fn webhook(
+        &self,
+        http: &serenity::Http,
+        channel_id: ChannelId,
+    ) -> Result<Webhook, Error> {
+        if let Some(webhook) = self.webhooks.get(&channel_id).await {
+            return Ok(webhook);
+        }
+
+        let existing = channel_id
+            .webhooks(http)
+            .await?
+            .into_iter()
+            .find(|w| w.token.is_some() && w.name.as_deref() == Some(MANAGED_WEBHOOK_NAME));
+
+        let webhook = match existing {
+            Some(webhook) => webhook,
+            None => {
+                channel_id
+                    .create_webhook(http, CreateWebhook::new(MANAGED_WEBHOOK_NAME))
+                    .await?
+            }
+        };
+
+        self.webhooks.insert(channel_id, webhook.clone()).await;
+
+        Ok(webhook)
+    }
+
+    /// Returns the webhook cache, for example to add it to a ``MemoryGovernor``
+    pub fn webhook_cache(&self) -> &TtlCache<ChannelId, Webhook> {
+        &self.webhooks
+    }
+
+    /// Drops the cached webhook of a channel
+    pub async fn invalidate(&self, channel_id: ChannelId) {
+        self.webhooks.remove(&channel_id).await;
+    }
+
+    /// Executes the managed webhook of a channel with a custom builder, waiting for the sent message
+    ///
+    /// If the cached webhook was deleted, a new one is created and the send is retried once.
+    /// Transient failures are retried according to ``retry``
+    pub async fn execute(
+        &self,
+        http: &serenity::Http,
+        channel_id: ChannelId,
+        builder: ExecuteWebhook<'_>,
+    ) -> Result<Message, Error> {
+        let builder = &builder;
+
+        let res = retry(&self.retry, || async move {
+            let webhook = self.webhook(http, channel_id).await?;
+
+            Ok(match webhook.execute(http, true, builder.clone()).await {
+                Err(e) if is_not_found(&e) => {
+                    self.invalidate(channel_id).await;
+                    let webhook = self.webhook(http, channel_id).await?;
+                    webhook.execute(http, true, builder.clone()).await?
+                }
+                res => res?,
+            })
+        })
+        .await?;
+
+        res.ok_or_else(|| "Webhook did not return a message".into())
+    }
+
+    /// Sends a message as ``name`` with the given avatar, mentions never ping
+    pub async fn send_as(
+        &self,
+        http: &serenity::Http,
+        channel_id: ChannelId,
+        name: &str,
+        avatar_url: Option<&str>,
+        content: &str,
+    ) -> Result<Message, Error> {
+        let mut builder = ExecuteWebhook::new()
+            .username(name)
+            .content(content)
+            .allowed_mentions(MentionPolicy::none().build());
+
+        if let Some(avatar_url) = avatar_url {
+            builder = builder.avatar_url(avatar_url);
+        }
+
+        self.execute(http, channel_id, builder).await
+    }
+}
@@ -0,0 +1,66 @@
+use tokio::task::JoinSet;
+
+use crate::bot::Bot;
+use crate::Error;
+
+/// Runs several bots (for example, a main bot and a beta bot) in one process
+///
+/// Each bot keeps its own token, gateway connection and tasks. Bots can share data through
+/// ``BotBuilder::shared_data`` or keep their own. Name bots with ``BotBuilder::name`` so their tasks
+/// and logs can be told apart, command metrics are labelled with the bots user id
+#[derive(Default)]
+pub struct Supervisor {
+    bots: Vec<Bot>,
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a bot
+    pub fn bot(mut self, bot: Bot) -> Self {
+        self.bots.push(bot);
+        self
+    }
+
+    /// Runs every bot until all of them stop, Ctrl+C shuts all of them down
+    ///
+    /// A bot failing does not stop the others, its error is logged and returned once all bots stopped
+    pub async fn run(self) -> Result<(), Error> {
+        let mut set = JoinSet::new();
+
+        for (i, bot) in self.bots.into_iter().enumerate() {
+            let name = bot
+                .name()
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("bot {}", i));
+
+            log::info!("Starting {}", name);
+
+            set.spawn(async move { (name, bot.run().await) });
+        }
+
+        let mut failed = Vec::new();
+
+        while let Some(res) = set.join_next().await {
+            match res {
+                Ok((name, Ok(()))) => log::info!("{} stopped", name),
+                Ok((name, Err(e))) => {
+                    log::error!("{} failed: {}", name, crate::secrets::redact_error(&*e));
+                    failed.push(name);
+                }
+                Err(e) => {
+                    log::error!("A bot panicked: {}", e);
+                    failed.push("unknown".to_string());
+                }
+            }
+        }
+
+        if !failed.is_empty() {
+            return Err(format!("Some bots failed: {}", failed.join(", ")).into());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,244 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{self as serenity, FullEvent, GuildId, Timestamp, User, UserId};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::privacy::DataHolder;
+use crate::sanitize::escape_markdown;
+use crate::Error;
+
+/// Names shown per page of ``names``
+const NAMES_PER_PAGE: usize = 15;
+
+/// What kind of name changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum NameKind {
+    Username,
+    GlobalName,
+    Nickname(GuildId),
+}
+
+/// A past name of a user
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NameEntry {
+    pub user_id: UserId,
+    pub kind: NameKind,
+    /// None if the name was removed (for example a cleared nickname)
+    pub name: Option<String>,
+    pub changed_at: Timestamp,
+}
+
+/// Storage backend for name history
+pub trait NameStore: Send + Sync {
+    /// Returns the name history of a user, newest first
+    fn history<'a>(&'a self, user_id: UserId) -> BoxFuture<'a, Result<Vec<NameEntry>, Error>>;
+
+    /// Adds an entry, dropping the oldest entries of the user beyond ``max``
+    fn push<'a>(&'a self, entry: &'a NameEntry, max: usize) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Deletes the name history of a user, returning the number of entries deleted
+    fn erase<'a>(&'a self, user_id: UserId) -> BoxFuture<'a, Result<usize, Error>>;
+
+    /// Deletes entries changed before ``before``, returning the number of entries deleted
+    fn prune_before<'a>(&'a self, before: Timestamp) -> BoxFuture<'a, Result<usize, Error>>;
+}
+
+/// Tracks username, display name and nickname changes
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct NameLog {
+    store: Arc<dyn NameStore>,
+    /// Maximum number of entries kept per user
+    pub max_per_user: usize,
+}
+
+impl NameLog {
+    pub fn new(store: Arc<dyn NameStore>) -> Self {
+        Self {
+            store,
+            max_per_user: 50,
+        }
+    }
+
+    /// Returns the name history of a user, newest first
+    pub async fn names(&self, user_id: UserId) -> Result<Vec<NameEntry>, Error> {
+        self.store.history(user_id).await
+    }
+
+    // Records a name if it differs from the last known name of that kind
+    async fn _record(
+        &self,
+        history: &[NameEntry],
+        user_id: UserId,
+        kind: NameKind,
+        name: Option<String>,
+    ) -> Result<(), Error> {
+        let last = history.iter().find(|e| e.kind == kind);
+
+        if last.is_some_and(|e| e.name == name) || (last.is_none() && name.is_none()) {
+            return Ok(());
+        }
+
+        self.store
+            .push(
+                &NameEntry {
+                    user_id,
+                    kind,
+                    name,
+                    changed_at: Timestamp::now(),
+                },
+                self.max_per_user,
+            )
+            .await
+    }
+
+    async fn _record_user(
+        &self,
+        user: &User,
+        nick: Option<(GuildId, Option<String>)>,
+    ) -> Result<(), Error> {
+        let history = self.store.history(user.id).await?;
+
+        self._record(
+            &history,
+            user.id,
+            NameKind::Username,
+            Some(user.name.to_string()),
+        )
+        .await?;
+
+        self._record(
+            &history,
+            user.id,
+            NameKind::GlobalName,
+            user.global_name.as_ref().map(|n| n.to_string()),
+        )
+        .await?;
+
+        if let Some((guild_id, nick)) = nick {
+            self._record(&history, user.id, NameKind::Nickname(guild_id), nick)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Records name changes, this should be called from your bots event handler
+    ///
+    /// Requires the ``GUILD_MEMBERS`` intent
+    pub async fn handle_event(&self, event: &FullEvent) -> Result<(), Error> {
+        match event {
+            FullEvent::GuildMemberUpdate { event, .. } => {
+                self._record_user(
+                    &event.user,
+                    Some((event.guild_id, event.nick.as_ref().map(|n| n.to_string()))),
+                )
+                .await
+            }
+            FullEvent::UserUpdate { new, .. } => self._record_user(new, None).await,
+            _ => Ok(()),
+        }
+    }
+}
+
+impl DataHolder for NameLog {
+    fn name(&self) -> &str {
+        "names"
+    }
+
+    fn export_user<'a>(
+        &'a self,
+        user_id: UserId,
+    ) -> BoxFuture<'a, Result<serde_json::Value, Error>> {
+        Box::pin(async move { Ok(serde_json::to_value(self.names(user_id).await?)?) })
+    }
+
+    fn erase_user<'a>(&'a self, user_id: UserId) -> BoxFuture<'a, Result<usize, Error>> {
+        self.store.erase(user_id)
+    }
+
+    fn prune_before<'a>(&'a self, before: Timestamp) -> BoxFuture<'a, Result<usize, Error>> {
+        self.store.prune_before(before)
+    }
+}
+
+/// Trait for bot data that holds a ``NameLog``
+pub trait HasNameLog {
+    fn name_log(&self) -> &NameLog;
+}
+
+/// Shows a page of a users past names, can be plugged into your bots ``/names`` command
+///
+/// Nicknames from other servers are hidden
+pub async fn names<Data: HasNameLog + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    user: User,
+    page: Option<usize>,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let guild_id = ctx.guild_id();
+
+    let history = data
+        .name_log()
+        .names(user.id)
+        .await?
+        .into_iter()
+        .filter(|e| match e.kind {
+            NameKind::Nickname(g) => Some(g) == guild_id,
+            _ => true,
+        })
+        .collect::<Vec<_>>();
+
+    if history.is_empty() {
+        ctx.say(format!("No name history for <@{}>", user.id))
+            .await?;
+        return Ok(());
+    }
+
+    let pages = history.len().div_ceil(NAMES_PER_PAGE);
+    let page = page.unwrap_or(1).clamp(1, pages);
+
+    let mut desc = String::new();
+
+    for entry in history
+        .iter()
+        .skip((page - 1) * NAMES_PER_PAGE)
+        .take(NAMES_PER_PAGE)
+    {
+        let kind = match entry.kind {
+            NameKind::Username => "Username",
+            NameKind::GlobalName => "Display name",
+            NameKind::Nickname(_) => "Nickname",
+        };
+
+        let _ = writeln!(
+            desc,
+            "<t:{}:d> **{}:** {}",
+            entry.changed_at.unix_timestamp(),
+            kind,
+            entry
+                .name
+                .as_deref()
+                .map(escape_markdown)
+                .unwrap_or_else(|| "*none*".to_string())
+        );
+    }
+
+    ctx.send(
+        CreateReply::default().embed(
+            serenity::CreateEmbed::default()
+                .title(format!("Names of {}", user.name))
+                .description(desc)
+                .footer(serenity::CreateEmbedFooter::new(format!(
+                    "Page {}/{}",
+                    page, pages
+                ))),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,259 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ComponentInteraction, CreateActionRow, CreateButton,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, Message, UserId,
+};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cache::TtlCache;
+use crate::i18n::{t, tr};
+use crate::Error;
+
+/// A category of DM notifications users can opt out of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum NotifyCategory {
+    LevelUps,
+    Reminders,
+    Moderation,
+    Suggestions,
+    Security,
+}
+
+impl NotifyCategory {
+    pub const ALL: [NotifyCategory; 5] = [
+        NotifyCategory::LevelUps,
+        NotifyCategory::Reminders,
+        NotifyCategory::Moderation,
+        NotifyCategory::Suggestions,
+        NotifyCategory::Security,
+    ];
+
+    /// Returns the translated name of the category
+    pub fn label(&self, locale: Option<&str>) -> String {
+        t(locale, &format!("notify.{}", self.id()), &[])
+    }
+
+    fn id(&self) -> &'static str {
+        match self {
+            NotifyCategory::LevelUps => "levelups",
+            NotifyCategory::Reminders => "reminders",
+            NotifyCategory::Moderation => "moderation",
+            NotifyCategory::Suggestions => "suggestions",
+            NotifyCategory::Security => "security",
+        }
+    }
+
+    fn from_id(id: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.id() == id)
+    }
+}
+
+/// Storage backend for notification preferences
+pub trait NotificationStore: Send + Sync {
+    /// Returns the categories a user has opted out of
+    fn opted_out<'a>(
+        &'a self,
+        user_id: UserId,
+    ) -> BoxFuture<'a, Result<Vec<NotifyCategory>, Error>>;
+
+    /// Opts a user in to or out of a category
+    fn set<'a>(
+        &'a self,
+        user_id: UserId,
+        category: NotifyCategory,
+        enabled: bool,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Per-user DM notification preferences with an in-memory cache in front of a ``NotificationStore``
+///
+/// Users are opted in to every category by default
+///
+/// Preferences are cached for an hour. This is cheap to clone
+#[derive(Clone)]
+pub struct Preferences {
+    store: Arc<dyn NotificationStore>,
+    cache: TtlCache<UserId, Vec<NotifyCategory>>,
+}
+
+impl Preferences {
+    pub fn new(store: Arc<dyn NotificationStore>) -> Self {
+        Self {
+            store,
+            cache: TtlCache::new(
+                "notification_preferences",
+                Duration::from_secs(60 * 60),
+                50_000,
+            ),
+        }
+    }
+
+    /// Returns the categories a user has opted out of
+    pub async fn opted_out(&self, user_id: UserId) -> Result<Vec<NotifyCategory>, Error> {
+        if let Some(opted_out) = self.cache.get(&user_id).await {
+            return Ok(opted_out);
+        }
+
+        let opted_out = self.store.opted_out(user_id).await?;
+        self.cache.insert(user_id, opted_out.clone()).await;
+
+        Ok(opted_out)
+    }
+
+    /// Returns whether a user wants notifications of a category
+    pub async fn allows(&self, user_id: UserId, category: NotifyCategory) -> Result<bool, Error> {
+        Ok(!self.opted_out(user_id).await?.contains(&category))
+    }
+
+    pub async fn set(
+        &self,
+        user_id: UserId,
+        category: NotifyCategory,
+        enabled: bool,
+    ) -> Result<(), Error> {
+        self.store.set(user_id, category, enabled).await?;
+        self.cache.remove(&user_id).await;
+
+        Ok(())
+    }
+
+    /// Returns the preference cache, for example to add it to a ``MemoryGovernor``
+    pub fn cache(&self) -> &TtlCache<UserId, Vec<NotifyCategory>> {
+        &self.cache
+    }
+}
+
+/// DMs a user unless they opted out of the category, returning whether the DM was sent
+///
+/// Users with DMs closed are not an error, false is returned instead
+pub async fn dm(
+    http: &serenity::Http,
+    preferences: Option<&Preferences>,
+    user_id: UserId,
+    category: NotifyCategory,
+    msg: CreateMessage<'_>,
+) -> Result<bool, Error> {
+    Ok(dm_message(http, preferences, user_id, category, msg)
+        .await?
+        .is_some())
+}
+
+/// Like ``dm``, but returns the sent message so it can be edited or retracted later
+pub async fn dm_message(
+    http: &serenity::Http,
+    preferences: Option<&Preferences>,
+    user_id: UserId,
+    category: NotifyCategory,
+    msg: CreateMessage<'_>,
+) -> Result<Option<Message>, Error> {
+    if let Some(preferences) = preferences {
+        if !preferences.allows(user_id, category).await? {
+            return Ok(None);
+        }
+    }
+
+    let dm = match user_id.create_dm_channel(http).await {
+        Ok(dm) => dm,
+        Err(e) => {
+            log::warn!("Failed to open DM with {}: {}", user_id, e);
+            return Ok(None);
+        }
+    };
+
+    match dm.id.send_message(http, msg).await {
+        Ok(msg) => Ok(Some(msg)),
+        Err(e) => {
+            log::warn!("Failed to DM {}: {}", user_id, e);
+            Ok(None)
+        }
+    }
+}
+
+fn _components(
+    opted_out: &[NotifyCategory],
+    locale: Option<&str>,
+) -> Vec<CreateActionRow<'static>> {
+    vec![CreateActionRow::Buttons(
+        NotifyCategory::ALL
+            .iter()
+            .map(|c| {
+                let enabled = !opted_out.contains(c);
+
+                CreateButton::new(format!("notif:{}", c.id()))
+                    .label(c.label(locale))
+                    .style(if enabled {
+                        serenity::ButtonStyle::Success
+                    } else {
+                        serenity::ButtonStyle::Secondary
+                    })
+            })
+            .collect(),
+    )]
+}
+
+/// Trait for bot data that holds notification ``Preferences``
+pub trait HasPreferences {
+    fn preferences(&self) -> &Preferences;
+}
+
+/// Shows a users notification preferences as toggle buttons, can be plugged into your bots ``/notifications`` command
+///
+/// Button presses are handled by ``handle_interaction``
+pub async fn notifications<Data: HasPreferences + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let opted_out = data.preferences().opted_out(ctx.author().id).await?;
+
+    ctx.send(
+        CreateReply::default()
+            .content(tr(ctx, "notify.toggle", &[]))
+            .components(_components(&opted_out, ctx.locale()))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Handles a notification toggle press, returning false if the interaction is not a notification interaction
+///
+/// This should be called from your bots event handler on every component interaction
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    preferences: &Preferences,
+) -> Result<bool, Error> {
+    let Some(category) = interaction
+        .data
+        .custom_id
+        .strip_prefix("notif:")
+        .and_then(NotifyCategory::from_id)
+    else {
+        return Ok(false);
+    };
+
+    let user_id = interaction.user.id;
+    let enabled = preferences.allows(user_id, category).await?;
+
+    preferences.set(user_id, category, !enabled).await?;
+
+    let opted_out = preferences.opted_out(user_id).await?;
+    let locale = Some(&*interaction.locale);
+
+    interaction
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .content(t(locale, "notify.toggle", &[]))
+                    .components(_components(&opted_out, locale)),
+            ),
+        )
+        .await?;
+
+    Ok(true)
+}
@@ -0,0 +1,575 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ComponentInteraction, CreateActionRow, CreateAttachment, CreateButton,
+    CreateEmbed, CreateInteractionResponse, CreateInteractionResponseFollowup,
+    CreateInteractionResponseMessage, MessageId, UserId,
+};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+use crate::cache::TtlCache;
+use crate::i18n::t;
+use crate::Error;
+
+/// Largest export uploaded, kept below Discords default upload limit of 10 MiB
+pub const MAX_EXPORT_BYTES: usize = 8 * 1024 * 1024;
+
+/// The id ``EmbedPages`` must be registered under
+pub const EMBED_PAGES_SOURCE: &str = "embeds";
+
+/// Renders the pages of a paginator
+///
+/// Sources are registered under a stable id so navigation can resume after a restart
+pub trait PageSource: Send + Sync {
+    /// Returns the number of pages for the given arguments
+    fn page_count<'a>(&'a self, args: &'a str) -> BoxFuture<'a, Result<usize, Error>>;
+
+    /// Renders a page (starting at 0) for the given arguments
+    fn render<'a>(
+        &'a self,
+        args: &'a str,
+        page: usize,
+    ) -> BoxFuture<'a, Result<CreateEmbed<'static>, Error>>;
+}
+
+/// Pages rendered up front, such as the output of a one-off command
+///
+/// Pages are held in memory for an hour, so unlike other sources they do not resume after a
+/// restart. This is cheap to clone
+#[derive(Clone)]
+pub struct EmbedPages {
+    pages: TtlCache<String, Arc<Vec<CreateEmbed<'static>>>>,
+}
+
+impl Default for EmbedPages {
+    fn default() -> Self {
+        Self {
+            pages: TtlCache::new("embed_pages", Duration::from_secs(60 * 60), 10_000),
+        }
+    }
+}
+
+impl EmbedPages {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a set of pages, returning the arguments to send them with
+    pub async fn add(&self, pages: Vec<CreateEmbed<'static>>) -> String {
+        let id = format!("{:016x}", rand::random::<u64>());
+        self.pages.insert(id.clone(), Arc::new(pages)).await;
+        id
+    }
+
+    /// Returns the page cache, for example to add it to a ``MemoryGovernor``
+    pub fn cache(&self) -> &TtlCache<String, Arc<Vec<CreateEmbed<'static>>>> {
+        &self.pages
+    }
+}
+
+impl PageSource for EmbedPages {
+    fn page_count<'a>(&'a self, args: &'a str) -> BoxFuture<'a, Result<usize, Error>> {
+        Box::pin(async move {
+            Ok(self
+                .pages
+                .get(&args.to_string())
+                .await
+                .map_or(0, |p| p.len()))
+        })
+    }
+
+    fn render<'a>(
+        &'a self,
+        args: &'a str,
+        page: usize,
+    ) -> BoxFuture<'a, Result<CreateEmbed<'static>, Error>> {
+        Box::pin(async move {
+            self.pages
+                .get(&args.to_string())
+                .await
+                .and_then(|p| p.get(page).cloned())
+                .ok_or_else(|| "These pages have expired".into())
+        })
+    }
+}
+
+/// The persisted state of a paginator message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaginatorState {
+    /// The id the ``PageSource`` was registered under
+    pub source: String,
+    /// Arguments passed to the source, such as a guild id or search query
+    pub args: String,
+    pub page: usize,
+    /// Only this user may navigate, if set
+    pub owner: Option<UserId>,
+    /// Shows an export button uploading every page in this format, if set
+    #[serde(default)]
+    pub export: Option<ExportFormat>,
+}
+
+/// The file format of a paginator export
+#[derive(poise::ChoiceParameter, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ExportFormat {
+    Text,
+    Markdown,
+    Csv,
+}
+
+impl ExportFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Text => "Text",
+            ExportFormat::Markdown => "Markdown",
+            ExportFormat::Csv => "CSV",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Text => "txt",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Csv => "csv",
+        }
+    }
+}
+
+/// The text of an embed, read back from its JSON since builders do not expose their fields
+struct _EmbedText {
+    title: String,
+    description: String,
+    fields: Vec<(String, String)>,
+}
+
+impl _EmbedText {
+    fn new(embed: &CreateEmbed<'_>) -> Self {
+        let value = serde_json::to_value(embed).unwrap_or_default();
+        let text = |v: &serde_json::Value, key: &str| {
+            v.get(key)
+                .and_then(|t| t.as_str())
+                .unwrap_or_default()
+                .to_string()
+        };
+
+        Self {
+            title: text(&value, "title"),
+            description: text(&value, "description"),
+            fields: value
+                .get("fields")
+                .and_then(|f| f.as_array())
+                .map(|fields| {
+                    fields
+                        .iter()
+                        .map(|f| (text(f, "name"), text(f, "value")))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }
+    }
+}
+
+fn _csv_cell(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Renders one page of an export, pages are numbered from 1
+fn _export_page(embed: &CreateEmbed<'_>, page: usize, format: ExportFormat) -> String {
+    let embed = _EmbedText::new(embed);
+    let mut out = String::new();
+
+    match format {
+        ExportFormat::Text => {
+            let _ = writeln!(out, "=== Page {} ===", page);
+
+            for text in [&embed.title, &embed.description] {
+                if !text.is_empty() {
+                    let _ = writeln!(out, "{}", text);
+                }
+            }
+
+            for (name, value) in &embed.fields {
+                let _ = writeln!(out, "{}:\n{}", name, value);
+            }
+
+            out.push('\n');
+        }
+        ExportFormat::Markdown => {
+            let _ = writeln!(out, "## {} (page {})\n", embed.title, page);
+
+            if !embed.description.is_empty() {
+                let _ = writeln!(out, "{}\n", embed.description);
+            }
+
+            for (name, value) in &embed.fields {
+                let _ = writeln!(out, "**{}**\n{}\n", name, value);
+            }
+        }
+        ExportFormat::Csv => {
+            let title = _csv_cell(&embed.title);
+
+            if !embed.description.is_empty() {
+                let _ = writeln!(
+                    out,
+                    "{},{},description,{}",
+                    page,
+                    title,
+                    _csv_cell(&embed.description)
+                );
+            }
+
+            for (name, value) in &embed.fields {
+                let _ = writeln!(
+                    out,
+                    "{},{},{},{}",
+                    page,
+                    title,
+                    _csv_cell(name),
+                    _csv_cell(value)
+                );
+            }
+        }
+    }
+
+    out
+}
+
+/// Builds an export from pages, stopping with a notice once ``MAX_EXPORT_BYTES`` would be exceeded
+struct _Export {
+    format: ExportFormat,
+    out: String,
+    pages: usize,
+    truncated: bool,
+}
+
+impl _Export {
+    fn new(format: ExportFormat) -> Self {
+        Self {
+            format,
+            out: match format {
+                ExportFormat::Csv => "page,title,field,value\n".to_string(),
+                _ => String::new(),
+            },
+            pages: 0,
+            truncated: false,
+        }
+    }
+
+    /// Adds a page, returning false if it did not fit
+    fn push(&mut self, embed: &CreateEmbed<'_>) -> bool {
+        let page = _export_page(embed, self.pages + 1, self.format);
+
+        // Leave room for the truncation notice
+        if self.out.len() + page.len() > MAX_EXPORT_BYTES - 256 {
+            self.truncated = true;
+            return false;
+        }
+
+        self.out.push_str(&page);
+        self.pages += 1;
+        true
+    }
+
+    fn finish(mut self, total: usize, name: &str) -> CreateAttachment<'static> {
+        if self.truncated {
+            let notice = format!(
+                "Export truncated to {} of {} pages to fit the upload limit",
+                self.pages, total
+            );
+
+            match self.format {
+                // A comment-like row keeps the file parseable
+                ExportFormat::Csv => {
+                    let _ = writeln!(self.out, ",,truncated,{}", _csv_cell(&notice));
+                }
+                _ => {
+                    let _ = writeln!(self.out, "{}", notice);
+                }
+            }
+        }
+
+        CreateAttachment::bytes(
+            self.out.into_bytes(),
+            format!("{}.{}", name, self.format.extension()),
+        )
+    }
+}
+
+/// Renders embeds (such as the pages from ``leaderboard::render``) into a single file, truncated to
+/// fit ``MAX_EXPORT_BYTES``
+pub fn export_attachment(
+    embeds: &[CreateEmbed<'_>],
+    format: ExportFormat,
+    name: &str,
+) -> CreateAttachment<'static> {
+    let mut export = _Export::new(format);
+
+    for embed in embeds {
+        if !export.push(embed) {
+            break;
+        }
+    }
+
+    export.finish(embeds.len(), name)
+}
+
+/// Storage backend for paginator state
+pub trait PaginatorStore: Send + Sync {
+    fn get<'a>(
+        &'a self,
+        message_id: MessageId,
+    ) -> BoxFuture<'a, Result<Option<PaginatorState>, Error>>;
+
+    fn save<'a>(
+        &'a self,
+        message_id: MessageId,
+        state: &'a PaginatorState,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    fn delete<'a>(&'a self, message_id: MessageId) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+fn _components(
+    page: usize,
+    pages: usize,
+    export: Option<ExportFormat>,
+) -> Vec<CreateActionRow<'static>> {
+    let last = pages.saturating_sub(1);
+
+    let mut rows = vec![CreateActionRow::Buttons(vec![
+        CreateButton::new("pg:first").label("⏮").disabled(page == 0),
+        CreateButton::new("pg:prev").label("◀").disabled(page == 0),
+        CreateButton::new("pg:page")
+            .label(format!("{}/{}", page + 1, pages.max(1)))
+            .style(serenity::ButtonStyle::Secondary)
+            .disabled(true),
+        CreateButton::new("pg:next")
+            .label("▶")
+            .disabled(page >= last),
+        CreateButton::new("pg:last")
+            .label("⏭")
+            .disabled(page >= last),
+    ])];
+
+    if let Some(format) = export {
+        rows.push(CreateActionRow::Buttons(vec![CreateButton::new(
+            "pg:export",
+        )
+        .label(format!("Export ({})", format.label()))
+        .style(serenity::ButtonStyle::Secondary)]));
+    }
+
+    rows
+}
+
+/// Paginated messages whose state lives in a ``PaginatorStore``, so buttons keep working across restarts
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct Paginator {
+    store: Arc<dyn PaginatorStore>,
+    sources: Arc<RwLock<HashMap<String, Arc<dyn PageSource>>>>,
+}
+
+impl Paginator {
+    pub fn new(store: Arc<dyn PaginatorStore>) -> Self {
+        Self {
+            store,
+            sources: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Registers a page source, this must be done on every startup for old messages to resume
+    pub async fn register(&self, id: impl Into<String>, source: impl PageSource + 'static) {
+        self.sources
+            .write()
+            .await
+            .insert(id.into(), Arc::new(source));
+    }
+
+    async fn _source(&self, id: &str) -> Result<Arc<dyn PageSource>, Error> {
+        self.sources
+            .read()
+            .await
+            .get(id)
+            .cloned()
+            .ok_or_else(|| format!("Unknown page source {}", id).into())
+    }
+
+    /// Sends the first page of a source, only the invoker may navigate it
+    pub async fn send<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+        source_id: &str,
+        args: impl Into<String>,
+    ) -> Result<(), Error> {
+        self.send_exportable(ctx, source_id, args, None).await
+    }
+
+    /// Like ``send``, with an export button uploading every page as a file if ``export`` is set
+    pub async fn send_exportable<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+        source_id: &str,
+        args: impl Into<String>,
+        export: Option<ExportFormat>,
+    ) -> Result<(), Error> {
+        self._send(ctx, source_id, args.into(), export, false).await
+    }
+
+    /// Like ``send``, as an ephemeral reply where the context allows it
+    pub async fn send_ephemeral<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+        source_id: &str,
+        args: impl Into<String>,
+    ) -> Result<(), Error> {
+        self._send(ctx, source_id, args.into(), None, true).await
+    }
+
+    async fn _send<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+        source_id: &str,
+        args: String,
+        export: Option<ExportFormat>,
+        ephemeral: bool,
+    ) -> Result<(), Error> {
+        let source = self._source(source_id).await?;
+
+        let state = PaginatorState {
+            source: source_id.to_string(),
+            args,
+            page: 0,
+            owner: Some(ctx.author().id),
+            export,
+        };
+
+        let pages = source.page_count(&state.args).await?;
+        let embed = source.render(&state.args, 0).await?;
+
+        let handle = ctx
+            .send(
+                CreateReply::default()
+                    .embed(embed)
+                    .components(_components(0, pages, state.export))
+                    .ephemeral(ephemeral),
+            )
+            .await?;
+
+        let msg = handle.message().await?;
+        self.store.save(msg.id, &state).await?;
+
+        Ok(())
+    }
+
+    /// Handles a paginator button press, returning false if the interaction is not a paginator interaction
+    ///
+    /// This should be called from your bots event handler on every component interaction
+    pub async fn handle_interaction(
+        &self,
+        ctx: &serenity::Context,
+        interaction: &ComponentInteraction,
+    ) -> Result<bool, Error> {
+        let Some(action) = interaction.data.custom_id.strip_prefix("pg:") else {
+            return Ok(false);
+        };
+
+        let locale = Some(&*interaction.locale);
+
+        let respond = |key: &str| {
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(t(locale, key, &[]))
+                    .ephemeral(true),
+            )
+        };
+
+        let Some(mut state) = self.store.get(interaction.message.id).await? else {
+            interaction
+                .create_response(&ctx.http, respond("paginator.expired"))
+                .await?;
+            return Ok(true);
+        };
+
+        if state.owner.is_some_and(|o| o != interaction.user.id) {
+            interaction
+                .create_response(&ctx.http, respond("paginator.not_owner"))
+                .await?;
+            return Ok(true);
+        }
+
+        let Ok(source) = self._source(&state.source).await else {
+            self.store.delete(interaction.message.id).await?;
+            interaction
+                .create_response(&ctx.http, respond("paginator.expired"))
+                .await?;
+            return Ok(true);
+        };
+
+        let pages = source.page_count(&state.args).await?;
+        let last = pages.saturating_sub(1);
+
+        if action == "export" {
+            let Some(format) = state.export else {
+                return Ok(false);
+            };
+
+            // Rendering every page can take longer than an interaction allows
+            interaction.defer_ephemeral(&ctx.http).await?;
+
+            let mut export = _Export::new(format);
+
+            for page in 0..pages {
+                if !export.push(&source.render(&state.args, page).await?) {
+                    break;
+                }
+            }
+
+            interaction
+                .create_followup(
+                    &ctx.http,
+                    CreateInteractionResponseFollowup::new()
+                        .add_file(export.finish(pages, &state.source))
+                        .ephemeral(true),
+                )
+                .await?;
+
+            return Ok(true);
+        }
+
+        state.page = match action {
+            "first" => 0,
+            "prev" => state.page.saturating_sub(1),
+            "next" => state.page + 1,
+            "last" => last,
+            _ => return Ok(false),
+        }
+        .min(last);
+
+        let embed = source.render(&state.args, state.page).await?;
+
+        interaction
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(embed)
+                        .components(_components(state.page, pages, state.export)),
+                ),
+            )
+            .await?;
+
+        self.store.save(interaction.message.id, &state).await?;
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,273 @@
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, GuildId, PermissionOverwrite, PermissionOverwriteType,
+    Permissions, RoleId, UserId,
+};
+
+use crate::i18n::t;
+
+/// Permissions shown in breakdowns, with their display names
+pub const RELEVANT_PERMISSIONS: &[(Permissions, &str)] = &[
+    (Permissions::VIEW_CHANNEL, "View Channel"),
+    (Permissions::SEND_MESSAGES, "Send Messages"),
+    (
+        Permissions::SEND_MESSAGES_IN_THREADS,
+        "Send Messages in Threads",
+    ),
+    (Permissions::EMBED_LINKS, "Embed Links"),
+    (Permissions::ATTACH_FILES, "Attach Files"),
+    (Permissions::ADD_REACTIONS, "Add Reactions"),
+    (Permissions::USE_EXTERNAL_EMOJIS, "Use External Emojis"),
+    (Permissions::READ_MESSAGE_HISTORY, "Read Message History"),
+    (Permissions::MANAGE_MESSAGES, "Manage Messages"),
+    (Permissions::MANAGE_CHANNELS, "Manage Channels"),
+    (Permissions::MANAGE_ROLES, "Manage Roles"),
+    (Permissions::MANAGE_WEBHOOKS, "Manage Webhooks"),
+    (Permissions::KICK_MEMBERS, "Kick Members"),
+    (Permissions::BAN_MEMBERS, "Ban Members"),
+    (Permissions::MODERATE_MEMBERS, "Timeout Members"),
+    (Permissions::CONNECT, "Connect"),
+    (Permissions::SPEAK, "Speak"),
+];
+
+/// Why a permission is or is not granted
+#[derive(Debug, Clone)]
+pub struct PermissionExplanation {
+    pub permission: Permissions,
+    pub name: &'static str,
+    pub allowed: bool,
+    pub reason: String,
+}
+
+/// Everything needed to compute a members permissions in a channel
+///
+/// This is owned so it can be built from the cache and used across awaits
+#[derive(Debug, Clone)]
+pub struct PermissionInputs {
+    pub guild_id: GuildId,
+    pub owner_id: UserId,
+    pub user_id: UserId,
+    /// Permissions of the @everyone role
+    pub everyone: Permissions,
+    /// The members roles (excluding @everyone) as id, name, permissions and position
+    pub roles: Vec<(RoleId, String, Permissions, u16)>,
+    /// Overwrites of the channel (or the parent channel, for threads)
+    pub overwrites: Vec<PermissionOverwrite>,
+    /// Locale explanations are translated to
+    pub locale: Option<String>,
+}
+
+impl PermissionInputs {
+    /// Builds the inputs from the cache, returns None if the guild is not cached
+    ///
+    /// Also returns None if ``channel_id`` is not a cached channel or thread of the guild, so
+    /// callers never fall back to guild-level permissions for a channel they know nothing about
+    pub fn from_cache(
+        cache: &serenity::Cache,
+        guild_id: GuildId,
+        user_id: UserId,
+        member_roles: &[RoleId],
+        channel_id: Option<ChannelId>,
+    ) -> Option<Self> {
+        let guild = cache.guild(guild_id)?;
+
+        let everyone = guild
+            .roles
+            .get(&RoleId::new(guild_id.get()))
+            .map(|r| r.permissions)
+            .unwrap_or_default();
+
+        let mut roles = member_roles
+            .iter()
+            .filter_map(|id| guild.roles.get(id))
+            .map(|r| (r.id, r.name.to_string(), r.permissions, r.position as u16))
+            .collect::<Vec<_>>();
+
+        roles.sort_by(|a, b| b.3.cmp(&a.3));
+
+        let overwrites = match channel_id {
+            Some(id) => {
+                let channel = guild.channels.get(&id).or_else(|| {
+                    // Threads use the overwrites of their parent channel
+                    let parent = guild.threads.iter().find(|t| t.id == id)?.parent_id?;
+                    guild.channels.get(&parent)
+                })?;
+
+                channel.permission_overwrites.to_vec()
+            }
+            None => Vec::new(),
+        };
+
+        Some(Self {
+            guild_id,
+            owner_id: guild.owner_id,
+            user_id,
+            everyone,
+            roles,
+            overwrites,
+            locale: None,
+        })
+    }
+
+    /// Sets the locale explanations are translated to
+    pub fn with_locale(mut self, locale: Option<&str>) -> Self {
+        self.locale = locale.map(|l| l.to_string());
+        self
+    }
+
+    fn _t(&self, key: &str, args: &[(&str, &str)]) -> String {
+        t(self.locale.as_deref(), key, args)
+    }
+
+    /// Returns the position of the members highest role, 0 if they only have @everyone
+    pub fn highest_position(&self) -> u16 {
+        self.roles.first().map(|r| r.3).unwrap_or(0)
+    }
+
+    fn _role_name(&self, id: RoleId) -> String {
+        if id.get() == self.guild_id.get() {
+            return "@everyone".to_string();
+        }
+
+        self.roles
+            .iter()
+            .find(|r| r.0 == id)
+            .map(|r| format!("@{}", r.1))
+            .unwrap_or_else(|| id.to_string())
+    }
+
+    /// Explains a single permission, following discords order of role permissions then
+    /// @everyone, role and member overwrites
+    pub fn explain(&self, permission: Permissions, name: &'static str) -> PermissionExplanation {
+        let result = |allowed: bool, reason: String| PermissionExplanation {
+            permission,
+            name,
+            allowed,
+            reason,
+        };
+
+        if self.user_id == self.owner_id {
+            return result(true, self._t("permissions.owner", &[]));
+        }
+
+        if self.everyone.contains(Permissions::ADMINISTRATOR) {
+            return result(true, self._t("permissions.admin_everyone", &[]));
+        }
+
+        if let Some(role) = self
+            .roles
+            .iter()
+            .find(|r| r.2.contains(Permissions::ADMINISTRATOR))
+        {
+            return result(
+                true,
+                self._t("permissions.admin_role", &[("role", &role.1)]),
+            );
+        }
+
+        let (mut allowed, mut reason) = if self.everyone.contains(permission) {
+            (true, self._t("permissions.granted_everyone", &[]))
+        } else if let Some(role) = self.roles.iter().find(|r| r.2.contains(permission)) {
+            (
+                true,
+                self._t("permissions.granted_role", &[("role", &role.1)]),
+            )
+        } else {
+            (false, self._t("permissions.not_granted", &[]))
+        };
+
+        let everyone_id = RoleId::new(self.guild_id.get());
+        let member_roles = self.roles.iter().map(|r| r.0).collect::<Vec<_>>();
+
+        // @everyone overwrite
+        for o in &self.overwrites {
+            if o.kind == PermissionOverwriteType::Role(everyone_id) {
+                if o.deny.contains(permission) {
+                    (allowed, reason) = (false, self._t("permissions.denied_everyone", &[]));
+                }
+                if o.allow.contains(permission) {
+                    (allowed, reason) = (true, self._t("permissions.allowed_everyone", &[]));
+                }
+            }
+        }
+
+        // Role overwrites, allows win over denies
+        let role_overwrites = self
+            .overwrites
+            .iter()
+            .filter_map(|o| match o.kind {
+                PermissionOverwriteType::Role(id) if member_roles.contains(&id) => Some((id, o)),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+
+        if let Some((id, _)) = role_overwrites
+            .iter()
+            .find(|(_, o)| o.deny.contains(permission))
+        {
+            (allowed, reason) = (
+                false,
+                self._t(
+                    "permissions.denied_role",
+                    &[("role", &self._role_name(*id))],
+                ),
+            );
+        }
+
+        if let Some((id, _)) = role_overwrites
+            .iter()
+            .find(|(_, o)| o.allow.contains(permission))
+        {
+            (allowed, reason) = (
+                true,
+                self._t(
+                    "permissions.allowed_role",
+                    &[("role", &self._role_name(*id))],
+                ),
+            );
+        }
+
+        // Member overwrite
+        for o in &self.overwrites {
+            if o.kind == PermissionOverwriteType::Member(self.user_id) {
+                if o.deny.contains(permission) {
+                    (allowed, reason) = (false, self._t("permissions.denied_member", &[]));
+                }
+                if o.allow.contains(permission) {
+                    (allowed, reason) = (true, self._t("permissions.allowed_member", &[]));
+                }
+            }
+        }
+
+        result(allowed, reason)
+    }
+
+    /// Explains every permission in ``RELEVANT_PERMISSIONS``
+    ///
+    /// Without View Channel, no other channel permission applies
+    pub fn explain_all(&self) -> Vec<PermissionExplanation> {
+        let view = self.explain(Permissions::VIEW_CHANNEL, "View Channel");
+
+        RELEVANT_PERMISSIONS
+            .iter()
+            .map(|(permission, name)| {
+                let mut explanation = self.explain(*permission, name);
+
+                if !view.allowed && *permission != Permissions::VIEW_CHANNEL && explanation.allowed
+                {
+                    explanation.allowed = false;
+                    explanation.reason = self._t("permissions.cannot_view", &[]);
+                }
+
+                explanation
+            })
+            .collect()
+    }
+
+    /// Returns the effective permissions among ``RELEVANT_PERMISSIONS``
+    pub fn effective(&self) -> Permissions {
+        self.explain_all()
+            .into_iter()
+            .filter(|e| e.allowed)
+            .fold(Permissions::empty(), |acc, e| acc | e.permission)
+    }
+}
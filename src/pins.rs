@@ -0,0 +1,204 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateEmbed, ExecuteWebhook, Message, MessageId,
+};
+use std::sync::Arc;
+
+use crate::mimic::Mimic;
+use crate::sanitize::MentionPolicy;
+use crate::Error;
+
+/// Maximum number of pins Discord allows per channel
+pub const MAX_PINS: usize = 50;
+
+/// Maximum length of a message, reposted content is cut to fit the jump link
+const MAX_CONTENT: usize = 2000;
+
+/// Storage backend for pin archive channels, configured per channel
+pub trait PinArchiveStore: Send + Sync {
+    /// Returns the channel old pins of a channel are moved to, if configured
+    fn archive_channel<'a>(
+        &'a self,
+        channel_id: ChannelId,
+    ) -> BoxFuture<'a, Result<Option<ChannelId>, Error>>;
+
+    /// Sets or clears the archive channel of a channel
+    fn set_archive_channel<'a>(
+        &'a self,
+        channel_id: ChannelId,
+        archive: Option<ChannelId>,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// What happened when pinning a message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PinOutcome {
+    Pinned,
+    /// The oldest pin was reposted to the archive channel and unpinned to make room
+    Archived {
+        unpinned: MessageId,
+        repost: MessageId,
+    },
+}
+
+/// Pins messages, moving the oldest pin to an archive channel once a channel is full
+pub struct Pins {
+    store: Arc<dyn PinArchiveStore>,
+    mimic: Mimic,
+}
+
+impl Pins {
+    pub fn new(store: Arc<dyn PinArchiveStore>, mimic: Mimic) -> Self {
+        Self { store, mimic }
+    }
+
+    /// Sets or clears where the old pins of a channel go
+    pub async fn set_archive(
+        &self,
+        channel_id: ChannelId,
+        archive: Option<ChannelId>,
+    ) -> Result<(), Error> {
+        if archive == Some(channel_id) {
+            return Err("A channel can't be its own pin archive".into());
+        }
+
+        self.store.set_archive_channel(channel_id, archive).await
+    }
+
+    /// Reposts a message to an archive channel as its author, with a jump link to the original
+    pub async fn archive(
+        &self,
+        http: &serenity::Http,
+        archive: ChannelId,
+        msg: &Message,
+    ) -> Result<Message, Error> {
+        let link = format!("\n\n[Jump to message]({})", msg.link());
+
+        let mut content = msg.content.to_string();
+
+        for attachment in &msg.attachments {
+            content.push('\n');
+            content.push_str(&attachment.url);
+        }
+
+        let max = MAX_CONTENT - link.chars().count();
+
+        if content.chars().count() > max {
+            content = content.chars().take(max - 1).collect();
+            content.push('…');
+        }
+
+        content.push_str(&link);
+
+        let mut builder = ExecuteWebhook::new()
+            .username(msg.author.display_name())
+            .avatar_url(msg.author.face())
+            .content(content)
+            .allowed_mentions(MentionPolicy::none().build());
+
+        for embed in &msg.embeds {
+            builder = builder.embed(CreateEmbed::from(embed.clone()));
+        }
+
+        self.mimic.execute(http, archive, builder).await
+    }
+
+    /// Pins a message, archiving and unpinning the oldest pin first if the channel is full
+    ///
+    /// Full channels without an archive channel are an error
+    pub async fn pin(
+        &self,
+        http: &serenity::Http,
+        channel_id: ChannelId,
+        message_id: MessageId,
+    ) -> Result<PinOutcome, Error> {
+        let pins = channel_id.pins(http).await?;
+
+        if pins.iter().any(|m| m.id == message_id) {
+            return Err("That message is already pinned".into());
+        }
+
+        if pins.len() < MAX_PINS {
+            channel_id.pin(http, message_id, None).await?;
+            return Ok(PinOutcome::Pinned);
+        }
+
+        let Some(archive) = self.store.archive_channel(channel_id).await? else {
+            return Err(format!(
+                "This channel has reached the {} pin limit and has no archive channel",
+                MAX_PINS
+            )
+            .into());
+        };
+
+        // Pins are returned newest first
+        let oldest = pins.last().ok_or("Failed to find the oldest pin")?;
+
+        let repost = self.archive(http, archive, oldest).await?;
+        channel_id.unpin(http, oldest.id, None).await?;
+        channel_id.pin(http, message_id, None).await?;
+
+        Ok(PinOutcome::Archived {
+            unpinned: oldest.id,
+            repost: repost.id,
+        })
+    }
+}
+
+/// Trait for bot data that holds ``Pins``
+pub trait HasPins {
+    fn pins(&self) -> &Pins;
+}
+
+/// Pins a message, can be plugged into your bots ``Pin message`` context menu command
+///
+/// Permission checks (such as Manage Messages) should be set on the command itself
+pub async fn pin_message<Data: HasPins + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    msg: Message,
+) -> Result<(), Error> {
+    let data = ctx.data();
+
+    let reply = match data.pins().pin(ctx.http(), msg.channel_id, msg.id).await? {
+        PinOutcome::Pinned => "Pinned".to_string(),
+        PinOutcome::Archived { unpinned, .. } => format!(
+            "Pinned, the oldest pin ({}) was moved to the archive channel",
+            unpinned.link(msg.channel_id, msg.guild_id)
+        ),
+    };
+
+    ctx.send(poise::CreateReply::default().content(reply).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
+
+/// Sets or clears the pin archive of a channel, can be plugged into your bots ``/pins archive`` command
+pub async fn pins_archive<Data: HasPins + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    channel: ChannelId,
+    archive: Option<ChannelId>,
+) -> Result<(), Error> {
+    if ctx.guild_id().is_none() {
+        return Err("Pin archives can only be set up in a server".into());
+    }
+
+    let data = ctx.data();
+    data.pins().set_archive(channel, archive).await?;
+
+    match archive {
+        Some(archive) => {
+            ctx.say(format!(
+                "Old pins of <#{}> will be moved to <#{}>",
+                channel, archive
+            ))
+            .await?
+        }
+        None => {
+            ctx.say(format!("Removed the pin archive of <#{}>", channel))
+                .await?
+        }
+    };
+
+    Ok(())
+}
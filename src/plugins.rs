@@ -0,0 +1,247 @@
+use indexmap::IndexMap;
+use poise::serenity_prelude::{self as serenity, FullEvent};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::sync::{Arc, RwLock};
+
+use crate::bot::EventHook;
+use crate::register::{sync_commands, RegistrationDiff};
+use crate::taskman::Task;
+use crate::Error;
+
+/// A bundle of commands, event hooks and tasks that can be toggled as one feature
+pub struct CommandGroup<Data> {
+    pub name: &'static str,
+    pub description: &'static str,
+    /// Whether the group is enabled on startup
+    pub enabled: bool,
+    pub commands: Vec<poise::Command<Data, Error>>,
+    pub events: Vec<EventHook>,
+    pub tasks: Vec<Task>,
+}
+
+struct _GroupState {
+    description: &'static str,
+    enabled: bool,
+    commands: HashSet<String>,
+    events: Arc<Vec<EventHook>>,
+}
+
+/// Registry of command groups that can be enabled and disabled at runtime
+///
+/// poise cannot add or remove commands after the framework is built, so every groups commands are
+/// registered up front and disabled groups are hidden from discord and rejected by ``command_check``.
+/// This is cheap to clone
+#[derive(Clone, Default)]
+pub struct Plugins {
+    groups: Arc<RwLock<IndexMap<&'static str, _GroupState>>>,
+}
+
+impl Plugins {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a group, returning its commands (to add to the framework) and tasks (to start as usual)
+    ///
+    /// The returned tasks skip their runs while the group is disabled
+    pub fn add<Data>(
+        &self,
+        group: CommandGroup<Data>,
+    ) -> (Vec<poise::Command<Data, Error>>, Vec<Task>) {
+        let name = group.name;
+
+        self.groups.write().unwrap().insert(
+            name,
+            _GroupState {
+                description: group.description,
+                enabled: group.enabled,
+                commands: group.commands.iter().map(|c| c.name.to_string()).collect(),
+                events: Arc::new(group.events),
+            },
+        );
+
+        let tasks = group
+            .tasks
+            .into_iter()
+            .map(|task| {
+                let plugins = self.clone();
+                let run = task.run;
+
+                Task {
+                    name: task.name,
+                    description: task.description,
+                    enabled: task.enabled,
+                    duration: task.duration,
+                    run: Box::new(move |ctx| {
+                        if plugins.is_enabled(name) {
+                            run(ctx)
+                        } else {
+                            Box::pin(async { Ok(()) })
+                        }
+                    }),
+                }
+            })
+            .collect();
+
+        (group.commands, tasks)
+    }
+
+    pub fn is_enabled(&self, group: &str) -> bool {
+        self.groups
+            .read()
+            .unwrap()
+            .get(group)
+            .is_some_and(|g| g.enabled)
+    }
+
+    /// Returns the group a top-level command belongs to
+    pub fn group_of(&self, command: &str) -> Option<&'static str> {
+        self.groups
+            .read()
+            .unwrap()
+            .iter()
+            .find(|(_, g)| g.commands.contains(command))
+            .map(|(name, _)| *name)
+    }
+
+    /// Enables or disables a group, call ``sync`` afterwards to update discord
+    pub fn set_enabled(&self, group: &str, enabled: bool) -> Result<(), Error> {
+        let mut groups = self.groups.write().unwrap();
+
+        let state = groups
+            .get_mut(group)
+            .ok_or_else(|| format!("Unknown command group ``{}``", group))?;
+
+        state.enabled = enabled;
+
+        Ok(())
+    }
+
+    /// Registers the commands of enabled groups (and commands outside any group) globally, if they changed
+    pub async fn sync<Data>(
+        &self,
+        http: &serenity::Http,
+        commands: &[poise::Command<Data, Error>],
+    ) -> Result<RegistrationDiff, Error> {
+        let enabled = commands
+            .iter()
+            .filter(|c| self.group_of(&c.name).map_or(true, |g| self.is_enabled(g)))
+            .collect::<Vec<_>>();
+
+        sync_commands(http, enabled).await
+    }
+
+    /// Runs the event hooks of enabled groups, this should be called from your bots event handler
+    pub async fn handle_event(&self, ctx: &serenity::Context, event: &FullEvent) {
+        let hooks = self
+            .groups
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|(_, g)| g.enabled)
+            .map(|(name, g)| (*name, g.events.clone()))
+            .collect::<Vec<_>>();
+
+        for (group, events) in hooks {
+            for hook in events.iter() {
+                if let Err(e) = hook(ctx, event).await {
+                    log::error!("Event hook of group {} failed: {}", group, e);
+                }
+            }
+        }
+    }
+}
+
+/// Trait for bot data that holds ``Plugins``
+pub trait HasPlugins {
+    fn plugins(&self) -> &Plugins;
+}
+
+/// Ready-made ``command_check`` that rejects commands of disabled groups
+///
+/// Discord may still show a disabled command until its global commands are synced
+pub fn command_check<Data: HasPlugins + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> futures::future::BoxFuture<'_, Result<bool, crate::Error>> {
+    Box::pin(async move {
+        let root = crate::cmdpath::root(ctx);
+
+        let data = ctx.data();
+        let plugins = data.plugins();
+
+        match plugins.group_of(&root) {
+            Some(group) if !plugins.is_enabled(group) => {
+                Err(format!("The ``{}`` feature is currently disabled", group).into())
+            }
+            _ => Ok(true),
+        }
+    })
+}
+
+/// Enables or disables a command group and syncs global commands, can be plugged into an owner-only ``/plugins toggle`` command
+pub async fn plugins_toggle<Data: HasPlugins + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    group: String,
+    enabled: bool,
+) -> Result<(), Error> {
+    let data = ctx.data();
+    let plugins = data.plugins();
+
+    plugins.set_enabled(&group, enabled)?;
+
+    let diff = plugins
+        .sync(ctx.http(), &ctx.framework().options().commands)
+        .await?;
+
+    let mut msg = format!(
+        "{} ``{}``",
+        if enabled { "Enabled" } else { "Disabled" },
+        group
+    );
+
+    if !diff.added.is_empty() {
+        let _ = write!(msg, "\nRegistered: {}", diff.added.join(", "));
+    }
+
+    if !diff.removed.is_empty() {
+        let _ = write!(msg, "\nUnregistered: {}", diff.removed.join(", "));
+    }
+
+    ctx.say(msg).await?;
+
+    Ok(())
+}
+
+/// Lists command groups and whether they are enabled, can be plugged into an owner-only ``/plugins list`` command
+pub async fn plugins_list<Data: HasPlugins + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> Result<(), Error> {
+    let msg = {
+        let data = ctx.data();
+        let groups = data.plugins().groups.read().unwrap();
+
+        let mut msg = String::new();
+
+        for (name, group) in groups.iter() {
+            let _ = writeln!(
+                msg,
+                "{} **{}** - {} ({} commands)",
+                if group.enabled { "✅" } else { "❌" },
+                name,
+                group.description,
+                group.commands.len()
+            );
+        }
+
+        msg
+    };
+
+    if msg.is_empty() {
+        ctx.say("No command groups registered").await?;
+    } else {
+        ctx.say(msg).await?;
+    }
+
+    Ok(())
+}
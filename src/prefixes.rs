@@ -0,0 +1,182 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::GuildId;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cache::TtlCache;
+use crate::i18n::{t, tr};
+use crate::Error;
+
+/// The maximum length of a custom prefix
+pub const MAX_PREFIX_LENGTH: usize = 32;
+
+/// Storage backend for per-guild prefixes
+pub trait PrefixStore: Send + Sync {
+    /// Returns the custom prefix of a guild, if any
+    fn get<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<Option<String>, Error>>;
+
+    /// Sets the custom prefix of a guild
+    fn set<'a>(&'a self, guild_id: GuildId, prefix: &'a str) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Removes the custom prefix of a guild
+    fn reset<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Per-guild prefix resolver with an in-memory cache in front of a ``PrefixStore``
+///
+/// Prefixes are cached for an hour, so changes made to the store by other processes are picked
+/// up eventually. This is cheap to clone
+#[derive(Clone)]
+pub struct DynamicPrefix {
+    store: Arc<dyn PrefixStore>,
+    default_prefix: Option<String>,
+    cache: TtlCache<GuildId, Option<String>>,
+}
+
+impl DynamicPrefix {
+    /// Creates a new ``DynamicPrefix``, ``default_prefix`` is used when a guild has no custom prefix
+    pub fn new(store: Arc<dyn PrefixStore>, default_prefix: Option<String>) -> Self {
+        Self {
+            store,
+            default_prefix,
+            cache: TtlCache::new("prefixes", Duration::from_secs(60 * 60), 50_000),
+        }
+    }
+
+    /// Returns the prefix to use in a guild
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get(&self, guild_id: Option<GuildId>) -> Result<Option<String>, Error> {
+        let Some(guild_id) = guild_id else {
+            return Ok(self.default_prefix.clone());
+        };
+
+        if let Some(prefix) = self.cache.get(&guild_id).await {
+            return Ok(prefix.or_else(|| self.default_prefix.clone()));
+        }
+
+        let prefix = self.store.get(guild_id).await?;
+
+        self.cache.insert(guild_id, prefix.clone()).await;
+
+        Ok(prefix.or_else(|| self.default_prefix.clone()))
+    }
+
+    /// Sets the prefix of a guild, updating the cache
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn set(&self, guild_id: GuildId, prefix: &str) -> Result<(), Error> {
+        validate_prefix(prefix)?;
+
+        self.store.set(guild_id, prefix).await?;
+        self.cache.insert(guild_id, Some(prefix.to_string())).await;
+
+        Ok(())
+    }
+
+    /// Resets the prefix of a guild back to the default, updating the cache
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn reset(&self, guild_id: GuildId) -> Result<(), Error> {
+        self.store.reset(guild_id).await?;
+        self.cache.insert(guild_id, None).await;
+
+        Ok(())
+    }
+
+    /// Drops the cached prefix of a guild, the next lookup will go to the store
+    pub async fn invalidate(&self, guild_id: GuildId) {
+        self.cache.remove(&guild_id).await;
+    }
+
+    /// Drops all cached prefixes
+    pub async fn invalidate_all(&self) {
+        self.cache.clear().await;
+    }
+
+    /// Returns the prefix cache, for example to add it to a ``MemoryGovernor``
+    pub fn cache(&self) -> &TtlCache<GuildId, Option<String>> {
+        &self.cache
+    }
+}
+
+/// Checks that a prefix is usable
+pub fn validate_prefix(prefix: &str) -> Result<(), Error> {
+    if prefix.is_empty() {
+        return Err(t(None, "prefix.empty", &[]).into());
+    }
+
+    if prefix.chars().count() > MAX_PREFIX_LENGTH {
+        return Err(t(
+            None,
+            "prefix.too_long",
+            &[("max", &MAX_PREFIX_LENGTH.to_string())],
+        )
+        .into());
+    }
+
+    if prefix.chars().any(char::is_whitespace) {
+        return Err(t(None, "prefix.whitespace", &[]).into());
+    }
+
+    Ok(())
+}
+
+/// Trait for bot data that holds a ``DynamicPrefix``
+pub trait HasDynamicPrefix {
+    fn dynamic_prefix(&self) -> &DynamicPrefix;
+}
+
+/// Ready-made ``dynamic_prefix`` for poise's ``PrefixFrameworkOptions``
+///
+/// Use as ``dynamic_prefix: Some(botox::prefixes::dynamic_prefix::<Data>)``
+pub fn dynamic_prefix<Data: HasDynamicPrefix + Send + Sync + 'static>(
+    ctx: poise::PartialContext<'_, Data, crate::Error>,
+) -> BoxFuture<'_, Result<Option<String>, crate::Error>> {
+    Box::pin(async move {
+        let data = ctx.framework.user_data();
+        let dp = data.dynamic_prefix().clone();
+
+        dp.get(ctx.guild_id).await
+    })
+}
+
+/// Sets the prefix of the current guild, can be plugged into your bots ``/prefix set`` command
+///
+/// Permission checks (such as Manage Server) should be set on the command itself
+pub async fn prefix_set<Data: HasDynamicPrefix + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    prefix: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err(tr(ctx, "prefix.server_only", &[]).into());
+    };
+
+    ctx.data().dynamic_prefix().set(guild_id, &prefix).await?;
+
+    ctx.say(tr(ctx, "prefix.set", &[("prefix", &prefix)]))
+        .await?;
+
+    Ok(())
+}
+
+/// Resets the prefix of the current guild, can be plugged into your bots ``/prefix reset`` command
+pub async fn prefix_reset<Data: HasDynamicPrefix + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err(tr(ctx, "prefix.server_only", &[]).into());
+    };
+
+    let data = ctx.data();
+    let dp = data.dynamic_prefix();
+
+    dp.reset(guild_id).await?;
+
+    match &dp.default_prefix {
+        Some(prefix) => {
+            ctx.say(tr(ctx, "prefix.reset_to", &[("prefix", prefix)]))
+                .await?
+        }
+        None => ctx.say(tr(ctx, "prefix.reset", &[])).await?,
+    };
+
+    Ok(())
+}
@@ -0,0 +1,236 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, CreateActionRow, CreateAttachment, CreateButton, CreateInteractionResponse,
+    CreateInteractionResponseMessage, Timestamp, UserId,
+};
+use poise::CreateReply;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::taskman::Task;
+use crate::Error;
+
+/// Something that stores data about users, such as name logs or moderation cases
+pub trait DataHolder: Send + Sync {
+    /// A short name, used as the key of the holders data in exports
+    fn name(&self) -> &str;
+
+    /// Returns everything stored about a user
+    fn export_user<'a>(
+        &'a self,
+        user_id: UserId,
+    ) -> BoxFuture<'a, Result<serde_json::Value, Error>>;
+
+    /// Deletes everything stored about a user, returning the number of records deleted
+    fn erase_user<'a>(&'a self, user_id: UserId) -> BoxFuture<'a, Result<usize, Error>>;
+
+    /// Deletes records created before ``before``, returning the number of records deleted
+    fn prune_before<'a>(&'a self, before: Timestamp) -> BoxFuture<'a, Result<usize, Error>>;
+}
+
+/// The outcome of an erasure or retention run, per data holder
+#[derive(Debug, Clone, Default)]
+pub struct PrivacyReport {
+    /// Holder name and the number of records deleted
+    pub deleted: Vec<(String, usize)>,
+    /// Holder name and the error it failed with
+    pub failed: Vec<(String, String)>,
+}
+
+impl PrivacyReport {
+    pub fn total(&self) -> usize {
+        self.deleted.iter().map(|(_, n)| n).sum()
+    }
+}
+
+struct _Holder {
+    holder: Arc<dyn DataHolder>,
+    retention: Option<Duration>,
+}
+
+/// Registry of data holders for GDPR style exports and erasure, and scheduled data retention
+#[derive(Default)]
+pub struct Privacy {
+    holders: Vec<_Holder>,
+}
+
+impl Privacy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a data holder, ``retention`` is how long its records are kept (None to keep them forever)
+    pub fn holder(mut self, holder: Arc<dyn DataHolder>, retention: Option<Duration>) -> Self {
+        self.holders.push(_Holder { holder, retention });
+        self
+    }
+
+    /// Returns everything every holder stores about a user, keyed by holder name
+    pub async fn export_user(&self, user_id: UserId) -> Result<serde_json::Value, Error> {
+        let mut export = serde_json::Map::new();
+
+        for h in self.holders.iter() {
+            export.insert(
+                h.holder.name().to_string(),
+                h.holder.export_user(user_id).await?,
+            );
+        }
+
+        Ok(serde_json::Value::Object(export))
+    }
+
+    /// Deletes everything every holder stores about a user
+    ///
+    /// A failing holder does not stop the others, check ``PrivacyReport::failed``
+    pub async fn erase_user(&self, user_id: UserId) -> PrivacyReport {
+        let mut report = PrivacyReport::default();
+
+        for h in self.holders.iter() {
+            let name = h.holder.name().to_string();
+
+            match h.holder.erase_user(user_id).await {
+                Ok(n) => report.deleted.push((name, n)),
+                Err(e) => {
+                    log::error!("Failed to erase {} from {}: {}", user_id, name, e);
+                    report.failed.push((name, e.to_string()));
+                }
+            }
+        }
+
+        report
+    }
+
+    /// Prunes every holder that has a retention period
+    pub async fn run_retention(&self) -> PrivacyReport {
+        let mut report = PrivacyReport::default();
+        let now = Timestamp::now().unix_timestamp();
+
+        for h in self.holders.iter() {
+            let Some(retention) = h.retention else {
+                continue;
+            };
+
+            let name = h.holder.name().to_string();
+            let before = Timestamp::from_unix_timestamp(now - retention.as_secs() as i64)
+                .unwrap_or_else(|_| Timestamp::now());
+
+            match h.holder.prune_before(before).await {
+                Ok(n) => report.deleted.push((name, n)),
+                Err(e) => {
+                    log::error!("Retention failed for {}: {}", name, e);
+                    report.failed.push((name, e.to_string()));
+                }
+            }
+        }
+
+        report
+    }
+}
+
+/// Returns a task that prunes data past its retention period, checking every ``interval``
+pub fn retention_task(privacy: Arc<Privacy>, interval: Duration) -> Task {
+    Task {
+        name: "privacy_retention",
+        description: "Prunes user data older than its retention period",
+        enabled: true,
+        duration: interval,
+        run: Box::new(move |_ctx| {
+            let privacy = privacy.clone();
+            Box::pin(async move {
+                let report = privacy.run_retention().await;
+
+                if report.total() > 0 {
+                    log::info!("Retention pruned {} records", report.total());
+                }
+
+                match report.failed.first() {
+                    Some((name, e)) => Err(format!("Retention failed for {}: {}", name, e).into()),
+                    None => Ok(()),
+                }
+            })
+        }),
+    }
+}
+
+/// Trait for bot data that holds a ``Privacy``
+pub trait HasPrivacy {
+    fn privacy(&self) -> &Privacy;
+}
+
+/// Sends the user a JSON export of their data, can be plugged into your bots ``/privacy export`` command
+pub async fn privacy_export<Data: HasPrivacy + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> Result<(), Error> {
+    ctx.defer_ephemeral().await?;
+
+    let data = ctx.data();
+    let export = data.privacy().export_user(ctx.author().id).await?;
+
+    ctx.send(
+        CreateReply::default()
+            .content("Here is everything this bot stores about you")
+            .attachment(CreateAttachment::bytes(
+                serde_json::to_vec_pretty(&export)?,
+                format!("data-{}.json", ctx.author().id),
+            ))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Erases the users data after a confirmation, can be plugged into your bots ``/privacy erase`` command
+pub async fn privacy_erase<Data: HasPrivacy + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> Result<(), Error> {
+    let msg = ctx
+        .send(
+            CreateReply::default()
+                .content(
+                    "This permanently deletes everything this bot stores about you. Are you sure?",
+                )
+                .components(vec![CreateActionRow::Buttons(vec![CreateButton::new(
+                    "privacy:erase",
+                )
+                .label("Erase my data")
+                .style(serenity::ButtonStyle::Danger)])])
+                .ephemeral(true),
+        )
+        .await?
+        .into_message()
+        .await?;
+
+    let Some(item) = msg
+        .await_component_interaction(ctx.serenity_context().shard.clone())
+        .author_id(ctx.author().id)
+        .timeout(Duration::from_secs(60))
+        .await
+    else {
+        return Ok(());
+    };
+
+    let data = ctx.data();
+    let report = data.privacy().erase_user(ctx.author().id).await;
+
+    let content = if report.failed.is_empty() {
+        format!("Deleted {} records", report.total())
+    } else {
+        format!(
+            "Deleted {} records, but some data could not be erased. Please contact the bot owner",
+            report.total()
+        )
+    };
+
+    item.create_response(
+        ctx.http(),
+        CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content(content)
+                .components(vec![]),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, ChannelType, CreateActionRow, CreateButton, CreateEmbed,
+    CreateEmbedAuthor, CreateEmbedFooter, GuildId, Message, MessageId, Permissions,
+};
+use poise::CreateReply;
+
+use crate::i18n::{t, tr};
+use crate::permissions::PermissionInputs;
+use crate::Error;
+
+/// Maximum length of an embed field value
+const MAX_FIELD_VALUE: usize = 1024;
+
+/// A parsed message link
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MessageLink {
+    /// None for DM links
+    pub guild_id: Option<GuildId>,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+}
+
+/// Parses a message link such as ``https://discord.com/channels/1/2/3``, including ptb/canary and discordapp.com links
+pub fn parse_message_link(link: &str) -> Option<MessageLink> {
+    let link = link.trim().trim_start_matches('<').trim_end_matches('>');
+    let path = link
+        .strip_prefix("https://")
+        .or_else(|| link.strip_prefix("http://"))?;
+
+    let (host, path) = path.split_once('/')?;
+
+    if !matches!(
+        host,
+        "discord.com"
+            | "ptb.discord.com"
+            | "canary.discord.com"
+            | "discordapp.com"
+            | "ptb.discordapp.com"
+            | "canary.discordapp.com"
+    ) {
+        return None;
+    }
+
+    let mut parts = path.strip_prefix("channels/")?.split('/');
+    let (guild, channel, message) = (parts.next()?, parts.next()?, parts.next()?);
+
+    let guild_id = match guild {
+        "@me" => None,
+        id => Some(GuildId::new(id.parse().ok().filter(|id| *id != 0)?)),
+    };
+
+    Some(MessageLink {
+        guild_id,
+        channel_id: ChannelId::new(channel.parse().ok().filter(|id| *id != 0)?),
+        message_id: MessageId::new(message.parse().ok().filter(|id| *id != 0)?),
+    })
+}
+
+/// Renders a message as a quote embed with a jump button
+pub fn render_quote(
+    msg: &Message,
+    locale: Option<&str>,
+) -> (CreateEmbed<'static>, CreateActionRow<'static>) {
+    let mut embed = CreateEmbed::default()
+        .author(
+            CreateEmbedAuthor::new(msg.author.display_name().to_string())
+                .icon_url(msg.author.face()),
+        )
+        .description(msg.content.to_string())
+        .timestamp(msg.timestamp)
+        .footer(CreateEmbedFooter::new(format!("#{}", msg.channel_id)));
+
+    // The first image is previewed, everything else is listed
+    let image = msg.attachments.iter().find(|a| {
+        a.content_type
+            .as_deref()
+            .is_some_and(|t| t.starts_with("image/"))
+    });
+
+    if let Some(image) = image {
+        embed = embed.image(image.url.to_string());
+    }
+
+    let others = msg
+        .attachments
+        .iter()
+        .filter(|a| !image.is_some_and(|i| i.id == a.id))
+        .collect::<Vec<_>>();
+
+    let mut listed = String::new();
+
+    for (i, attachment) in others.iter().enumerate() {
+        let line = format!("[{}]({})\n", attachment.filename, attachment.url);
+
+        // Leave room for the "and n more" line
+        if listed.chars().count() + line.chars().count() > MAX_FIELD_VALUE - 20 {
+            listed.push_str(&t(
+                locale,
+                "quote.more_attachments",
+                &[("count", &(others.len() - i).to_string())],
+            ));
+            break;
+        }
+
+        listed.push_str(&line);
+    }
+
+    if !listed.is_empty() {
+        embed = embed.field(t(locale, "quote.attachments", &[]), listed, false);
+    }
+
+    let jump = CreateButton::new_link(msg.link()).label(t(locale, "quote.jump", &[]));
+
+    (embed, CreateActionRow::Buttons(vec![jump]))
+}
+
+/// Quotes a message from its link, can be plugged into your bots ``/quote`` command
+///
+/// The invoker must be able to view the source channel and read its history, and be a member
+/// of it if it is a private thread (which needs the Server Members intent to check)
+pub async fn quote_message<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    message_link: String,
+) -> Result<(), Error> {
+    let Some(link) = parse_message_link(&message_link) else {
+        return Err(tr(ctx, "quote.invalid_link", &[]).into());
+    };
+
+    let Some(guild_id) = link.guild_id else {
+        return Err(tr(ctx, "quote.dm", &[]).into());
+    };
+
+    let member = guild_id
+        .member(ctx.http(), ctx.author().id)
+        .await
+        .map_err(|_| tr(ctx, "quote.not_member", &[]))?;
+
+    // The link can pair any guild with any channel, so the channel has to belong to that guild
+    let channel = ctx
+        .http()
+        .get_channel(link.channel_id)
+        .await
+        .ok()
+        .and_then(|c| c.guild())
+        .filter(|c| c.guild_id == guild_id)
+        .ok_or_else(|| tr(ctx, "quote.not_found", &[]))?;
+
+    let is_thread = matches!(
+        channel.kind,
+        ChannelType::PublicThread | ChannelType::PrivateThread | ChannelType::NewsThread
+    );
+
+    // Threads (including archived ones, which are not cached) use the permissions of their parent
+    let permission_channel = match channel.parent_id {
+        Some(parent) if is_thread => parent,
+        _ => channel.id,
+    };
+
+    let Some(inputs) = PermissionInputs::from_cache(
+        ctx.cache(),
+        guild_id,
+        ctx.author().id,
+        &member.roles,
+        Some(permission_channel),
+    ) else {
+        return Err(tr(ctx, "quote.bot_no_access", &[]).into());
+    };
+
+    let needed = Permissions::VIEW_CHANNEL | Permissions::READ_MESSAGE_HISTORY;
+
+    if !inputs.effective().contains(needed) {
+        return Err(tr(ctx, "quote.no_access", &[]).into());
+    }
+
+    if channel.kind == ChannelType::PrivateThread
+        && !inputs
+            .explain(Permissions::MANAGE_THREADS, "Manage Threads")
+            .allowed
+    {
+        let members = channel.id.get_thread_members(ctx.http()).await?;
+
+        if !members.iter().any(|m| m.user_id == ctx.author().id) {
+            return Err(tr(ctx, "quote.no_access", &[]).into());
+        }
+    }
+
+    let msg = link
+        .channel_id
+        .message(ctx.http(), link.message_id)
+        .await
+        .map_err(|_| tr(ctx, "quote.not_found", &[]))?;
+
+    let (embed, row) = render_quote(&msg, ctx.locale());
+
+    ctx.send(
+        CreateReply::default()
+            .embed(embed)
+            .components(vec![row])
+            .allowed_mentions(crate::sanitize::MentionPolicy::none().build()),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,327 @@
+use poise::serenity_prelude::{
+    self as serenity, EditGuild, FullEvent, GuildId, Member, Timestamp, UserId, VerificationLevel,
+};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Mutex};
+
+use crate::features::{Feature, FeatureMatrix};
+use crate::i18n::tr;
+use crate::reason::{Reason, ReasonExt};
+use crate::Error;
+
+const INVITES_DISABLED: &str = "INVITES_DISABLED";
+
+pub struct RaidConfig {
+    /// How many joins within ``interval`` count as a raid
+    pub max_joins: usize,
+    pub interval: Duration,
+    /// Accounts younger than this are flagged
+    pub min_account_age: Duration,
+    /// Verification level set while panic mode is on
+    pub panic_verification_level: VerificationLevel,
+    /// Whether to pause invites while panic mode is on
+    pub pause_invites: bool,
+    /// Whether to kick flagged accounts that join while panic mode is on
+    pub kick_flagged: bool,
+}
+
+impl Default for RaidConfig {
+    fn default() -> Self {
+        Self {
+            max_joins: 10,
+            interval: Duration::from_secs(10),
+            min_account_age: Duration::from_secs(7 * 24 * 60 * 60),
+            panic_verification_level: VerificationLevel::Higher,
+            pause_invites: true,
+            kick_flagged: false,
+        }
+    }
+}
+
+/// Notifications sent to subscribers of a ``RaidGuard``
+#[derive(Debug, Clone)]
+pub enum RaidEvent {
+    /// A join spike was detected
+    Detected {
+        guild_id: GuildId,
+        joins: usize,
+    },
+    PanicEnabled {
+        guild_id: GuildId,
+        manual: bool,
+    },
+    PanicDisabled {
+        guild_id: GuildId,
+    },
+    /// A flagged account was kicked during panic mode
+    Kicked {
+        guild_id: GuildId,
+        user_id: UserId,
+    },
+}
+
+// What panic mode changed, so it can be undone
+struct PanicState {
+    previous_verification_level: VerificationLevel,
+    paused_invites: bool,
+}
+
+/// Detects join spikes and puts guilds into panic mode
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct RaidGuard {
+    config: Arc<RaidConfig>,
+    joins: Arc<Mutex<HashMap<GuildId, VecDeque<Instant>>>>,
+    panics: Arc<Mutex<HashMap<GuildId, PanicState>>>,
+    events: broadcast::Sender<RaidEvent>,
+    /// Joins are not tracked in guilds that disabled ``Feature::AntiRaid``, if set
+    pub features: Option<FeatureMatrix>,
+}
+
+impl RaidGuard {
+    pub fn new(config: RaidConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            joins: Arc::new(Mutex::new(HashMap::new())),
+            panics: Arc::new(Mutex::new(HashMap::new())),
+            events: broadcast::channel(64).0,
+            features: None,
+        }
+    }
+
+    /// Subscribes to raid notifications
+    pub fn subscribe(&self) -> broadcast::Receiver<RaidEvent> {
+        self.events.subscribe()
+    }
+
+    fn _notify(&self, event: RaidEvent) {
+        // No subscribers is fine
+        let _ = self.events.send(event);
+    }
+
+    pub async fn is_panicking(&self, guild_id: GuildId) -> bool {
+        self.panics.lock().await.contains_key(&guild_id)
+    }
+
+    /// Returns whether a member looks suspicious, currently based on account age
+    pub fn is_flagged(&self, member: &Member) -> bool {
+        let age = Timestamp::now().unix_timestamp() - member.user.id.created_at().unix_timestamp();
+        age < self.config.min_account_age.as_secs() as i64
+    }
+
+    /// Records a join, returning the number of joins in the interval if this is a spike
+    async fn _record_join(&self, guild_id: GuildId) -> Option<usize> {
+        let now = Instant::now();
+        let mut joins = self.joins.lock().await;
+        let times = joins.entry(guild_id).or_default();
+
+        times.push_back(now);
+
+        while times
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > self.config.interval)
+        {
+            times.pop_front();
+        }
+
+        (times.len() >= self.config.max_joins).then_some(times.len())
+    }
+
+    /// Handles member joins, enabling panic mode on join spikes
+    ///
+    /// This should be called from your bots event handler, requires the ``GUILD_MEMBERS`` intent
+    pub async fn handle_event(
+        &self,
+        ctx: &serenity::Context,
+        event: &FullEvent,
+    ) -> Result<(), Error> {
+        if !crate::features::allows(self.features.as_ref(), event, Feature::AntiRaid).await {
+            return Ok(());
+        }
+
+        let FullEvent::GuildMemberAddition { new_member } = event else {
+            return Ok(());
+        };
+
+        let guild_id = new_member.guild_id;
+
+        if self.is_panicking(guild_id).await {
+            if self.config.kick_flagged && self.is_flagged(new_member) {
+                guild_id
+                    .kick_with_reason(
+                        &ctx.http,
+                        new_member.user.id,
+                        "Raid protection: flagged account",
+                    )
+                    .await?;
+
+                self._notify(RaidEvent::Kicked {
+                    guild_id,
+                    user_id: new_member.user.id,
+                });
+            }
+
+            return Ok(());
+        }
+
+        if let Some(joins) = self._record_join(guild_id).await {
+            log::warn!("Raid detected in {}: {} joins", guild_id, joins);
+
+            self._notify(RaidEvent::Detected { guild_id, joins });
+            self.panic_on(
+                &ctx.http,
+                guild_id,
+                false,
+                &Reason::new(format!("Raid detected: {} joins", joins)),
+            )
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Enables panic mode, raising the verification level and pausing invites
+    pub async fn panic_on(
+        &self,
+        http: &serenity::Http,
+        guild_id: GuildId,
+        manual: bool,
+        reason: &Reason,
+    ) -> Result<(), Error> {
+        let mut panics = self.panics.lock().await;
+
+        if panics.contains_key(&guild_id) {
+            return Ok(());
+        }
+
+        let guild = http.get_guild(guild_id).await?;
+
+        let mut edit = EditGuild::new()
+            .verification_level(self.config.panic_verification_level)
+            .audit_log_reason(reason.as_str());
+
+        let paused_invites =
+            self.config.pause_invites && !guild.features.iter().any(|f| &**f == INVITES_DISABLED);
+
+        if paused_invites {
+            let mut features = guild
+                .features
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>();
+            features.push(INVITES_DISABLED.to_string());
+            edit = edit.features(features);
+        }
+
+        guild_id.edit(http, edit).await?;
+
+        panics.insert(
+            guild_id,
+            PanicState {
+                previous_verification_level: guild.verification_level,
+                paused_invites,
+            },
+        );
+
+        log::warn!("Panic mode enabled in {} (manual: {})", guild_id, manual);
+        self._notify(RaidEvent::PanicEnabled { guild_id, manual });
+
+        Ok(())
+    }
+
+    /// Disables panic mode, restoring the verification level and invites
+    pub async fn panic_off(
+        &self,
+        http: &serenity::Http,
+        guild_id: GuildId,
+        reason: &Reason,
+    ) -> Result<(), Error> {
+        let mut panics = self.panics.lock().await;
+
+        // The state is only dropped once the guild is restored, so a failed edit can be retried
+        let Some(state) = panics.get(&guild_id) else {
+            return Ok(());
+        };
+
+        let mut edit = EditGuild::new()
+            .verification_level(state.previous_verification_level)
+            .audit_log_reason(reason.as_str());
+
+        if state.paused_invites {
+            let guild = http.get_guild(guild_id).await?;
+
+            edit = edit.features(
+                guild
+                    .features
+                    .iter()
+                    .filter(|f| &***f != INVITES_DISABLED)
+                    .map(|f| f.to_string())
+                    .collect::<Vec<_>>(),
+            );
+        }
+
+        guild_id.edit(http, edit).await?;
+
+        panics.remove(&guild_id);
+        drop(panics);
+
+        self.joins.lock().await.remove(&guild_id);
+
+        log::info!("Panic mode disabled in {}", guild_id);
+        self._notify(RaidEvent::PanicDisabled { guild_id });
+
+        Ok(())
+    }
+}
+
+/// Trait for bot data that holds a ``RaidGuard``
+pub trait HasRaidGuard {
+    fn raid_guard(&self) -> &RaidGuard;
+}
+
+/// Whether to turn panic mode on or off
+#[derive(poise::ChoiceParameter, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PanicToggle {
+    On,
+    Off,
+}
+
+/// Manually toggles panic mode, can be plugged into your bots ``/panic on|off`` command
+///
+/// Permission checks (such as Manage Server) should be set on the command itself
+pub async fn panic<Data: HasRaidGuard + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    toggle: PanicToggle,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err(tr(ctx, "raid.server_only", &[]).into());
+    };
+
+    let data = ctx.data();
+    let guard = data.raid_guard();
+
+    match toggle {
+        PanicToggle::On => {
+            guard
+                .panic_on(
+                    ctx.http(),
+                    guild_id,
+                    true,
+                    &ctx.reason("Panic mode enabled"),
+                )
+                .await?;
+            ctx.say(tr(ctx, "raid.enabled", &[])).await?;
+        }
+        PanicToggle::Off => {
+            guard
+                .panic_off(ctx.http(), guild_id, &ctx.reason("Panic mode disabled"))
+                .await?;
+            ctx.say(tr(ctx, "raid.disabled", &[])).await?;
+        }
+    }
+
+    Ok(())
+}
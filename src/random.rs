@@ -0,0 +1,142 @@
+use poise::CreateReply;
+use rand::distributions::WeightedIndex;
+use rand::prelude::Distribution;
+use rand::rngs::{OsRng, StdRng};
+use rand::seq::SliceRandom;
+use rand::{RngCore, SeedableRng};
+
+use crate::sanitize::{escape_markdown, MentionPolicy};
+use crate::Error;
+
+/// Maximum number of options accepted by ``choose``
+const MAX_CHOICES: usize = 100;
+
+/// A fair random picker with a recorded seed
+///
+/// Every picker is seeded, so a draw can be audited by replaying it with ``Picker::from_hex`` and the
+/// same inputs. Seeds are taken from the OS by default, making draws unpredictable
+pub struct Picker {
+    rng: StdRng,
+    seed: [u8; 32],
+}
+
+impl Default for Picker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Picker {
+    /// Creates a picker with a seed from the OS random number generator
+    pub fn new() -> Self {
+        let mut seed = [0u8; 32];
+        OsRng.fill_bytes(&mut seed);
+        Self::from_seed(seed)
+    }
+
+    /// Creates a picker from a known seed, picks with the same inputs are reproducible
+    pub fn from_seed(seed: [u8; 32]) -> Self {
+        Self {
+            rng: StdRng::from_seed(seed),
+            seed,
+        }
+    }
+
+    /// Creates a picker from a seed returned by ``Picker::seed_hex``
+    pub fn from_hex(hex: &str) -> Result<Self, Error> {
+        if hex.len() != 64 || !hex.is_ascii() {
+            return Err("Seeds must be 64 hex characters".into());
+        }
+
+        let mut seed = [0u8; 32];
+
+        for (i, byte) in seed.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+        }
+
+        Ok(Self::from_seed(seed))
+    }
+
+    /// Returns the seed as hex, record this to allow draws to be audited
+    pub fn seed_hex(&self) -> String {
+        self.seed.iter().map(|b| format!("{:02x}", b)).collect()
+    }
+
+    /// Picks one item, None if there are no items
+    pub fn pick_one<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        items.choose(&mut self.rng)
+    }
+
+    /// Picks up to ``n`` distinct items, in random order
+    pub fn pick_n_unique<'a, T>(&mut self, items: &'a [T], n: usize) -> Vec<&'a T> {
+        items.choose_multiple(&mut self.rng, n).collect()
+    }
+
+    /// Picks one item with a probability proportional to its weight
+    ///
+    /// None if there are no items or every weight is 0
+    pub fn pick_weighted<'a, T>(&mut self, items: &'a [(T, u64)]) -> Option<&'a T> {
+        let dist = WeightedIndex::new(items.iter().map(|(_, w)| *w)).ok()?;
+        Some(&items[dist.sample(&mut self.rng)].0)
+    }
+
+    /// Shuffles items in place
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        items.shuffle(&mut self.rng);
+    }
+}
+
+/// Picks one item with a fresh ``Picker``
+pub fn pick_one<T>(items: &[T]) -> Option<&T> {
+    Picker::new().pick_one(items)
+}
+
+/// Picks up to ``n`` distinct items with a fresh ``Picker``
+pub fn pick_n_unique<T>(items: &[T], n: usize) -> Vec<&T> {
+    Picker::new().pick_n_unique(items, n)
+}
+
+/// Picks one weighted item with a fresh ``Picker``
+pub fn pick_weighted<T>(items: &[(T, u64)]) -> Option<&T> {
+    Picker::new().pick_weighted(items)
+}
+
+/// Shuffles items with a fresh ``Picker``
+pub fn shuffle<T>(items: &mut [T]) {
+    Picker::new().shuffle(items)
+}
+
+/// Picks one of several comma or ``|`` separated options, can be plugged into your bots ``/choose`` command
+pub async fn choose<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    options: String,
+) -> Result<(), Error> {
+    let sep = if options.contains('|') { '|' } else { ',' };
+
+    let options = options
+        .split(sep)
+        .map(str::trim)
+        .filter(|o| !o.is_empty())
+        .collect::<Vec<_>>();
+
+    if options.len() < 2 {
+        return Err("Give at least two options, separated by commas".into());
+    }
+
+    if options.len() > MAX_CHOICES {
+        return Err(format!("You can give at most {} options", MAX_CHOICES).into());
+    }
+
+    let Some(choice) = pick_one(&options) else {
+        return Ok(());
+    };
+
+    ctx.send(
+        CreateReply::default()
+            .content(format!("I choose **{}**", escape_markdown(choice)))
+            .allowed_mentions(MentionPolicy::none().build()),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+use poise::serenity_prelude::User;
+
+/// Maximum length of an audit log reason
+pub const MAX_REASON_LENGTH: usize = 512;
+
+/// An audit log reason, truncated and cleaned up to fit discords limits
+///
+/// serenity takes care of encoding the ``X-Audit-Log-Reason`` header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reason(String);
+
+impl Reason {
+    /// Creates a reason, replacing newlines and control characters and truncating it to ``MAX_REASON_LENGTH``
+    pub fn new(text: impl AsRef<str>) -> Self {
+        let cleaned = text
+            .as_ref()
+            .chars()
+            .map(|c| if c.is_control() { ' ' } else { c })
+            .collect::<String>();
+
+        let cleaned = cleaned.trim();
+
+        if cleaned.chars().count() <= MAX_REASON_LENGTH {
+            return Self(cleaned.to_string());
+        }
+
+        let mut truncated = cleaned
+            .chars()
+            .take(MAX_REASON_LENGTH - 1)
+            .collect::<String>();
+        truncated.push('…');
+
+        Self(truncated)
+    }
+
+    /// Creates a ``Requested by @user: text`` reason
+    pub fn requested_by(user: &User, text: impl AsRef<str>) -> Self {
+        let text = text.as_ref();
+
+        if text.is_empty() {
+            return Self::new(format!("Requested by @{}", user.name));
+        }
+
+        Self::new(format!("Requested by @{}: {}", user.name, text))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for Reason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Reason {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+/// Builds audit log reasons from the invoking context
+pub trait ReasonExt {
+    /// Returns a ``Requested by @author: text`` reason
+    fn reason(&self, text: impl AsRef<str>) -> Reason;
+}
+
+impl<Data: Send + Sync + 'static> ReasonExt for poise::Context<'_, Data, crate::Error> {
+    fn reason(&self, text: impl AsRef<str>) -> Reason {
+        Reason::requested_by(self.author(), text)
+    }
+}
@@ -0,0 +1,183 @@
+use poise::serenity_prelude::{self as serenity, Permissions};
+use std::collections::HashMap;
+
+use crate::Error;
+
+/// A command whose permissions on discord do not match what was declared
+#[derive(Debug, Clone)]
+pub struct PermissionDrift {
+    pub command: String,
+    pub expected: Option<Permissions>,
+    pub actual: Option<Permissions>,
+}
+
+/// Adds the permissions required by each commands category to its ``default_member_permissions``
+///
+/// ``requirements`` maps a category name to the permissions every command in it needs
+pub fn apply_category_permissions<U, E>(
+    commands: &mut [poise::Command<U, E>],
+    requirements: &HashMap<String, Permissions>,
+) {
+    for command in commands {
+        let Some(required) = command
+            .category
+            .as_deref()
+            .and_then(|c| requirements.get(c))
+        else {
+            continue;
+        };
+
+        command.default_member_permissions |= *required;
+    }
+}
+
+fn _expected<U, E>(commands: &[poise::Command<U, E>]) -> HashMap<String, Option<Permissions>> {
+    let mut expected = HashMap::new();
+
+    for command in commands {
+        let permissions = (!command.default_member_permissions.is_empty())
+            .then_some(command.default_member_permissions);
+
+        if command.slash_action.is_some() || !command.subcommands.is_empty() {
+            expected.insert(command.name.to_string(), permissions);
+        }
+
+        if let Some(name) = &command.context_menu_name {
+            expected.insert(name.to_string(), permissions);
+        }
+    }
+
+    expected
+}
+
+/// Compares the declared permissions of commands against what discord has for the registered global commands
+pub async fn verify_permissions<U, E>(
+    http: &serenity::Http,
+    commands: &[poise::Command<U, E>],
+) -> Result<Vec<PermissionDrift>, Error> {
+    let expected = _expected(commands);
+    let registered = http.get_global_commands().await?;
+
+    let mut drift = Vec::new();
+
+    for (name, expected) in expected {
+        let Some(registered) = registered.iter().find(|c| *c.name == *name) else {
+            drift.push(PermissionDrift {
+                command: name,
+                expected,
+                actual: None,
+            });
+            continue;
+        };
+
+        let actual = registered
+            .default_member_permissions
+            .filter(|p| !p.is_empty());
+
+        if actual != expected {
+            drift.push(PermissionDrift {
+                command: name,
+                expected,
+                actual,
+            });
+        }
+    }
+
+    drift.sort_by(|a, b| a.command.cmp(&b.command));
+
+    Ok(drift)
+}
+
+/// Applies category requirements, registers the commands globally and verifies that discord reflects the declared permissions
+///
+/// Any drift is logged and returned
+pub async fn permissions_sync<U, E>(
+    http: &serenity::Http,
+    commands: &mut [poise::Command<U, E>],
+    requirements: &HashMap<String, Permissions>,
+) -> Result<Vec<PermissionDrift>, Error> {
+    apply_category_permissions(commands, requirements);
+
+    poise::builtins::register_globally(http, commands).await?;
+
+    let drift = verify_permissions(http, commands).await?;
+
+    for d in &drift {
+        log::warn!(
+            "Permission drift on command {}: expected {:?}, discord has {:?}",
+            d.command,
+            d.expected,
+            d.actual
+        );
+    }
+
+    Ok(drift)
+}
+
+/// Global commands added and removed by ``sync_commands``
+#[derive(Debug, Clone, Default)]
+pub struct RegistrationDiff {
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+impl RegistrationDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+}
+
+/// Registers commands globally only if the set of registered command names differs
+///
+/// Only names are compared, use ``poise::builtins::register_globally`` after changing a commands options
+pub async fn sync_commands<'a, U: 'a, E: 'a>(
+    http: &serenity::Http,
+    commands: impl IntoIterator<Item = &'a poise::Command<U, E>>,
+) -> Result<RegistrationDiff, Error> {
+    let mut builders = Vec::new();
+    let mut desired = Vec::new();
+
+    for command in commands {
+        if let Some(slash) = command.create_as_slash_command() {
+            builders.push(slash);
+            desired.push(command.name.to_string());
+        }
+
+        if let Some(context_menu) = command.create_as_context_menu_command() {
+            builders.push(context_menu);
+            desired.push(
+                command
+                    .context_menu_name
+                    .as_deref()
+                    .unwrap_or(&command.name)
+                    .to_string(),
+            );
+        }
+    }
+
+    let registered = http
+        .get_global_commands()
+        .await?
+        .into_iter()
+        .map(|c| c.name.to_string())
+        .collect::<Vec<_>>();
+
+    let diff = RegistrationDiff {
+        added: desired
+            .iter()
+            .filter(|n| !registered.contains(n))
+            .cloned()
+            .collect(),
+        removed: registered
+            .iter()
+            .filter(|n| !desired.contains(n))
+            .cloned()
+            .collect(),
+    };
+
+    if !diff.is_empty() {
+        serenity::Command::set_global_commands(http, &builders).await?;
+    }
+
+    Ok(diff)
+}
@@ -0,0 +1,379 @@
+use poise::serenity_prelude as serenity;
+use rand::Rng;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::Error;
+
+/// The state of a circuit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Calls go through as normal
+    Closed,
+    /// Calls fail immediately
+    Open,
+    /// A limited number of probe calls go through to test whether the service has recovered
+    HalfOpen,
+}
+
+impl CircuitState {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CircuitState::Closed => "closed",
+            CircuitState::Open => "open",
+            CircuitState::HalfOpen => "half_open",
+        }
+    }
+}
+
+/// Returned instead of calling a service whose circuit is open
+#[derive(Debug, Clone)]
+pub struct CircuitOpen {
+    pub service: String,
+}
+
+impl std::fmt::Display for CircuitOpen {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} is currently unavailable, please try again later",
+            self.service
+        )
+    }
+}
+
+impl std::error::Error for CircuitOpen {}
+
+pub struct BreakerConfig {
+    /// Number of recent calls the failure rate is computed over
+    pub window: usize,
+    /// Minimum number of calls in the window before the circuit can open
+    pub min_calls: usize,
+    /// Failure rate (0.0 to 1.0) at which the circuit opens
+    pub failure_rate: f64,
+    /// How long the circuit stays open before probing
+    pub open_for: Duration,
+    /// Number of successful probes needed to close the circuit again
+    pub half_open_probes: usize,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            window: 20,
+            min_calls: 5,
+            failure_rate: 0.5,
+            open_for: Duration::from_secs(30),
+            half_open_probes: 2,
+        }
+    }
+}
+
+struct ServiceState {
+    state: CircuitState,
+    /// Outcomes of recent calls, true for success
+    recent: VecDeque<bool>,
+    opened_at: Option<Instant>,
+    probes_in_flight: usize,
+    probe_successes: usize,
+}
+
+impl Default for ServiceState {
+    fn default() -> Self {
+        Self {
+            state: CircuitState::Closed,
+            recent: VecDeque::new(),
+            opened_at: None,
+            probes_in_flight: 0,
+            probe_successes: 0,
+        }
+    }
+}
+
+/// Per-service circuit breaker, so a dead dependency fails fast instead of piling up timeouts
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct CircuitBreaker {
+    config: Arc<BreakerConfig>,
+    services: Arc<Mutex<HashMap<String, ServiceState>>>,
+}
+
+impl CircuitBreaker {
+    pub fn new(config: BreakerConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            services: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    fn _transition(service: &str, state: &mut ServiceState, to: CircuitState) {
+        if state.state == to {
+            return;
+        }
+
+        log::info!(
+            "Circuit for {} is now {} (was {})",
+            service,
+            to.label(),
+            state.state.label()
+        );
+
+        #[cfg(feature = "otel")]
+        crate::telemetry::circuit_transition_counter().add(
+            1,
+            &[
+                opentelemetry::KeyValue::new("service", service.to_string()),
+                opentelemetry::KeyValue::new("state", to.label()),
+            ],
+        );
+
+        state.state = to;
+        state.probes_in_flight = 0;
+        state.probe_successes = 0;
+
+        match to {
+            CircuitState::Open => state.opened_at = Some(Instant::now()),
+            CircuitState::Closed => {
+                state.opened_at = None;
+                state.recent.clear();
+            }
+            CircuitState::HalfOpen => {}
+        }
+    }
+
+    // Returns whether a call may go through, moving open circuits to half open once their time is up
+    fn _before(&self, service: &str) -> Result<(), CircuitOpen> {
+        let mut services = self.services.lock().unwrap();
+        let state = services.entry(service.to_string()).or_default();
+
+        if state.state == CircuitState::Open
+            && state
+                .opened_at
+                .is_some_and(|t| t.elapsed() >= self.config.open_for)
+        {
+            Self::_transition(service, state, CircuitState::HalfOpen);
+        }
+
+        match state.state {
+            CircuitState::Closed => Ok(()),
+            CircuitState::HalfOpen if state.probes_in_flight < self.config.half_open_probes => {
+                state.probes_in_flight += 1;
+                Ok(())
+            }
+            _ => Err(CircuitOpen {
+                service: service.to_string(),
+            }),
+        }
+    }
+
+    fn _after(&self, service: &str, success: bool) {
+        let mut services = self.services.lock().unwrap();
+        let state = services.entry(service.to_string()).or_default();
+
+        match state.state {
+            CircuitState::HalfOpen => {
+                state.probes_in_flight = state.probes_in_flight.saturating_sub(1);
+
+                if !success {
+                    Self::_transition(service, state, CircuitState::Open);
+                    return;
+                }
+
+                state.probe_successes += 1;
+
+                if state.probe_successes >= self.config.half_open_probes {
+                    Self::_transition(service, state, CircuitState::Closed);
+                }
+            }
+            CircuitState::Closed => {
+                state.recent.push_back(success);
+
+                while state.recent.len() > self.config.window {
+                    state.recent.pop_front();
+                }
+
+                let failures = state.recent.iter().filter(|s| !**s).count();
+
+                if state.recent.len() >= self.config.min_calls
+                    && failures as f64 / state.recent.len() as f64 >= self.config.failure_rate
+                {
+                    Self::_transition(service, state, CircuitState::Open);
+                }
+            }
+            // A call that started before the circuit opened
+            CircuitState::Open => {}
+        }
+    }
+
+    /// Runs a call to a service through the breaker
+    ///
+    /// If the circuit is open, a ``CircuitOpen`` error with a user friendly message is returned without calling ``op``
+    pub async fn call<T, F>(&self, service: &str, op: F) -> Result<T, Error>
+    where
+        F: Future<Output = Result<T, Error>>,
+    {
+        self._before(service)?;
+
+        let res = op.await;
+        self._after(service, res.is_ok());
+
+        res
+    }
+
+    /// Returns the state of a service, services that were never called are closed
+    pub fn state(&self, service: &str) -> CircuitState {
+        self.services
+            .lock()
+            .unwrap()
+            .get(service)
+            .map(|s| s.state)
+            .unwrap_or(CircuitState::Closed)
+    }
+
+    /// Returns the state of every known service, for metrics and status pages
+    pub fn states(&self) -> Vec<(String, CircuitState)> {
+        let mut states = self
+            .services
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(k, v)| (k.clone(), v.state))
+            .collect::<Vec<_>>();
+
+        states.sort_by(|a, b| a.0.cmp(&b.0));
+
+        states
+    }
+
+    /// Forces a service back to closed, for example from an owner command
+    pub fn reset(&self, service: &str) {
+        let mut services = self.services.lock().unwrap();
+
+        if let Some(state) = services.get_mut(service) {
+            Self::_transition(service, state, CircuitState::Closed);
+        }
+    }
+}
+
+/// Returns whether an error is worth retrying: timeouts, connection errors, ratelimits and 5xx responses
+///
+/// ``CircuitOpen`` errors are never retried
+pub fn is_transient(err: &Error) -> bool {
+    if err.is::<CircuitOpen>() {
+        return false;
+    }
+
+    if let Some(e) = err.downcast_ref::<reqwest::Error>() {
+        return e.is_timeout()
+            || e.is_connect()
+            || e.status()
+                .is_some_and(|s| s.is_server_error() || s.as_u16() == 429);
+    }
+
+    if let Some(serenity::Error::Http(e)) = err.downcast_ref::<serenity::Error>() {
+        return e
+            .status_code()
+            .is_some_and(|s| s.is_server_error() || s.as_u16() == 429);
+    }
+
+    false
+}
+
+/// How an operation is retried
+#[derive(Clone)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on each further retry
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    /// Whether to randomize delays (full jitter) so many clients do not retry in lockstep
+    pub jitter: bool,
+    /// Decides whether an error is retried
+    pub retry_on: fn(&Error) -> bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(10),
+            jitter: true,
+            retry_on: is_transient,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that never retries
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Returns the delay before retry number ``retry`` (starting at 1)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(retry.saturating_sub(1)))
+            .min(self.max_delay);
+
+        if self.jitter {
+            delay.mul_f64(rand::thread_rng().gen_range(0.0..=1.0))
+        } else {
+            delay
+        }
+    }
+}
+
+/// Runs an operation, retrying failures the policy classifies as retryable
+pub async fn retry<T, F, Fut>(policy: &RetryPolicy, mut op: F) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    let mut attempt = 1;
+
+    loop {
+        match op().await {
+            Ok(v) => return Ok(v),
+            Err(e) if attempt < policy.max_attempts && (policy.retry_on)(&e) => {
+                let delay = policy.delay(attempt);
+
+                log::debug!(
+                    "Attempt {}/{} failed, retrying in {:?}: {}",
+                    attempt,
+                    policy.max_attempts,
+                    delay,
+                    e
+                );
+
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+/// Like ``retry``, but every attempt goes through a circuit breaker
+///
+/// Once the circuit opens, retrying stops immediately with a ``CircuitOpen`` error
+pub async fn retry_with_breaker<T, F, Fut>(
+    policy: &RetryPolicy,
+    breaker: &CircuitBreaker,
+    service: &str,
+    mut op: F,
+) -> Result<T, Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, Error>>,
+{
+    retry(policy, || breaker.call(service, op())).await
+}
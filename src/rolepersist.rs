@@ -0,0 +1,171 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, EditMember, FullEvent, GuildId, Permissions, RoleId, UserId,
+};
+use std::sync::Arc;
+
+use crate::features::{Feature, FeatureMatrix};
+use crate::reason::Reason;
+use crate::Error;
+
+/// Storage backend for persisted roles
+pub trait RolePersistStore: Send + Sync {
+    /// Returns whether role persistence is enabled in a guild
+    fn enabled<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Saves the roles a member had when they left
+    fn save<'a>(
+        &'a self,
+        guild_id: GuildId,
+        user_id: UserId,
+        roles: &'a [RoleId],
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Removes and returns the saved roles of a member
+    fn take<'a>(
+        &'a self,
+        guild_id: GuildId,
+        user_id: UserId,
+    ) -> BoxFuture<'a, Result<Vec<RoleId>, Error>>;
+}
+
+/// Which roles may be restored
+pub struct RolePersistPolicy {
+    /// Roles with any of these permissions are never restored
+    pub dangerous_permissions: Permissions,
+    /// Roles that are never restored
+    pub denied_roles: Vec<RoleId>,
+}
+
+impl Default for RolePersistPolicy {
+    fn default() -> Self {
+        Self {
+            dangerous_permissions: Permissions::ADMINISTRATOR
+                | Permissions::MANAGE_GUILD
+                | Permissions::MANAGE_ROLES
+                | Permissions::MANAGE_CHANNELS
+                | Permissions::MANAGE_WEBHOOKS
+                | Permissions::BAN_MEMBERS
+                | Permissions::KICK_MEMBERS,
+            denied_roles: Vec::new(),
+        }
+    }
+}
+
+/// Restores a members roles when they rejoin a guild
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct RolePersist {
+    store: Arc<dyn RolePersistStore>,
+    policy: Arc<RolePersistPolicy>,
+    /// Roles are neither saved nor restored in guilds that disabled ``Feature::RolePersist``, if set
+    pub features: Option<FeatureMatrix>,
+}
+
+impl RolePersist {
+    pub fn new(store: Arc<dyn RolePersistStore>, policy: RolePersistPolicy) -> Self {
+        Self {
+            store,
+            policy: Arc::new(policy),
+            features: None,
+        }
+    }
+
+    /// Filters out managed, dangerous, denied and unknown roles using the cache
+    pub fn filter_roles(
+        &self,
+        cache: &serenity::Cache,
+        guild_id: GuildId,
+        roles: &[RoleId],
+    ) -> Vec<RoleId> {
+        let Some(guild) = cache.guild(guild_id) else {
+            return Vec::new();
+        };
+
+        roles
+            .iter()
+            .filter(|id| **id != RoleId::new(guild_id.get()))
+            .filter(|id| !self.policy.denied_roles.contains(id))
+            .filter(|id| {
+                guild.roles.get(*id).is_some_and(|r| {
+                    !r.managed && !r.permissions.intersects(self.policy.dangerous_permissions)
+                })
+            })
+            .copied()
+            .collect()
+    }
+
+    /// Saves roles on leave and restores them on rejoin, this should be called from your bots event handler
+    ///
+    /// Requires the ``GUILD_MEMBERS`` intent and the member cache, as roles of leaving members come from the cache
+    pub async fn handle_event(
+        &self,
+        ctx: &serenity::Context,
+        event: &FullEvent,
+    ) -> Result<(), Error> {
+        if !crate::features::allows(self.features.as_ref(), event, Feature::RolePersist).await {
+            return Ok(());
+        }
+
+        match event {
+            FullEvent::GuildMemberRemoval {
+                guild_id,
+                user,
+                member_data_if_available: Some(member),
+            } => {
+                if !self.store.enabled(*guild_id).await? {
+                    return Ok(());
+                }
+
+                let roles = self.filter_roles(&ctx.cache, *guild_id, &member.roles);
+
+                if !roles.is_empty() {
+                    self.store.save(*guild_id, user.id, &roles).await?;
+                }
+            }
+            FullEvent::GuildMemberAddition { new_member } => {
+                let guild_id = new_member.guild_id;
+
+                if !self.store.enabled(guild_id).await? {
+                    return Ok(());
+                }
+
+                let saved = self.store.take(guild_id, new_member.user.id).await?;
+
+                // The policy may have changed since the roles were saved
+                let mut roles = self.filter_roles(&ctx.cache, guild_id, &saved);
+
+                if roles.is_empty() {
+                    return Ok(());
+                }
+
+                log::info!(
+                    "Restoring {} role(s) of {} in {}",
+                    roles.len(),
+                    new_member.user.id,
+                    guild_id
+                );
+
+                for role in new_member.roles.iter() {
+                    if !roles.contains(role) {
+                        roles.push(*role);
+                    }
+                }
+
+                guild_id
+                    .edit_member(
+                        &ctx.http,
+                        new_member.user.id,
+                        EditMember::new().roles(roles).audit_log_reason(
+                            Reason::new("Restoring roles from before the member left").as_str(),
+                        ),
+                    )
+                    .await?;
+            }
+            _ => {}
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,571 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, ComponentInteraction, ComponentInteractionDataKind,
+    CreateActionRow, CreateEmbed, CreateInteractionResponse, CreateInteractionResponseMessage,
+    CreateMessage, CreateSelectMenu, CreateSelectMenuKind, CreateSelectMenuOption, EditMember,
+    EditMessage, GuildId, MessageId, RoleId, UserId,
+};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::sync::Arc;
+
+use crate::mimic::is_not_found;
+use crate::reason::Reason;
+use crate::Error;
+
+/// Maximum number of groups per panel, one select menu per action row
+pub const MAX_GROUPS: usize = 5;
+
+/// Maximum number of roles per group, the option limit of a select menu
+pub const MAX_GROUP_ROLES: usize = 25;
+
+/// A role that can be picked from a group
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleOption {
+    pub role_id: RoleId,
+    pub label: String,
+    pub description: Option<String>,
+}
+
+/// A set of roles picked from one select menu, such as interests, pings or pronouns
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoleGroup {
+    pub id: String,
+    pub name: String,
+    pub options: Vec<RoleOption>,
+    /// Minimum number of roles a member must pick, 0 to allow clearing the group
+    pub min: u8,
+    pub max: u8,
+}
+
+impl RoleGroup {
+    /// Returns the constraints clamped to the number of options
+    fn _bounds(&self) -> (u8, u8) {
+        let count = self.options.len().min(MAX_GROUP_ROLES) as u8;
+        let max = self.max.clamp(1, count.max(1));
+
+        (self.min.min(max), max)
+    }
+}
+
+/// Roles a member may hold at most one of, such as colour roles
+///
+/// Picking one of them from any group of the panel removes the others, even if the member got
+/// them some other way
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ExclusiveGroup {
+    pub name: String,
+    pub roles: Vec<RoleId>,
+}
+
+/// A persisted onboarding panel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PanelConfig {
+    pub id: String,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    /// The posted message, None until the panel is posted
+    pub message_id: Option<MessageId>,
+    pub title: String,
+    pub description: String,
+    pub groups: Vec<RoleGroup>,
+    #[serde(default)]
+    pub exclusive: Vec<ExclusiveGroup>,
+}
+
+impl PanelConfig {
+    /// Returns the exclusive groups with more than one role picked
+    fn _conflicts(&self, picked: &[RoleId]) -> Vec<&ExclusiveGroup> {
+        self.exclusive
+            .iter()
+            .filter(|e| picked.iter().filter(|r| e.roles.contains(r)).count() > 1)
+            .collect()
+    }
+
+    /// Returns the roles that picking ``picked`` displaces through exclusive groups
+    fn _displaced(&self, picked: &[RoleId]) -> HashSet<RoleId> {
+        self.exclusive
+            .iter()
+            .filter(|e| picked.iter().any(|r| e.roles.contains(r)))
+            .flat_map(|e| e.roles.iter().copied())
+            .filter(|r| !picked.contains(r))
+            .collect()
+    }
+}
+
+/// Storage backend for onboarding panels
+pub trait PanelStore: Send + Sync {
+    fn get<'a>(
+        &'a self,
+        guild_id: GuildId,
+        id: &'a str,
+    ) -> BoxFuture<'a, Result<Option<PanelConfig>, Error>>;
+
+    /// Lists the panels of a guild
+    fn for_guild<'a>(&'a self, guild_id: GuildId)
+        -> BoxFuture<'a, Result<Vec<PanelConfig>, Error>>;
+
+    /// Adds or replaces a panel
+    fn save<'a>(&'a self, panel: &'a PanelConfig) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// What ``OnboardingPanel::repair`` changed on a panel
+#[derive(Debug, Clone)]
+pub struct PanelRepair {
+    pub panel_id: String,
+    /// Options dropped because their role was deleted
+    pub removed_roles: Vec<RoleId>,
+    /// Whether the message was missing and posted again
+    pub reposted: bool,
+}
+
+/// Role select menus members use to pick their own roles, one menu per group
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct OnboardingPanel {
+    store: Arc<dyn PanelStore>,
+}
+
+impl OnboardingPanel {
+    pub fn new(store: Arc<dyn PanelStore>) -> Self {
+        Self { store }
+    }
+
+    /// Renders a panel as an embed and one select menu per group
+    pub fn render(
+        &self,
+        panel: &PanelConfig,
+    ) -> (CreateEmbed<'static>, Vec<CreateActionRow<'static>>) {
+        let embed = CreateEmbed::default()
+            .title(panel.title.clone())
+            .description(panel.description.clone())
+            .colour(serenity::Colour::BLURPLE);
+
+        let components = panel
+            .groups
+            .iter()
+            .take(MAX_GROUPS)
+            .filter(|g| !g.options.is_empty())
+            .map(|group| {
+                let (min, max) = group._bounds();
+
+                let options = group
+                    .options
+                    .iter()
+                    .take(MAX_GROUP_ROLES)
+                    .map(|o| {
+                        let mut option =
+                            CreateSelectMenuOption::new(o.label.clone(), o.role_id.to_string());
+
+                        if let Some(description) = &o.description {
+                            option = option.description(description.clone());
+                        }
+
+                        option
+                    })
+                    .collect::<Vec<_>>();
+
+                CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new(
+                        format!("onboard:{}:{}", panel.id, group.id),
+                        CreateSelectMenuKind::String {
+                            options: options.into(),
+                        },
+                    )
+                    .placeholder(group.name.clone())
+                    .min_values(min)
+                    .max_values(max),
+                )
+            })
+            .collect();
+
+        (embed, components)
+    }
+
+    /// Posts a panel to its channel and saves the message id
+    pub async fn post(
+        &self,
+        http: &serenity::Http,
+        mut panel: PanelConfig,
+    ) -> Result<PanelConfig, Error> {
+        if panel.groups.len() > MAX_GROUPS {
+            return Err(format!("A panel can have at most {} groups", MAX_GROUPS).into());
+        }
+
+        let (embed, components) = self.render(&panel);
+
+        let msg = panel
+            .channel_id
+            .send_message(
+                http,
+                CreateMessage::new().embed(embed).components(components),
+            )
+            .await?;
+
+        panel.message_id = Some(msg.id);
+        self.store.save(&panel).await?;
+
+        Ok(panel)
+    }
+
+    /// Drops options whose role was deleted and re-renders every panel of a guild, posting panels
+    /// again if their message was deleted
+    pub async fn repair(
+        &self,
+        http: &serenity::Http,
+        guild_id: GuildId,
+    ) -> Result<Vec<PanelRepair>, Error> {
+        let roles = guild_id
+            .roles(http)
+            .await?
+            .into_iter()
+            .map(|r| r.id)
+            .collect::<HashSet<_>>();
+        let mut repairs = Vec::new();
+
+        for mut panel in self.store.for_guild(guild_id).await? {
+            let mut removed_roles = Vec::new();
+
+            for group in panel.groups.iter_mut() {
+                group.options.retain(|o| {
+                    let exists = roles.contains(&o.role_id);
+
+                    if !exists {
+                        removed_roles.push(o.role_id);
+                    }
+
+                    exists
+                });
+            }
+
+            for exclusive in panel.exclusive.iter_mut() {
+                exclusive.roles.retain(|r| roles.contains(r));
+            }
+
+            let (embed, components) = self.render(&panel);
+
+            let edited = match panel.message_id {
+                Some(message_id) => {
+                    match panel
+                        .channel_id
+                        .edit_message(
+                            http,
+                            message_id,
+                            EditMessage::new()
+                                .embed(embed.clone())
+                                .components(components.clone()),
+                        )
+                        .await
+                    {
+                        Ok(_) => true,
+                        Err(e) if is_not_found(&e) => false,
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                None => false,
+            };
+
+            let reposted = !edited;
+
+            if reposted {
+                let msg = panel
+                    .channel_id
+                    .send_message(
+                        http,
+                        CreateMessage::new().embed(embed).components(components),
+                    )
+                    .await?;
+
+                panel.message_id = Some(msg.id);
+            }
+
+            self.store.save(&panel).await?;
+
+            repairs.push(PanelRepair {
+                panel_id: panel.id.clone(),
+                removed_roles,
+                reposted,
+            });
+        }
+
+        Ok(repairs)
+    }
+
+    /// Applies a group selection, returning false if the interaction is not a panel interaction
+    ///
+    /// Roles of the group that were not picked are removed, as are roles sharing an
+    /// ``ExclusiveGroup`` with a picked role. This should be called from your bots event handler
+    /// on every component interaction
+    pub async fn handle_interaction(
+        &self,
+        ctx: &serenity::Context,
+        interaction: &ComponentInteraction,
+    ) -> Result<bool, Error> {
+        let Some((panel_id, group_id)) = interaction
+            .data
+            .custom_id
+            .strip_prefix("onboard:")
+            .and_then(|rest| rest.split_once(':'))
+        else {
+            return Ok(false);
+        };
+
+        let (Some(guild_id), Some(member)) = (interaction.guild_id, interaction.member.as_ref())
+        else {
+            return Ok(true);
+        };
+
+        let ComponentInteractionDataKind::StringSelect { values } = &interaction.data.kind else {
+            return Ok(true);
+        };
+
+        let panel = self.store.get(guild_id, panel_id).await?;
+        let group = panel
+            .as_ref()
+            .and_then(|p| p.groups.iter().find(|g| g.id == group_id));
+
+        let (Some(panel), Some(group)) = (&panel, group) else {
+            _respond(ctx, interaction, "This role menu no longer exists").await?;
+            return Ok(true);
+        };
+
+        let picked = values
+            .iter()
+            .filter_map(|v| v.parse::<RoleId>().ok())
+            .filter(|r| group.options.iter().any(|o| o.role_id == *r))
+            .collect::<Vec<_>>();
+
+        let (min, max) = group._bounds();
+
+        if picked.len() < min as usize || picked.len() > max as usize {
+            _respond(
+                ctx,
+                interaction,
+                &format!("Pick between {} and {} roles from {}", min, max, group.name),
+            )
+            .await?;
+            return Ok(true);
+        }
+
+        let conflicts = panel._conflicts(&picked);
+
+        if !conflicts.is_empty() {
+            let names = conflicts
+                .iter()
+                .map(|e| e.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ");
+
+            _respond(
+                ctx,
+                interaction,
+                &format!("You can only pick one role from each of: {}", names),
+            )
+            .await?;
+            return Ok(true);
+        }
+
+        let displaced = panel._displaced(&picked);
+        let previous = member.roles.to_vec();
+
+        let mut roles = previous
+            .iter()
+            .copied()
+            .filter(|r| !group.options.iter().any(|o| o.role_id == *r))
+            .filter(|r| !displaced.contains(r))
+            .collect::<Vec<_>>();
+
+        roles.extend(picked.iter().copied());
+
+        if let Err(e) = _apply_roles(ctx, guild_id, interaction.user.id, &previous, roles).await {
+            _respond(ctx, interaction, &e).await?;
+            return Ok(true);
+        }
+
+        let added = picked
+            .iter()
+            .filter(|r| !previous.contains(r))
+            .copied()
+            .collect::<Vec<_>>();
+        let removed = previous
+            .iter()
+            .filter(|r| !picked.contains(r))
+            .filter(|r| displaced.contains(r) || group.options.iter().any(|o| o.role_id == **r))
+            .copied()
+            .collect::<Vec<_>>();
+
+        let mut content = format!("Updated your {} roles", group.name);
+
+        if added.is_empty() && removed.is_empty() {
+            content.push_str(", nothing changed");
+        }
+
+        if !added.is_empty() {
+            let _ = write!(content, "\nAdded: {}", _mentions(&added));
+        }
+
+        if !removed.is_empty() {
+            let _ = write!(content, "\nRemoved: {}", _mentions(&removed));
+        }
+
+        _respond(ctx, interaction, &content).await?;
+
+        Ok(true)
+    }
+}
+
+fn _mentions(roles: &[RoleId]) -> String {
+    roles
+        .iter()
+        .map(|r| format!("<@&{}>", r))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Replaces a members roles in one request, putting the previous roles back if Discord did not
+/// apply exactly the requested ones
+///
+/// Returns a message for the member on failure
+async fn _apply_roles(
+    ctx: &serenity::Context,
+    guild_id: GuildId,
+    user_id: UserId,
+    previous: &[RoleId],
+    roles: Vec<RoleId>,
+) -> Result<(), String> {
+    let wanted = roles.iter().copied().collect::<HashSet<_>>();
+
+    let member = match guild_id
+        .edit_member(
+            &ctx.http,
+            user_id,
+            EditMember::new()
+                .roles(roles)
+                .audit_log_reason(Reason::new("Onboarding role menu").as_str()),
+        )
+        .await
+    {
+        Ok(member) => member,
+        Err(e) => {
+            log::warn!(
+                "Failed to update roles of {} in {}: {}",
+                user_id,
+                guild_id,
+                e
+            );
+            return Err(
+                "I couldn't update your roles, some of them may be above my highest role. \
+                 Nothing was changed"
+                    .to_string(),
+            );
+        }
+    };
+
+    if member.roles.iter().copied().collect::<HashSet<_>>() == wanted {
+        return Ok(());
+    }
+
+    let rollback = guild_id
+        .edit_member(
+            &ctx.http,
+            user_id,
+            EditMember::new()
+                .roles(previous.to_vec())
+                .audit_log_reason(Reason::new("Onboarding role menu rollback").as_str()),
+        )
+        .await;
+
+    match rollback {
+        Ok(_) => Err("Your roles could not all be applied, so nothing was changed".to_string()),
+        Err(e) => {
+            log::error!(
+                "Failed to roll back roles of {} in {}: {}",
+                user_id,
+                guild_id,
+                e
+            );
+            Err(
+                "Your roles could only be partly applied, ask a moderator to check them"
+                    .to_string(),
+            )
+        }
+    }
+}
+
+async fn _respond(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    content: &str,
+) -> Result<(), Error> {
+    interaction
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Trait for bot data that holds an ``OnboardingPanel``
+pub trait HasOnboardingPanel {
+    fn onboarding_panel(&self) -> &OnboardingPanel;
+}
+
+/// Re-renders the guilds onboarding panels after roles changed, can be plugged into your bots ``/panels repair`` command
+pub async fn panels_repair<Data: HasOnboardingPanel + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Panels can only be repaired in a server".into());
+    };
+
+    ctx.defer_ephemeral().await?;
+
+    let data = ctx.data();
+    let repairs = data.onboarding_panel().repair(ctx.http(), guild_id).await?;
+
+    if repairs.is_empty() {
+        ctx.say("This server has no role panels").await?;
+        return Ok(());
+    }
+
+    let mut msg = String::new();
+
+    for repair in repairs {
+        let _ = write!(msg, "``{}``: ", repair.panel_id);
+
+        if repair.removed_roles.is_empty() && !repair.reposted {
+            msg.push_str("re-rendered");
+        } else {
+            let mut changes = Vec::new();
+
+            if !repair.removed_roles.is_empty() {
+                changes.push(format!(
+                    "dropped {} deleted role(s)",
+                    repair.removed_roles.len()
+                ));
+            }
+
+            if repair.reposted {
+                changes.push("posted again".to_string());
+            }
+
+            msg.push_str(&changes.join(", "));
+        }
+
+        msg.push('\n');
+    }
+
+    ctx.send(CreateReply::default().content(msg).ephemeral(true))
+        .await?;
+
+    Ok(())
+}
@@ -0,0 +1,153 @@
+use poise::serenity_prelude::CreateAllowedMentions;
+
+/// Zero width space, used to break up mentions without visibly changing the text
+const ZWSP: char = '\u{200B}';
+
+/// Number of user/role mentions after which a message counts as a mass mention
+pub const MASS_MENTION_THRESHOLD: usize = 5;
+
+/// How aggressively text is cleaned
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub enum SanitizeLevel {
+    /// Neutralizes @everyone/@here and mass mentions
+    #[default]
+    Mentions,
+    /// ``Mentions`` plus removal of invite links and zero-width characters
+    Strict,
+    /// ``Strict`` plus escaping of all markdown
+    Full,
+}
+
+/// Returns true if a character is a zero-width or otherwise invisible formatting character
+pub fn is_zero_width(c: char) -> bool {
+    matches!(
+        c,
+        '\u{200B}'..='\u{200F}' | '\u{2060}'..='\u{2064}' | '\u{FEFF}' | '\u{180E}' | '\u{00AD}'
+    )
+}
+
+/// Returns true if a word looks like a discord invite link
+pub fn is_invite_link(word: &str) -> bool {
+    let word = word.to_lowercase();
+
+    [
+        "discord.gg/",
+        "discord.com/invite/",
+        "discordapp.com/invite/",
+        "discord.me/",
+    ]
+    .iter()
+    .any(|p| word.contains(p))
+}
+
+/// Returns the number of user and role mentions in a text
+pub fn count_mentions(text: &str) -> usize {
+    text.match_indices("<@")
+        .filter(|(i, _)| {
+            let rest = &text[i + 2..];
+            let rest = rest.trim_start_matches(['!', '&']);
+            let digits = rest.chars().take_while(char::is_ascii_digit).count();
+            digits > 0 && rest[digits..].starts_with('>')
+        })
+        .count()
+}
+
+/// Escapes all markdown formatting characters
+pub fn escape_markdown(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '*' | '_' | '~' | '`' | '|' | '>' | '#' | '-' | '[' | ']'
+        ) {
+            out.push('\\');
+        }
+
+        out.push(c);
+    }
+
+    out
+}
+
+/// Cleans text according to the given level
+pub fn clean(text: &str, level: SanitizeLevel) -> String {
+    let mut text = if level >= SanitizeLevel::Strict {
+        let stripped = text
+            .chars()
+            .filter(|c| !is_zero_width(*c))
+            .collect::<String>();
+
+        stripped
+            .split_inclusive(char::is_whitespace)
+            .map(|word| {
+                if is_invite_link(word) {
+                    let trailing = &word[word.trim_end().len()..];
+                    format!("[invite removed]{}", trailing)
+                } else {
+                    word.to_string()
+                }
+            })
+            .collect::<String>()
+    } else {
+        text.to_string()
+    };
+
+    if level >= SanitizeLevel::Full {
+        text = escape_markdown(&text);
+    }
+
+    text = text
+        .replace("@everyone", &format!("@{}everyone", ZWSP))
+        .replace("@here", &format!("@{}here", ZWSP));
+
+    if count_mentions(&text) > MASS_MENTION_THRESHOLD {
+        text = text.replace("<@", &format!("<{}@", ZWSP));
+    }
+
+    text
+}
+
+/// Preset for which mentions may ping when sending a message
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MentionPolicy {
+    pub users: bool,
+    pub roles: bool,
+    pub everyone: bool,
+    pub replied_user: bool,
+}
+
+impl MentionPolicy {
+    /// Nothing pings, this is the default used by the ``send`` helpers
+    pub fn none() -> Self {
+        Self::default()
+    }
+
+    /// Users (including the replied user) may be pinged, roles and @everyone may not
+    pub fn users_only() -> Self {
+        Self {
+            users: true,
+            replied_user: true,
+            ..Self::default()
+        }
+    }
+
+    /// Everything may ping
+    pub fn all() -> Self {
+        Self {
+            users: true,
+            roles: true,
+            everyone: true,
+            replied_user: true,
+        }
+    }
+
+    /// Builds the ``CreateAllowedMentions`` for this policy
+    pub fn build(&self) -> CreateAllowedMentions {
+        CreateAllowedMentions::new()
+            .all_users(self.users)
+            .all_roles(self.roles)
+            .everyone(self.everyone)
+            .replied_user(self.replied_user)
+    }
+}
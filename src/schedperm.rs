@@ -0,0 +1,324 @@
+use chrono::NaiveDateTime;
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateMessage, GuildId, PermissionOverwrite,
+    PermissionOverwriteType, Permissions, RoleId, Timestamp, UserId,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::taskman::Task;
+use crate::time::GuildClock;
+use crate::Error;
+
+/// Who a scheduled overwrite applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverwriteTarget {
+    Role(RoleId),
+    Member(UserId),
+}
+
+impl OverwriteTarget {
+    fn kind(&self) -> PermissionOverwriteType {
+        match self {
+            OverwriteTarget::Role(id) => PermissionOverwriteType::Role(*id),
+            OverwriteTarget::Member(id) => PermissionOverwriteType::Member(*id),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ScheduleState {
+    /// Waiting for ``at``
+    Pending,
+    /// Applied, waiting for ``revert_at``
+    Applied,
+    Done,
+    Failed,
+}
+
+/// A permission overwrite change scheduled for a future time
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledPermission {
+    pub id: String,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub target: OverwriteTarget,
+    /// Permission bitflags to allow and deny
+    pub allow: u64,
+    pub deny: u64,
+    pub at: Timestamp,
+    /// When to put the previous overwrite back, if at all
+    pub revert_at: Option<Timestamp>,
+    /// The overwrite (allow, deny) before the change was applied, None if there was none
+    pub previous: Option<(u64, u64)>,
+    pub state: ScheduleState,
+}
+
+/// Storage backend for scheduled permission changes
+pub trait SchedPermStore: Send + Sync {
+    /// Returns all changes that are pending or applied
+    fn active<'a>(&'a self) -> BoxFuture<'a, Result<Vec<ScheduledPermission>, Error>>;
+
+    /// Saves a change, creating it if it does not already exist
+    fn save<'a>(&'a self, change: &'a ScheduledPermission) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Deletes a change, returning whether it existed
+    fn delete<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<bool, Error>>;
+}
+
+/// Applies and reverts scheduled permission overwrite changes
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct SchedPerm {
+    store: Arc<dyn SchedPermStore>,
+    /// Channel failures are reported to, in addition to being logged
+    pub alert_channel: Option<ChannelId>,
+    /// Used to read local times and show times in the guilds timezone, UTC otherwise
+    pub clock: Option<GuildClock>,
+}
+
+impl SchedPerm {
+    pub fn new(store: Arc<dyn SchedPermStore>) -> Self {
+        Self {
+            store,
+            alert_channel: None,
+            clock: None,
+        }
+    }
+
+    async fn _to_timestamp(
+        &self,
+        local: NaiveDateTime,
+        guild_id: GuildId,
+    ) -> Result<Timestamp, Error> {
+        match &self.clock {
+            Some(clock) => clock.to_timestamp(local, guild_id).await,
+            None => Ok(Timestamp::from_unix_timestamp(local.and_utc().timestamp())?),
+        }
+    }
+
+    async fn _format(&self, ts: Timestamp, guild_id: GuildId) -> String {
+        const FORMAT: &str = "%Y-%m-%d %H:%M %Z";
+
+        if let Some(clock) = &self.clock {
+            match clock.format(ts, guild_id, FORMAT).await {
+                Ok(formatted) => return formatted,
+                Err(e) => log::warn!("Failed to get the timezone of {}: {}", guild_id, e),
+            }
+        }
+
+        crate::time::to_chrono(ts).format(FORMAT).to_string()
+    }
+
+    /// Schedules a change, returning its id
+    #[allow(clippy::too_many_arguments)]
+    pub async fn schedule(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        target: OverwriteTarget,
+        allow: Permissions,
+        deny: Permissions,
+        at: Timestamp,
+        revert_at: Option<Timestamp>,
+    ) -> Result<String, Error> {
+        if revert_at.is_some_and(|r| r.unix_timestamp() <= at.unix_timestamp()) {
+            return Err("The revert time must be after the change time".into());
+        }
+
+        let change = ScheduledPermission {
+            id: crate::crypto::gen_random(16),
+            guild_id,
+            channel_id,
+            target,
+            allow: allow.bits(),
+            deny: deny.bits(),
+            at,
+            revert_at,
+            previous: None,
+            state: ScheduleState::Pending,
+        };
+
+        self.store.save(&change).await?;
+
+        Ok(change.id)
+    }
+
+    /// Schedules a change from wall clock times in the guilds timezone, returning its id
+    #[allow(clippy::too_many_arguments)]
+    pub async fn schedule_local(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+        target: OverwriteTarget,
+        allow: Permissions,
+        deny: Permissions,
+        at: NaiveDateTime,
+        revert_at: Option<NaiveDateTime>,
+    ) -> Result<String, Error> {
+        let at = self._to_timestamp(at, guild_id).await?;
+        let revert_at = match revert_at {
+            Some(revert_at) => Some(self._to_timestamp(revert_at, guild_id).await?),
+            None => None,
+        };
+
+        self.schedule(guild_id, channel_id, target, allow, deny, at, revert_at)
+            .await
+    }
+
+    /// Cancels a change, an applied change is not reverted
+    pub async fn cancel(&self, id: &str) -> Result<bool, Error> {
+        self.store.delete(id).await
+    }
+
+    async fn _current(
+        http: &serenity::Http,
+        channel_id: ChannelId,
+        target: OverwriteTarget,
+    ) -> Result<Option<(u64, u64)>, Error> {
+        let Some(channel) = http.get_channel(channel_id).await?.guild() else {
+            return Err("Scheduled permission changes only work on server channels".into());
+        };
+
+        Ok(channel
+            .permission_overwrites
+            .iter()
+            .find(|o| o.kind == target.kind())
+            .map(|o| (o.allow.bits(), o.deny.bits())))
+    }
+
+    async fn _set(
+        http: &serenity::Http,
+        change: &ScheduledPermission,
+        overwrite: Option<(u64, u64)>,
+    ) -> Result<(), Error> {
+        match overwrite {
+            Some((allow, deny)) => {
+                change
+                    .channel_id
+                    .create_permission(
+                        http,
+                        PermissionOverwrite {
+                            allow: Permissions::from_bits_truncate(allow),
+                            deny: Permissions::from_bits_truncate(deny),
+                            kind: change.target.kind(),
+                        },
+                    )
+                    .await?
+            }
+            None => {
+                change
+                    .channel_id
+                    .delete_permission(http, change.target.kind())
+                    .await?
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn _step(
+        &self,
+        http: &serenity::Http,
+        change: &mut ScheduledPermission,
+        now: Timestamp,
+    ) -> Result<bool, Error> {
+        match change.state {
+            ScheduleState::Pending if change.at.unix_timestamp() <= now.unix_timestamp() => {
+                change.previous = Self::_current(http, change.channel_id, change.target).await?;
+                Self::_set(http, change, Some((change.allow, change.deny))).await?;
+
+                change.state = if change.revert_at.is_some() {
+                    ScheduleState::Applied
+                } else {
+                    ScheduleState::Done
+                };
+
+                Ok(true)
+            }
+            ScheduleState::Applied
+                if change
+                    .revert_at
+                    .is_some_and(|r| r.unix_timestamp() <= now.unix_timestamp()) =>
+            {
+                Self::_set(http, change, change.previous).await?;
+                change.state = ScheduleState::Done;
+
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn _alert(&self, http: &serenity::Http, change: &ScheduledPermission, e: &Error) {
+        log::error!(
+            "Scheduled permission change {} in <#{}> failed: {}",
+            change.id,
+            change.channel_id,
+            e
+        );
+
+        let Some(alert_channel) = self.alert_channel else {
+            return;
+        };
+
+        let due = match change.state {
+            ScheduleState::Applied => change.revert_at.unwrap_or(change.at),
+            _ => change.at,
+        };
+
+        let res = alert_channel
+            .send_message(
+                http,
+                CreateMessage::new().content(format!(
+                    "Scheduled permission change ``{}`` in <#{}> due {} failed: {}",
+                    change.id,
+                    change.channel_id,
+                    self._format(due, change.guild_id).await,
+                    e
+                )),
+            )
+            .await;
+
+        if let Err(e) = res {
+            log::error!("Failed to send scheduled permission alert: {}", e);
+        }
+    }
+
+    /// Applies and reverts every change that is due
+    pub async fn run(&self, http: &serenity::Http) -> Result<(), Error> {
+        let now = Timestamp::now();
+
+        for mut change in self.store.active().await? {
+            match self._step(http, &mut change, now).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    self._alert(http, &change, &e).await;
+                    change.state = ScheduleState::Failed;
+                }
+            }
+
+            self.store.save(&change).await?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns a task that applies due permission changes, checking every ``check_interval``
+pub fn schedperm_task(schedperm: SchedPerm, check_interval: Duration) -> Task {
+    Task {
+        name: "schedperm",
+        description: "Applies and reverts scheduled channel permission changes",
+        enabled: true,
+        duration: check_interval,
+        run: Box::new(move |ctx| {
+            let schedperm = schedperm.clone();
+            Box::pin(async move { schedperm.run(&ctx.http).await })
+        }),
+    }
+}
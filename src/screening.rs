@@ -0,0 +1,179 @@
+use poise::serenity_prelude::{self as serenity, FullEvent, GuildId, UserId};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, Mutex};
+
+use crate::taskman::Task;
+use crate::Error;
+
+/// A change in a members screening state
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScreeningEvent {
+    /// The member can now talk, either because they accepted the rules or because the guild has
+    /// no membership screening
+    MemberVerified {
+        guild_id: GuildId,
+        user_id: UserId,
+        /// How long they took to accept the rules, None if they never had to
+        waited: Option<Duration>,
+    },
+    /// The member did not accept the rules within ``Screening::timeout``
+    TimedOut {
+        guild_id: GuildId,
+        user_id: UserId,
+        kicked: bool,
+    },
+}
+
+/// Tracks members who have not accepted a guilds rules (membership screening) yet
+///
+/// Welcome messages and autoroles should wait for ``ScreeningEvent::MemberVerified`` rather than
+/// the member join, since pending members cannot talk or be given roles. Requires the
+/// ``GUILD_MEMBERS`` intent. This is cheap to clone
+#[derive(Clone)]
+pub struct Screening {
+    pending: Arc<Mutex<HashMap<(GuildId, UserId), Instant>>>,
+    events: broadcast::Sender<ScreeningEvent>,
+    /// How long a member may stay pending, None to wait forever
+    pub timeout: Option<Duration>,
+    /// Kick members who time out, otherwise they are only reported
+    pub kick_on_timeout: bool,
+}
+
+impl Default for Screening {
+    fn default() -> Self {
+        Self {
+            pending: Arc::default(),
+            events: broadcast::channel(256).0,
+            timeout: None,
+            kick_on_timeout: false,
+        }
+    }
+}
+
+impl Screening {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a receiver of screening events
+    pub fn subscribe(&self) -> broadcast::Receiver<ScreeningEvent> {
+        self.events.subscribe()
+    }
+
+    /// Returns true if a member joined since startup and has not accepted the rules yet
+    pub async fn is_pending(&self, guild_id: GuildId, user_id: UserId) -> bool {
+        self.pending.lock().await.contains_key(&(guild_id, user_id))
+    }
+
+    fn _verified(&self, guild_id: GuildId, user_id: UserId, waited: Option<Duration>) {
+        let _ = self.events.send(ScreeningEvent::MemberVerified {
+            guild_id,
+            user_id,
+            waited,
+        });
+    }
+
+    /// Tracks pending members, this should be called from your bots event handler
+    pub async fn handle_event(&self, event: &FullEvent) {
+        match event {
+            FullEvent::GuildMemberAddition { new_member } => {
+                let key = (new_member.guild_id, new_member.user.id);
+
+                if new_member.pending() {
+                    self.pending.lock().await.insert(key, Instant::now());
+                } else {
+                    self._verified(key.0, key.1, None);
+                }
+            }
+            FullEvent::GuildMemberUpdate {
+                old_if_available,
+                event,
+                ..
+            } => {
+                if event.pending() {
+                    return;
+                }
+
+                let key = (event.guild_id, event.user.id);
+                let started = self.pending.lock().await.remove(&key);
+
+                // Members who joined before startup are only known to be pending through the cache
+                let was_pending =
+                    started.is_some() || old_if_available.as_ref().is_some_and(|m| m.pending());
+
+                if was_pending {
+                    self._verified(key.0, key.1, started.map(|s| s.elapsed()));
+                }
+            }
+            FullEvent::GuildMemberRemoval { guild_id, user, .. } => {
+                self.pending.lock().await.remove(&(*guild_id, user.id));
+            }
+            _ => {}
+        }
+    }
+
+    /// Reports (and kicks, if ``kick_on_timeout`` is set) members who stayed pending longer than ``timeout``
+    pub async fn expire(&self, http: &serenity::Http) -> Result<(), Error> {
+        let Some(timeout) = self.timeout else {
+            return Ok(());
+        };
+
+        let expired = {
+            let mut pending = self.pending.lock().await;
+            let expired = pending
+                .iter()
+                .filter(|(_, joined)| joined.elapsed() >= timeout)
+                .map(|(key, _)| *key)
+                .collect::<Vec<_>>();
+
+            for key in &expired {
+                pending.remove(key);
+            }
+
+            expired
+        };
+
+        for (guild_id, user_id) in expired {
+            let kicked = self.kick_on_timeout
+                && match guild_id
+                    .kick_with_reason(http, user_id, "Did not accept the server rules in time")
+                    .await
+                {
+                    Ok(()) => true,
+                    Err(e) => {
+                        log::warn!(
+                            "Failed to kick pending member {} from {}: {}",
+                            user_id,
+                            guild_id,
+                            e
+                        );
+                        false
+                    }
+                };
+
+            let _ = self.events.send(ScreeningEvent::TimedOut {
+                guild_id,
+                user_id,
+                kicked,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns a task that handles members who never complete screening, checking every ``check_interval``
+pub fn screening_task(screening: Screening, check_interval: Duration) -> Task {
+    Task {
+        name: "screening",
+        description: "Handles members who never accept the server rules",
+        enabled: true,
+        duration: check_interval,
+        run: Box::new(move |ctx| {
+            let screening = screening.clone();
+            Box::pin(async move { screening.expire(&ctx.http).await })
+        }),
+    }
+}
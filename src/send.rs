@@ -0,0 +1,235 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateMessage, Message, MessageId, Timestamp,
+};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::sanitize::MentionPolicy;
+use crate::taskman::Task;
+use crate::Error;
+
+/// A message waiting to be deleted by ``ephemeral_like``, ``sensitive`` or ``schedule_deletion``
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PendingDeletion {
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub at: Timestamp,
+}
+
+/// Storage backend for pending deletions, so they survive restarts
+pub trait DeletionStore: Send + Sync {
+    fn add<'a>(&'a self, deletion: &'a PendingDeletion) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Returns deletions due at or before ``now``
+    fn due<'a>(&'a self, now: Timestamp) -> BoxFuture<'a, Result<Vec<PendingDeletion>, Error>>;
+
+    fn remove<'a>(&'a self, message_id: MessageId) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Persists scheduled deletions to a ``DeletionStore``, so they survive restarts
+///
+/// Pass this to ``schedule_deletion``, ``ephemeral_like`` and ``sensitive``, and add
+/// ``deletion_task`` to your bot to delete messages whose timer was lost to a restart.
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct Deletions {
+    store: Arc<dyn DeletionStore>,
+}
+
+impl Deletions {
+    pub fn new(store: Arc<dyn DeletionStore>) -> Self {
+        Self { store }
+    }
+
+    /// Deletes every persisted message that is overdue
+    pub async fn run(&self, http: &serenity::Http) -> Result<(), Error> {
+        for deletion in self.store.due(Timestamp::now()).await? {
+            _delete(http, Some(self), &deletion).await;
+        }
+
+        Ok(())
+    }
+}
+
+/// Sends a reply, applying ``MentionPolicy::none`` unless the reply already sets allowed mentions
+pub async fn send<'a, Data: Send + Sync + 'static>(
+    ctx: poise::Context<'a, Data, crate::Error>,
+    mut reply: CreateReply<'_>,
+) -> Result<poise::ReplyHandle<'a>, Error> {
+    if reply.allowed_mentions.is_none() {
+        reply = reply.allowed_mentions(MentionPolicy::none().build());
+    }
+
+    Ok(ctx.send(reply).await?)
+}
+
+/// Sends a plain text reply with no mentions pinging
+pub async fn say<'a, Data: Send + Sync + 'static>(
+    ctx: poise::Context<'a, Data, crate::Error>,
+    content: impl Into<String>,
+) -> Result<poise::ReplyHandle<'a>, Error> {
+    send(ctx, CreateReply::default().content(content.into())).await
+}
+
+/// Sends a message to a channel with no mentions pinging
+///
+/// Any allowed mentions set on ``msg`` are replaced, use ``send_message_with`` to choose a policy
+pub async fn send_message(
+    http: &serenity::Http,
+    channel_id: ChannelId,
+    msg: CreateMessage<'_>,
+) -> Result<Message, Error> {
+    send_message_with(http, channel_id, msg, MentionPolicy::none()).await
+}
+
+/// Sends a message to a channel using the given mention policy
+pub async fn send_message_with(
+    http: &serenity::Http,
+    channel_id: ChannelId,
+    msg: CreateMessage<'_>,
+    policy: MentionPolicy,
+) -> Result<Message, Error> {
+    Ok(channel_id
+        .send_message(http, msg.allowed_mentions(policy.build()))
+        .await?)
+}
+
+async fn _delete(http: &serenity::Http, deletions: Option<&Deletions>, deletion: &PendingDeletion) {
+    if let Err(e) = deletion
+        .channel_id
+        .delete_message(http, deletion.message_id, None)
+        .await
+    {
+        // Already deleted messages are not worth a warning
+        if !crate::mimic::is_not_found(&e) {
+            log::warn!("Failed to delete message {}: {}", deletion.message_id, e);
+        }
+    }
+
+    if let Some(deletions) = deletions {
+        if let Err(e) = deletions.store.remove(deletion.message_id).await {
+            log::warn!("Failed to remove pending deletion: {}", e);
+        }
+    }
+}
+
+/// Deletes a message after ``ttl``, persisting the deletion if ``deletions`` is set
+pub async fn schedule_deletion(
+    http: Arc<serenity::Http>,
+    deletions: Option<&Deletions>,
+    channel_id: ChannelId,
+    message_id: MessageId,
+    ttl: Duration,
+) -> Result<(), Error> {
+    let deletion = PendingDeletion {
+        channel_id,
+        message_id,
+        at: Timestamp::from_unix_timestamp(
+            Timestamp::now().unix_timestamp() + ttl.as_secs() as i64,
+        )?,
+    };
+
+    if let Some(deletions) = deletions {
+        deletions.store.add(&deletion).await?;
+    }
+
+    let deletions = deletions.cloned();
+
+    tokio::spawn(async move {
+        tokio::time::sleep(ttl).await;
+        _delete(&http, deletions.as_ref(), &deletion).await;
+    });
+
+    Ok(())
+}
+
+/// Sends a reply that disappears after ``ttl``
+///
+/// Slash commands get a real ephemeral reply. Prefix commands, which can't, get a normal reply
+/// that is deleted after ``ttl``, see ``schedule_deletion``
+pub async fn ephemeral_like<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    reply: CreateReply<'_>,
+    ttl: Duration,
+    deletions: Option<&Deletions>,
+) -> Result<(), Error> {
+    if let poise::Context::Application(_) = ctx {
+        send(ctx, reply.ephemeral(true)).await?;
+        return Ok(());
+    }
+
+    let msg = send(ctx, reply).await?.into_message().await?;
+
+    schedule_deletion(
+        ctx.serenity_context().http.clone(),
+        deletions,
+        msg.channel_id,
+        msg.id,
+        ttl,
+    )
+    .await
+}
+
+/// Sends a reply containing sensitive data (such as tokens or personal data) and tears it down after ``ttl``
+///
+/// Slash commands get an ephemeral reply that is deleted after ``ttl``, as long as the interaction
+/// token is still valid (15 minutes). Prefix commands get a normal reply that is deleted after ``ttl``,
+/// and the invoking message is deleted right away if the bot is allowed to
+pub async fn sensitive<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    reply: CreateReply<'_>,
+    ttl: Duration,
+    deletions: Option<&Deletions>,
+) -> Result<(), Error> {
+    let http = ctx.serenity_context().http.clone();
+
+    match ctx {
+        poise::Context::Application(actx) => {
+            send(ctx, reply.ephemeral(true)).await?;
+
+            let token = actx.interaction.token.to_string();
+
+            tokio::spawn(async move {
+                tokio::time::sleep(ttl).await;
+
+                if let Err(e) = http.delete_original_interaction_response(&token).await {
+                    log::debug!("Failed to delete sensitive reply: {}", e);
+                }
+            });
+
+            Ok(())
+        }
+        poise::Context::Prefix(pctx) => {
+            let msg = send(ctx, reply).await?.into_message().await?;
+
+            if let Err(e) = pctx
+                .msg
+                .channel_id
+                .delete_message(&http, pctx.msg.id, None)
+                .await
+            {
+                // Usually a missing Manage Messages permission or a DM, where the bot can't delete
+                log::debug!("Failed to delete invoking message {}: {}", pctx.msg.id, e);
+            }
+
+            schedule_deletion(http, deletions, msg.channel_id, msg.id, ttl).await
+        }
+    }
+}
+
+/// Returns a task that deletes persisted messages that are overdue, for example after a restart
+pub fn deletion_task(deletions: Deletions, interval: Duration) -> Task {
+    Task {
+        name: "deletions",
+        description: "Deletes overdue auto-deleting messages",
+        enabled: true,
+        duration: interval,
+        run: Box::new(move |ctx| {
+            let deletions = deletions.clone();
+            Box::pin(async move { deletions.run(&ctx.http).await })
+        }),
+    }
+}
@@ -0,0 +1,510 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, AutocompleteChoice, CacheHttp, ChannelId, ChannelType,
+    ComponentInteractionDataKind, CreateActionRow, CreateAutocompleteResponse, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateSelectMenu,
+    CreateSelectMenuKind, CreateSelectMenuOption, GuildId, RoleId,
+};
+use poise::CreateReply;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::embeds::FieldPacker;
+use crate::fuzzy::jaro_winkler;
+use crate::Error;
+
+/// Discord shows at most 25 autocomplete choices
+const MAX_CHOICES: usize = 25;
+
+/// Maximum length of an autocomplete choice name
+const MAX_CHOICE_NAME: usize = 100;
+
+/// Minimum similarity for a key to be suggested when it does not contain the input
+const MIN_SIMILARITY: f64 = 0.7;
+
+/// The type of a setting, deciding how its value is parsed and prompted for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SettingKind {
+    Text,
+    Bool,
+    Integer,
+    /// Prompted for with a channel picker
+    Channel,
+    /// Prompted for with a role picker
+    Role,
+}
+
+impl SettingKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            SettingKind::Text => "Text",
+            SettingKind::Bool => "True/false",
+            SettingKind::Integer => "Number",
+            SettingKind::Channel => "Channel",
+            SettingKind::Role => "Role",
+        }
+    }
+
+    /// Parses user input into a value, accepting mentions for channels and roles
+    pub fn parse(&self, input: &str) -> Result<Value, Error> {
+        let input = input.trim();
+
+        let id = |prefix: &str| -> Result<u64, Error> {
+            let id = input
+                .strip_prefix(prefix)
+                .and_then(|s| s.strip_suffix('>'))
+                .unwrap_or(input);
+
+            id.parse::<u64>()
+                .ok()
+                .filter(|id| *id != 0)
+                .ok_or_else(|| format!("{} is not a valid {}", input, self.label()).into())
+        };
+
+        Ok(match self {
+            SettingKind::Text => Value::String(input.to_string()),
+            SettingKind::Bool => match input.to_lowercase().as_str() {
+                "true" | "yes" | "on" | "enable" | "enabled" => Value::Bool(true),
+                "false" | "no" | "off" | "disable" | "disabled" => Value::Bool(false),
+                _ => return Err(format!("{} is not true or false", input).into()),
+            },
+            SettingKind::Integer => Value::from(
+                input
+                    .parse::<i64>()
+                    .map_err(|_| format!("{} is not a number", input))?,
+            ),
+            SettingKind::Channel => Value::String(id("<#")?.to_string()),
+            SettingKind::Role => Value::String(id("<@&")?.to_string()),
+        })
+    }
+
+    /// Formats a stored value for display
+    pub fn display(&self, value: &Value) -> String {
+        match (self, value) {
+            (SettingKind::Channel, Value::String(id)) => format!("<#{}>", id),
+            (SettingKind::Role, Value::String(id)) => format!("<@&{}>", id),
+            (_, Value::Array(items)) => items
+                .iter()
+                .map(|v| self.display(v))
+                .collect::<Vec<_>>()
+                .join(", "),
+            (_, Value::String(s)) => s.clone(),
+            (_, value) => value.to_string(),
+        }
+    }
+}
+
+/// A setting guilds can change
+#[derive(Debug, Clone)]
+pub struct SettingDef {
+    /// Key such as ``logs.channel``
+    pub key: String,
+    pub description: String,
+    pub kind: SettingKind,
+}
+
+/// Storage backend for guild settings
+pub trait SettingsStore: Send + Sync {
+    /// Returns the value of a setting in a guild, if set
+    fn get<'a>(
+        &'a self,
+        guild_id: GuildId,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<Option<Value>, Error>>;
+
+    /// Returns every setting set in a guild, by key
+    fn all<'a>(&'a self, guild_id: GuildId)
+        -> BoxFuture<'a, Result<HashMap<String, Value>, Error>>;
+
+    /// Sets or clears the value of a setting in a guild
+    fn set<'a>(
+        &'a self,
+        guild_id: GuildId,
+        key: &'a str,
+        value: Option<Value>,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// A registry of typed guild settings backed by a ``SettingsStore``
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct Settings {
+    store: Arc<dyn SettingsStore>,
+    defs: Arc<Vec<SettingDef>>,
+    /// How long to wait for a value to be picked, defaults to 2 minutes
+    pub timeout: Duration,
+}
+
+impl Settings {
+    pub fn new(store: Arc<dyn SettingsStore>, defs: Vec<SettingDef>) -> Self {
+        Self {
+            store,
+            defs: Arc::new(defs),
+            timeout: Duration::from_secs(120),
+        }
+    }
+
+    pub fn defs(&self) -> &[SettingDef] {
+        &self.defs
+    }
+
+    pub fn def(&self, key: &str) -> Option<&SettingDef> {
+        self.defs.iter().find(|d| d.key.eq_ignore_ascii_case(key))
+    }
+
+    /// Returns the settings best matching a partial key, best match first
+    ///
+    /// Keys containing the input rank first (prefixes before other matches), then keys that are
+    /// merely similar. An empty input returns every setting
+    pub fn search(&self, query: &str) -> Vec<&SettingDef> {
+        let query = query.trim().to_lowercase();
+
+        if query.is_empty() {
+            return self.defs.iter().collect();
+        }
+
+        let mut scored = self
+            .defs
+            .iter()
+            .filter_map(|def| {
+                let key = def.key.to_lowercase();
+
+                let score = if key.starts_with(&query) {
+                    3.0
+                } else if key.contains(&query) {
+                    2.0
+                } else {
+                    // Also compare against each part of dotted keys, so ``chanel`` finds ``logs.channel``
+                    let similarity = key
+                        .split(['.', '_'])
+                        .chain(std::iter::once(key.as_str()))
+                        .map(|part| jaro_winkler(&query, part))
+                        .fold(0.0, f64::max);
+
+                    if similarity < MIN_SIMILARITY {
+                        return None;
+                    }
+
+                    similarity
+                };
+
+                Some((def, score))
+            })
+            .collect::<Vec<_>>();
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.key.cmp(&b.0.key)));
+
+        scored.into_iter().map(|(def, _)| def).collect()
+    }
+
+    pub async fn get(&self, guild_id: GuildId, key: &str) -> Result<Option<Value>, Error> {
+        let def = self.def(key).ok_or("Unknown setting")?;
+        self.store.get(guild_id, &def.key).await
+    }
+
+    /// Returns the value of every setting set in a guild, by key
+    pub async fn all(&self, guild_id: GuildId) -> Result<HashMap<String, Value>, Error> {
+        self.store.all(guild_id).await
+    }
+
+    /// Parses and saves a value, returning the value as displayed
+    ///
+    /// Channels and roles must belong to the guild, they are looked up in the cache and then over HTTP
+    pub async fn set(
+        &self,
+        cache_http: impl CacheHttp,
+        guild_id: GuildId,
+        key: &str,
+        input: &str,
+    ) -> Result<String, Error> {
+        let def = self.def(key).ok_or("Unknown setting")?;
+        let value = def.kind.parse(input)?;
+
+        if !_in_guild(&cache_http, guild_id, def.kind, &value).await? {
+            return Err(format!(
+                "{} is not a {} of this server",
+                input.trim(),
+                def.kind.label().to_lowercase()
+            )
+            .into());
+        }
+
+        let display = def.kind.display(&value);
+
+        self.store.set(guild_id, &def.key, Some(value)).await?;
+
+        Ok(display)
+    }
+
+    /// Saves an already parsed value, or clears the setting if None
+    ///
+    /// Unlike ``set`` the value is not parsed or checked against the guild, so it should come from
+    /// a picker such as the ones used by ``setup::SetupWizard``
+    pub async fn set_value(
+        &self,
+        guild_id: GuildId,
+        key: &str,
+        value: Option<Value>,
+    ) -> Result<(), Error> {
+        let def = self.def(key).ok_or("Unknown setting")?;
+        self.store.set(guild_id, &def.key, value).await
+    }
+
+    pub async fn reset(&self, guild_id: GuildId, key: &str) -> Result<(), Error> {
+        let def = self.def(key).ok_or("Unknown setting")?;
+        self.store.set(guild_id, &def.key, None).await
+    }
+}
+
+/// Returns whether a parsed channel or role value belongs to a guild, other kinds always do
+async fn _in_guild(
+    cache_http: &impl CacheHttp,
+    guild_id: GuildId,
+    kind: SettingKind,
+    value: &Value,
+) -> Result<bool, Error> {
+    let Some(id) = value.as_str().and_then(|id| id.parse::<u64>().ok()) else {
+        return Ok(!matches!(kind, SettingKind::Channel | SettingKind::Role));
+    };
+
+    let cached = cache_http
+        .cache()
+        .and_then(|cache| cache.guild(guild_id))
+        .map(|guild| match kind {
+            SettingKind::Channel => guild.channels.get(&ChannelId::new(id)).is_some(),
+            SettingKind::Role => guild.roles.get(&RoleId::new(id)).is_some(),
+            _ => true,
+        });
+
+    if cached == Some(true) {
+        return Ok(true);
+    }
+
+    // The cache may be cold or missing a newly created channel or role
+    Ok(match kind {
+        SettingKind::Channel => match cache_http.http().get_channel(ChannelId::new(id)).await {
+            Ok(channel) => channel.guild().is_some_and(|c| c.guild_id == guild_id),
+            Err(_) => false,
+        },
+        SettingKind::Role => cache_http
+            .http()
+            .get_guild_roles(guild_id)
+            .await?
+            .iter()
+            .any(|r| r.id.get() == id),
+        _ => true,
+    })
+}
+
+/// Trait for bot data that holds ``Settings``
+pub trait HasSettings {
+    fn settings(&self) -> &Settings;
+}
+
+/// Autocompletes setting keys with their descriptions, use as
+/// ``#[autocomplete = "botox::settings::autocomplete_key"]`` on the ``key`` parameter
+pub async fn autocomplete_key<'a, Data: HasSettings + Send + Sync + 'static>(
+    ctx: poise::ApplicationContext<'_, Data, crate::Error>,
+    partial: &str,
+) -> CreateAutocompleteResponse<'a> {
+    let data = ctx.data();
+
+    let choices = data
+        .settings()
+        .search(partial)
+        .into_iter()
+        .take(MAX_CHOICES)
+        .map(|def| {
+            let mut name = format!("{} - {}", def.key, def.description);
+
+            if name.chars().count() > MAX_CHOICE_NAME {
+                name = name.chars().take(MAX_CHOICE_NAME - 1).collect();
+                name.push('…');
+            }
+
+            AutocompleteChoice::new(name, def.key.clone())
+        })
+        .collect::<Vec<_>>();
+
+    CreateAutocompleteResponse::new().set_choices(choices)
+}
+
+/// Builds the picker a setting is prompted with, None for settings that must be typed
+fn _picker(def: &SettingDef) -> Option<CreateSelectMenu<'static>> {
+    let kind = match def.kind {
+        SettingKind::Channel => CreateSelectMenuKind::Channel {
+            channel_types: Some(vec![ChannelType::Text, ChannelType::News].into()),
+            default_channels: None,
+        },
+        SettingKind::Role => CreateSelectMenuKind::Role {
+            default_roles: None,
+        },
+        SettingKind::Bool => CreateSelectMenuKind::String {
+            options: vec![
+                CreateSelectMenuOption::new("True", "true"),
+                CreateSelectMenuOption::new("False", "false"),
+            ]
+            .into(),
+        },
+        SettingKind::Text | SettingKind::Integer => return None,
+    };
+
+    Some(
+        CreateSelectMenu::new("settings:value", kind)
+            .placeholder(format!("Pick a value for {}", def.key)),
+    )
+}
+
+/// Sets a setting, can be plugged into your bots ``/settings set`` command
+///
+/// If no value is given, channel, role and true/false settings are prompted for with a select
+/// menu. Permission checks (such as Manage Server) should be set on the command itself
+pub async fn settings_set<Data: HasSettings + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    key: String,
+    value: Option<String>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Settings can only be changed in a server".into());
+    };
+
+    let data = ctx.data();
+    let settings = data.settings();
+
+    let Some(def) = settings.def(&key) else {
+        let suggestions = settings
+            .search(&key)
+            .iter()
+            .take(3)
+            .map(|d| format!("``{}``", d.key))
+            .collect::<Vec<_>>();
+
+        return Err(if suggestions.is_empty() {
+            format!("Unknown setting ``{}``", key).into()
+        } else {
+            format!(
+                "Unknown setting ``{}``, did you mean {}?",
+                key,
+                suggestions.join(", ")
+            )
+            .into()
+        });
+    };
+
+    if let Some(value) = value {
+        let display = settings
+            .set(ctx.serenity_context(), guild_id, &def.key, &value)
+            .await?;
+        ctx.say(format!("Set ``{}`` to {}", def.key, display))
+            .await?;
+        return Ok(());
+    }
+
+    let Some(picker) = _picker(def) else {
+        return Err(format!(
+            "``{}`` is a {} setting, pass it as the value",
+            def.key,
+            def.kind.label().to_lowercase()
+        )
+        .into());
+    };
+
+    let msg = ctx
+        .send(
+            CreateReply::default()
+                .embed(
+                    CreateEmbed::default()
+                        .title(&def.key)
+                        .description(&def.description)
+                        .colour(serenity::Colour::BLURPLE),
+                )
+                .components(vec![CreateActionRow::SelectMenu(picker)])
+                .ephemeral(true),
+        )
+        .await?
+        .into_message()
+        .await?;
+
+    let Some(item) = msg
+        .await_component_interaction(ctx.serenity_context().shard.clone())
+        .author_id(ctx.author().id)
+        .timeout(settings.timeout)
+        .await
+    else {
+        return Err("No value was picked in time".into());
+    };
+
+    let input = match &item.data.kind {
+        ComponentInteractionDataKind::ChannelSelect { values } => {
+            values.first().map(ChannelId::to_string)
+        }
+        ComponentInteractionDataKind::RoleSelect { values } => {
+            values.first().map(RoleId::to_string)
+        }
+        ComponentInteractionDataKind::StringSelect { values } => values.first().cloned(),
+        _ => None,
+    }
+    .ok_or("No value was picked")?;
+
+    let display = settings
+        .set(ctx.serenity_context(), guild_id, &def.key, &input)
+        .await?;
+
+    item.create_response(
+        ctx.http(),
+        CreateInteractionResponse::UpdateMessage(
+            CreateInteractionResponseMessage::new()
+                .content(format!("Set ``{}`` to {}", def.key, display))
+                .embeds(vec![])
+                .components(vec![]),
+        ),
+    )
+    .await?;
+
+    Ok(())
+}
+
+/// Lists every setting with its current value, can be plugged into your bots ``/settings view`` command
+///
+/// Settings are packed into as many embeds (and messages) as needed
+pub async fn settings_view<Data: HasSettings + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Settings can only be viewed in a server".into());
+    };
+
+    let data = ctx.data();
+    let settings = data.settings();
+    let values = settings.all(guild_id).await?;
+
+    let mut packer = FieldPacker::new("Settings").colour(serenity::Colour::BLURPLE);
+
+    if settings.defs().is_empty() {
+        packer = packer.description("No settings");
+    }
+
+    for def in settings.defs() {
+        packer = packer.field(
+            format!("{} ({})", def.key, def.kind.label()),
+            format!(
+                "{}\n{}",
+                values
+                    .get(&def.key)
+                    .map(|v| def.kind.display(v))
+                    .unwrap_or_else(|| "Not set".to_string()),
+                def.description
+            ),
+            false,
+        );
+    }
+
+    for embeds in packer.pack_messages() {
+        ctx.send(CreateReply::default().embeds(embeds)).await?;
+    }
+
+    Ok(())
+}
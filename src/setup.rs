@@ -0,0 +1,615 @@
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, ChannelType, ComponentInteraction, ComponentInteractionDataKind,
+    CreateActionRow, CreateButton, CreateEmbed, CreateInteractionResponse,
+    CreateInteractionResponseMessage, CreateMessage, CreateSelectMenu, CreateSelectMenuKind,
+    CreateSelectMenuOption, FullEvent, GuildId, Message, Permissions, RoleId, UserId,
+};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fmt::Write;
+use std::time::Duration;
+
+use crate::i18n::t;
+use crate::settings::Settings;
+use crate::Error;
+
+/// The result of a completed setup
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SetupResult {
+    pub log_channel: Option<ChannelId>,
+    pub mod_roles: Vec<RoleId>,
+    /// The ids of the enabled features
+    pub features: Vec<String>,
+}
+
+/// A feature that can be toggled during setup
+#[derive(Debug, Clone)]
+pub struct SetupFeature {
+    /// Key of the true/false setting this feature is saved to
+    pub id: String,
+    pub name: String,
+    pub description: String,
+}
+
+/// The steps of the setup wizard
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    LogChannel,
+    ModRoles,
+    Features,
+    Summary,
+}
+
+/// Guided multi-step setup flow for onboarding a guild
+///
+/// The result is written to the guilds ``Settings``, so every key the wizard uses must be defined there
+pub struct SetupWizard {
+    pub settings: Settings,
+    /// Key of the channel setting the log channel is saved to, defaults to ``logs.channel``
+    pub log_channel_key: String,
+    /// Key of the role setting the mod roles are saved to, defaults to ``moderation.roles``
+    pub mod_roles_key: String,
+    /// Features the user may enable, the features step is skipped if empty
+    pub features: Vec<SetupFeature>,
+    /// Whether a log channel must be picked
+    pub require_log_channel: bool,
+    /// Minimum number of mod roles that must be picked
+    pub min_mod_roles: u8,
+    /// Extra validation run before the summary is shown, returning a reason if the setup is invalid
+    #[allow(clippy::type_complexity)]
+    pub validate: Option<Box<dyn Fn(&SetupResult) -> Option<String> + Send + Sync>>,
+    /// How long to wait for each step before giving up
+    pub timeout: Duration,
+    /// Whether to post a "start setup" prompt in the system channel when the bot joins a new guild
+    pub prompt_on_join: bool,
+}
+
+impl SetupWizard {
+    pub fn new(settings: Settings) -> Self {
+        Self {
+            settings,
+            log_channel_key: "logs.channel".to_string(),
+            mod_roles_key: "moderation.roles".to_string(),
+            features: Vec::new(),
+            require_log_channel: false,
+            min_mod_roles: 0,
+            validate: None,
+            timeout: Duration::from_secs(300),
+            prompt_on_join: true,
+        }
+    }
+
+    fn _validate(&self, result: &SetupResult, locale: Option<&str>) -> Option<String> {
+        if self.require_log_channel && result.log_channel.is_none() {
+            return Some(t(locale, "setup.log_channel_required", &[]));
+        }
+
+        if result.mod_roles.len() < self.min_mod_roles as usize {
+            return Some(t(
+                locale,
+                "setup.mod_roles_required",
+                &[("min", &self.min_mod_roles.to_string())],
+            ));
+        }
+
+        if let Some(validate) = &self.validate {
+            return validate(result);
+        }
+
+        None
+    }
+
+    /// Returns the steps shown before the summary, in order
+    fn _steps(&self) -> Vec<Step> {
+        let mut steps = vec![Step::LogChannel, Step::ModRoles];
+
+        if !self.features.is_empty() {
+            steps.push(Step::Features);
+        }
+
+        steps
+    }
+
+    /// Returns the title of a step, numbered among the steps that are shown
+    fn _title(&self, step: Step, name: &str, locale: Option<&str>) -> String {
+        let steps = self._steps();
+        let n = steps.iter().position(|s| *s == step).unwrap_or(0) + 1;
+
+        t(
+            locale,
+            "setup.step",
+            &[
+                ("step", &n.to_string()),
+                ("total", &steps.len().to_string()),
+                ("name", &t(locale, name, &[])),
+            ],
+        )
+    }
+
+    fn _render(
+        &self,
+        step: Step,
+        result: &SetupResult,
+        error: Option<&str>,
+        locale: Option<&str>,
+    ) -> (CreateEmbed<'_>, Vec<CreateActionRow<'_>>) {
+        let mut embed = CreateEmbed::default().colour(serenity::Colour::BLURPLE);
+
+        let mut nav = vec![CreateButton::new("setup:cancel")
+            .label(t(locale, "setup.cancel", &[]))
+            .style(serenity::ButtonStyle::Danger)];
+
+        let skip = || CreateButton::new("setup:skip").label(t(locale, "setup.skip", &[]));
+        let none = || t(locale, "setup.none", &[]);
+
+        let mut components = Vec::new();
+
+        match step {
+            Step::LogChannel => {
+                embed = embed
+                    .title(self._title(step, "setup.log_channel", locale))
+                    .description(t(locale, "setup.log_channel_description", &[]));
+
+                components.push(CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new(
+                        "setup:log_channel",
+                        CreateSelectMenuKind::Channel {
+                            channel_types: Some(vec![ChannelType::Text].into()),
+                            default_channels: None,
+                        },
+                    )
+                    .placeholder(t(
+                        locale,
+                        "setup.log_channel_placeholder",
+                        &[],
+                    )),
+                ));
+
+                if !self.require_log_channel {
+                    nav.push(skip());
+                }
+            }
+            Step::ModRoles => {
+                embed = embed
+                    .title(self._title(step, "setup.mod_roles", locale))
+                    .description(t(locale, "setup.mod_roles_description", &[]));
+
+                components.push(CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new(
+                        "setup:mod_roles",
+                        CreateSelectMenuKind::Role {
+                            default_roles: None,
+                        },
+                    )
+                    .placeholder(t(locale, "setup.mod_roles_placeholder", &[]))
+                    .min_values(self.min_mod_roles.max(1))
+                    .max_values(25),
+                ));
+
+                if self.min_mod_roles == 0 {
+                    nav.push(skip());
+                }
+            }
+            Step::Features => {
+                embed = embed
+                    .title(self._title(step, "setup.features", locale))
+                    .description(t(locale, "setup.features_description", &[]));
+
+                let options = self
+                    .features
+                    .iter()
+                    .take(25)
+                    .map(|f| {
+                        CreateSelectMenuOption::new(f.name.as_str(), f.id.as_str())
+                            .description(f.description.as_str())
+                    })
+                    .collect::<Vec<_>>();
+
+                let max = options.len() as u8;
+
+                components.push(CreateActionRow::SelectMenu(
+                    CreateSelectMenu::new(
+                        "setup:features",
+                        CreateSelectMenuKind::String {
+                            options: options.into(),
+                        },
+                    )
+                    .placeholder(t(locale, "setup.features_placeholder", &[]))
+                    .min_values(0)
+                    .max_values(max),
+                ));
+
+                nav.push(skip());
+            }
+            Step::Summary => {
+                let mut desc = String::new();
+
+                let _ = writeln!(
+                    desc,
+                    "**{}:** {}",
+                    t(locale, "setup.log_channel", &[]),
+                    result
+                        .log_channel
+                        .map(|c| format!("<#{}>", c))
+                        .unwrap_or_else(none)
+                );
+
+                let _ = writeln!(
+                    desc,
+                    "**{}:** {}",
+                    t(locale, "setup.mod_roles", &[]),
+                    if result.mod_roles.is_empty() {
+                        none()
+                    } else {
+                        result
+                            .mod_roles
+                            .iter()
+                            .map(|r| format!("<@&{}>", r))
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    }
+                );
+
+                if !self.features.is_empty() {
+                    let _ = writeln!(
+                        desc,
+                        "**{}:** {}",
+                        t(locale, "setup.features", &[]),
+                        if result.features.is_empty() {
+                            none()
+                        } else {
+                            self.features
+                                .iter()
+                                .filter(|f| result.features.contains(&f.id))
+                                .map(|f| f.name.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        }
+                    );
+                }
+
+                embed = embed
+                    .title(t(locale, "setup.summary", &[]))
+                    .description(desc);
+
+                nav.insert(
+                    0,
+                    CreateButton::new("setup:confirm")
+                        .label(t(locale, "setup.confirm", &[]))
+                        .style(serenity::ButtonStyle::Success),
+                );
+                nav.insert(
+                    1,
+                    CreateButton::new("setup:restart").label(t(locale, "setup.restart", &[])),
+                );
+            }
+        }
+
+        if let Some(error) = error {
+            embed = embed.field(t(locale, "setup.error", &[]), error.to_string(), false);
+        }
+
+        components.push(CreateActionRow::Buttons(nav));
+
+        (embed, components)
+    }
+
+    async fn _update(
+        &self,
+        ctx: &serenity::Context,
+        interaction: &ComponentInteraction,
+        step: Step,
+        result: &SetupResult,
+        error: Option<&str>,
+        locale: Option<&str>,
+    ) -> Result<(), Error> {
+        let (embed, components) = self._render(step, result, error, locale);
+
+        interaction
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(embed)
+                        .components(components),
+                ),
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    fn _next(&self, step: Step) -> Step {
+        let steps = self._steps();
+
+        steps
+            .iter()
+            .position(|s| *s == step)
+            .and_then(|i| steps.get(i + 1).copied())
+            .unwrap_or(Step::Summary)
+    }
+
+    /// Writes a confirmed result to the settings, clearing anything that was skipped
+    async fn _save(&self, guild_id: GuildId, result: &SetupResult) -> Result<(), Error> {
+        self.settings
+            .set_value(
+                guild_id,
+                &self.log_channel_key,
+                result.log_channel.map(|c| Value::String(c.to_string())),
+            )
+            .await?;
+
+        let mod_roles = result
+            .mod_roles
+            .iter()
+            .map(|r| Value::String(r.to_string()))
+            .collect::<Vec<_>>();
+
+        self.settings
+            .set_value(
+                guild_id,
+                &self.mod_roles_key,
+                (!mod_roles.is_empty()).then_some(Value::Array(mod_roles)),
+            )
+            .await?;
+
+        for feature in &self.features {
+            self.settings
+                .set_value(
+                    guild_id,
+                    &feature.id,
+                    Some(Value::Bool(result.features.contains(&feature.id))),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Runs the wizard on an already sent message, returning the saved result if the setup was confirmed
+    ///
+    /// Only ``user_id`` may interact with the wizard
+    pub async fn run(
+        &self,
+        ctx: &serenity::Context,
+        guild_id: GuildId,
+        user_id: UserId,
+        msg: &Message,
+        locale: Option<&str>,
+    ) -> Result<Option<SetupResult>, Error> {
+        let mut step = Step::LogChannel;
+        let mut result = SetupResult::default();
+
+        loop {
+            let Some(item) = msg
+                .await_component_interaction(ctx.shard.clone())
+                .author_id(user_id)
+                .timeout(self.timeout)
+                .await
+            else {
+                msg.channel_id
+                    .say(&ctx.http, t(locale, "setup.timed_out", &[]))
+                    .await?;
+                return Ok(None);
+            };
+
+            let id: &str = &item.data.custom_id;
+
+            match id {
+                "setup:cancel" => {
+                    item.create_response(
+                        &ctx.http,
+                        CreateInteractionResponse::UpdateMessage(
+                            CreateInteractionResponseMessage::new()
+                                .content(t(locale, "setup.cancelled", &[]))
+                                .embeds(vec![])
+                                .components(vec![]),
+                        ),
+                    )
+                    .await?;
+                    return Ok(None);
+                }
+                "setup:restart" => {
+                    step = Step::LogChannel;
+                    result = SetupResult::default();
+                }
+                "setup:skip" => {
+                    step = self._next(step);
+                }
+                "setup:confirm" => {
+                    if let Some(error) = self._validate(&result, locale) {
+                        self._update(ctx, &item, step, &result, Some(&error), locale)
+                            .await?;
+                        continue;
+                    }
+
+                    self._save(guild_id, &result).await?;
+
+                    item.create_response(
+                        &ctx.http,
+                        CreateInteractionResponse::UpdateMessage(
+                            CreateInteractionResponseMessage::new()
+                                .embed(
+                                    self._render(Step::Summary, &result, None, locale)
+                                        .0
+                                        .title(t(locale, "setup.complete", &[]))
+                                        .colour(serenity::Colour::DARK_GREEN),
+                                )
+                                .components(vec![]),
+                        ),
+                    )
+                    .await?;
+
+                    return Ok(Some(result));
+                }
+                _ => {
+                    match &item.data.kind {
+                        ComponentInteractionDataKind::ChannelSelect { values } => {
+                            result.log_channel = values.first().copied();
+                        }
+                        ComponentInteractionDataKind::RoleSelect { values } => {
+                            result.mod_roles = values.to_vec();
+                        }
+                        ComponentInteractionDataKind::StringSelect { values } => {
+                            result.features = values.to_vec();
+                        }
+                        _ => {}
+                    }
+
+                    step = self._next(step);
+                }
+            }
+
+            let error = if step == Step::Summary {
+                self._validate(&result, locale)
+            } else {
+                None
+            };
+
+            self._update(ctx, &item, step, &result, error.as_deref(), locale)
+                .await?;
+        }
+    }
+
+    /// Starts the wizard from a command, can be plugged into your bots ``/setup`` command
+    ///
+    /// Permission checks (such as Manage Server) should be set on the command itself
+    pub async fn setup<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+    ) -> Result<Option<SetupResult>, Error> {
+        let locale = ctx.locale();
+
+        let Some(guild_id) = ctx.guild_id() else {
+            return Err(t(locale, "setup.server_only", &[]).into());
+        };
+
+        let (embed, components) =
+            self._render(Step::LogChannel, &SetupResult::default(), None, locale);
+
+        let msg = ctx
+            .send(CreateReply::default().embed(embed).components(components))
+            .await?
+            .into_message()
+            .await?;
+
+        self.run(
+            ctx.serenity_context(),
+            guild_id,
+            ctx.author().id,
+            &msg,
+            locale,
+        )
+        .await
+    }
+
+    /// Posts a setup prompt in the system channel of newly joined guilds
+    ///
+    /// This should be called from your bots event handler
+    pub async fn handle_event(
+        &self,
+        ctx: &serenity::Context,
+        event: &FullEvent,
+    ) -> Result<(), Error> {
+        if !self.prompt_on_join {
+            return Ok(());
+        }
+
+        if let FullEvent::GuildCreate {
+            guild,
+            is_new: Some(true),
+        } = event
+        {
+            let Some(channel_id) = guild.system_channel_id else {
+                return Ok(());
+            };
+
+            let locale = Some(&*guild.preferred_locale);
+
+            channel_id
+                .send_message(
+                    &ctx.http,
+                    CreateMessage::new()
+                        .embed(
+                            CreateEmbed::default()
+                                .title(t(locale, "setup.welcome_title", &[]))
+                                .description(t(locale, "setup.welcome", &[]))
+                                .colour(serenity::Colour::BLURPLE),
+                        )
+                        .components(vec![CreateActionRow::Buttons(vec![CreateButton::new(
+                            "setup:start",
+                        )
+                        .label(t(locale, "setup.start", &[]))
+                        .style(serenity::ButtonStyle::Primary)])]),
+                )
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Starts the wizard from the "Start setup" button posted by ``handle_event``, returning false if the interaction is not a setup start
+    ///
+    /// This should be called from your bots event handler on every component interaction
+    pub async fn handle_interaction(
+        &self,
+        ctx: &serenity::Context,
+        interaction: &ComponentInteraction,
+    ) -> Result<bool, Error> {
+        if &*interaction.data.custom_id != "setup:start" {
+            return Ok(false);
+        }
+
+        let Some(guild_id) = interaction.guild_id else {
+            return Ok(false);
+        };
+
+        let locale = Some(&*interaction.locale);
+
+        let allowed = interaction
+            .member
+            .as_ref()
+            .and_then(|m| m.permissions)
+            .is_some_and(|p| p.contains(Permissions::MANAGE_GUILD));
+
+        if !allowed {
+            interaction
+                .create_response(
+                    &ctx.http,
+                    CreateInteractionResponse::Message(
+                        CreateInteractionResponseMessage::new()
+                            .content(t(locale, "setup.missing_permission", &[]))
+                            .ephemeral(true),
+                    ),
+                )
+                .await?;
+            return Ok(true);
+        }
+
+        let (embed, components) =
+            self._render(Step::LogChannel, &SetupResult::default(), None, locale);
+
+        interaction
+            .create_response(
+                &ctx.http,
+                CreateInteractionResponse::UpdateMessage(
+                    CreateInteractionResponseMessage::new()
+                        .embed(embed)
+                        .components(components),
+                ),
+            )
+            .await?;
+
+        self.run(
+            ctx,
+            guild_id,
+            interaction.user.id,
+            &interaction.message,
+            locale,
+        )
+        .await?;
+
+        Ok(true)
+    }
+}
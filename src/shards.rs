@@ -0,0 +1,533 @@
+use poise::serenity_prelude::{self as serenity, ConnectionStage, FullEvent, ShardId};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, RwLock};
+
+use crate::taskman::Task;
+
+/// The last known health of a shard
+#[derive(Debug, Clone)]
+pub struct ShardHealth {
+    pub stage: ConnectionStage,
+    /// When the last gateway event was received on this shard
+    pub last_event: Instant,
+}
+
+/// Serializable snapshot of a shards health
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShardStatus {
+    pub id: u32,
+    pub stage: String,
+    pub connected: bool,
+    /// Seconds since the last gateway event was received on this shard
+    pub last_event_secs: u64,
+}
+
+/// Tracks the connection stage and activity of every shard on this process
+///
+/// This is cheap to clone
+#[derive(Clone, Default)]
+pub struct ShardMonitor {
+    shards: Arc<RwLock<HashMap<ShardId, ShardHealth>>>,
+}
+
+impl ShardMonitor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records an event, this should be called from your bots event handler on every event
+    pub async fn handle_event(&self, ctx: &serenity::Context, event: &FullEvent) {
+        let mut shards = self.shards.write().await;
+
+        match event {
+            FullEvent::ShardStageUpdate { event } => {
+                shards.insert(
+                    event.shard_id,
+                    ShardHealth {
+                        stage: event.new,
+                        last_event: Instant::now(),
+                    },
+                );
+            }
+            FullEvent::Ready { .. } => {
+                shards.insert(
+                    ctx.shard_id,
+                    ShardHealth {
+                        stage: ConnectionStage::Connected,
+                        last_event: Instant::now(),
+                    },
+                );
+            }
+            _ => {
+                shards
+                    .entry(ctx.shard_id)
+                    .and_modify(|h| h.last_event = Instant::now())
+                    .or_insert_with(|| ShardHealth {
+                        stage: ConnectionStage::Connected,
+                        last_event: Instant::now(),
+                    });
+            }
+        }
+    }
+
+    /// Returns the health of a single shard
+    pub async fn get(&self, shard_id: ShardId) -> Option<ShardHealth> {
+        self.shards.read().await.get(&shard_id).cloned()
+    }
+
+    /// Returns the ids of all shards seen on this process
+    pub async fn shard_ids(&self) -> Vec<ShardId> {
+        let mut ids = self.shards.read().await.keys().copied().collect::<Vec<_>>();
+        ids.sort_by_key(|id| id.0);
+        ids
+    }
+
+    /// Returns a serializable snapshot of all shards, sorted by shard id
+    pub async fn snapshot(&self) -> Vec<ShardStatus> {
+        let mut status = self
+            .shards
+            .read()
+            .await
+            .iter()
+            .map(|(id, h)| ShardStatus {
+                id: u32::from(id.0),
+                stage: format!("{:?}", h.stage),
+                connected: h.stage == ConnectionStage::Connected,
+                last_event_secs: h.last_event.elapsed().as_secs(),
+            })
+            .collect::<Vec<_>>();
+
+        status.sort_by_key(|s| s.id);
+
+        status
+    }
+
+    /// Returns true if every known shard is connected
+    pub async fn all_connected(&self) -> bool {
+        self.shards
+            .read()
+            .await
+            .values()
+            .all(|h| h.stage == ConnectionStage::Connected)
+    }
+}
+
+/// Alerts when an event type slows down on a shard compared to its recent baseline
+#[derive(Debug, Clone)]
+pub struct RateRule {
+    /// The event name, as returned by ``FullEvent::snake_case_name`` (such as ``message``)
+    pub event: &'static str,
+    /// Alert when the rate drops by at least this fraction, from 0.0 to 1.0
+    pub drop: f64,
+    /// How long the rate must stay low before alerting
+    pub window: Duration,
+    /// The period before ``window`` the rate is compared against
+    pub baseline: Duration,
+    /// Baselines with fewer events per minute than this are too quiet to judge
+    pub min_baseline_per_minute: f64,
+}
+
+impl RateRule {
+    /// Alerts when ``event`` drops by ``drop`` for 5 minutes compared to the hour before
+    pub fn drop(event: &'static str, drop: f64) -> Self {
+        Self {
+            event,
+            drop,
+            window: Duration::from_secs(5 * 60),
+            baseline: Duration::from_secs(60 * 60),
+            min_baseline_per_minute: 1.0,
+        }
+    }
+}
+
+/// A triggered ``RateRule``
+#[derive(Debug, Clone, PartialEq)]
+pub struct RateAlert {
+    pub shard_id: ShardId,
+    pub event: &'static str,
+    pub baseline_per_minute: f64,
+    pub current_per_minute: f64,
+    pub window: Duration,
+}
+
+impl RateAlert {
+    /// How much the rate dropped, from 0.0 to 1.0
+    pub fn drop(&self) -> f64 {
+        1.0 - self.current_per_minute / self.baseline_per_minute
+    }
+}
+
+impl std::fmt::Display for RateAlert {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} dropped {:.0}% on shard {} for {} minutes ({:.1}/min, usually {:.1}/min)",
+            self.event,
+            self.drop() * 100.0,
+            self.shard_id.0,
+            self.window.as_secs() / 60,
+            self.current_per_minute,
+            self.baseline_per_minute
+        )
+    }
+}
+
+/// Per-minute event counts of a shard and event type
+#[derive(Default)]
+struct _Buckets {
+    /// Minute (since ``EventRates`` was created) and count, oldest first
+    minutes: VecDeque<(u64, u64)>,
+}
+
+impl _Buckets {
+    fn record(&mut self, minute: u64, keep: u64) {
+        match self.minutes.back_mut() {
+            Some((m, count)) if *m == minute => *count += 1,
+            _ => self.minutes.push_back((minute, 1)),
+        }
+
+        while self
+            .minutes
+            .front()
+            .is_some_and(|(m, _)| *m + keep < minute)
+        {
+            self.minutes.pop_front();
+        }
+    }
+
+    /// Events per minute over the minutes in ``[from, to)``
+    fn rate(&self, from: u64, to: u64) -> f64 {
+        let total = self
+            .minutes
+            .iter()
+            .filter(|(m, _)| *m >= from && *m < to)
+            .map(|(_, c)| c)
+            .sum::<u64>();
+
+        total as f64 / (to - from).max(1) as f64
+    }
+}
+
+/// Tracks per-shard, per-event-type rates and raises alerts on sudden drops, catching shards that
+/// still heartbeat but stopped receiving events
+///
+/// Alerts are logged and sent to subscribers once per incident. This is cheap to clone
+#[derive(Clone)]
+pub struct EventRates {
+    started: Instant,
+    rules: Arc<Vec<RateRule>>,
+    buckets: Arc<std::sync::Mutex<HashMap<(ShardId, &'static str), _Buckets>>>,
+    active: Arc<std::sync::Mutex<HashSet<(ShardId, &'static str)>>>,
+    alerts: broadcast::Sender<RateAlert>,
+}
+
+impl EventRates {
+    pub fn new(rules: Vec<RateRule>) -> Self {
+        Self {
+            started: Instant::now(),
+            rules: Arc::new(rules),
+            buckets: Arc::default(),
+            active: Arc::default(),
+            alerts: broadcast::channel(64).0,
+        }
+    }
+
+    /// Returns a receiver of alerts, such as to forward them to a log channel
+    pub fn subscribe(&self) -> broadcast::Receiver<RateAlert> {
+        self.alerts.subscribe()
+    }
+
+    fn _minute(&self) -> u64 {
+        self.started.elapsed().as_secs() / 60
+    }
+
+    /// Counts an event, this should be called from your bots event handler on every event
+    pub fn handle_event(&self, ctx: &serenity::Context, event: &FullEvent) {
+        let name = event.snake_case_name();
+
+        #[cfg(feature = "otel")]
+        crate::telemetry::shard_event_counter().add(
+            1,
+            &[
+                opentelemetry::KeyValue::new("shard", i64::from(ctx.shard_id.0)),
+                opentelemetry::KeyValue::new("event", name),
+            ],
+        );
+
+        let keep = self
+            .rules
+            .iter()
+            .filter(|r| r.event == name)
+            .map(|r| (r.window + r.baseline).as_secs().div_ceil(60))
+            .max();
+
+        // Only events with a rule are bucketed
+        let Some(keep) = keep else {
+            return;
+        };
+
+        self.buckets
+            .lock()
+            .unwrap()
+            .entry((ctx.shard_id, name))
+            .or_default()
+            .record(self._minute(), keep);
+    }
+
+    /// Returns the current rate of an event on a shard, in events per minute over the last ``window``
+    pub fn rate(&self, shard_id: ShardId, event: &'static str, window: Duration) -> f64 {
+        let now = self._minute();
+        let from = now.saturating_sub(window.as_secs().div_ceil(60));
+
+        self.buckets
+            .lock()
+            .unwrap()
+            .get(&(shard_id, event))
+            .map(|b| b.rate(from, now))
+            .unwrap_or_default()
+    }
+
+    /// Evaluates every rule on every shard, returning (and broadcasting) new alerts
+    ///
+    /// A shard and event only alerts again after its rate recovered
+    pub fn check(&self) -> Vec<RateAlert> {
+        let now = self._minute();
+        let buckets = self.buckets.lock().unwrap();
+        let mut active = self.active.lock().unwrap();
+        let mut alerts = Vec::new();
+
+        for ((shard_id, event), b) in buckets.iter() {
+            let Some(rule) = self.rules.iter().find(|r| r.event == *event) else {
+                continue;
+            };
+
+            let window = rule.window.as_secs().div_ceil(60);
+            let baseline = rule.baseline.as_secs().div_ceil(60);
+
+            // Not enough history yet, the current minute is still filling up so it is excluded
+            if now < window + baseline {
+                continue;
+            }
+
+            let window_start = now - window;
+            let baseline_rate = b.rate(window_start - baseline, window_start);
+            let current_rate = b.rate(window_start, now);
+
+            let key = (*shard_id, *event);
+
+            if baseline_rate < rule.min_baseline_per_minute
+                || current_rate > baseline_rate * (1.0 - rule.drop)
+            {
+                active.remove(&key);
+                continue;
+            }
+
+            if !active.insert(key) {
+                continue;
+            }
+
+            alerts.push(RateAlert {
+                shard_id: *shard_id,
+                event,
+                baseline_per_minute: baseline_rate,
+                current_per_minute: current_rate,
+                window: rule.window,
+            });
+        }
+
+        for alert in &alerts {
+            log::error!("Event rate alert: {}", alert);
+            let _ = self.alerts.send(alert.clone());
+        }
+
+        alerts
+    }
+}
+
+/// Returns a task that checks event rates every ``interval``, see ``EventRates::check``
+pub fn event_rate_task(rates: EventRates, interval: Duration) -> Task {
+    Task {
+        name: "event_rates",
+        description: "Alerts when gateway event rates drop on a shard",
+        enabled: true,
+        duration: interval,
+        run: Box::new(move |_ctx| {
+            let rates = rates.clone();
+            Box::pin(async move {
+                rates.check();
+                Ok(())
+            })
+        }),
+    }
+}
+
+/// A sign of gateway or REST instability
+#[derive(Debug, Clone, PartialEq)]
+pub enum GatewayEvent {
+    /// A shard resumed its session after a disconnect
+    Resumed { shard_id: ShardId },
+    /// A connected shard lost its connection and is reconnecting
+    Reconnecting {
+        shard_id: ShardId,
+        stage: ConnectionStage,
+    },
+    /// Discord rejected a resume, so the shard has to identify again and missed events are lost
+    InvalidSession { shard_id: ShardId },
+    /// A REST request hit a 429
+    Ratelimited {
+        /// The route of the bucket, such as ``/channels/{id}/messages``
+        path: String,
+        method: String,
+        /// The request limit of the bucket
+        limit: i64,
+        timeout: Duration,
+        global: bool,
+    },
+}
+
+impl GatewayEvent {
+    /// A short name for labelling metrics, such as ``resumed``
+    pub fn kind(&self) -> &'static str {
+        match self {
+            GatewayEvent::Resumed { .. } => "resumed",
+            GatewayEvent::Reconnecting { .. } => "reconnecting",
+            GatewayEvent::InvalidSession { .. } => "invalid_session",
+            GatewayEvent::Ratelimited { .. } => "ratelimited",
+        }
+    }
+}
+
+impl std::fmt::Display for GatewayEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GatewayEvent::Resumed { shard_id } => write!(f, "Shard {} resumed", shard_id.0),
+            GatewayEvent::Reconnecting { shard_id, stage } => {
+                write!(f, "Shard {} is reconnecting ({:?})", shard_id.0, stage)
+            }
+            GatewayEvent::InvalidSession { shard_id } => {
+                write!(f, "Shard {} had its session invalidated", shard_id.0)
+            }
+            GatewayEvent::Ratelimited {
+                path,
+                method,
+                limit,
+                timeout,
+                global,
+            } => write!(
+                f,
+                "{} {} hit a {}ratelimit (limit {}), retrying in {}ms",
+                method,
+                path,
+                if *global { "global " } else { "" },
+                limit,
+                timeout.as_millis()
+            ),
+        }
+    }
+}
+
+/// Turns resumes, reconnects, invalid sessions and REST 429s into typed ``GatewayEvent``s for
+/// subscribers, so operator bots can alert on instability
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct GatewayEvents {
+    events: broadcast::Sender<GatewayEvent>,
+}
+
+impl Default for GatewayEvents {
+    fn default() -> Self {
+        Self {
+            events: broadcast::channel(256).0,
+        }
+    }
+}
+
+impl GatewayEvents {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns a receiver of gateway events
+    pub fn subscribe(&self) -> broadcast::Receiver<GatewayEvent> {
+        self.events.subscribe()
+    }
+
+    /// Maps a serenity event to a ``GatewayEvent``, if it is one
+    pub fn classify(event: &FullEvent) -> Option<GatewayEvent> {
+        match event {
+            FullEvent::ShardStageUpdate { event } => match (event.old, event.new) {
+                (ConnectionStage::Resuming, ConnectionStage::Connected) => {
+                    Some(GatewayEvent::Resumed {
+                        shard_id: event.shard_id,
+                    })
+                }
+                (ConnectionStage::Resuming, ConnectionStage::Identifying) => {
+                    Some(GatewayEvent::InvalidSession {
+                        shard_id: event.shard_id,
+                    })
+                }
+                (ConnectionStage::Connected, stage) if stage != ConnectionStage::Connected => {
+                    Some(GatewayEvent::Reconnecting {
+                        shard_id: event.shard_id,
+                        stage,
+                    })
+                }
+                _ => None,
+            },
+            FullEvent::Ratelimit { data } => Some(GatewayEvent::Ratelimited {
+                path: data.path.to_string(),
+                method: format!("{:?}", data.method),
+                limit: data.limit,
+                timeout: data.timeout,
+                global: data.global,
+            }),
+            _ => None,
+        }
+    }
+
+    /// Publishes gateway events, this should be called from your bots event handler on every event
+    pub fn handle_event(&self, event: &FullEvent) {
+        let Some(event) = Self::classify(event) else {
+            return;
+        };
+
+        if matches!(
+            event,
+            GatewayEvent::Ratelimited { .. } | GatewayEvent::InvalidSession { .. }
+        ) {
+            log::warn!("{}", event);
+        } else {
+            log::info!("{}", event);
+        }
+
+        #[cfg(feature = "otel")]
+        match &event {
+            GatewayEvent::Ratelimited { path, global, .. } => {
+                crate::telemetry::http_ratelimit_counter().add(
+                    1,
+                    &[
+                        opentelemetry::KeyValue::new("path", path.clone()),
+                        opentelemetry::KeyValue::new("global", *global),
+                    ],
+                )
+            }
+            GatewayEvent::Resumed { shard_id }
+            | GatewayEvent::Reconnecting { shard_id, .. }
+            | GatewayEvent::InvalidSession { shard_id } => {
+                crate::telemetry::gateway_instability_counter().add(
+                    1,
+                    &[
+                        opentelemetry::KeyValue::new("shard", i64::from(shard_id.0)),
+                        opentelemetry::KeyValue::new("kind", event.kind()),
+                    ],
+                )
+            }
+        }
+
+        let _ = self.events.send(event);
+    }
+}
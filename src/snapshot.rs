@@ -0,0 +1,568 @@
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, ChannelType, CreateChannel, EditChannel, EditRole, GuildChannel,
+    GuildId, PermissionOverwrite, PermissionOverwriteType, Permissions, Role, RoleId, Timestamp,
+    UserId,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+use crate::reason::Reason;
+use crate::Error;
+
+/// The current snapshot format version, bumped on breaking changes to the document
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// Who a permission overwrite applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OverwriteTarget {
+    Role(u64),
+    Member(u64),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OverwriteSnapshot {
+    pub target: OverwriteTarget,
+    pub allow: u64,
+    pub deny: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoleSnapshot {
+    pub id: u64,
+    pub name: String,
+    pub colour: u32,
+    pub hoist: bool,
+    pub mentionable: bool,
+    pub permissions: u64,
+    pub position: i64,
+    /// Whether this is the @everyone role
+    pub everyone: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChannelSnapshot {
+    pub id: u64,
+    pub name: String,
+    pub kind: ChannelType,
+    pub parent_id: Option<u64>,
+    pub position: i64,
+    pub topic: Option<String>,
+    pub nsfw: bool,
+    pub overwrites: Vec<OverwriteSnapshot>,
+}
+
+/// A versioned snapshot of a guilds roles, categories, channels and permission overwrites
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuildSnapshot {
+    pub version: u32,
+    pub guild_id: GuildId,
+    pub name: String,
+    pub taken_at: Timestamp,
+    pub roles: Vec<RoleSnapshot>,
+    pub channels: Vec<ChannelSnapshot>,
+}
+
+impl GuildSnapshot {
+    pub fn to_json(&self) -> Result<String, Error> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// Parses a snapshot, rejecting versions newer than this crate understands
+    pub fn from_json(json: &str) -> Result<Self, Error> {
+        let snapshot: Self = serde_json::from_str(json)?;
+
+        if snapshot.version > SNAPSHOT_VERSION {
+            return Err(format!(
+                "Snapshot version {} is newer than the supported version {}",
+                snapshot.version, SNAPSHOT_VERSION
+            )
+            .into());
+        }
+
+        Ok(snapshot)
+    }
+}
+
+fn _role_snapshot(guild_id: GuildId, role: &Role) -> RoleSnapshot {
+    RoleSnapshot {
+        id: role.id.get(),
+        name: role.name.to_string(),
+        colour: role.colour.0,
+        hoist: role.hoist,
+        mentionable: role.mentionable,
+        permissions: role.permissions.bits(),
+        position: role.position as i64,
+        everyone: role.id.get() == guild_id.get(),
+    }
+}
+
+fn _channel_snapshot(channel: &GuildChannel) -> ChannelSnapshot {
+    ChannelSnapshot {
+        id: channel.id.get(),
+        name: channel.name.to_string(),
+        kind: channel.kind,
+        parent_id: channel.parent_id.map(|p| p.get()),
+        position: channel.position as i64,
+        topic: channel.topic.as_ref().map(|t| t.to_string()),
+        nsfw: channel.nsfw,
+        overwrites: channel
+            .permission_overwrites
+            .iter()
+            .filter_map(|o| {
+                let target = match o.kind {
+                    PermissionOverwriteType::Role(id) => OverwriteTarget::Role(id.get()),
+                    PermissionOverwriteType::Member(id) => OverwriteTarget::Member(id.get()),
+                    _ => return None,
+                };
+
+                Some(OverwriteSnapshot {
+                    target,
+                    allow: o.allow.bits(),
+                    deny: o.deny.bits(),
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Takes a snapshot of a guild
+///
+/// Managed roles (bot and integration roles) are skipped as they cannot be recreated
+pub async fn snapshot(http: &serenity::Http, guild_id: GuildId) -> Result<GuildSnapshot, Error> {
+    let guild = http.get_guild(guild_id).await?;
+
+    let mut roles = http
+        .get_guild_roles(guild_id)
+        .await?
+        .iter()
+        .filter(|r| !r.managed)
+        .map(|r| _role_snapshot(guild_id, r))
+        .collect::<Vec<_>>();
+
+    roles.sort_by_key(|r| r.position);
+
+    let mut channels = http
+        .get_channels(guild_id)
+        .await?
+        .iter()
+        .map(_channel_snapshot)
+        .collect::<Vec<_>>();
+
+    // Categories first so restores can create parents before their children
+    channels.sort_by_key(|c| (c.kind != ChannelType::Category, c.position));
+
+    Ok(GuildSnapshot {
+        version: SNAPSHOT_VERSION,
+        guild_id,
+        name: guild.name.to_string(),
+        taken_at: Timestamp::now(),
+        roles,
+        channels,
+    })
+}
+
+/// A single change a restore would make
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub enum Change {
+    CreateRole { name: String },
+    UpdateRole { id: u64, name: String },
+    DeleteRole { id: u64, name: String },
+    CreateChannel { name: String, kind: ChannelType },
+    UpdateChannel { id: u64, name: String },
+    DeleteChannel { id: u64, name: String },
+}
+
+/// The outcome of a restore
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RestoreReport {
+    /// Changes made, or that would be made on a dry run
+    pub changes: Vec<Change>,
+    /// Deletions that failed, the restore continues past these
+    pub errors: Vec<String>,
+}
+
+impl Change {
+    /// Returns a human readable description of the change, for diff previews
+    pub fn describe(&self) -> String {
+        match self {
+            Change::CreateRole { name } => format!("+ role {}", name),
+            Change::UpdateRole { name, .. } => format!("~ role {}", name),
+            Change::DeleteRole { name, .. } => format!("- role {}", name),
+            Change::CreateChannel { name, kind } => format!("+ {:?} #{}", kind, name),
+            Change::UpdateChannel { name, .. } => format!("~ channel #{}", name),
+            Change::DeleteChannel { name, .. } => format!("- channel #{}", name),
+        }
+    }
+}
+
+pub struct RestoreOptions {
+    /// Only compute the changes, do not apply them
+    pub dry_run: bool,
+    /// Delete roles and channels that are not in the snapshot
+    pub delete_extra: bool,
+    /// Time to wait between each change, to stay clear of ratelimits
+    pub pace: Duration,
+    /// Audit log reason attached to every change, such as ``ctx.reason("restoring backup")``
+    pub reason: Reason,
+}
+
+impl Default for RestoreOptions {
+    fn default() -> Self {
+        Self {
+            dry_run: true,
+            delete_extra: false,
+            pace: Duration::from_millis(750),
+            reason: Reason::new("Restoring guild snapshot"),
+        }
+    }
+}
+
+// The current state of the guild being restored into
+struct _Current {
+    roles: Vec<RoleSnapshot>,
+    channels: Vec<ChannelSnapshot>,
+}
+
+impl _Current {
+    async fn fetch(http: &serenity::Http, guild_id: GuildId) -> Result<Self, Error> {
+        let snapshot = snapshot(http, guild_id).await?;
+
+        Ok(Self {
+            roles: snapshot.roles,
+            channels: snapshot.channels,
+        })
+    }
+
+    // Matches by id when restoring into the same guild, otherwise by name
+    fn find_role(&self, same_guild: bool, role: &RoleSnapshot) -> Option<&RoleSnapshot> {
+        if role.everyone {
+            return self.roles.iter().find(|r| r.everyone);
+        }
+
+        if same_guild {
+            if let Some(r) = self.roles.iter().find(|r| r.id == role.id) {
+                return Some(r);
+            }
+        }
+
+        self.roles
+            .iter()
+            .find(|r| !r.everyone && r.name == role.name)
+    }
+
+    fn find_channel(
+        &self,
+        same_guild: bool,
+        channel: &ChannelSnapshot,
+    ) -> Option<&ChannelSnapshot> {
+        if same_guild {
+            if let Some(c) = self.channels.iter().find(|c| c.id == channel.id) {
+                return Some(c);
+            }
+        }
+
+        self.channels
+            .iter()
+            .find(|c| c.name == channel.name && c.kind == channel.kind)
+    }
+}
+
+fn _role_differs(a: &RoleSnapshot, b: &RoleSnapshot) -> bool {
+    a.name != b.name
+        || a.colour != b.colour
+        || a.hoist != b.hoist
+        || a.mentionable != b.mentionable
+        || a.permissions != b.permissions
+}
+
+fn _channel_differs(
+    a: &ChannelSnapshot,
+    b: &ChannelSnapshot,
+    role_map: &HashMap<u64, u64>,
+    channel_map: &HashMap<u64, u64>,
+) -> bool {
+    let parent = a.parent_id.and_then(|p| channel_map.get(&p).copied());
+
+    let overwrites = _merge_overwrites(&a.overwrites, &b.overwrites, role_map);
+
+    a.name != b.name
+        || a.topic != b.topic
+        || a.nsfw != b.nsfw
+        || a.position != b.position
+        || parent != b.parent_id
+        || overwrites.iter().any(|o| !b.overwrites.contains(o))
+        || overwrites.len() != b.overwrites.len()
+}
+
+fn _map_overwrites(
+    overwrites: &[OverwriteSnapshot],
+    role_map: &HashMap<u64, u64>,
+) -> Vec<OverwriteSnapshot> {
+    overwrites
+        .iter()
+        .filter_map(|o| {
+            let target = match o.target {
+                OverwriteTarget::Role(id) => OverwriteTarget::Role(*role_map.get(&id)?),
+                OverwriteTarget::Member(id) => OverwriteTarget::Member(id),
+            };
+
+            Some(OverwriteSnapshot {
+                target,
+                allow: o.allow,
+                deny: o.deny,
+            })
+        })
+        .collect()
+}
+
+/// Maps the overwrites of a snapshot channel and keeps the existing overwrites of roles the
+/// snapshot does not know about, such as managed bot and integration roles
+fn _merge_overwrites(
+    overwrites: &[OverwriteSnapshot],
+    existing: &[OverwriteSnapshot],
+    role_map: &HashMap<u64, u64>,
+) -> Vec<OverwriteSnapshot> {
+    let mut merged = _map_overwrites(overwrites, role_map);
+
+    for o in existing {
+        if let OverwriteTarget::Role(id) = o.target {
+            if !role_map.values().any(|r| *r == id) {
+                merged.push(o.clone());
+            }
+        }
+    }
+
+    merged
+}
+
+fn _to_overwrites(overwrites: &[OverwriteSnapshot]) -> Vec<PermissionOverwrite> {
+    overwrites
+        .iter()
+        .map(|o| PermissionOverwrite {
+            allow: Permissions::from_bits_truncate(o.allow),
+            deny: Permissions::from_bits_truncate(o.deny),
+            kind: match o.target {
+                OverwriteTarget::Role(id) => PermissionOverwriteType::Role(RoleId::new(id)),
+                OverwriteTarget::Member(id) => PermissionOverwriteType::Member(UserId::new(id)),
+            },
+        })
+        .collect()
+}
+
+fn _edit_role(role: &RoleSnapshot) -> EditRole<'_> {
+    EditRole::new()
+        .name(&role.name)
+        .colour(role.colour)
+        .hoist(role.hoist)
+        .mentionable(role.mentionable)
+        .permissions(Permissions::from_bits_truncate(role.permissions))
+}
+
+/// Restores a snapshot into a guild, returning the changes made (or that would be made on a dry run)
+///
+/// Roles and channels are matched by id when restoring into the guild the snapshot was taken from,
+/// and by name otherwise. Role positions are not reordered. Overwrites of roles missing from the
+/// snapshot (such as managed roles) are kept
+pub async fn restore(
+    http: &serenity::Http,
+    guild_id: GuildId,
+    snapshot: &GuildSnapshot,
+    opts: &RestoreOptions,
+) -> Result<RestoreReport, Error> {
+    let same_guild = snapshot.guild_id == guild_id;
+    let current = _Current::fetch(http, guild_id).await?;
+
+    let mut changes = Vec::new();
+    let mut errors = Vec::new();
+
+    // Snapshot role id -> role id in the target guild
+    let mut role_map = HashMap::new();
+
+    for role in &snapshot.roles {
+        match current.find_role(same_guild, role) {
+            Some(existing) => {
+                role_map.insert(role.id, existing.id);
+
+                if _role_differs(role, existing) {
+                    changes.push(Change::UpdateRole {
+                        id: existing.id,
+                        name: role.name.clone(),
+                    });
+
+                    if !opts.dry_run {
+                        guild_id
+                            .edit_role(
+                                http,
+                                RoleId::new(existing.id),
+                                _edit_role(role).audit_log_reason(opts.reason.as_str()),
+                            )
+                            .await?;
+                        tokio::time::sleep(opts.pace).await;
+                    }
+                }
+            }
+            None => {
+                changes.push(Change::CreateRole {
+                    name: role.name.clone(),
+                });
+
+                if !opts.dry_run {
+                    let created = guild_id
+                        .create_role(
+                            http,
+                            _edit_role(role).audit_log_reason(opts.reason.as_str()),
+                        )
+                        .await?;
+                    role_map.insert(role.id, created.id.get());
+                    tokio::time::sleep(opts.pace).await;
+                } else {
+                    // Placeholder so overwrites referencing this role still show up in the diff
+                    role_map.insert(role.id, role.id);
+                }
+            }
+        }
+    }
+
+    // Snapshot channel id -> channel id in the target guild
+    let mut channel_map = HashMap::new();
+
+    for channel in &snapshot.channels {
+        let parent = channel
+            .parent_id
+            .and_then(|p| channel_map.get(&p).copied())
+            .map(ChannelId::new);
+
+        match current.find_channel(same_guild, channel) {
+            Some(existing) => {
+                channel_map.insert(channel.id, existing.id);
+
+                if _channel_differs(channel, existing, &role_map, &channel_map) {
+                    changes.push(Change::UpdateChannel {
+                        id: existing.id,
+                        name: channel.name.clone(),
+                    });
+
+                    if !opts.dry_run {
+                        let overwrites = _to_overwrites(&_merge_overwrites(
+                            &channel.overwrites,
+                            &existing.overwrites,
+                            &role_map,
+                        ));
+
+                        let mut edit = EditChannel::new()
+                            .name(&channel.name)
+                            .nsfw(channel.nsfw)
+                            .category(parent)
+                            .position(channel.position as u16)
+                            .permissions(overwrites)
+                            .audit_log_reason(opts.reason.as_str());
+
+                        if let Some(topic) = &channel.topic {
+                            edit = edit.topic(topic);
+                        }
+
+                        ChannelId::new(existing.id).edit(http, edit).await?;
+                        tokio::time::sleep(opts.pace).await;
+                    }
+                }
+            }
+            None => {
+                changes.push(Change::CreateChannel {
+                    name: channel.name.clone(),
+                    kind: channel.kind,
+                });
+
+                if !opts.dry_run {
+                    let overwrites =
+                        _to_overwrites(&_map_overwrites(&channel.overwrites, &role_map));
+
+                    let mut create = CreateChannel::new(&channel.name)
+                        .kind(channel.kind)
+                        .nsfw(channel.nsfw)
+                        .position(channel.position as u16)
+                        .permissions(overwrites)
+                        .audit_log_reason(opts.reason.as_str());
+
+                    if let Some(parent) = parent {
+                        create = create.category(parent);
+                    }
+
+                    if let Some(topic) = &channel.topic {
+                        create = create.topic(topic);
+                    }
+
+                    let created = guild_id.create_channel(http, create).await?;
+                    channel_map.insert(channel.id, created.id.get());
+                    tokio::time::sleep(opts.pace).await;
+                } else {
+                    channel_map.insert(channel.id, channel.id);
+                }
+            }
+        }
+    }
+
+    if opts.delete_extra {
+        let kept_roles = role_map.values().copied().collect::<Vec<_>>();
+        let kept_channels = channel_map.values().copied().collect::<Vec<_>>();
+
+        for role in current
+            .roles
+            .iter()
+            .filter(|r| !r.everyone && !kept_roles.contains(&r.id))
+        {
+            changes.push(Change::DeleteRole {
+                id: role.id,
+                name: role.name.clone(),
+            });
+
+            if !opts.dry_run {
+                if let Err(e) = guild_id
+                    .delete_role(http, RoleId::new(role.id), Some(opts.reason.as_str()))
+                    .await
+                {
+                    errors.push(format!("Failed to delete role {}: {}", role.name, e));
+                }
+
+                tokio::time::sleep(opts.pace).await;
+            }
+        }
+
+        for channel in current
+            .channels
+            .iter()
+            .filter(|c| !kept_channels.contains(&c.id))
+        {
+            changes.push(Change::DeleteChannel {
+                id: channel.id,
+                name: channel.name.clone(),
+            });
+
+            if !opts.dry_run {
+                if let Err(e) = ChannelId::new(channel.id)
+                    .delete(http, Some(opts.reason.as_str()))
+                    .await
+                {
+                    errors.push(format!("Failed to delete channel #{}: {}", channel.name, e));
+                }
+
+                tokio::time::sleep(opts.pace).await;
+            }
+        }
+    }
+
+    Ok(RestoreReport { changes, errors })
+}
+
+/// Renders a list of changes as a diff preview
+pub fn preview(changes: &[Change]) -> String {
+    if changes.is_empty() {
+        return "No changes".to_string();
+    }
+
+    changes
+        .iter()
+        .map(Change::describe)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -0,0 +1,72 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Tracks one ``tracing`` span per in-flight command invocation
+///
+/// poise does not allow wrapping command execution, so the span is opened in ``pre_command``
+/// and closed in ``post_command`` (or ``on_error``), covering the full invocation. This is cheap to clone
+#[derive(Clone, Default)]
+pub struct CommandSpans {
+    spans: Arc<Mutex<HashMap<u64, (tracing::Span, Instant)>>>,
+}
+
+impl CommandSpans {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens the span for a command invocation, this should be called from your bots ``pre_command`` hook
+    pub fn start<Data: Send + Sync + 'static>(&self, ctx: poise::Context<'_, Data, crate::Error>) {
+        let span = tracing::info_span!(
+            "command",
+            command = %ctx.command().qualified_name,
+            guild_id = ctx.guild_id().map(|g| g.get()),
+            channel_id = ctx.channel_id().get(),
+            user_id = ctx.author().id.get(),
+            shard_id = ctx.serenity_context().shard_id.0,
+            prefix = matches!(ctx, poise::Context::Prefix(_)),
+            error = tracing::field::Empty,
+            elapsed_ms = tracing::field::Empty,
+        );
+
+        self.spans
+            .lock()
+            .unwrap()
+            .insert(ctx.id(), (span, Instant::now()));
+    }
+
+    /// Closes the span for a command invocation, this should be called from your bots ``post_command`` hook
+    pub fn finish<Data: Send + Sync + 'static>(&self, ctx: poise::Context<'_, Data, crate::Error>) {
+        self.finish_with_error(ctx, None);
+    }
+
+    /// Closes the span for a failed command invocation, this should be called from your bots ``on_error`` handler
+    pub fn finish_with_error<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+        error: Option<&str>,
+    ) {
+        let Some((span, started)) = self.spans.lock().unwrap().remove(&ctx.id()) else {
+            return;
+        };
+
+        span.record("elapsed_ms", started.elapsed().as_millis() as u64);
+
+        if let Some(error) = error {
+            span.record("error", error);
+        }
+    }
+
+    /// Returns the span of an in-flight command invocation, for parenting spans created by the command
+    pub fn current<Data: Send + Sync + 'static>(
+        &self,
+        ctx: poise::Context<'_, Data, crate::Error>,
+    ) -> Option<tracing::Span> {
+        self.spans
+            .lock()
+            .unwrap()
+            .get(&ctx.id())
+            .map(|(span, _)| span.clone())
+    }
+}
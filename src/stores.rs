@@ -0,0 +1,9 @@
+#[cfg(feature = "redis")]
+pub mod redis;
+
+#[cfg(feature = "postgres")]
+pub mod postgres;
+
+pub mod doc;
+
+pub use doc::{DocStore, FileDocStore, MemoryDocStore, TypedDocs};
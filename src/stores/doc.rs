@@ -0,0 +1,348 @@
+use futures::future::BoxFuture;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+
+use crate::Error;
+
+/// A namespaced store of JSON documents, for subsystems without a dedicated database backend yet
+///
+/// Use ``TypedDocs`` (or the typed helpers on ``dyn DocStore``) to read and write serde types
+pub trait DocStore: Send + Sync {
+    fn get_raw<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<Option<serde_json::Value>, Error>>;
+
+    /// Adds or replaces a document
+    fn put_raw<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+        value: serde_json::Value,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Deletes a document, returning whether it existed
+    fn delete<'a>(&'a self, namespace: &'a str, key: &'a str)
+        -> BoxFuture<'a, Result<bool, Error>>;
+
+    /// Returns the keys of a namespace starting with ``prefix``, sorted
+    fn list<'a>(
+        &'a self,
+        namespace: &'a str,
+        prefix: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, Error>>;
+}
+
+impl dyn DocStore {
+    /// Fetches and deserializes a document
+    pub async fn get<T: DeserializeOwned>(
+        &self,
+        namespace: &str,
+        key: &str,
+    ) -> Result<Option<T>, Error> {
+        match self.get_raw(namespace, key).await? {
+            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Serializes and stores a document
+    pub async fn put<T: Serialize>(
+        &self,
+        namespace: &str,
+        key: &str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.put_raw(namespace, key, serde_json::to_value(value)?)
+            .await
+    }
+}
+
+/// A single namespace of a ``DocStore`` holding documents of one type
+///
+/// This is cheap to clone
+pub struct TypedDocs<T> {
+    store: Arc<dyn DocStore>,
+    namespace: String,
+    _marker: PhantomData<fn() -> T>,
+}
+
+impl<T> Clone for TypedDocs<T> {
+    fn clone(&self) -> Self {
+        Self {
+            store: self.store.clone(),
+            namespace: self.namespace.clone(),
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<T: Serialize + DeserializeOwned> TypedDocs<T> {
+    pub fn new(store: Arc<dyn DocStore>, namespace: impl Into<String>) -> Self {
+        Self {
+            store,
+            namespace: namespace.into(),
+            _marker: PhantomData,
+        }
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Option<T>, Error> {
+        self.store.get(&self.namespace, key).await
+    }
+
+    pub async fn put(&self, key: &str, value: &T) -> Result<(), Error> {
+        self.store.put(&self.namespace, key, value).await
+    }
+
+    pub async fn delete(&self, key: &str) -> Result<bool, Error> {
+        self.store.delete(&self.namespace, key).await
+    }
+
+    /// Returns the documents whose key starts with ``prefix``, sorted by key
+    pub async fn list(&self, prefix: &str) -> Result<Vec<(String, T)>, Error> {
+        let mut docs = Vec::new();
+
+        for key in self.store.list(&self.namespace, prefix).await? {
+            // The document may have been deleted since listing
+            if let Some(doc) = self.get(&key).await? {
+                docs.push((key, doc));
+            }
+        }
+
+        Ok(docs)
+    }
+}
+
+/// In-memory ``DocStore``, documents are lost on restart
+///
+/// This is cheap to clone
+#[derive(Clone, Default)]
+pub struct MemoryDocStore {
+    docs: Arc<RwLock<HashMap<String, BTreeMap<String, serde_json::Value>>>>,
+}
+
+impl MemoryDocStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl DocStore for MemoryDocStore {
+    fn get_raw<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<Option<serde_json::Value>, Error>> {
+        Box::pin(async move {
+            Ok(self
+                .docs
+                .read()
+                .await
+                .get(namespace)
+                .and_then(|ns| ns.get(key))
+                .cloned())
+        })
+    }
+
+    fn put_raw<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+        value: serde_json::Value,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.docs
+                .write()
+                .await
+                .entry(namespace.to_string())
+                .or_default()
+                .insert(key.to_string(), value);
+
+            Ok(())
+        })
+    }
+
+    fn delete<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            Ok(self
+                .docs
+                .write()
+                .await
+                .get_mut(namespace)
+                .and_then(|ns| ns.remove(key))
+                .is_some())
+        })
+    }
+
+    fn list<'a>(
+        &'a self,
+        namespace: &'a str,
+        prefix: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, Error>> {
+        Box::pin(async move {
+            Ok(self
+                .docs
+                .read()
+                .await
+                .get(namespace)
+                .map(|ns| {
+                    ns.range(prefix.to_string()..)
+                        .take_while(|(k, _)| k.starts_with(prefix))
+                        .map(|(k, _)| k.clone())
+                        .collect()
+                })
+                .unwrap_or_default())
+        })
+    }
+}
+
+/// File-backed ``DocStore`` storing each document as a JSON file, under one directory per namespace
+///
+/// Writes go through a temporary file and a rename, so a crash never leaves a half-written document
+#[derive(Clone)]
+pub struct FileDocStore {
+    root: PathBuf,
+}
+
+impl FileDocStore {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    // Hex-encodes anything that is not safe in a file name, so keys like "guild:123" work on every platform
+    fn _encode(name: &str) -> String {
+        let mut out = String::with_capacity(name.len());
+
+        for b in name.bytes() {
+            if b.is_ascii_alphanumeric() || b == b'-' || b == b'_' {
+                out.push(b as char);
+            } else {
+                out.push_str(&format!("%{:02x}", b));
+            }
+        }
+
+        out
+    }
+
+    fn _decode(name: &str) -> Option<String> {
+        let mut bytes = Vec::with_capacity(name.len());
+        let mut iter = name.bytes();
+
+        while let Some(b) = iter.next() {
+            if b == b'%' {
+                let hex = [iter.next()?, iter.next()?];
+                bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+            } else {
+                bytes.push(b);
+            }
+        }
+
+        String::from_utf8(bytes).ok()
+    }
+
+    fn _path(&self, namespace: &str, key: &str) -> PathBuf {
+        self.root
+            .join(Self::_encode(namespace))
+            .join(format!("{}.json", Self::_encode(key)))
+    }
+}
+
+impl DocStore for FileDocStore {
+    fn get_raw<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<Option<serde_json::Value>, Error>> {
+        Box::pin(async move {
+            match tokio::fs::read(self._path(namespace, key)).await {
+                Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn put_raw<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+        value: serde_json::Value,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let path = self._path(namespace, key);
+
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+
+            let tmp = path.with_extension("json.tmp");
+
+            tokio::fs::write(&tmp, serde_json::to_vec(&value)?).await?;
+            tokio::fs::rename(&tmp, &path).await?;
+
+            Ok(())
+        })
+    }
+
+    fn delete<'a>(
+        &'a self,
+        namespace: &'a str,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            match tokio::fs::remove_file(self._path(namespace, key)).await {
+                Ok(()) => Ok(true),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(false),
+                Err(e) => Err(e.into()),
+            }
+        })
+    }
+
+    fn list<'a>(
+        &'a self,
+        namespace: &'a str,
+        prefix: &'a str,
+    ) -> BoxFuture<'a, Result<Vec<String>, Error>> {
+        Box::pin(async move {
+            let dir = self.root.join(Self::_encode(namespace));
+
+            let mut entries = match tokio::fs::read_dir(&dir).await {
+                Ok(entries) => entries,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+                Err(e) => return Err(e.into()),
+            };
+
+            let mut keys = Vec::new();
+
+            while let Some(entry) = entries.next_entry().await? {
+                let name = entry.file_name();
+
+                let Some(key) = name
+                    .to_str()
+                    .and_then(|n| n.strip_suffix(".json"))
+                    .and_then(Self::_decode)
+                else {
+                    continue;
+                };
+
+                if key.starts_with(prefix) {
+                    keys.push(key);
+                }
+            }
+
+            keys.sort();
+
+            Ok(keys)
+        })
+    }
+}
@@ -0,0 +1,423 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{ChannelId, GuildId, MessageId, Timestamp, UserId};
+use sqlx::{PgPool, Row};
+
+use crate::blacklist::{BlacklistEntry, BlacklistKind, BlacklistStore};
+use crate::checks::{ChannelRestriction, ChannelRestrictionStore};
+use crate::devtools::PingProbe;
+use crate::prefixes::PrefixStore;
+use crate::suggestions::{Suggestion, SuggestionStatus, SuggestionStore, Vote};
+use crate::Error;
+
+/// Postgres implementation of the crates store traits
+///
+/// Call ``migrate`` once on startup to create the tables this store needs
+#[derive(Clone)]
+pub struct PgStore {
+    pool: PgPool,
+}
+
+impl PgStore {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+
+    /// Connects to postgres, ``url`` is a connection URL such as ``postgres://user@localhost/db``
+    pub async fn connect(url: &str) -> Result<Self, Error> {
+        Ok(Self {
+            pool: PgPool::connect(url).await?,
+        })
+    }
+
+    pub fn pool(&self) -> &PgPool {
+        &self.pool
+    }
+
+    /// Runs the crates migrations, creating or updating the ``botox_*`` tables
+    pub async fn migrate(&self) -> Result<(), Error> {
+        sqlx::migrate!("./migrations/postgres")
+            .run(&self.pool)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Sets the channel suggestions are posted to in a guild
+    pub async fn set_suggestion_channel(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> Result<(), Error> {
+        sqlx::query(
+            "INSERT INTO botox_suggestion_channels (guild_id, channel_id) VALUES ($1, $2)
+            ON CONFLICT (guild_id) DO UPDATE SET channel_id = EXCLUDED.channel_id",
+        )
+        .bind(guild_id.get() as i64)
+        .bind(channel_id.get() as i64)
+        .execute(&self.pool)
+        .await?;
+
+        Ok(())
+    }
+}
+
+fn _status_to_str(status: SuggestionStatus) -> &'static str {
+    match status {
+        SuggestionStatus::Pending => "pending",
+        SuggestionStatus::Approved => "approved",
+        SuggestionStatus::Denied => "denied",
+        SuggestionStatus::Implemented => "implemented",
+    }
+}
+
+fn _status_from_str(status: &str) -> Result<SuggestionStatus, Error> {
+    match status {
+        "pending" => Ok(SuggestionStatus::Pending),
+        "approved" => Ok(SuggestionStatus::Approved),
+        "denied" => Ok(SuggestionStatus::Denied),
+        "implemented" => Ok(SuggestionStatus::Implemented),
+        _ => Err(format!("Unknown suggestion status: {}", status).into()),
+    }
+}
+
+const SUGGESTION_COLUMNS: &str =
+    "id, guild_id, channel_id, message_id, author_id, content, status, votes";
+
+fn _suggestion_from_row(row: &sqlx::postgres::PgRow) -> Result<Suggestion, Error> {
+    let status: String = row.try_get("status")?;
+    let votes: serde_json::Value = row.try_get("votes")?;
+
+    Ok(Suggestion {
+        id: row.try_get("id")?,
+        guild_id: GuildId::new(row.try_get::<i64, _>("guild_id")? as u64),
+        channel_id: ChannelId::new(row.try_get::<i64, _>("channel_id")? as u64),
+        message_id: MessageId::new(row.try_get::<i64, _>("message_id")? as u64),
+        author_id: UserId::new(row.try_get::<i64, _>("author_id")? as u64),
+        content: row.try_get("content")?,
+        status: _status_from_str(&status)?,
+        votes: serde_json::from_value(votes)?,
+    })
+}
+
+fn _kind_to_str(kind: BlacklistKind) -> &'static str {
+    match kind {
+        BlacklistKind::User => "user",
+        BlacklistKind::Guild => "guild",
+    }
+}
+
+fn _entry_from_row(
+    kind: BlacklistKind,
+    row: &sqlx::postgres::PgRow,
+) -> Result<BlacklistEntry, Error> {
+    let added_at: i64 = row.try_get("added_at")?;
+
+    Ok(BlacklistEntry {
+        kind,
+        id: row.try_get::<i64, _>("id")? as u64,
+        reason: row.try_get("reason")?,
+        added_by: UserId::new(row.try_get::<i64, _>("added_by")? as u64),
+        added_at: Timestamp::from_unix_timestamp(added_at)?,
+    })
+}
+
+impl SuggestionStore for PgStore {
+    fn suggestion_channel<'a>(
+        &'a self,
+        guild_id: GuildId,
+    ) -> BoxFuture<'a, Result<Option<ChannelId>, Error>> {
+        Box::pin(async move {
+            let channel_id: Option<i64> = sqlx::query_scalar(
+                "SELECT channel_id FROM botox_suggestion_channels WHERE guild_id = $1",
+            )
+            .bind(guild_id.get() as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+
+            Ok(channel_id.map(|c| ChannelId::new(c as u64)))
+        })
+    }
+
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<Suggestion>, Error>> {
+        Box::pin(async move {
+            let row = sqlx::query(&format!(
+                "SELECT {} FROM botox_suggestions WHERE id = $1",
+                SUGGESTION_COLUMNS
+            ))
+            .bind(id)
+            .fetch_optional(&self.pool)
+            .await?;
+
+            row.as_ref().map(_suggestion_from_row).transpose()
+        })
+    }
+
+    fn save<'a>(&'a self, suggestion: &'a Suggestion) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            sqlx::query(
+                "INSERT INTO botox_suggestions (id, guild_id, channel_id, message_id, author_id, content, status, votes)
+                VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+                ON CONFLICT (id) DO UPDATE SET
+                    channel_id = EXCLUDED.channel_id,
+                    message_id = EXCLUDED.message_id,
+                    content = EXCLUDED.content,
+                    status = EXCLUDED.status,
+                    votes = EXCLUDED.votes",
+            )
+            .bind(&suggestion.id)
+            .bind(suggestion.guild_id.get() as i64)
+            .bind(suggestion.channel_id.get() as i64)
+            .bind(suggestion.message_id.get() as i64)
+            .bind(suggestion.author_id.get() as i64)
+            .bind(&suggestion.content)
+            .bind(_status_to_str(suggestion.status))
+            .bind(serde_json::to_value(&suggestion.votes)?)
+            .execute(&self.pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    fn toggle_vote<'a>(
+        &'a self,
+        id: &'a str,
+        user_id: UserId,
+        vote: Vote,
+    ) -> BoxFuture<'a, Result<Option<Suggestion>, Error>> {
+        Box::pin(async move {
+            let vote = serde_json::to_value(vote)?;
+
+            let row = sqlx::query(&format!(
+                "UPDATE botox_suggestions SET votes = CASE
+                    WHEN votes->>$2::text = $3::text THEN votes - $2::text
+                    ELSE votes || jsonb_build_object($2::text, $3::text)
+                END
+                WHERE id = $1 AND status = 'pending'
+                RETURNING {}",
+                SUGGESTION_COLUMNS
+            ))
+            .bind(id)
+            .bind(user_id.to_string())
+            .bind(vote.as_str())
+            .fetch_optional(&self.pool)
+            .await?;
+
+            row.as_ref().map(_suggestion_from_row).transpose()
+        })
+    }
+
+    fn set_status<'a>(
+        &'a self,
+        id: &'a str,
+        status: SuggestionStatus,
+    ) -> BoxFuture<'a, Result<Option<Suggestion>, Error>> {
+        Box::pin(async move {
+            let row = sqlx::query(&format!(
+                "UPDATE botox_suggestions SET status = $2 WHERE id = $1 RETURNING {}",
+                SUGGESTION_COLUMNS
+            ))
+            .bind(id)
+            .bind(_status_to_str(status))
+            .fetch_optional(&self.pool)
+            .await?;
+
+            row.as_ref().map(_suggestion_from_row).transpose()
+        })
+    }
+}
+
+impl PrefixStore for PgStore {
+    fn get<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<Option<String>, Error>> {
+        Box::pin(async move {
+            Ok(
+                sqlx::query_scalar("SELECT prefix FROM botox_prefixes WHERE guild_id = $1")
+                    .bind(guild_id.get() as i64)
+                    .fetch_optional(&self.pool)
+                    .await?,
+            )
+        })
+    }
+
+    fn set<'a>(&'a self, guild_id: GuildId, prefix: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            sqlx::query(
+                "INSERT INTO botox_prefixes (guild_id, prefix) VALUES ($1, $2)
+                ON CONFLICT (guild_id) DO UPDATE SET prefix = EXCLUDED.prefix",
+            )
+            .bind(guild_id.get() as i64)
+            .bind(prefix)
+            .execute(&self.pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    fn reset<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            sqlx::query("DELETE FROM botox_prefixes WHERE guild_id = $1")
+                .bind(guild_id.get() as i64)
+                .execute(&self.pool)
+                .await?;
+
+            Ok(())
+        })
+    }
+}
+
+impl BlacklistStore for PgStore {
+    fn get<'a>(
+        &'a self,
+        kind: BlacklistKind,
+        id: u64,
+    ) -> BoxFuture<'a, Result<Option<BlacklistEntry>, Error>> {
+        Box::pin(async move {
+            let row = sqlx::query(
+                "SELECT id, reason, added_by, EXTRACT(EPOCH FROM added_at)::BIGINT AS added_at
+                FROM botox_blacklist WHERE kind = $1 AND id = $2",
+            )
+            .bind(_kind_to_str(kind))
+            .bind(id as i64)
+            .fetch_optional(&self.pool)
+            .await?;
+
+            row.map(|row| _entry_from_row(kind, &row)).transpose()
+        })
+    }
+
+    fn add<'a>(&'a self, entry: &'a BlacklistEntry) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            sqlx::query(
+                "INSERT INTO botox_blacklist (kind, id, reason, added_by, added_at)
+                VALUES ($1, $2, $3, $4, to_timestamp($5))
+                ON CONFLICT (kind, id) DO UPDATE SET
+                    reason = EXCLUDED.reason,
+                    added_by = EXCLUDED.added_by,
+                    added_at = EXCLUDED.added_at",
+            )
+            .bind(_kind_to_str(entry.kind))
+            .bind(entry.id as i64)
+            .bind(&entry.reason)
+            .bind(entry.added_by.get() as i64)
+            .bind(entry.added_at.unix_timestamp() as f64)
+            .execute(&self.pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    fn remove<'a>(&'a self, kind: BlacklistKind, id: u64) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let res = sqlx::query("DELETE FROM botox_blacklist WHERE kind = $1 AND id = $2")
+                .bind(_kind_to_str(kind))
+                .bind(id as i64)
+                .execute(&self.pool)
+                .await?;
+
+            Ok(res.rows_affected() > 0)
+        })
+    }
+
+    fn list<'a>(
+        &'a self,
+        kind: BlacklistKind,
+    ) -> BoxFuture<'a, Result<Vec<BlacklistEntry>, Error>> {
+        Box::pin(async move {
+            let rows = sqlx::query(
+                "SELECT id, reason, added_by, EXTRACT(EPOCH FROM added_at)::BIGINT AS added_at
+                FROM botox_blacklist WHERE kind = $1 ORDER BY added_at",
+            )
+            .bind(_kind_to_str(kind))
+            .fetch_all(&self.pool)
+            .await?;
+
+            rows.iter().map(|row| _entry_from_row(kind, row)).collect()
+        })
+    }
+}
+
+impl ChannelRestrictionStore for PgStore {
+    fn get<'a>(
+        &'a self,
+        guild_id: GuildId,
+    ) -> BoxFuture<'a, Result<Option<ChannelRestriction>, Error>> {
+        Box::pin(async move {
+            let Some(row) = sqlx::query(
+                "SELECT allowed, denied, bypass_managers FROM botox_channel_restrictions WHERE guild_id = $1",
+            )
+            .bind(guild_id.get() as i64)
+            .fetch_optional(&self.pool)
+            .await?
+            else {
+                return Ok(None);
+            };
+
+            let allowed: Vec<i64> = row.try_get("allowed")?;
+            let denied: Vec<i64> = row.try_get("denied")?;
+
+            Ok(Some(ChannelRestriction {
+                allowed: allowed
+                    .into_iter()
+                    .map(|c| ChannelId::new(c as u64))
+                    .collect(),
+                denied: denied
+                    .into_iter()
+                    .map(|c| ChannelId::new(c as u64))
+                    .collect(),
+                bypass_managers: row.try_get("bypass_managers")?,
+            }))
+        })
+    }
+
+    fn set<'a>(
+        &'a self,
+        guild_id: GuildId,
+        restriction: &'a ChannelRestriction,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            sqlx::query(
+                "INSERT INTO botox_channel_restrictions (guild_id, allowed, denied, bypass_managers)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (guild_id) DO UPDATE SET
+                    allowed = EXCLUDED.allowed,
+                    denied = EXCLUDED.denied,
+                    bypass_managers = EXCLUDED.bypass_managers",
+            )
+            .bind(guild_id.get() as i64)
+            .bind(
+                restriction
+                    .allowed
+                    .iter()
+                    .map(|c| c.get() as i64)
+                    .collect::<Vec<_>>(),
+            )
+            .bind(
+                restriction
+                    .denied
+                    .iter()
+                    .map(|c| c.get() as i64)
+                    .collect::<Vec<_>>(),
+            )
+            .bind(restriction.bypass_managers)
+            .execute(&self.pool)
+            .await?;
+
+            Ok(())
+        })
+    }
+}
+
+impl PingProbe for PgStore {
+    fn name(&self) -> &str {
+        "Postgres"
+    }
+
+    fn ping<'a>(&'a self) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            sqlx::query("SELECT 1").execute(&self.pool).await?;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,379 @@
+use ::redis::aio::ConnectionManager;
+use ::redis::AsyncCommands;
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{ChannelId, GuildId, UserId};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::time::Duration;
+
+use crate::blacklist::{BlacklistEntry, BlacklistKind, BlacklistStore};
+use crate::checks::{ChannelRestriction, ChannelRestrictionStore};
+use crate::devtools::PingProbe;
+use crate::prefixes::PrefixStore;
+use crate::suggestions::{Suggestion, SuggestionStatus, SuggestionStore, Vote};
+use crate::Error;
+
+/// Redis implementation of the crates store traits
+///
+/// All keys are prefixed with ``namespace`` so multiple bots can share a redis instance. The
+/// underlying ``ConnectionManager`` multiplexes a single auto-reconnecting connection, so this is
+/// cheap to clone and share between tasks and processes
+#[derive(Clone)]
+pub struct RedisStore {
+    conn: ConnectionManager,
+    namespace: String,
+}
+
+impl RedisStore {
+    /// Connects to redis, ``url`` is a redis connection URL such as ``redis://127.0.0.1/``
+    pub async fn connect(url: &str, namespace: impl Into<String>) -> Result<Self, Error> {
+        let client = ::redis::Client::open(url)?;
+        let conn = ConnectionManager::new(client).await?;
+
+        Ok(Self {
+            conn,
+            namespace: namespace.into(),
+        })
+    }
+
+    /// Creates a store from an existing connection manager
+    pub fn from_manager(conn: ConnectionManager, namespace: impl Into<String>) -> Self {
+        Self {
+            conn,
+            namespace: namespace.into(),
+        }
+    }
+
+    /// Returns a namespaced key from its parts
+    pub fn key(&self, parts: &[&str]) -> String {
+        format!("{}:{}", self.namespace, parts.join(":"))
+    }
+
+    /// Returns a clone of the underlying connection, for running custom commands
+    pub fn connection(&self) -> ConnectionManager {
+        self.conn.clone()
+    }
+
+    /// Fetches and deserializes a JSON value
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn get_json<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>, Error> {
+        let mut conn = self.conn.clone();
+        let value: Option<String> = conn.get(key).await?;
+
+        match value {
+            Some(value) => Ok(Some(serde_json::from_str(&value)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Serializes and stores a JSON value, expiring it after ``ttl`` if set
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self, value)))]
+    pub async fn set_json<T: Serialize>(
+        &self,
+        key: &str,
+        value: &T,
+        ttl: Option<Duration>,
+    ) -> Result<(), Error> {
+        let mut conn = self.conn.clone();
+        let value = serde_json::to_string(value)?;
+
+        match ttl {
+            // Redis rejects an expiry of 0, so round sub-second TTLs up
+            Some(ttl) => {
+                conn.set_ex::<_, _, ()>(key, value, ttl.as_secs().max(1))
+                    .await?
+            }
+            None => conn.set::<_, _, ()>(key, value).await?,
+        }
+
+        Ok(())
+    }
+
+    /// Deletes a key, returning whether it existed
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip(self)))]
+    pub async fn delete(&self, key: &str) -> Result<bool, Error> {
+        let mut conn = self.conn.clone();
+        let deleted: u64 = conn.del(key).await?;
+
+        Ok(deleted > 0)
+    }
+
+    /// Returns the remaining time to live of a key, if it exists and has an expiry
+    pub async fn ttl(&self, key: &str) -> Result<Option<Duration>, Error> {
+        let mut conn = self.conn.clone();
+        let ttl: i64 = conn.ttl(key).await?;
+
+        // -1 means no expiry, -2 means the key does not exist
+        if ttl < 0 {
+            return Ok(None);
+        }
+
+        Ok(Some(Duration::from_secs(ttl as u64)))
+    }
+
+    /// Sets the channel suggestions are posted to in a guild
+    pub async fn set_suggestion_channel(
+        &self,
+        guild_id: GuildId,
+        channel_id: ChannelId,
+    ) -> Result<(), Error> {
+        let key = self.key(&["suggestions", "channel", &guild_id.to_string()]);
+        self.set_json(&key, &channel_id, None).await
+    }
+
+    fn _blacklist_key(&self, kind: BlacklistKind) -> String {
+        match kind {
+            BlacklistKind::User => self.key(&["blacklist", "user"]),
+            BlacklistKind::Guild => self.key(&["blacklist", "guild"]),
+        }
+    }
+}
+
+/// Toggles ``ARGV[1]``s vote ``ARGV[2]`` on the pending suggestion at ``KEYS[1]``
+const TOGGLE_VOTE_SCRIPT: &str = r#"
+local raw = redis.call('GET', KEYS[1])
+if not raw then return false end
+local suggestion = cjson.decode(raw)
+if suggestion.status ~= 'Pending' then return false end
+if suggestion.votes[ARGV[1]] == ARGV[2] then
+    suggestion.votes[ARGV[1]] = nil
+else
+    suggestion.votes[ARGV[1]] = ARGV[2]
+end
+raw = cjson.encode(suggestion)
+redis.call('SET', KEYS[1], raw)
+return raw
+"#;
+
+/// Sets the status of the suggestion at ``KEYS[1]`` to ``ARGV[1]``
+const SET_STATUS_SCRIPT: &str = r#"
+local raw = redis.call('GET', KEYS[1])
+if not raw then return false end
+local suggestion = cjson.decode(raw)
+suggestion.status = ARGV[1]
+raw = cjson.encode(suggestion)
+redis.call('SET', KEYS[1], raw)
+return raw
+"#;
+
+impl RedisStore {
+    /// Runs a suggestion script, returning the updated suggestion if the script returned one
+    async fn _suggestion_script(
+        &self,
+        script: &str,
+        id: &str,
+        args: &[String],
+    ) -> Result<Option<Suggestion>, Error> {
+        let mut conn = self.conn.clone();
+        let script = ::redis::Script::new(script);
+        let mut invocation = script.key(self.key(&["suggestions", id]));
+
+        for arg in args {
+            invocation.arg(arg);
+        }
+
+        let raw: Option<String> = invocation.invoke_async(&mut conn).await?;
+
+        match raw {
+            // cjson encodes an empty votes table as an object, which matches an empty map
+            Some(raw) => Ok(Some(serde_json::from_str(&raw)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+impl SuggestionStore for RedisStore {
+    fn suggestion_channel<'a>(
+        &'a self,
+        guild_id: GuildId,
+    ) -> BoxFuture<'a, Result<Option<ChannelId>, Error>> {
+        Box::pin(async move {
+            let key = self.key(&["suggestions", "channel", &guild_id.to_string()]);
+            self.get_json(&key).await
+        })
+    }
+
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<Suggestion>, Error>> {
+        Box::pin(async move { self.get_json(&self.key(&["suggestions", id])).await })
+    }
+
+    fn save<'a>(&'a self, suggestion: &'a Suggestion) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.set_json(
+                &self.key(&["suggestions", &suggestion.id]),
+                suggestion,
+                None,
+            )
+            .await
+        })
+    }
+
+    fn toggle_vote<'a>(
+        &'a self,
+        id: &'a str,
+        user_id: UserId,
+        vote: Vote,
+    ) -> BoxFuture<'a, Result<Option<Suggestion>, Error>> {
+        Box::pin(async move {
+            let vote = serde_json::to_value(vote)?;
+
+            self._suggestion_script(
+                TOGGLE_VOTE_SCRIPT,
+                id,
+                &[
+                    user_id.to_string(),
+                    vote.as_str().unwrap_or_default().to_string(),
+                ],
+            )
+            .await
+        })
+    }
+
+    fn set_status<'a>(
+        &'a self,
+        id: &'a str,
+        status: SuggestionStatus,
+    ) -> BoxFuture<'a, Result<Option<Suggestion>, Error>> {
+        Box::pin(async move {
+            let status = serde_json::to_value(status)?;
+
+            self._suggestion_script(
+                SET_STATUS_SCRIPT,
+                id,
+                &[status.as_str().unwrap_or_default().to_string()],
+            )
+            .await
+        })
+    }
+}
+
+impl PrefixStore for RedisStore {
+    fn get<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<Option<String>, Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn.clone();
+            let prefix: Option<String> = conn
+                .get(self.key(&["prefixes", &guild_id.to_string()]))
+                .await?;
+
+            Ok(prefix)
+        })
+    }
+
+    fn set<'a>(&'a self, guild_id: GuildId, prefix: &'a str) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn.clone();
+            conn.set::<_, _, ()>(self.key(&["prefixes", &guild_id.to_string()]), prefix)
+                .await?;
+
+            Ok(())
+        })
+    }
+
+    fn reset<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.delete(&self.key(&["prefixes", &guild_id.to_string()]))
+                .await?;
+
+            Ok(())
+        })
+    }
+}
+
+impl BlacklistStore for RedisStore {
+    fn get<'a>(
+        &'a self,
+        kind: BlacklistKind,
+        id: u64,
+    ) -> BoxFuture<'a, Result<Option<BlacklistEntry>, Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn.clone();
+            let entry: Option<String> = conn.hget(self._blacklist_key(kind), id).await?;
+
+            match entry {
+                Some(entry) => Ok(Some(serde_json::from_str(&entry)?)),
+                None => Ok(None),
+            }
+        })
+    }
+
+    fn add<'a>(&'a self, entry: &'a BlacklistEntry) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn.clone();
+            conn.hset::<_, _, _, ()>(
+                self._blacklist_key(entry.kind),
+                entry.id,
+                serde_json::to_string(entry)?,
+            )
+            .await?;
+
+            Ok(())
+        })
+    }
+
+    fn remove<'a>(&'a self, kind: BlacklistKind, id: u64) -> BoxFuture<'a, Result<bool, Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn.clone();
+            let removed: u64 = conn.hdel(self._blacklist_key(kind), id).await?;
+
+            Ok(removed > 0)
+        })
+    }
+
+    fn list<'a>(
+        &'a self,
+        kind: BlacklistKind,
+    ) -> BoxFuture<'a, Result<Vec<BlacklistEntry>, Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn.clone();
+            let entries: Vec<String> = conn.hvals(self._blacklist_key(kind)).await?;
+
+            entries
+                .iter()
+                .map(|e| serde_json::from_str(e).map_err(|e| e.into()))
+                .collect()
+        })
+    }
+}
+
+impl ChannelRestrictionStore for RedisStore {
+    fn get<'a>(
+        &'a self,
+        guild_id: GuildId,
+    ) -> BoxFuture<'a, Result<Option<ChannelRestriction>, Error>> {
+        Box::pin(async move {
+            self.get_json(&self.key(&["restrictions", &guild_id.to_string()]))
+                .await
+        })
+    }
+
+    fn set<'a>(
+        &'a self,
+        guild_id: GuildId,
+        restriction: &'a ChannelRestriction,
+    ) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            self.set_json(
+                &self.key(&["restrictions", &guild_id.to_string()]),
+                restriction,
+                None,
+            )
+            .await
+        })
+    }
+}
+
+impl PingProbe for RedisStore {
+    fn name(&self) -> &str {
+        "Redis"
+    }
+
+    fn ping<'a>(&'a self) -> BoxFuture<'a, Result<(), Error>> {
+        Box::pin(async move {
+            let mut conn = self.conn.clone();
+            ::redis::cmd("PING")
+                .query_async::<_, String>(&mut conn)
+                .await?;
+            Ok(())
+        })
+    }
+}
@@ -0,0 +1,425 @@
+use futures::future::BoxFuture;
+use indexmap::IndexMap;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, ComponentInteraction, CreateActionRow, CreateButton, CreateEmbed,
+    CreateInteractionResponse, CreateInteractionResponseMessage, CreateMessage, EditMessage,
+    GuildId, MessageId, Permissions, UserId,
+};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::i18n::{t, tr};
+use crate::Error;
+
+/// The status of a suggestion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SuggestionStatus {
+    #[default]
+    Pending,
+    Approved,
+    Denied,
+    Implemented,
+}
+
+impl SuggestionStatus {
+    /// Returns the embed colour for this status
+    pub fn colour(&self) -> serenity::Colour {
+        match self {
+            SuggestionStatus::Pending => serenity::Colour::BLURPLE,
+            SuggestionStatus::Approved => serenity::Colour::DARK_GREEN,
+            SuggestionStatus::Denied => serenity::Colour::RED,
+            SuggestionStatus::Implemented => serenity::Colour::GOLD,
+        }
+    }
+
+    /// Returns a human readable label for this status
+    pub fn label(&self, locale: Option<&str>) -> String {
+        let key = match self {
+            SuggestionStatus::Pending => "suggestions.pending",
+            SuggestionStatus::Approved => "suggestions.approved",
+            SuggestionStatus::Denied => "suggestions.denied",
+            SuggestionStatus::Implemented => "suggestions.implemented",
+        };
+
+        t(locale, key, &[])
+    }
+}
+
+/// A vote on a suggestion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Vote {
+    Up,
+    Down,
+}
+
+/// A suggestion posted to a suggestion channel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suggestion {
+    pub id: String,
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub message_id: MessageId,
+    pub author_id: UserId,
+    pub content: String,
+    pub status: SuggestionStatus,
+    /// The vote of every user who has voted, a user may only have one vote at a time
+    pub votes: IndexMap<UserId, Vote>,
+}
+
+impl Suggestion {
+    /// Returns the number of upvotes and downvotes
+    pub fn tally(&self) -> (usize, usize) {
+        let up = self.votes.values().filter(|v| **v == Vote::Up).count();
+        (up, self.votes.len() - up)
+    }
+}
+
+/// Storage backend for suggestions
+pub trait SuggestionStore: Send + Sync {
+    /// Returns the channel suggestions should be posted to in a guild
+    fn suggestion_channel<'a>(
+        &'a self,
+        guild_id: GuildId,
+    ) -> BoxFuture<'a, Result<Option<ChannelId>, Error>>;
+
+    /// Fetches a suggestion by its id
+    fn get<'a>(&'a self, id: &'a str) -> BoxFuture<'a, Result<Option<Suggestion>, Error>>;
+
+    /// Saves a suggestion, creating it if it does not already exist
+    fn save<'a>(&'a self, suggestion: &'a Suggestion) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Atomically sets a users vote on a pending suggestion, removing it instead if the user
+    /// already cast the same vote
+    ///
+    /// Returns the updated suggestion, or None if it does not exist or is no longer pending
+    fn toggle_vote<'a>(
+        &'a self,
+        id: &'a str,
+        user_id: UserId,
+        vote: Vote,
+    ) -> BoxFuture<'a, Result<Option<Suggestion>, Error>>;
+
+    /// Atomically sets the status of a suggestion, leaving its votes untouched
+    ///
+    /// Returns the updated suggestion, or None if it does not exist
+    fn set_status<'a>(
+        &'a self,
+        id: &'a str,
+        status: SuggestionStatus,
+    ) -> BoxFuture<'a, Result<Option<Suggestion>, Error>>;
+}
+
+pub struct SuggestionOptions {
+    /// Store for suggestions
+    pub store: Arc<dyn SuggestionStore>,
+    /// Returns whether the user pressing a staff button may do so
+    ///
+    /// Defaults to checking for the Manage Server permission
+    #[allow(clippy::type_complexity)]
+    pub is_staff: Option<
+        Box<
+            dyn Send
+                + Sync
+                + for<'a> Fn(
+                    &'a serenity::Context,
+                    &'a ComponentInteraction,
+                ) -> BoxFuture<'a, Result<bool, crate::Error>>,
+        >,
+    >,
+    /// Whether to DM the author when the status of their suggestion changes
+    pub notify_author: bool,
+    /// Notification preferences, authors who opted out of suggestion updates are not DMed
+    pub preferences: Option<crate::notify::Preferences>,
+}
+
+impl SuggestionOptions {
+    pub fn new(store: Arc<dyn SuggestionStore>) -> Self {
+        Self {
+            store,
+            is_staff: None,
+            notify_author: true,
+            preferences: None,
+        }
+    }
+}
+
+fn _create_embed<'a>(suggestion: &'a Suggestion, locale: Option<&str>) -> CreateEmbed<'a> {
+    let (up, down) = suggestion.tally();
+
+    CreateEmbed::default()
+        .title(t(
+            locale,
+            "suggestions.title",
+            &[("status", &suggestion.status.label(locale))],
+        ))
+        .description(&suggestion.content)
+        .colour(suggestion.status.colour())
+        .field(
+            t(locale, "suggestions.author", &[]),
+            format!("<@{}>", suggestion.author_id),
+            true,
+        )
+        .field(
+            t(locale, "suggestions.votes", &[]),
+            format!("👍 {} | 👎 {}", up, down),
+            true,
+        )
+        .footer(serenity::CreateEmbedFooter::new(format!(
+            "ID: {}",
+            suggestion.id
+        )))
+}
+
+fn _create_components<'a>(
+    suggestion: &'a Suggestion,
+    locale: Option<&str>,
+) -> Vec<CreateActionRow<'a>> {
+    let (up, down) = suggestion.tally();
+    let closed = suggestion.status != SuggestionStatus::Pending;
+
+    vec![
+        CreateActionRow::Buttons(vec![
+            CreateButton::new(format!("sugg:up:{}", suggestion.id))
+                .label(format!("👍 {}", up))
+                .style(serenity::ButtonStyle::Success)
+                .disabled(closed),
+            CreateButton::new(format!("sugg:down:{}", suggestion.id))
+                .label(format!("👎 {}", down))
+                .style(serenity::ButtonStyle::Danger)
+                .disabled(closed),
+        ]),
+        CreateActionRow::Buttons(vec![
+            CreateButton::new(format!("sugg:approve:{}", suggestion.id))
+                .label(t(locale, "suggestions.approve", &[]))
+                .style(serenity::ButtonStyle::Secondary),
+            CreateButton::new(format!("sugg:deny:{}", suggestion.id))
+                .label(t(locale, "suggestions.deny", &[]))
+                .style(serenity::ButtonStyle::Secondary),
+            CreateButton::new(format!("sugg:implement:{}", suggestion.id))
+                .label(t(locale, "suggestions.implemented", &[]))
+                .style(serenity::ButtonStyle::Secondary),
+        ]),
+    ]
+}
+
+/// Posts a suggestion to the guilds suggestion channel, can be plugged into your bots ``/suggest`` command
+pub async fn suggest<Data: Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    content: String,
+    so: &SuggestionOptions,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err(tr(ctx, "suggestions.server_only", &[]).into());
+    };
+
+    let Some(channel_id) = so.store.suggestion_channel(guild_id).await? else {
+        return Err(tr(ctx, "suggestions.no_channel", &[]).into());
+    };
+
+    let mut suggestion = Suggestion {
+        id: crate::crypto::gen_random(16),
+        guild_id,
+        channel_id,
+        message_id: MessageId::new(1),
+        author_id: ctx.author().id,
+        content,
+        status: SuggestionStatus::Pending,
+        votes: IndexMap::new(),
+    };
+
+    // Suggestions are public, so they use the servers locale rather than the authors
+    let locale = ctx.guild().map(|g| g.preferred_locale.to_string());
+    let locale = locale.as_deref();
+
+    let msg = channel_id
+        .send_message(
+            ctx.http(),
+            CreateMessage::new()
+                .embed(_create_embed(&suggestion, locale))
+                .components(_create_components(&suggestion, locale)),
+        )
+        .await?;
+
+    suggestion.message_id = msg.id;
+
+    // The message id is only known once posted, so remove the message again if it can't be saved
+    if let Err(e) = so.store.save(&suggestion).await {
+        if let Err(e) = msg.delete(ctx.http(), None).await {
+            log::warn!("Failed to delete unsaved suggestion {}: {}", msg.id, e);
+        }
+
+        return Err(e);
+    }
+
+    ctx.send(
+        CreateReply::default()
+            .content(tr(ctx, "suggestions.posted", &[("link", &msg.link())]))
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
+
+async fn _is_staff(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    so: &SuggestionOptions,
+) -> Result<bool, Error> {
+    if let Some(is_staff) = &so.is_staff {
+        return is_staff(ctx, interaction).await;
+    }
+
+    Ok(interaction
+        .member
+        .as_ref()
+        .and_then(|m| m.permissions)
+        .is_some_and(|p| p.contains(Permissions::MANAGE_GUILD)))
+}
+
+async fn _respond_ephemeral(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    content: &str,
+) -> Result<(), Error> {
+    interaction
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::Message(
+                CreateInteractionResponseMessage::new()
+                    .content(content)
+                    .ephemeral(true),
+            ),
+        )
+        .await?;
+
+    Ok(())
+}
+
+/// Handles a suggestion button press, returning false if the interaction is not a suggestion interaction
+///
+/// This should be called from your bots event handler on every component interaction
+pub async fn handle_interaction(
+    ctx: &serenity::Context,
+    interaction: &ComponentInteraction,
+    so: &SuggestionOptions,
+) -> Result<bool, Error> {
+    let Some(data) = interaction.data.custom_id.strip_prefix("sugg:") else {
+        return Ok(false);
+    };
+
+    let Some((action, id)) = data.split_once(':') else {
+        return Ok(false);
+    };
+
+    let locale = Some(&*interaction.locale);
+    let guild_locale = interaction.guild_locale.as_deref();
+
+    let Some(suggestion) = so
+        .store
+        .get(id)
+        .await?
+        .filter(|s| interaction.guild_id == Some(s.guild_id))
+    else {
+        _respond_ephemeral(ctx, interaction, &t(locale, "suggestions.missing", &[])).await?;
+        return Ok(true);
+    };
+
+    let suggestion = match action {
+        "up" | "down" => {
+            if suggestion.status != SuggestionStatus::Pending {
+                _respond_ephemeral(ctx, interaction, &t(locale, "suggestions.closed", &[])).await?;
+                return Ok(true);
+            }
+
+            let vote = if action == "up" { Vote::Up } else { Vote::Down };
+
+            // Pressing the same button again removes the vote
+            let Some(suggestion) = so.store.toggle_vote(id, interaction.user.id, vote).await?
+            else {
+                _respond_ephemeral(ctx, interaction, &t(locale, "suggestions.closed", &[])).await?;
+                return Ok(true);
+            };
+
+            suggestion
+        }
+        "approve" | "deny" | "implement" => {
+            if !_is_staff(ctx, interaction, so).await? {
+                _respond_ephemeral(ctx, interaction, &t(locale, "suggestions.not_staff", &[]))
+                    .await?;
+                return Ok(true);
+            }
+
+            let status = match action {
+                "approve" => SuggestionStatus::Approved,
+                "deny" => SuggestionStatus::Denied,
+                _ => SuggestionStatus::Implemented,
+            };
+
+            let Some(suggestion) = so.store.set_status(id, status).await? else {
+                _respond_ephemeral(ctx, interaction, &t(locale, "suggestions.missing", &[]))
+                    .await?;
+                return Ok(true);
+            };
+
+            if so.notify_author {
+                crate::notify::dm(
+                    &ctx.http,
+                    so.preferences.as_ref(),
+                    suggestion.author_id,
+                    crate::notify::NotifyCategory::Suggestions,
+                    CreateMessage::new().embed(
+                        CreateEmbed::default()
+                            .title(t(
+                                guild_locale,
+                                "suggestions.marked",
+                                &[("status", &suggestion.status.label(guild_locale))],
+                            ))
+                            .description(&suggestion.content)
+                            .colour(suggestion.status.colour()),
+                    ),
+                )
+                .await?;
+            }
+
+            suggestion
+        }
+        _ => return Ok(false),
+    };
+
+    interaction
+        .create_response(
+            &ctx.http,
+            CreateInteractionResponse::UpdateMessage(
+                CreateInteractionResponseMessage::new()
+                    .embed(_create_embed(&suggestion, guild_locale))
+                    .components(_create_components(&suggestion, guild_locale)),
+            ),
+        )
+        .await?;
+
+    Ok(true)
+}
+
+/// Re-renders a suggestion message, useful after editing a suggestion in the store directly
+///
+/// ``locale`` should be the preferred locale of the suggestions server
+pub async fn refresh_message(
+    http: &serenity::Http,
+    suggestion: &Suggestion,
+    locale: Option<&str>,
+) -> Result<(), Error> {
+    suggestion
+        .channel_id
+        .edit_message(
+            http,
+            suggestion.message_id,
+            EditMessage::new()
+                .embed(_create_embed(suggestion, locale))
+                .components(_create_components(suggestion, locale)),
+        )
+        .await?;
+
+    Ok(())
+}
@@ -3,8 +3,11 @@ use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::task::JoinSet;
 use futures::future::BoxFuture;
+use serenity::all::{ConnectionStage, GuildId, ShardId};
 use std::sync::Arc;
 
+use crate::shards::ShardMonitor;
+
 pub type RunFunction = Box<
 dyn Send
 + Sync
@@ -13,6 +16,30 @@ dyn Send
 ) -> BoxFuture<'a, Result<(), crate::Error>>,
 >;
 
+pub type GuildRunFunction = Box<
+dyn Send
++ Sync
++ for<'a> Fn(
+    &'a serenity::client::Context,
+    GuildId,
+) -> BoxFuture<'a, Result<(), crate::Error>>,
+>;
+
+/// What a task runs over on every tick, see ``Task::scope``
+pub enum Scope {
+    /// ``run`` is called once
+    Global,
+    /// ``run`` is called once, then ``run`` of the scope is called for every cached guild on a
+    /// connected shard of this process
+    ///
+    /// In clustered deployments every process only sees its own shards, so the work is partitioned
+    /// between processes without running twice for a guild
+    PerGuild {
+        monitor: ShardMonitor,
+        run: GuildRunFunction,
+    },
+}
+
 pub struct Task {
     pub name: &'static str,
     pub description: &'static str,
@@ -21,6 +48,102 @@ pub struct Task {
     pub run: RunFunction
 }
 
+impl Task {
+    /// Creates an enabled task running over ``Scope::Global``
+    pub fn new(
+        name: &'static str,
+        description: &'static str,
+        duration: Duration,
+        run: RunFunction,
+    ) -> Self {
+        Self {
+            name,
+            description,
+            enabled: true,
+            duration,
+            run,
+        }
+    }
+
+    /// Sets what the task runs over
+    ///
+    /// Per-guild runs happen in the background once ``run`` finishes, so a slow guild does not
+    /// hold up other tasks. A tick is skipped if the guilds of the previous tick are still running
+    pub fn scope(mut self, scope: Scope) -> Self {
+        let Scope::PerGuild {
+            monitor,
+            run: guild_run,
+        } = scope
+        else {
+            return self;
+        };
+
+        let name = self.name;
+        let run = self.run;
+        let guild_run = Arc::new(guild_run);
+        let running = Arc::new(Mutex::new(()));
+
+        self.run = Box::new(move |ctx| {
+            let fut = run(ctx);
+            let monitor = monitor.clone();
+            let guild_run = guild_run.clone();
+            let running = running.clone();
+
+            Box::pin(async move {
+                fut.await?;
+
+                let Ok(guard) = running.try_lock_owned() else {
+                    log::warn!("TASK {} is still running over guilds, skipping", name);
+                    return Ok(());
+                };
+
+                let guilds = guilds_on_connected_shards(ctx, &monitor).await;
+                let ctx = ctx.clone();
+
+                tokio::spawn(async move {
+                    for guild_id in guilds {
+                        if let Err(e) = guild_run(&ctx, guild_id).await {
+                            log::error!("TASK {} ERROR'd in guild {}: {:?}", name, guild_id, e);
+                        }
+                    }
+
+                    drop(guard);
+                });
+
+                Ok(())
+            })
+        });
+
+        self
+    }
+}
+
+/// Returns the cached guilds whose shard is connected on this process
+///
+/// Guilds on shards that are reconnecting are skipped, as their cached state may be stale
+pub async fn guilds_on_connected_shards(
+    ctx: &serenity::client::Context,
+    monitor: &ShardMonitor,
+) -> Vec<GuildId> {
+    let shard_count = u64::from(ctx.cache.shard_count().get());
+    let mut guilds = Vec::new();
+
+    for guild_id in ctx.cache.guilds() {
+        let shard_id = ShardId(((guild_id.get() >> 22) % shard_count) as u16);
+
+        let connected = monitor
+            .get(shard_id)
+            .await
+            .is_some_and(|h| h.stage == ConnectionStage::Connected);
+
+        if connected {
+            guilds.push(guild_id);
+        }
+    }
+
+    guilds
+}
+
 /// Starts all tasks from a list of Tasks
 pub async fn start_all_tasks(
     tasks: Vec<Task>,
@@ -81,7 +204,15 @@ async fn taskcat(
             task.description
         );
 
-        if let Err(e) = (task.run)(&ctx).await {
+        let fut = (task.run)(&ctx);
+
+        #[cfg(feature = "tracing")]
+        let fut = tracing::Instrument::instrument(
+            fut,
+            tracing::info_span!("task", name = task.name, interval_secs = task.duration.as_secs()),
+        );
+
+        if let Err(e) = fut.await {
             log::error!("TASK {} ERROR'd: {:?}", task.name, e);
         }
 
@@ -0,0 +1,203 @@
+use opentelemetry::metrics::Counter;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::metrics::SdkMeterProvider;
+use opentelemetry_sdk::Resource;
+use std::sync::OnceLock;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use crate::Error;
+
+/// Resource attributes attached to every exported span and metric
+#[derive(Debug, Clone)]
+pub struct TelemetryResource {
+    /// Exported as ``service.name``
+    pub bot_name: String,
+    /// Exported as ``service.version``
+    pub version: String,
+    /// The first and last shard this process runs, exported as ``discord.shard_range``
+    pub shard_range: Option<(u32, u32)>,
+}
+
+impl TelemetryResource {
+    fn _to_resource(&self) -> Resource {
+        let mut attrs = vec![
+            KeyValue::new("service.name", self.bot_name.clone()),
+            KeyValue::new("service.version", self.version.clone()),
+            KeyValue::new("botox.version", env!("CARGO_PKG_VERSION")),
+        ];
+
+        if let Some((first, last)) = self.shard_range {
+            attrs.push(KeyValue::new(
+                "discord.shard_range",
+                format!("{}-{}", first, last),
+            ));
+        }
+
+        Resource::new(attrs)
+    }
+}
+
+/// Flushes and shuts down the exporters when dropped, keep this alive for the lifetime of the bot
+pub struct TelemetryGuard {
+    meter_provider: SdkMeterProvider,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        if let Err(e) = self.meter_provider.shutdown() {
+            log::error!("Failed to shut down meter provider: {}", e);
+        }
+
+        opentelemetry::global::shutdown_tracer_provider();
+    }
+}
+
+/// Configures OTLP (gRPC) trace and metric export and installs a global ``tracing`` subscriber
+/// bridging the crates spans to OpenTelemetry
+///
+/// Fails if a global ``tracing`` subscriber is already installed
+pub fn init(otlp_endpoint: &str, resource: TelemetryResource) -> Result<TelemetryGuard, Error> {
+    let resource = resource._to_resource();
+
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(otlp_endpoint),
+        )
+        .with_trace_config(opentelemetry_sdk::trace::config().with_resource(resource.clone()))
+        .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+
+    let meter_provider = opentelemetry_otlp::new_pipeline()
+        .metrics(opentelemetry_sdk::runtime::Tokio)
+        .with_exporter(
+            opentelemetry_otlp::new_exporter()
+                .tonic()
+                .with_endpoint(otlp_endpoint),
+        )
+        .with_resource(resource)
+        .build()?;
+
+    opentelemetry::global::set_meter_provider(meter_provider.clone());
+
+    tracing_subscriber::registry()
+        .with(tracing_opentelemetry::layer().with_tracer(tracer))
+        .try_init()?;
+
+    Ok(TelemetryGuard { meter_provider })
+}
+
+/// Counter of command invocations, labelled by ``command``
+///
+/// ``analytics::UsageTracker`` records into this automatically
+pub fn command_counter() -> &'static Counter<u64> {
+    static COUNTER: OnceLock<Counter<u64>> = OnceLock::new();
+
+    COUNTER.get_or_init(|| {
+        opentelemetry::global::meter("botox")
+            .u64_counter("botox.command.invocations")
+            .with_description("Number of command invocations")
+            .init()
+    })
+}
+
+/// Counter of circuit breaker state transitions, labelled by ``service`` and ``state``
+///
+/// ``resilience::CircuitBreaker`` records into this automatically
+pub fn circuit_transition_counter() -> &'static Counter<u64> {
+    static COUNTER: OnceLock<Counter<u64>> = OnceLock::new();
+
+    COUNTER.get_or_init(|| {
+        opentelemetry::global::meter("botox")
+            .u64_counter("botox.circuit.transitions")
+            .with_description("Number of circuit breaker state transitions")
+            .init()
+    })
+}
+
+/// Counter of cache entries evicted by the memory governor, labelled by ``cache``
+///
+/// ``cache::MemoryGovernor`` records into this automatically
+pub fn cache_eviction_counter() -> &'static Counter<u64> {
+    static COUNTER: OnceLock<Counter<u64>> = OnceLock::new();
+
+    COUNTER.get_or_init(|| {
+        opentelemetry::global::meter("botox")
+            .u64_counter("botox.cache.evictions")
+            .with_description("Number of cache entries evicted by the memory governor")
+            .init()
+    })
+}
+
+/// Counter of cache lookups, labelled by ``cache`` and ``result`` (``hit`` or ``miss``)
+///
+/// ``cache::TtlCache`` records into this automatically
+pub fn cache_lookup_counter() -> &'static Counter<u64> {
+    static COUNTER: OnceLock<Counter<u64>> = OnceLock::new();
+
+    COUNTER.get_or_init(|| {
+        opentelemetry::global::meter("botox")
+            .u64_counter("botox.cache.lookups")
+            .with_description("Number of cache lookups by result")
+            .init()
+    })
+}
+
+/// Counter of gateway events, labelled by ``shard`` and ``event``
+///
+/// ``shards::EventRates`` records into this automatically
+pub fn shard_event_counter() -> &'static Counter<u64> {
+    static COUNTER: OnceLock<Counter<u64>> = OnceLock::new();
+
+    COUNTER.get_or_init(|| {
+        opentelemetry::global::meter("botox")
+            .u64_counter("botox.gateway.events")
+            .with_description("Number of gateway events received")
+            .init()
+    })
+}
+
+/// Counter of shard resumes, reconnects and invalid sessions, labelled by ``shard`` and ``kind``
+///
+/// ``shards::GatewayEvents`` records into this automatically
+pub fn gateway_instability_counter() -> &'static Counter<u64> {
+    static COUNTER: OnceLock<Counter<u64>> = OnceLock::new();
+
+    COUNTER.get_or_init(|| {
+        opentelemetry::global::meter("botox")
+            .u64_counter("botox.gateway.instability")
+            .with_description("Number of shard resumes, reconnects and invalid sessions")
+            .init()
+    })
+}
+
+/// Counter of REST requests that hit a ratelimit, labelled by ``path`` and ``global``
+///
+/// ``shards::GatewayEvents`` records into this automatically
+pub fn http_ratelimit_counter() -> &'static Counter<u64> {
+    static COUNTER: OnceLock<Counter<u64>> = OnceLock::new();
+
+    COUNTER.get_or_init(|| {
+        opentelemetry::global::meter("botox")
+            .u64_counter("botox.http.ratelimits")
+            .with_description("Number of REST requests that hit a ratelimit")
+            .init()
+    })
+}
+
+/// Counter of experiment outcomes, labelled by ``experiment``, ``variant`` and ``outcome``
+///
+/// ``analytics::UsageTracker::record_outcome`` records into this automatically
+pub fn experiment_counter() -> &'static Counter<u64> {
+    static COUNTER: OnceLock<Counter<u64>> = OnceLock::new();
+
+    COUNTER.get_or_init(|| {
+        opentelemetry::global::meter("botox")
+            .u64_counter("botox.experiment.outcomes")
+            .with_description("Number of recorded experiment outcomes")
+            .init()
+    })
+}
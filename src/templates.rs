@@ -0,0 +1,406 @@
+use poise::serenity_prelude::{ChannelId, CreateEmbed, Embed, GuildId, Message, UserId};
+use std::fmt::Display;
+
+/// Every placeholder a template may use, with a short description
+pub const PLACEHOLDERS: &[(&str, &str)] = &[
+    ("user.mention", "Mentions the user"),
+    ("user.name", "The users display name"),
+    ("user.id", "The users id"),
+    ("guild.name", "The servers name"),
+    ("guild.id", "The servers id"),
+    ("channel.mention", "Mentions the channel"),
+    ("channel.id", "The channels id"),
+    ("args", "Every argument, separated by spaces"),
+    ("args.N", "The Nth argument, starting at 1"),
+];
+
+/// Older placeholder names, kept so existing templates keep working
+const ALIASES: &[(&str, &str)] = &[
+    ("user", "user.mention"),
+    ("server", "guild.name"),
+    ("channel", "channel.mention"),
+];
+
+/// A template that failed to parse
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TemplateError {
+    /// Where in an embed template the error is, such as ``fields[0].value``, empty for plain templates
+    pub path: String,
+    /// The 1-based character the error starts at
+    pub column: usize,
+    pub message: String,
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.path.is_empty() {
+            write!(f, "Column {}: {}", self.column, self.message)
+        } else {
+            write!(
+                f,
+                "``{}`` column {}: {}",
+                self.path, self.column, self.message
+            )
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// The values placeholders are filled in with, missing values render as nothing
+#[derive(Debug, Clone, Default)]
+pub struct TemplateContext {
+    pub user_id: Option<UserId>,
+    pub user_name: Option<String>,
+    pub guild_id: Option<GuildId>,
+    pub guild_name: Option<String>,
+    pub channel_id: Option<ChannelId>,
+    pub args: Vec<String>,
+}
+
+impl TemplateContext {
+    /// Returns a context for the author, guild and channel of a message
+    pub fn from_message(msg: &Message, guild_name: Option<&str>) -> Self {
+        Self {
+            user_id: Some(msg.author.id),
+            user_name: Some(msg.author.display_name().to_string()),
+            guild_id: msg.guild_id,
+            guild_name: guild_name.map(|n| n.to_string()),
+            channel_id: Some(msg.channel_id),
+            args: Vec::new(),
+        }
+    }
+
+    /// Returns a context with only a guild set, such as for broadcasts
+    pub fn for_guild(guild_id: GuildId, guild_name: Option<&str>) -> Self {
+        Self {
+            guild_id: Some(guild_id),
+            guild_name: guild_name.map(|n| n.to_string()),
+            ..Default::default()
+        }
+    }
+
+    /// Sets the arguments ``{args}`` and ``{args.N}`` are filled in from
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args = args.into_iter().map(|a| a.into()).collect();
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Placeholder {
+    UserMention,
+    UserName,
+    UserId,
+    GuildName,
+    GuildId,
+    ChannelMention,
+    ChannelId,
+    Args,
+    /// 0-based index into the arguments
+    Arg(usize),
+}
+
+impl Placeholder {
+    fn _parse(name: &str) -> Result<Self, String> {
+        let name = ALIASES
+            .iter()
+            .find(|(alias, _)| *alias == name)
+            .map(|(_, canonical)| *canonical)
+            .unwrap_or(name);
+
+        Ok(match name {
+            "user.mention" => Placeholder::UserMention,
+            "user.name" => Placeholder::UserName,
+            "user.id" => Placeholder::UserId,
+            "guild.name" => Placeholder::GuildName,
+            "guild.id" => Placeholder::GuildId,
+            "channel.mention" => Placeholder::ChannelMention,
+            "channel.id" => Placeholder::ChannelId,
+            "args" => Placeholder::Args,
+            _ => {
+                let Some(index) = name.strip_prefix("args.") else {
+                    return Err(format!("Unknown placeholder ``{{{}}}``", name));
+                };
+
+                match index.parse::<usize>() {
+                    Ok(index) if index >= 1 => Placeholder::Arg(index - 1),
+                    _ => {
+                        return Err(format!(
+                            "``{{{}}}`` must use a number starting at 1, such as ``{{args.1}}``",
+                            name
+                        ))
+                    }
+                }
+            }
+        })
+    }
+
+    fn _render(&self, ctx: &TemplateContext, out: &mut String) {
+        match self {
+            Placeholder::UserMention => {
+                if let Some(id) = ctx.user_id {
+                    out.push_str(&format!("<@{}>", id));
+                }
+            }
+            Placeholder::UserName => out.push_str(ctx.user_name.as_deref().unwrap_or_default()),
+            Placeholder::UserId => {
+                if let Some(id) = ctx.user_id {
+                    out.push_str(&id.to_string());
+                }
+            }
+            Placeholder::GuildName => out.push_str(ctx.guild_name.as_deref().unwrap_or_default()),
+            Placeholder::GuildId => {
+                if let Some(id) = ctx.guild_id {
+                    out.push_str(&id.to_string());
+                }
+            }
+            Placeholder::ChannelMention => {
+                if let Some(id) = ctx.channel_id {
+                    out.push_str(&format!("<#{}>", id));
+                }
+            }
+            Placeholder::ChannelId => {
+                if let Some(id) = ctx.channel_id {
+                    out.push_str(&id.to_string());
+                }
+            }
+            Placeholder::Args => out.push_str(&ctx.args.join(" ")),
+            Placeholder::Arg(index) => {
+                if let Some(arg) = ctx.args.get(*index) {
+                    out.push_str(arg);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// Parses a template, collecting every error instead of stopping at the first
+fn _parse(text: &str, path: &str) -> (Vec<Segment>, Vec<TemplateError>) {
+    let mut segments = Vec::new();
+    let mut errors = Vec::new();
+    let mut literal = String::new();
+    let mut chars = text.chars().enumerate().peekable();
+
+    let mut error = |column: usize, message: String| {
+        errors.push(TemplateError {
+            path: path.to_string(),
+            column: column + 1,
+            message,
+        })
+    };
+
+    while let Some((column, c)) = chars.next() {
+        match c {
+            '{' if chars.peek().is_some_and(|(_, c)| *c == '{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek().is_some_and(|(_, c)| *c == '}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '}' => error(
+                column,
+                "Unmatched ``}``, use ``}}`` for a literal brace".to_string(),
+            ),
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+
+                while let Some(&(_, c)) = chars.peek() {
+                    match c {
+                        '}' => {
+                            chars.next();
+                            closed = true;
+                            break;
+                        }
+                        // Leave the brace for the outer loop so it is reported on its own
+                        '{' => break,
+                        c => {
+                            name.push(c);
+                            chars.next();
+                        }
+                    }
+                }
+
+                if !closed {
+                    error(
+                        column,
+                        "Unclosed placeholder, use ``{{`` for a literal brace".to_string(),
+                    );
+                    continue;
+                }
+
+                match Placeholder::_parse(name.trim()) {
+                    Ok(placeholder) => {
+                        if !literal.is_empty() {
+                            segments.push(Segment::Text(std::mem::take(&mut literal)));
+                        }
+
+                        segments.push(Segment::Placeholder(placeholder));
+                    }
+                    Err(message) => error(column, message),
+                }
+            }
+            c => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Text(literal));
+    }
+
+    (segments, errors)
+}
+
+/// A parsed template such as ``Welcome {user.mention} to {guild.name}!``
+///
+/// Parsing is strict, unknown placeholders and stray braces are errors rather than being passed
+/// through, so a template that validates renders the same everywhere it is used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    /// Parses a template, failing on the first error
+    pub fn parse(text: &str) -> Result<Self, TemplateError> {
+        let (segments, mut errors) = _parse(text, "");
+
+        if !errors.is_empty() {
+            return Err(errors.swap_remove(0));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Fills in the placeholders
+    pub fn render(&self, ctx: &TemplateContext) -> String {
+        let mut out = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Placeholder(placeholder) => placeholder._render(ctx, &mut out),
+            }
+        }
+
+        out
+    }
+}
+
+/// Checks a template, returning every error found
+pub fn validate(text: &str) -> Result<(), Vec<TemplateError>> {
+    let (_, errors) = _parse(text, "");
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Renders a list of errors as one line each, for showing to users
+pub fn format_errors(errors: &[TemplateError]) -> String {
+    errors
+        .iter()
+        .map(|e| format!("- {}", e))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Calls ``f`` on every string of a JSON value along with its path
+fn _walk_strings(
+    value: &mut serde_json::Value,
+    path: &mut String,
+    f: &mut impl FnMut(&mut String, &str),
+) {
+    match value {
+        serde_json::Value::String(s) => f(s, path),
+        serde_json::Value::Array(items) => {
+            for (i, item) in items.iter_mut().enumerate() {
+                let len = path.len();
+                path.push_str(&format!("[{}]", i));
+                _walk_strings(item, path, f);
+                path.truncate(len);
+            }
+        }
+        serde_json::Value::Object(map) => {
+            for (key, item) in map.iter_mut() {
+                let len = path.len();
+
+                if !path.is_empty() {
+                    path.push('.');
+                }
+
+                path.push_str(key);
+                _walk_strings(item, path, f);
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// An embed written as Discord embed JSON, with placeholders allowed in every string
+#[derive(Debug, Clone)]
+pub struct EmbedTemplate {
+    value: serde_json::Value,
+}
+
+impl EmbedTemplate {
+    /// Parses an embed template, returning every error found
+    ///
+    /// Besides placeholder errors, the JSON must be a valid embed
+    pub fn parse(json: &str) -> Result<Self, Vec<TemplateError>> {
+        let mut value = serde_json::from_str::<serde_json::Value>(json).map_err(|e| {
+            vec![TemplateError {
+                path: String::new(),
+                column: e.column(),
+                message: e.to_string(),
+            }]
+        })?;
+
+        let mut errors = Vec::new();
+
+        _walk_strings(&mut value, &mut String::new(), &mut |s, path| {
+            errors.extend(_parse(s, path).1);
+        });
+
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        if let Err(e) = serde_json::from_value::<Embed>(value.clone()) {
+            return Err(vec![TemplateError {
+                path: String::new(),
+                column: 1,
+                message: format!("Not a valid embed: {}", e),
+            }]);
+        }
+
+        Ok(Self { value })
+    }
+
+    /// Fills in the placeholders and builds the embed
+    pub fn render(&self, ctx: &TemplateContext) -> Result<CreateEmbed<'static>, crate::Error> {
+        let mut value = self.value.clone();
+
+        _walk_strings(&mut value, &mut String::new(), &mut |s, _| {
+            if let Ok(template) = Template::parse(s) {
+                *s = template.render(ctx);
+            }
+        });
+
+        let embed = serde_json::from_value::<Embed>(value)?;
+
+        Ok(CreateEmbed::from(embed))
+    }
+}
@@ -0,0 +1,147 @@
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{GuildId, Timestamp};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cache::TtlCache;
+use crate::Error;
+
+/// Storage backend for per-guild timezones
+pub trait TimezoneStore: Send + Sync {
+    /// Returns the IANA timezone name of a guild (such as ``Europe/Berlin``), if set
+    fn get<'a>(&'a self, guild_id: GuildId) -> BoxFuture<'a, Result<Option<String>, Error>>;
+
+    fn set<'a>(&'a self, guild_id: GuildId, timezone: &'a str) -> BoxFuture<'a, Result<(), Error>>;
+}
+
+/// Parses an IANA timezone name
+pub fn parse_timezone(name: &str) -> Result<Tz, Error> {
+    name.parse::<Tz>().map_err(|_| {
+        format!(
+            "``{}`` is not a known timezone, use a name like ``Europe/Berlin``",
+            name
+        )
+        .into()
+    })
+}
+
+/// Converts a serenity timestamp to a chrono UTC datetime
+pub fn to_chrono(ts: Timestamp) -> DateTime<Utc> {
+    DateTime::from_timestamp(ts.unix_timestamp(), 0).unwrap_or_default()
+}
+
+/// Per-guild timezones with an in-memory cache in front of a ``TimezoneStore``, guilds without a timezone use UTC
+///
+/// Timezones are cached for an hour. This is cheap to clone
+#[derive(Clone)]
+pub struct GuildClock {
+    store: Arc<dyn TimezoneStore>,
+    cache: TtlCache<GuildId, Tz>,
+}
+
+impl GuildClock {
+    pub fn new(store: Arc<dyn TimezoneStore>) -> Self {
+        Self {
+            store,
+            cache: TtlCache::new("timezones", Duration::from_secs(60 * 60), 50_000),
+        }
+    }
+
+    /// Returns the timezone of a guild
+    pub async fn timezone(&self, guild_id: GuildId) -> Result<Tz, Error> {
+        if let Some(tz) = self.cache.get(&guild_id).await {
+            return Ok(tz);
+        }
+
+        let tz = match self.store.get(guild_id).await? {
+            Some(name) => parse_timezone(&name).unwrap_or_else(|e| {
+                log::warn!("Invalid stored timezone for {}: {}", guild_id, e);
+                Tz::UTC
+            }),
+            None => Tz::UTC,
+        };
+
+        self.cache.insert(guild_id, tz).await;
+
+        Ok(tz)
+    }
+
+    /// Sets the timezone of a guild from its IANA name
+    pub async fn set(&self, guild_id: GuildId, name: &str) -> Result<Tz, Error> {
+        let tz = parse_timezone(name)?;
+
+        self.store.set(guild_id, tz.name()).await?;
+        self.cache.insert(guild_id, tz).await;
+
+        Ok(tz)
+    }
+
+    /// Returns the timezone cache, for example to add it to a ``MemoryGovernor``
+    pub fn cache(&self) -> &TtlCache<GuildId, Tz> {
+        &self.cache
+    }
+
+    /// Returns the current time in a guilds timezone
+    pub async fn now(&self, guild_id: GuildId) -> Result<DateTime<Tz>, Error> {
+        Ok(Utc::now().with_timezone(&self.timezone(guild_id).await?))
+    }
+
+    /// Formats a timestamp in a guilds timezone using a chrono format string, such as ``%Y-%m-%d %H:%M %Z``
+    pub async fn format(
+        &self,
+        ts: Timestamp,
+        guild_id: GuildId,
+        fmt: &str,
+    ) -> Result<String, Error> {
+        let tz = self.timezone(guild_id).await?;
+        Ok(to_chrono(ts).with_timezone(&tz).format(fmt).to_string())
+    }
+
+    /// Interprets a wall clock time in a guilds timezone, for scheduling things users entered as local times
+    ///
+    /// Ambiguous times (during DST changes) resolve to the earlier one
+    pub async fn to_timestamp(
+        &self,
+        local: NaiveDateTime,
+        guild_id: GuildId,
+    ) -> Result<Timestamp, Error> {
+        let tz = self.timezone(guild_id).await?;
+
+        let Some(dt) = tz.from_local_datetime(&local).earliest() else {
+            return Err("That time does not exist in this servers timezone".into());
+        };
+
+        Ok(Timestamp::from_unix_timestamp(dt.timestamp())?)
+    }
+}
+
+/// Trait for bot data that holds a ``GuildClock``
+pub trait HasGuildClock {
+    fn guild_clock(&self) -> &GuildClock;
+}
+
+/// Sets the timezone of the current guild, can be plugged into your bots ``/timezone`` command
+///
+/// Permission checks (such as Manage Server) should be set on the command itself
+pub async fn timezone_set<Data: HasGuildClock + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    timezone: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Timezones can only be set in a server".into());
+    };
+
+    let data = ctx.data();
+    let tz = data.guild_clock().set(guild_id, &timezone).await?;
+
+    ctx.say(format!(
+        "Timezone set to ``{}``, it is currently {}",
+        tz.name(),
+        Utc::now().with_timezone(&tz).format("%H:%M")
+    ))
+    .await?;
+
+    Ok(())
+}
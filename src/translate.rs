@@ -0,0 +1,354 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{self as serenity, CreateEmbed, Message, MessageId};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cache::TtlCache;
+use crate::Error;
+
+/// Maximum length of an embed description, longer translations are cut
+const MAX_DESCRIPTION: usize = 4096;
+
+/// The result of translating a piece of text
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Translation {
+    pub text: String,
+    /// The language the text was detected as, if the translator reports it
+    pub source_language: Option<String>,
+}
+
+/// A translation backend
+pub trait Translator: Send + Sync {
+    /// Name of the backend, shown in the footer of translations
+    fn name(&self) -> &'static str;
+
+    /// Translates text to a Discord locale (such as ``de`` or ``pt-BR``), detecting the source language
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        target: &'a str,
+    ) -> BoxFuture<'a, Result<Translation, Error>>;
+}
+
+/// Returns the language to translate to for a Discord locale, only keeping the region where it
+/// changes the language (``en-GB``, ``pt-BR``, ``zh-CN`` and ``zh-TW``)
+pub fn target_language(locale: Option<&str>) -> String {
+    let locale = locale.unwrap_or("en-US");
+
+    match locale {
+        "en-GB" | "en-US" | "pt-BR" | "zh-CN" | "zh-TW" => locale.to_string(),
+        _ => locale.split('-').next().unwrap_or(locale).to_string(),
+    }
+}
+
+/// Translator used when translation is not configured, always errors
+pub struct NoTranslator;
+
+impl Translator for NoTranslator {
+    fn name(&self) -> &'static str {
+        "none"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        _text: &'a str,
+        _target: &'a str,
+    ) -> BoxFuture<'a, Result<Translation, Error>> {
+        Box::pin(async move { Err("Translation is not set up on this bot".into()) })
+    }
+}
+
+/// Translates using the [DeepL API](https://developers.deepl.com/docs)
+pub struct DeepL {
+    client: reqwest::Client,
+    api_key: String,
+    url: &'static str,
+}
+
+#[derive(Serialize)]
+struct DeepLRequest<'a> {
+    text: [&'a str; 1],
+    target_lang: String,
+}
+
+#[derive(Deserialize)]
+struct DeepLResponse {
+    translations: Vec<DeepLTranslation>,
+}
+
+#[derive(Deserialize)]
+struct DeepLTranslation {
+    detected_source_language: Option<String>,
+    text: String,
+}
+
+impl DeepL {
+    /// Keys ending in ``:fx`` belong to the free plan and are sent to the free API
+    pub fn new(api_key: impl Into<String>) -> Result<Self, Error> {
+        let api_key = api_key.into();
+
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()?,
+            url: if api_key.ends_with(":fx") {
+                "https://api-free.deepl.com/v2/translate"
+            } else {
+                "https://api.deepl.com/v2/translate"
+            },
+            api_key,
+        })
+    }
+
+    /// DeepL has no generic Chinese or English target
+    fn _target(target: &str) -> String {
+        match target {
+            "zh-CN" | "zh" => "ZH-HANS".to_string(),
+            "zh-TW" => "ZH-HANT".to_string(),
+            "en" => "EN-US".to_string(),
+            "pt" => "PT-PT".to_string(),
+            _ => target.to_uppercase(),
+        }
+    }
+}
+
+impl Translator for DeepL {
+    fn name(&self) -> &'static str {
+        "DeepL"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        target: &'a str,
+    ) -> BoxFuture<'a, Result<Translation, Error>> {
+        Box::pin(async move {
+            let res: DeepLResponse = self
+                .client
+                .post(self.url)
+                .header("Authorization", format!("DeepL-Auth-Key {}", self.api_key))
+                .json(&DeepLRequest {
+                    text: [text],
+                    target_lang: Self::_target(target),
+                })
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            let translation = res
+                .translations
+                .into_iter()
+                .next()
+                .ok_or("DeepL returned no translation")?;
+
+            Ok(Translation {
+                text: translation.text,
+                source_language: translation
+                    .detected_source_language
+                    .map(|l| l.to_lowercase()),
+            })
+        })
+    }
+}
+
+/// Translates using a [LibreTranslate](https://libretranslate.com) instance
+pub struct LibreTranslate {
+    client: reqwest::Client,
+    url: String,
+    api_key: Option<String>,
+}
+
+#[derive(Serialize)]
+struct LibreTranslateRequest<'a> {
+    q: &'a str,
+    source: &'static str,
+    target: &'a str,
+    format: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    api_key: Option<&'a str>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibreTranslateResponse {
+    translated_text: String,
+    detected_language: Option<LibreTranslateLanguage>,
+}
+
+#[derive(Deserialize)]
+struct LibreTranslateLanguage {
+    language: String,
+}
+
+impl LibreTranslate {
+    /// ``url`` is the base url of the instance, such as ``https://libretranslate.com``
+    pub fn new(url: impl Into<String>, api_key: Option<String>) -> Result<Self, Error> {
+        Ok(Self {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()?,
+            url: url.into().trim_end_matches('/').to_string(),
+            api_key,
+        })
+    }
+}
+
+impl Translator for LibreTranslate {
+    fn name(&self) -> &'static str {
+        "LibreTranslate"
+    }
+
+    fn translate<'a>(
+        &'a self,
+        text: &'a str,
+        target: &'a str,
+    ) -> BoxFuture<'a, Result<Translation, Error>> {
+        Box::pin(async move {
+            // LibreTranslate only knows chinese variants by their script
+            let target = match target {
+                "zh-TW" => "zt",
+                "zh-CN" => "zh",
+                _ => target.split('-').next().unwrap_or(target),
+            };
+
+            let res: LibreTranslateResponse = self
+                .client
+                .post(format!("{}/translate", self.url))
+                .json(&LibreTranslateRequest {
+                    q: text,
+                    source: "auto",
+                    target,
+                    format: "text",
+                    api_key: self.api_key.as_deref(),
+                })
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+
+            Ok(Translation {
+                text: res.translated_text,
+                source_language: res.detected_language.map(|l| l.language),
+            })
+        })
+    }
+}
+
+/// Translates messages through a ``Translator``, caching recent translations so repeated
+/// requests for a popular message only hit the backend once
+///
+/// Cached translations are keyed by message and target language, edited messages are
+/// translated again. This is cheap to clone
+#[derive(Clone)]
+pub struct Translations {
+    translator: Arc<dyn Translator>,
+    /// Translations by message and target, along with the edit timestamp they were made from
+    cache: TtlCache<(MessageId, String), (u64, Translation)>,
+}
+
+impl Translations {
+    /// Caches up to 1000 translations for an hour, see ``with_cache``
+    pub fn new(translator: Arc<dyn Translator>) -> Self {
+        Self::with_cache(translator, Duration::from_secs(60 * 60), 1000)
+    }
+
+    pub fn with_cache(translator: Arc<dyn Translator>, ttl: Duration, capacity: usize) -> Self {
+        Self {
+            translator,
+            cache: TtlCache::new("translations", ttl, capacity),
+        }
+    }
+
+    /// Returns the name of the translation backend
+    pub fn backend(&self) -> &'static str {
+        self.translator.name()
+    }
+
+    /// Returns the translation cache, for example to add it to a ``MemoryGovernor``
+    pub fn cache(&self) -> &TtlCache<(MessageId, String), (u64, Translation)> {
+        &self.cache
+    }
+
+    /// Translates a message to a Discord locale, using the cache where possible
+    pub async fn translate(&self, msg: &Message, target: &str) -> Result<Translation, Error> {
+        let key = (msg.id, target.to_string());
+        let version = msg
+            .edited_timestamp
+            .map(|t| t.unix_timestamp() as u64)
+            .unwrap_or_default();
+
+        if let Some((cached_version, translation)) = self.cache.get(&key).await {
+            if cached_version == version {
+                return Ok(translation);
+            }
+        }
+
+        let translation = self.translator.translate(&msg.content, target).await?;
+
+        self.cache.insert(key, (version, translation.clone())).await;
+
+        Ok(translation)
+    }
+}
+
+/// Trait for bot data that holds ``Translations``
+pub trait HasTranslations {
+    fn translations(&self) -> &Translations;
+}
+
+/// Translates a message to the invokers language, can be plugged into your bots
+/// ``Translate message`` context menu command
+///
+/// The translation is only shown to the invoker
+pub async fn translate_message<Data: HasTranslations + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    msg: Message,
+) -> Result<(), Error> {
+    if msg.content.trim().is_empty() {
+        return Err("That message has no text to translate".into());
+    }
+
+    ctx.defer_ephemeral().await?;
+
+    let target = target_language(ctx.locale());
+
+    let data = ctx.data();
+    let translations = data.translations();
+    let translation = translations.translate(&msg, &target).await?;
+
+    let mut text = translation.text;
+
+    if text.chars().count() > MAX_DESCRIPTION {
+        text = text.chars().take(MAX_DESCRIPTION - 1).collect();
+        text.push('…');
+    }
+
+    let footer = match translation.source_language {
+        Some(source) => format!("{} → {} · {}", source, target, translations.backend()),
+        None => format!("{} · {}", target, translations.backend()),
+    };
+
+    ctx.send(
+        CreateReply::default()
+            .embed(
+                CreateEmbed::default()
+                    .author(
+                        serenity::CreateEmbedAuthor::new(msg.author.display_name())
+                            .icon_url(msg.author.face()),
+                    )
+                    .title("Translation")
+                    .url(msg.link())
+                    .description(text)
+                    .footer(serenity::CreateEmbedFooter::new(footer)),
+            )
+            .ephemeral(true),
+    )
+    .await?;
+
+    Ok(())
+}
@@ -0,0 +1,267 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, FullEvent, GuildId, UserId, VoiceState,
+};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use crate::digest::{DigestPeriod, DigestProvider};
+use crate::leaderboard::LeaderboardEntry;
+use crate::taskman::Task;
+use crate::Error;
+
+/// Storage backend for voice time, kept in seconds
+pub trait VoiceStatsStore: Send + Sync {
+    /// Adds voice time to a user in a guild
+    fn add<'a>(
+        &'a self,
+        guild_id: GuildId,
+        user_id: UserId,
+        seconds: u64,
+    ) -> BoxFuture<'a, Result<(), Error>>;
+
+    /// Returns the total voice time of a user in a guild
+    fn get<'a>(&'a self, guild_id: GuildId, user_id: UserId) -> BoxFuture<'a, Result<u64, Error>>;
+
+    /// Returns the users with the most voice time in a guild, most first
+    fn top<'a>(
+        &'a self,
+        guild_id: GuildId,
+        limit: usize,
+    ) -> BoxFuture<'a, Result<Vec<(UserId, u64)>, Error>>;
+}
+
+/// Which voice states count towards voice time
+#[derive(Debug, Clone, Default)]
+pub struct VoiceStatsConfig {
+    /// Count time spent in the guilds AFK channel, defaults to false
+    pub count_afk: bool,
+    /// Count time spent deafened (by themselves or a moderator), defaults to false
+    pub count_deafened: bool,
+}
+
+/// Accumulates how long users spend in voice channels per guild
+///
+/// Requires the ``GUILD_VOICE_STATES`` intent and the guild cache (for the AFK channel). Time is
+/// written to the store when a user stops counting and every ``flush_task`` interval. This is
+/// cheap to clone
+#[derive(Clone)]
+pub struct VoiceStats {
+    store: Arc<dyn VoiceStatsStore>,
+    pub config: VoiceStatsConfig,
+    sessions: Arc<Mutex<HashMap<(GuildId, UserId), Instant>>>,
+    /// Voice time since the last digest, for ``DigestProvider``
+    period: Arc<Mutex<HashMap<GuildId, HashMap<UserId, u64>>>>,
+}
+
+impl VoiceStats {
+    pub fn new(store: Arc<dyn VoiceStatsStore>, config: VoiceStatsConfig) -> Self {
+        Self {
+            store,
+            config,
+            sessions: Arc::default(),
+            period: Arc::default(),
+        }
+    }
+
+    /// Returns true if a voice state counts towards voice time
+    pub fn counts(&self, state: &VoiceState, afk_channel: Option<ChannelId>) -> bool {
+        let Some(channel_id) = state.channel_id else {
+            return false;
+        };
+
+        if !self.config.count_afk && afk_channel == Some(channel_id) {
+            return false;
+        }
+
+        self.config.count_deafened || !(state.deaf() || state.self_deaf())
+    }
+
+    async fn _record(&self, guild_id: GuildId, user_id: UserId, seconds: u64) -> Result<(), Error> {
+        if seconds == 0 {
+            return Ok(());
+        }
+
+        *self
+            .period
+            .lock()
+            .await
+            .entry(guild_id)
+            .or_default()
+            .entry(user_id)
+            .or_default() += seconds;
+
+        self.store.add(guild_id, user_id, seconds).await
+    }
+
+    async fn _update(
+        &self,
+        guild_id: GuildId,
+        user_id: UserId,
+        counting: bool,
+    ) -> Result<(), Error> {
+        let ended = {
+            let mut sessions = self.sessions.lock().await;
+
+            match (sessions.contains_key(&(guild_id, user_id)), counting) {
+                (false, true) => {
+                    sessions.insert((guild_id, user_id), Instant::now());
+                    None
+                }
+                (true, false) => sessions.remove(&(guild_id, user_id)),
+                _ => None,
+            }
+        };
+
+        if let Some(started) = ended {
+            self._record(guild_id, user_id, started.elapsed().as_secs())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Starts and ends voice sessions, this should be called from your bots event handler
+    ///
+    /// Users already in voice are picked up when their guild becomes available
+    pub async fn handle_event(
+        &self,
+        ctx: &serenity::Context,
+        event: &FullEvent,
+    ) -> Result<(), Error> {
+        match event {
+            FullEvent::VoiceStateUpdate { new, .. } => {
+                let Some(guild_id) = new.guild_id else {
+                    return Ok(());
+                };
+
+                let afk_channel = ctx
+                    .cache
+                    .guild(guild_id)
+                    .and_then(|g| g.afk_metadata.as_ref().map(|m| m.afk_channel_id));
+
+                let counting = self.counts(new, afk_channel);
+                self._update(guild_id, new.user_id, counting).await
+            }
+            FullEvent::GuildCreate { guild, .. } => {
+                let afk_channel = guild.afk_metadata.as_ref().map(|m| m.afk_channel_id);
+
+                for state in guild.voice_states.iter() {
+                    if self.counts(state, afk_channel) {
+                        self._update(guild.id, state.user_id, true).await?;
+                    }
+                }
+
+                Ok(())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Writes the time of every ongoing session to the store without ending it
+    pub async fn flush(&self) -> Result<(), Error> {
+        let now = Instant::now();
+
+        let elapsed = {
+            let mut sessions = self.sessions.lock().await;
+            let mut elapsed = Vec::with_capacity(sessions.len());
+
+            for ((guild_id, user_id), started) in sessions.iter_mut() {
+                let seconds = now.duration_since(*started).as_secs();
+
+                // Keep the sub-second remainder for the next flush
+                *started += Duration::from_secs(seconds);
+                elapsed.push((*guild_id, *user_id, seconds));
+            }
+
+            elapsed
+        };
+
+        for (guild_id, user_id, seconds) in elapsed {
+            self._record(guild_id, user_id, seconds).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the total voice minutes of a user in a guild
+    pub async fn minutes(&self, guild_id: GuildId, user_id: UserId) -> Result<u64, Error> {
+        Ok(self.store.get(guild_id, user_id).await? / 60)
+    }
+
+    /// Returns the users with the most voice minutes in a guild, for ``leaderboard::render``
+    pub async fn leaderboard(
+        &self,
+        guild_id: GuildId,
+        limit: usize,
+    ) -> Result<Vec<LeaderboardEntry>, Error> {
+        Ok(self
+            .store
+            .top(guild_id, limit)
+            .await?
+            .into_iter()
+            .map(|(user_id, seconds)| LeaderboardEntry {
+                user_id,
+                score: (seconds / 60) as i64,
+            })
+            .collect())
+    }
+
+    /// Returns and resets the voice time per user of a guild since the last call, most first
+    pub async fn take_period(&self, guild_id: GuildId) -> Vec<(UserId, u64)> {
+        let mut users = self
+            .period
+            .lock()
+            .await
+            .remove(&guild_id)
+            .unwrap_or_default()
+            .into_iter()
+            .collect::<Vec<_>>();
+
+        users.sort_by(|a, b| b.1.cmp(&a.1));
+        users
+    }
+}
+
+impl DigestProvider for VoiceStats {
+    fn section<'a>(
+        &'a self,
+        guild_id: GuildId,
+        _period: DigestPeriod,
+    ) -> BoxFuture<'a, Result<Option<(String, String)>, Error>> {
+        Box::pin(async move {
+            let users = self.take_period(guild_id).await;
+
+            if users.is_empty() {
+                return Ok(None);
+            }
+
+            let total = users.iter().map(|(_, s)| s).sum::<u64>() / 60;
+            let mut value = format!("🔊 {} minutes from {} members", total, users.len());
+
+            for (user_id, seconds) in users.iter().take(3) {
+                let _ = write!(value, "\n<@{}> - {} minutes", user_id, seconds / 60);
+            }
+
+            Ok(Some(("Voice activity".to_string(), value)))
+        })
+    }
+}
+
+/// Returns a task that writes ongoing voice sessions to the store every ``interval``, so a
+/// restart loses at most one interval of voice time
+pub fn flush_task(stats: VoiceStats, interval: Duration) -> Task {
+    Task {
+        name: "voice_stats_flush",
+        description: "Writes ongoing voice sessions to the store",
+        enabled: true,
+        duration: interval,
+        run: Box::new(move |_ctx| {
+            let stats = stats.clone();
+            Box::pin(async move { stats.flush().await })
+        }),
+    }
+}
@@ -0,0 +1,221 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{self as serenity, GuildId, Timestamp, UserId};
+use poise::CreateReply;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::cases::{Case, CaseAction, Cases, NewCase};
+use crate::Error;
+
+/// An action taken once a user reaches a number of active warnings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationStep {
+    pub warns: usize,
+    pub action: CaseAction,
+    /// Length of a timeout
+    pub duration: Option<Duration>,
+}
+
+/// A guilds warning escalation policy
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EscalationPolicy {
+    pub steps: Vec<EscalationStep>,
+    /// Warnings older than this no longer count, if set
+    pub decay: Option<Duration>,
+}
+
+impl Default for EscalationPolicy {
+    /// 3 warnings time out for an hour, 5 kick, warnings decay after 30 days
+    fn default() -> Self {
+        Self {
+            steps: vec![
+                EscalationStep {
+                    warns: 3,
+                    action: CaseAction::Timeout,
+                    duration: Some(Duration::from_secs(60 * 60)),
+                },
+                EscalationStep {
+                    warns: 5,
+                    action: CaseAction::Kick,
+                    duration: None,
+                },
+            ],
+            decay: Some(Duration::from_secs(30 * 24 * 60 * 60)),
+        }
+    }
+}
+
+impl EscalationPolicy {
+    /// Returns the warn cases that still count at ``now``, newest first
+    pub fn active<'a>(&self, cases: &'a [Case], now: Timestamp) -> Vec<&'a Case> {
+        cases
+            .iter()
+            .filter(|c| c.action == CaseAction::Warn)
+            .filter(|c| match self.decay {
+                Some(decay) => {
+                    now.unix_timestamp() - c.created_at.unix_timestamp() < decay.as_secs() as i64
+                }
+                None => true,
+            })
+            .collect()
+    }
+
+    /// Returns the step reached at exactly ``warns`` active warnings, if any
+    pub fn evaluate(&self, warns: usize) -> Option<&EscalationStep> {
+        self.steps.iter().find(|s| s.warns == warns)
+    }
+}
+
+/// Storage backend for escalation policies
+pub trait EscalationPolicyStore: Send + Sync {
+    /// Returns the escalation policy of a guild, None uses ``EscalationPolicy::default``
+    fn policy<'a>(
+        &'a self,
+        guild_id: GuildId,
+    ) -> BoxFuture<'a, Result<Option<EscalationPolicy>, Error>>;
+}
+
+/// The result of ``Warnings::warn``
+#[derive(Debug, Clone)]
+pub struct WarnOutcome {
+    pub warn: Case,
+    /// Number of active warnings, including this one
+    pub active: usize,
+    /// The case of the escalation this warning triggered, if any
+    pub escalation: Option<Case>,
+}
+
+/// Issues warnings and escalates them according to each guilds ``EscalationPolicy``
+///
+/// This is cheap to clone
+#[derive(Clone)]
+pub struct Warnings {
+    cases: Cases,
+    store: Arc<dyn EscalationPolicyStore>,
+}
+
+impl Warnings {
+    pub fn new(cases: Cases, store: Arc<dyn EscalationPolicyStore>) -> Self {
+        Self { cases, store }
+    }
+
+    /// Returns the case system warnings are recorded in
+    pub fn cases(&self) -> &Cases {
+        &self.cases
+    }
+
+    /// Returns the escalation policy of a guild
+    pub async fn policy(&self, guild_id: GuildId) -> Result<EscalationPolicy, Error> {
+        Ok(self.store.policy(guild_id).await?.unwrap_or_default())
+    }
+
+    /// Warns a user, taking the escalation step reached if any
+    ///
+    /// The escalation case is linked to the active warnings that caused it
+    pub async fn warn(
+        &self,
+        http: &serenity::Http,
+        guild_id: GuildId,
+        user_id: UserId,
+        moderator_id: UserId,
+        reason: String,
+    ) -> Result<WarnOutcome, Error> {
+        let warn = self
+            .cases
+            .apply(
+                http,
+                NewCase {
+                    guild_id,
+                    action: CaseAction::Warn,
+                    user_id,
+                    moderator_id,
+                    reason,
+                    duration: None,
+                    related: Vec::new(),
+                },
+            )
+            .await?;
+
+        let policy = self.policy(guild_id).await?;
+        let history = self.cases.history(guild_id, user_id).await?;
+        let active = policy.active(&history, Timestamp::now());
+
+        let Some(step) = policy.evaluate(active.len()) else {
+            return Ok(WarnOutcome {
+                warn,
+                active: active.len(),
+                escalation: None,
+            });
+        };
+
+        let escalation = self
+            .cases
+            .apply(
+                http,
+                NewCase {
+                    guild_id,
+                    action: step.action,
+                    user_id,
+                    moderator_id,
+                    reason: format!("Reached {} active warnings", active.len()),
+                    duration: step.duration,
+                    related: active.iter().map(|c| c.number).collect(),
+                },
+            )
+            .await;
+
+        // The warning stands even if the escalation fails, for example due to role hierarchy
+        let escalation = match escalation {
+            Ok(case) => Some(case),
+            Err(e) => {
+                log::warn!("Failed to escalate warnings of {}: {}", user_id, e);
+                None
+            }
+        };
+
+        Ok(WarnOutcome {
+            warn,
+            active: active.len(),
+            escalation,
+        })
+    }
+}
+
+/// Trait for bot data that holds ``Warnings``
+pub trait HasWarnings {
+    fn warnings(&self) -> &Warnings;
+}
+
+/// Warns a user, can be plugged into your bots ``/warn`` command
+pub async fn warn<Data: HasWarnings + Send + Sync + 'static>(
+    ctx: poise::Context<'_, Data, crate::Error>,
+    user: serenity::User,
+    reason: String,
+) -> Result<(), Error> {
+    let Some(guild_id) = ctx.guild_id() else {
+        return Err("Warnings can only be issued in a server".into());
+    };
+
+    let data = ctx.data();
+    let warnings = data.warnings();
+
+    let outcome = warnings
+        .warn(ctx.http(), guild_id, user.id, ctx.author().id, reason)
+        .await?;
+
+    let mut reply = CreateReply::default()
+        .content(format!(
+            "Warned <@{}>, they now have {} active warning(s)",
+            user.id, outcome.active
+        ))
+        .embed(warnings.cases().embed(&outcome.warn).await);
+
+    if let Some(escalation) = outcome.escalation {
+        reply = reply.embed(warnings.cases().embed(&escalation).await);
+    }
+
+    ctx.send(reply).await?;
+
+    Ok(())
+}
@@ -0,0 +1,419 @@
+use futures::future::BoxFuture;
+use poise::serenity_prelude::{
+    self as serenity, ChannelId, CreateMessage, EditMember, FullEvent, GuildId, Message, RoleId,
+    Timestamp, UserId,
+};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+
+use crate::cache::{Prunable, TtlCache};
+use crate::features::{Feature, FeatureMatrix};
+use crate::messagelog::MessageLog;
+use crate::reason::Reason;
+use crate::sanitize::MentionPolicy;
+use crate::Error;
+
+/// A guilds word filter configuration
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WordFilterConfig {
+    /// Banned words, matched as whole words after normalization
+    pub words: Vec<String>,
+    /// Banned regular expressions, matched case-insensitively against the original message
+    pub patterns: Vec<String>,
+    /// Also match words hidden with separators, such as ``b.a.d``
+    pub strict: bool,
+    pub actions: FilterActions,
+    pub exempt_roles: Vec<RoleId>,
+    pub exempt_channels: Vec<ChannelId>,
+}
+
+/// What happens to a message that trips the filter
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FilterActions {
+    pub delete: bool,
+    /// Reply with a warning mentioning the author
+    pub warn: bool,
+    /// Time the author out for this long
+    pub timeout: Option<Duration>,
+}
+
+/// The actions that were successfully taken on a message
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ActionsTaken {
+    pub deleted: bool,
+    pub warned: bool,
+    pub timed_out: bool,
+}
+
+impl FilterActions {
+    /// Applies the actions to a message, failed actions are logged and left out of the result
+    ///
+    /// ``warning`` is sent after a mention of the author. Deleted messages are logged to ``message_log`` with the reason
+    pub async fn apply(
+        &self,
+        http: &serenity::Http,
+        msg: &Message,
+        reason: &Reason,
+        warning: &str,
+        message_log: Option<&MessageLog>,
+    ) -> Result<ActionsTaken, Error> {
+        let mut taken = ActionsTaken::default();
+
+        if self.delete {
+            if let Some(message_log) = message_log {
+                if let Err(e) = message_log.log_removal(http, msg, reason.as_str()).await {
+                    log::warn!("Failed to log removed message: {}", e);
+                }
+            }
+
+            match msg.delete(http, Some(reason.as_str())).await {
+                Ok(()) => taken.deleted = true,
+                Err(e) => log::warn!("Failed to delete message {}: {}", msg.id, e),
+            }
+        }
+
+        if self.warn {
+            let res = crate::send::send_message_with(
+                http,
+                msg.channel_id,
+                CreateMessage::new().content(format!("<@{}>, {}", msg.author.id, warning)),
+                MentionPolicy::users_only(),
+            )
+            .await;
+
+            match res {
+                Ok(_) => taken.warned = true,
+                Err(e) => log::warn!("Failed to warn {}: {}", msg.author.id, e),
+            }
+        }
+
+        if let (Some(timeout), Some(guild_id)) = (self.timeout, msg.guild_id) {
+            let until = Timestamp::from_unix_timestamp(
+                Timestamp::now().unix_timestamp() + timeout.as_secs() as i64,
+            )?;
+
+            let res = guild_id
+                .edit_member(
+                    http,
+                    msg.author.id,
+                    EditMember::new()
+                        .disable_communication_until(until)
+                        .audit_log_reason(reason.as_str()),
+                )
+                .await;
+
+            match res {
+                Ok(_) => taken.timed_out = true,
+                Err(e) => log::warn!("Failed to time out {}: {}", msg.author.id, e),
+            }
+        }
+
+        Ok(taken)
+    }
+}
+
+/// Storage backend for word filter configuration
+pub trait WordFilterStore: Send + Sync {
+    /// Returns the word filter configuration of a guild, if enabled
+    fn config<'a>(
+        &'a self,
+        guild_id: GuildId,
+    ) -> BoxFuture<'a, Result<Option<WordFilterConfig>, Error>>;
+}
+
+/// Emitted for every filtered message, so bots can route violations to their own logs
+#[derive(Debug, Clone)]
+pub struct WordFilterEvent {
+    pub guild_id: GuildId,
+    pub channel_id: ChannelId,
+    pub user_id: UserId,
+    /// The word or pattern that matched
+    pub matched: String,
+    pub content: String,
+    pub actions: ActionsTaken,
+}
+
+/// Folds a character to the letter it is commonly used in place of, digits and symbols are only
+/// folded with ``leet``
+fn _fold(c: char, leet: bool) -> Option<char> {
+    let c = match c {
+        // Fullwidth forms
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+        _ => c,
+    };
+
+    Some(match c {
+        // Invisible characters used to split words
+        '\u{200B}'..='\u{200D}' | '\u{2060}' | '\u{FEFF}' | '\u{00AD}' => return None,
+        c if !leet && !c.is_alphabetic() => c,
+        '0' => 'o',
+        '1' | '!' | '|' => 'i',
+        '3' => 'e',
+        '4' | '@' => 'a',
+        '5' | '$' => 's',
+        '7' => 't',
+        '8' => 'b',
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'а' => 'a',
+        'ç' | 'с' => 'c',
+        'è' | 'é' | 'ê' | 'ë' | 'е' => 'e',
+        'ì' | 'í' | 'î' | 'ï' | 'і' => 'i',
+        'ñ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'о' => 'o',
+        'р' => 'p',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'ý' | 'ÿ' | 'у' => 'y',
+        'х' => 'x',
+        c => c,
+    })
+}
+
+/// Lowercases text and folds unicode look-alikes, accents and leetspeak to plain letters
+pub fn normalize(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .filter_map(|c| _fold(c, true))
+        .collect()
+}
+
+/// Like ``normalize`` but keeps digits and symbols, so punctuation next to a word still ends it
+fn _normalize_letters(text: &str) -> String {
+    text.chars()
+        .flat_map(char::to_lowercase)
+        .filter_map(|c| _fold(c, false))
+        .collect()
+}
+
+/// Normalizes text and drops everything but letters and digits, so separated words are joined
+pub fn squash(text: &str) -> String {
+    normalize(text)
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+struct _CompiledFilter {
+    config: WordFilterConfig,
+    words: Vec<(String, Regex)>,
+    patterns: Vec<(String, Regex)>,
+}
+
+impl _CompiledFilter {
+    fn new(config: WordFilterConfig) -> Self {
+        let build = |pattern: &str| {
+            RegexBuilder::new(pattern)
+                .case_insensitive(true)
+                .size_limit(1 << 16)
+                .build()
+        };
+
+        let words = config
+            .words
+            .iter()
+            .filter_map(|w| {
+                let word = normalize(w);
+                match build(&format!(r"\b{}\b", regex::escape(&word))) {
+                    Ok(r) => Some((w.clone(), r)),
+                    Err(e) => {
+                        log::warn!("Skipping invalid word filter word {}: {}", w, e);
+                        None
+                    }
+                }
+            })
+            .collect();
+
+        let patterns = config
+            .patterns
+            .iter()
+            .filter_map(|p| match build(p) {
+                Ok(r) => Some((p.clone(), r)),
+                Err(e) => {
+                    log::warn!("Skipping invalid word filter pattern {}: {}", p, e);
+                    None
+                }
+            })
+            .collect();
+
+        Self {
+            config,
+            words,
+            patterns,
+        }
+    }
+
+    /// Returns the word or pattern a message trips, if any
+    ///
+    /// Words are matched against the message with and without leetspeak folded, as folding turns
+    /// trailing punctuation (such as the ``!`` in ``word!``) into letters
+    fn check(&self, content: &str) -> Option<String> {
+        let normalized = normalize(content);
+        let letters = _normalize_letters(content);
+
+        for (word, regex) in &self.words {
+            if regex.is_match(&normalized) || regex.is_match(&letters) {
+                return Some(word.clone());
+            }
+        }
+
+        for (pattern, regex) in &self.patterns {
+            if regex.is_match(content) {
+                return Some(pattern.clone());
+            }
+        }
+
+        if self.config.strict {
+            let squashed = squash(content);
+
+            for word in &self.config.words {
+                let word_squashed = squash(word);
+
+                if !word_squashed.is_empty() && squashed.contains(&word_squashed) {
+                    return Some(word.clone());
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Per-guild banned words and patterns with configurable actions
+///
+/// Compiled filters are cached per guild for an hour. This is cheap to clone
+#[derive(Clone)]
+pub struct WordFilter {
+    store: Arc<dyn WordFilterStore>,
+    cache: TtlCache<GuildId, Option<Arc<_CompiledFilter>>>,
+    events: broadcast::Sender<WordFilterEvent>,
+    /// Removed messages are logged here with the matched word, if set
+    pub message_log: Option<MessageLog>,
+    /// Messages are not filtered in guilds that disabled ``Feature::WordFilter``, if set
+    pub features: Option<FeatureMatrix>,
+}
+
+impl WordFilter {
+    pub fn new(store: Arc<dyn WordFilterStore>) -> Self {
+        Self {
+            store,
+            cache: TtlCache::new("wordfilter", Duration::from_secs(60 * 60), 10_000),
+            events: broadcast::channel(64).0,
+            message_log: None,
+            features: None,
+        }
+    }
+
+    /// Returns a receiver of filter violations
+    pub fn subscribe(&self) -> broadcast::Receiver<WordFilterEvent> {
+        self.events.subscribe()
+    }
+
+    /// Drops the cached filter of a guild, call this after changing its configuration
+    pub async fn invalidate(&self, guild_id: GuildId) {
+        self.cache.remove(&guild_id).await;
+    }
+
+    /// Returns the compiled filter cache, for example to add it to a ``MemoryGovernor``
+    pub fn cache(&self) -> Arc<dyn Prunable> {
+        Arc::new(self.cache.clone())
+    }
+
+    async fn _filter(&self, guild_id: GuildId) -> Result<Option<Arc<_CompiledFilter>>, Error> {
+        if let Some(filter) = self.cache.get(&guild_id).await {
+            return Ok(filter);
+        }
+
+        let filter = self
+            .store
+            .config(guild_id)
+            .await?
+            .map(|c| Arc::new(_CompiledFilter::new(c)));
+
+        self.cache.insert(guild_id, filter.clone()).await;
+
+        Ok(filter)
+    }
+
+    /// Returns the word or pattern a message would trip in a guild, ignoring exemptions
+    pub async fn check(&self, guild_id: GuildId, content: &str) -> Result<Option<String>, Error> {
+        Ok(self._filter(guild_id).await?.and_then(|f| f.check(content)))
+    }
+
+    async fn _on_message(&self, http: &serenity::Http, msg: &Message) -> Result<(), Error> {
+        let Some(guild_id) = msg.guild_id else {
+            return Ok(());
+        };
+
+        if msg.author.bot() || msg.content.is_empty() {
+            return Ok(());
+        }
+
+        let Some(filter) = self._filter(guild_id).await? else {
+            return Ok(());
+        };
+
+        let config = &filter.config;
+
+        if config.exempt_channels.contains(&msg.channel_id) {
+            return Ok(());
+        }
+
+        if msg
+            .member
+            .as_ref()
+            .is_some_and(|m| m.roles.iter().any(|r| config.exempt_roles.contains(r)))
+        {
+            return Ok(());
+        }
+
+        let Some(matched) = filter.check(&msg.content) else {
+            return Ok(());
+        };
+
+        let reason = Reason::new(format!("Word filter: {}", matched));
+
+        let actions = config
+            .actions
+            .apply(
+                http,
+                msg,
+                &reason,
+                "your message was removed for breaking this servers word filter",
+                self.message_log.as_ref(),
+            )
+            .await?;
+
+        let _ = self.events.send(WordFilterEvent {
+            guild_id,
+            channel_id: msg.channel_id,
+            user_id: msg.author.id,
+            matched,
+            content: msg.content.to_string(),
+            actions,
+        });
+
+        Ok(())
+    }
+
+    /// Filters new and edited messages, this should be called from your bots event handler
+    ///
+    /// Requires the ``MESSAGE_CONTENT`` intent
+    pub async fn handle_event(
+        &self,
+        ctx: &serenity::Context,
+        event: &FullEvent,
+    ) -> Result<(), Error> {
+        if !crate::features::allows(self.features.as_ref(), event, Feature::WordFilter).await {
+            return Ok(());
+        }
+
+        match event {
+            FullEvent::Message { new_message } => self._on_message(&ctx.http, new_message).await,
+            FullEvent::MessageUpdate { new: Some(new), .. } => {
+                self._on_message(&ctx.http, new).await
+            }
+            _ => Ok(()),
+        }
+    }
+}